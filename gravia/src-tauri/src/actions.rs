@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Url};
+use tauri_plugin_opener::OpenerExt;
+use ts_rs::TS;
+
+use crate::error::CommandResult;
+use crate::events::{emit_event, GraviaEvent};
+
+// Anything the shell would run rather than show.
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "exe", "com", "bat", "cmd", "msi", "msp", "msc", "ps1", "psm1", "vbs", "vbe", "js", "jse", "wsf",
+    "wsh", "scr", "pif", "cpl", "lnk", "url", "reg", "hta", "jar", "sh", "command", "app",
+];
+
+// Something the assistant offered to open. Only these shapes are accepted,
+// and each is checked against an allowlist before anything runs.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OpenAction {
+    OpenUrl { url: String },
+    OpenPath { path: PathBuf },
+    OpenSettingsPage { page: String },
+    LaunchApp { name: String },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ActionError {
+    #[error("action not allowed: {0}")]
+    NotAllowed(String),
+    #[error("{0} can't be opened on this platform")]
+    Unsupported(&'static str),
+    #[error("failed to open: {0}")]
+    Failed(String),
+}
+
+impl ActionError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ActionError::NotAllowed(_) => "not_allowed",
+            ActionError::Unsupported(_) => "unsupported",
+            ActionError::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ActionExecuted {
+    pub action: OpenAction,
+    // What was actually handed to the OS.
+    pub target: String,
+}
+
+enum Target {
+    Url(String),
+    Path(String),
+}
+
+fn check_url(url: &str) -> Result<String, ActionError> {
+    let parsed = Url::parse(url).map_err(|_| ActionError::NotAllowed(format!("`{url}` is not a valid URL")))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ActionError::NotAllowed(format!("`{}` links can't be opened", parsed.scheme())));
+    }
+    Ok(parsed.to_string())
+}
+
+fn is_executable(path: &Path) -> bool {
+    let by_extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXECUTABLE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    #[cfg(unix)]
+    let by_mode = {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    };
+    #[cfg(not(unix))]
+    let by_mode = false;
+    by_extension || by_mode
+}
+
+fn check_path(path: &Path) -> Result<String, ActionError> {
+    // Resolves `..` and symlinks, so the check applies to what gets opened.
+    let path = std::fs::canonicalize(path)
+        .map_err(|_| ActionError::NotAllowed(format!("{} does not exist", path.display())))?;
+    if is_executable(&path) {
+        return Err(ActionError::NotAllowed(format!("{} is a program", path.display())));
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+fn resolve(action: &OpenAction) -> Result<Target, ActionError> {
+    match action {
+        OpenAction::OpenUrl { url } => check_url(url).map(Target::Url),
+        OpenAction::OpenPath { path } => check_path(path).map(Target::Path),
+        OpenAction::OpenSettingsPage { page } => platform::settings_uri(page).map(|uri| Target::Url(uri.to_string())),
+        OpenAction::LaunchApp { name } => platform::app_target(name).map(|target| Target::Path(target.to_string())),
+    }
+}
+
+// Looks `name` up in one of the platform's curated tables, forgiving case
+// and spaces or dashes for underscores.
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn lookup(table: &[(&'static str, &'static str)], kind: &str, name: &str) -> Result<&'static str, ActionError> {
+    let key = name.trim().to_ascii_lowercase().replace([' ', '-'], "_");
+    table
+        .iter()
+        .find(|(entry, _)| *entry == key)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| ActionError::NotAllowed(format!("unknown {kind} `{name}`")))
+}
+
+#[tauri::command]
+pub fn execute_open_action(app: AppHandle, action: OpenAction) -> CommandResult<()> {
+    let target = match resolve(&action) {
+        Ok(target) => target,
+        Err(e) => {
+            tracing::warn!(?action, error = %e, "refused open action");
+            return Err(e.into());
+        }
+    };
+    let (result, target) = match target {
+        Target::Url(url) => (app.opener().open_url(url.as_str(), None::<&str>), url),
+        Target::Path(path) => (app.opener().open_path(path.as_str(), None::<&str>), path),
+    };
+    result.map_err(|e| ActionError::Failed(e.to_string()))?;
+    tracing::info!(?action, %target, "executed open action");
+    emit_event(&app, GraviaEvent::ActionExecuted(ActionExecuted { action, target })).ok();
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{lookup, ActionError};
+
+    // Friendly names the assistant may use, mapped to ms-settings: pages.
+    const SETTINGS_PAGES: &[(&str, &str)] = &[
+        ("about", "ms-settings:about"),
+        ("apps", "ms-settings:appsfeatures"),
+        ("bluetooth", "ms-settings:bluetooth"),
+        ("default_apps", "ms-settings:defaultapps"),
+        ("display", "ms-settings:display"),
+        ("network", "ms-settings:network-status"),
+        ("notifications", "ms-settings:notifications"),
+        ("personalization", "ms-settings:personalization"),
+        ("power", "ms-settings:powersleep"),
+        ("privacy_camera", "ms-settings:privacy-webcam"),
+        ("privacy_microphone", "ms-settings:privacy-microphone"),
+        ("sound", "ms-settings:sound"),
+        ("storage", "ms-settings:storagesense"),
+        ("windows_update", "ms-settings:windowsupdate"),
+        ("wifi", "ms-settings:network-wifi"),
+    ];
+
+    // System tools only; these resolve from System32.
+    const APPS: &[(&str, &str)] = &[
+        ("calculator", "calc.exe"),
+        ("control_panel", "control.exe"),
+        ("device_manager", "devmgmt.msc"),
+        ("disk_management", "diskmgmt.msc"),
+        ("event_viewer", "eventvwr.msc"),
+        ("file_explorer", "explorer.exe"),
+        ("notepad", "notepad.exe"),
+        ("services", "services.msc"),
+        ("task_manager", "taskmgr.exe"),
+    ];
+
+    pub fn settings_uri(page: &str) -> Result<&'static str, ActionError> {
+        lookup(SETTINGS_PAGES, "settings page", page)
+    }
+
+    pub fn app_target(name: &str) -> Result<&'static str, ActionError> {
+        lookup(APPS, "app", name)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{lookup, ActionError};
+
+    // The same friendly names where macOS has a matching pane. The legacy
+    // pane ids still open the right page in System Settings.
+    const SETTINGS_PAGES: &[(&str, &str)] = &[
+        ("accessibility", "x-apple.systempreferences:com.apple.preference.universalaccess"),
+        ("bluetooth", "x-apple.systempreferences:com.apple.preferences.Bluetooth"),
+        ("display", "x-apple.systempreferences:com.apple.preference.displays"),
+        ("network", "x-apple.systempreferences:com.apple.preference.network"),
+        ("notifications", "x-apple.systempreferences:com.apple.preference.notifications"),
+        ("power", "x-apple.systempreferences:com.apple.preference.battery"),
+        ("privacy_camera", "x-apple.systempreferences:com.apple.preference.security?Privacy_Camera"),
+        ("privacy_microphone", "x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone"),
+        ("privacy_screen_recording", "x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture"),
+        ("software_update", "x-apple.systempreferences:com.apple.preferences.softwareupdate"),
+        ("sound", "x-apple.systempreferences:com.apple.preference.sound"),
+        ("wifi", "x-apple.systempreferences:com.apple.preference.network"),
+    ];
+
+    // Bundled system apps only, by absolute path.
+    const APPS: &[(&str, &str)] = &[
+        ("activity_monitor", "/System/Applications/Utilities/Activity Monitor.app"),
+        ("calculator", "/System/Applications/Calculator.app"),
+        ("console", "/System/Applications/Utilities/Console.app"),
+        ("disk_utility", "/System/Applications/Utilities/Disk Utility.app"),
+        ("finder", "/System/Library/CoreServices/Finder.app"),
+        ("system_information", "/System/Applications/Utilities/System Information.app"),
+        ("textedit", "/System/Applications/TextEdit.app"),
+    ];
+
+    pub fn settings_uri(page: &str) -> Result<&'static str, ActionError> {
+        lookup(SETTINGS_PAGES, "settings page", page)
+    }
+
+    pub fn app_target(name: &str) -> Result<&'static str, ActionError> {
+        lookup(APPS, "app", name)
+    }
+}
+
+// Settings apps and their page names differ per desktop environment, and
+// there is no fixed set of system tools to allowlist, so both are refused
+// outright rather than guessed at.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::ActionError;
+
+    pub fn settings_uri(_page: &str) -> Result<&'static str, ActionError> {
+        Err(ActionError::Unsupported("settings pages"))
+    }
+
+    pub fn app_target(_name: &str) -> Result<&'static str, ActionError> {
+        Err(ActionError::Unsupported("apps"))
+    }
+}
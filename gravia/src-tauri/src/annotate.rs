@@ -0,0 +1,560 @@
+use base64::Engine;
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::capture::CaptureError;
+#[cfg(feature = "capture")]
+use crate::capture::StampCorner;
+use crate::capture_history::CaptureHistory;
+use crate::command_stats;
+use crate::error::{CommandResult, GraviaError};
+
+const MAX_ANNOTATIONS: usize = 64;
+const MAX_LABEL_CHARS: usize = 200;
+const MAX_STROKE_WIDTH: u32 = 64;
+const MAX_LABEL_SIZE: u32 = 8;
+const DEFAULT_STROKE_WIDTH: u32 = 4;
+// Each glyph pixel is drawn this many image pixels square.
+const DEFAULT_LABEL_SIZE: u32 = 3;
+// Between a label's text and the edge of its background.
+const LABEL_PADDING: u32 = 2;
+
+// An RGBA color, written `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, TS)]
+#[ts(export, type = "string")]
+#[serde(try_from = "String")]
+pub struct Color([u8; 4]);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(&hex);
+        let channel = |i: usize| digits.get(i..i + 2).and_then(|d| u8::from_str_radix(d, 16).ok());
+        match (digits.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color([r, g, b, 255])),
+            (8, Some(r), Some(g), Some(b)) => match channel(6) {
+                Some(a) => Ok(Color([r, g, b, a])),
+                None => Err(format!("invalid color {hex:?}")),
+            },
+            _ => Err(format!("invalid color {hex:?}, expected #rrggbb or #rrggbbaa")),
+        }
+    }
+}
+
+const HIGHLIGHT: Color = Color([255, 59, 48, 255]);
+const HIGHLIGHT_FILL: Color = Color([255, 214, 10, 96]);
+const LABEL_TEXT: Color = Color([255, 255, 255, 255]);
+const LABEL_BACKGROUND: Color = Color([0, 0, 0, 192]);
+// Lighter, to cover less of what's under it.
+#[cfg(feature = "capture")]
+const STAMP_BACKGROUND: Color = Color([0, 0, 0, 128]);
+
+#[derive(Debug, Clone, Copy, Deserialize, TS)]
+#[ts(export)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+// A shape drawn onto a screenshot, in the image's own pixels. Shapes are
+// drawn in order, and whatever falls outside the image is cut off.
+#[derive(Debug, Clone, Deserialize, TS)]
+#[ts(export)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Annotation {
+    // An outline drawn inside the rectangle's edges.
+    Rect {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: Option<Color>,
+        stroke_width: Option<u32>,
+    },
+    // Translucent yellow unless `color` says otherwise.
+    FilledRect { x: i32, y: i32, width: u32, height: u32, color: Option<Color> },
+    // Pointing at `to`.
+    Arrow { from: Point, to: Point, color: Option<Color>, stroke_width: Option<u32> },
+    // Text on a background box with its top-left at `x`, `y`, moved in to
+    // stay on the image. Letters, digits and common punctuation; lowercase
+    // comes out as capitals and anything else as a box.
+    Label {
+        x: i32,
+        y: i32,
+        text: String,
+        color: Option<Color>,
+        background: Option<Color>,
+        // Glyphs are 5x7 pixels times this.
+        size: Option<u32>,
+    },
+}
+
+impl Annotation {
+    fn validate(&self) -> Result<(), GraviaError> {
+        let stroke_width = match self {
+            Annotation::Rect { stroke_width, .. } | Annotation::Arrow { stroke_width, .. } => *stroke_width,
+            _ => None,
+        };
+        if stroke_width.is_some_and(|w| w == 0 || w > MAX_STROKE_WIDTH) {
+            return Err(GraviaError::InvalidArgument(format!("stroke_width must be between 1 and {MAX_STROKE_WIDTH}")));
+        }
+        if let Annotation::Label { text, size, .. } = self {
+            if text.chars().count() > MAX_LABEL_CHARS {
+                return Err(GraviaError::InvalidArgument(format!("labels are at most {MAX_LABEL_CHARS} characters")));
+            }
+            if size.is_some_and(|s| s == 0 || s > MAX_LABEL_SIZE) {
+                return Err(GraviaError::InvalidArgument(format!("size must be between 1 and {MAX_LABEL_SIZE}")));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Pixels a shape covers within a box on the image, so overlapping parts of
+// a translucent shape are only blended once.
+struct Mask {
+    left: i64,
+    top: i64,
+    width: i64,
+    covered: Vec<bool>,
+}
+
+impl Mask {
+    // Marks each pixel in `[left, top, right, bottom)`, cut to the image,
+    // whose center `inside` accepts.
+    fn new(image: (u32, u32), [left, top, right, bottom]: [i64; 4], inside: impl Fn(f64, f64) -> bool) -> Self {
+        let left = left.clamp(0, image.0 as i64);
+        let top = top.clamp(0, image.1 as i64);
+        let right = right.clamp(left, image.0 as i64);
+        let bottom = bottom.clamp(top, image.1 as i64);
+        let width = right - left;
+        let mut covered = Vec::with_capacity((width * (bottom - top)) as usize);
+        for y in top..bottom {
+            for x in left..right {
+                covered.push(inside(x as f64 + 0.5, y as f64 + 0.5));
+            }
+        }
+        Self { left, top, width, covered }
+    }
+
+    fn blend(&self, rgba: &mut [u8], image_width: u32, Color(color): Color) {
+        let alpha = color[3] as u32;
+        if self.width == 0 || alpha == 0 {
+            return;
+        }
+        for (row, covered) in self.covered.chunks_exact(self.width as usize).enumerate() {
+            let start = ((self.top as usize + row) * image_width as usize + self.left as usize) * 4;
+            let pixels = &mut rgba[start..start + covered.len() * 4];
+            for (px, _) in pixels.chunks_exact_mut(4).zip(covered).filter(|(_, c)| **c) {
+                for c in 0..3 {
+                    px[c] = ((px[c] as u32 * (255 - alpha) + color[c] as u32 * alpha + 127) / 255) as u8;
+                }
+                px[3] = px[3].max(color[3]);
+            }
+        }
+    }
+}
+
+// Draws `annotations` onto a `width` x `height` RGBA image.
+pub fn draw(rgba: &mut [u8], width: u32, height: u32, annotations: &[Annotation]) {
+    let image = (width, height);
+    for annotation in annotations {
+        match annotation {
+            Annotation::Rect { x, y, width: w, height: h, color, stroke_width } => {
+                let [left, top] = [*x as i64, *y as i64];
+                let [right, bottom] = [left + *w as i64, top + *h as i64];
+                let stroke = stroke_width.unwrap_or(DEFAULT_STROKE_WIDTH) as f64;
+                let (l, t, r, b) = (left as f64, top as f64, right as f64, bottom as f64);
+                let mask = Mask::new(image, [left, top, right, bottom], |px, py| {
+                    px < l + stroke || px > r - stroke || py < t + stroke || py > b - stroke
+                });
+                mask.blend(rgba, width, color.unwrap_or(HIGHLIGHT));
+            }
+            Annotation::FilledRect { x, y, width: w, height: h, color } => {
+                let [left, top] = [*x as i64, *y as i64];
+                let mask = Mask::new(image, [left, top, left + *w as i64, top + *h as i64], |_, _| true);
+                mask.blend(rgba, width, color.unwrap_or(HIGHLIGHT_FILL));
+            }
+            Annotation::Arrow { from, to, color, stroke_width } => {
+                let stroke = stroke_width.unwrap_or(DEFAULT_STROKE_WIDTH) as f64;
+                let mask = arrow_mask(image, clamp_point(*from, image), clamp_point(*to, image), stroke);
+                mask.blend(rgba, width, color.unwrap_or(HIGHLIGHT));
+            }
+            Annotation::Label { x, y, text, color, background, size } => {
+                let colors = (color.unwrap_or(LABEL_TEXT), background.unwrap_or(LABEL_BACKGROUND));
+                draw_label(rgba, image, (*x, *y), text, size.unwrap_or(DEFAULT_LABEL_SIZE), colors);
+            }
+        }
+    }
+}
+
+// Keeps a point on the image, so an arrow aimed off it still points the
+// same way at the edge.
+fn clamp_point(point: Point, (width, height): (u32, u32)) -> (f64, f64) {
+    let x = point.x.clamp(0, width.saturating_sub(1) as i32);
+    let y = point.y.clamp(0, height.saturating_sub(1) as i32);
+    (x as f64 + 0.5, y as f64 + 0.5)
+}
+
+// A shaft from `from` ending in a triangular head with its tip at `to`.
+// The head grows with the stroke, but never past the arrow's length.
+fn arrow_mask(image: (u32, u32), from: (f64, f64), to: (f64, f64), stroke: f64) -> Mask {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt().max(1.0);
+    let (ux, uy) = (dx / length, dy / length);
+    let head = (stroke * 4.0).max(12.0).min(length);
+    let base = (to.0 - ux * head, to.1 - uy * head);
+    let wing = (-uy * head * 0.5, ux * head * 0.5);
+    let corners = [to, (base.0 + wing.0, base.1 + wing.1), (base.0 - wing.0, base.1 - wing.1)];
+
+    let reach = head.max(stroke);
+    let bounds = [
+        (from.0.min(to.0) - reach).floor() as i64,
+        (from.1.min(to.1) - reach).floor() as i64,
+        (from.0.max(to.0) + reach).ceil() as i64,
+        (from.1.max(to.1) + reach).ceil() as i64,
+    ];
+    Mask::new(image, bounds, |px, py| {
+        distance_to_segment((px, py), from, base) <= stroke / 2.0 || in_triangle((px, py), corners)
+    })
+}
+
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 { 0.0 } else { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0) };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+fn in_triangle(p: (f64, f64), [a, b, c]: [(f64, f64); 3]) -> bool {
+    let side = |u: (f64, f64), v: (f64, f64)| (v.0 - u.0) * (p.1 - u.1) - (v.1 - u.1) * (p.0 - u.0);
+    let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+}
+
+fn draw_label(
+    rgba: &mut [u8],
+    image: (u32, u32),
+    (x, y): (i32, i32),
+    text: &str,
+    size: u32,
+    (color, background): (Color, Color),
+) {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return;
+    }
+    let (box_width, box_height) = label_box(chars.len(), size);
+    let left = (x as i64).min(image.0 as i64 - box_width).max(0);
+    let top = (y as i64).min(image.1 as i64 - box_height).max(0);
+    Mask::new(image, [left, top, left + box_width, top + box_height], |_, _| true).blend(rgba, image.0, background);
+
+    let (size, padding) = (size as i64, (LABEL_PADDING * size) as i64);
+    let (text_left, text_top) = (left + padding, top + padding);
+    let bounds = [text_left, text_top, left + box_width - padding, top + box_height - padding];
+    let mask = Mask::new(image, bounds, |px, py| {
+        let column = (px as i64 - text_left) / size;
+        let row = ((py as i64 - text_top) / size) as usize;
+        let (index, x_in_glyph) = ((column / 6) as usize, column % 6);
+        x_in_glyph < 5 && row < 7 && chars.get(index).is_some_and(|c| (glyph(*c)[row] >> (4 - x_in_glyph)) & 1 == 1)
+    });
+    mask.blend(rgba, image.0, color);
+}
+
+// Size of a label's background for `chars` characters. Glyphs are 5 wide
+// with a pixel between them.
+fn label_box(chars: usize, size: u32) -> (i64, i64) {
+    let width = (chars as u32 * 6).saturating_sub(1) * size + 2 * LABEL_PADDING * size;
+    (width as i64, (7 * size + 2 * LABEL_PADDING * size) as i64)
+}
+
+// Labels `text` into `corner` of a capture, a margin in from the edges.
+// Glyphs grow with the image's short side, a pixel per 360, so the stamp
+// looks the same size on a 720p laptop and a 4K display.
+#[cfg(feature = "capture")]
+pub fn stamp(rgba: &mut [u8], width: u32, height: u32, text: &str, corner: StampCorner) {
+    let size = (width.min(height) / 360).clamp(1, MAX_LABEL_SIZE);
+    let margin = 4 * size as i64;
+    let (box_width, box_height) = label_box(text.chars().count(), size);
+    let left = match corner {
+        StampCorner::TopLeft | StampCorner::BottomLeft => margin,
+        StampCorner::TopRight | StampCorner::BottomRight => width as i64 - box_width - margin,
+    };
+    let top = match corner {
+        StampCorner::TopLeft | StampCorner::TopRight => margin,
+        StampCorner::BottomLeft | StampCorner::BottomRight => height as i64 - box_height - margin,
+    };
+    let (x, y) = (left.max(0) as i32, top.max(0) as i32);
+    draw_label(rgba, (width, height), (x, y), text, size, (LABEL_TEXT, STAMP_BACKGROUND));
+}
+
+// Rows of a 5x7 glyph, top first, leftmost pixel in bit 4.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0; 7],
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        ',' => [0, 0, 0, 0, 0b01100, 0b00100, 0b01000],
+        ':' => [0, 0b01100, 0b01100, 0, 0b01100, 0b01100, 0],
+        ';' => [0, 0b01100, 0b01100, 0, 0b01100, 0b00100, 0b01000],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0, 0b00100],
+        '?' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0, 0b00100],
+        '-' => [0, 0, 0, 0b11111, 0, 0, 0],
+        '+' => [0, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0],
+        '=' => [0, 0, 0b11111, 0, 0b11111, 0, 0],
+        '_' => [0, 0, 0, 0, 0, 0, 0b11111],
+        '*' => [0, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0],
+        '/' => [0b00001, 0b00010, 0b00010, 0b00100, 0b01000, 0b01000, 0b10000],
+        '\'' => [0b00100, 0b00100, 0b01000, 0, 0, 0, 0],
+        '"' => [0b01010, 0b01010, 0, 0, 0, 0, 0],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '<' => [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010],
+        '>' => [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000],
+        '#' => [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        '&' => [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101],
+        '@' => [0b01110, 0b10001, 0b10111, 0b10101, 0b10111, 0b10000, 0b01110],
+        _ => [0b11111, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11111],
+    }
+}
+
+// The image a capture id or base64 string (a data URL is fine) refers to.
+fn load_image(app: &AppHandle, image: &str) -> CommandResult<(u32, u32, Vec<u8>)> {
+    let bytes = if image.starts_with("cap-") {
+        app.state::<CaptureHistory>()
+            .image_bytes(image)
+            .ok_or_else(|| CaptureError::CaptureNotFound(image.to_string()))?
+    } else {
+        let data = image.split_once(";base64,").map_or(image, |(_, data)| data);
+        base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|e| GraviaError::InvalidArgument(format!("image is not a capture id or base64: {e}")))?
+    };
+    codec::decode(&bytes)
+}
+
+// Draws highlight shapes onto a capture from the history, by id, or a
+// base64 image, e.g. to point out what an answer refers to. Returns the
+// result as a base64 PNG; the capture itself is left as it was.
+#[tauri::command]
+pub async fn annotate_screenshot(app: AppHandle, image: String, annotations: Vec<Annotation>) -> CommandResult<String> {
+    command_stats::track(&app, "annotate_screenshot", async {
+        if annotations.len() > MAX_ANNOTATIONS {
+            return Err(GraviaError::InvalidArgument(format!("at most {MAX_ANNOTATIONS} annotations")));
+        }
+        annotations.iter().try_for_each(Annotation::validate)?;
+        let worker_app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let (width, height, mut rgba) = load_image(&worker_app, &image)?;
+            draw(&mut rgba, width, height, &annotations);
+            let png = codec::encode_png(width, height, rgba)?;
+            Ok::<_, GraviaError>(base64::engine::general_purpose::STANDARD.encode(png))
+        })
+        .await?
+    })
+    .await
+}
+
+#[cfg(feature = "capture")]
+mod codec {
+    use crate::capture::CaptureError;
+    use crate::error::GraviaError;
+
+    pub fn decode(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), GraviaError> {
+        let decoded = image::load_from_memory(bytes)
+            .map_err(|e| GraviaError::InvalidArgument(format!("image could not be decoded: {e}")))?
+            .into_rgba8();
+        let (width, height) = decoded.dimensions();
+        Ok((width, height, decoded.into_raw()))
+    }
+
+    pub fn encode_png(width: u32, height: u32, rgba: Vec<u8>) -> Result<Vec<u8>, GraviaError> {
+        let image = image::RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| CaptureError::EncodeFailed("pixel buffer does not match its size".into()))?;
+        let mut png = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| CaptureError::EncodeFailed(e.to_string()))?;
+        Ok(png)
+    }
+}
+
+// Built without the `capture` feature there's no image codec to read or
+// write the picture with.
+#[cfg(not(feature = "capture"))]
+mod codec {
+    use crate::capture::CaptureError;
+    use crate::error::GraviaError;
+
+    pub fn decode(_bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), GraviaError> {
+        Err(CaptureError::Unavailable.into())
+    }
+
+    pub fn encode_png(_width: u32, _height: u32, _rgba: Vec<u8>) -> Result<Vec<u8>, GraviaError> {
+        Err(CaptureError::Unavailable.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 40;
+    const HEIGHT: u32 = 30;
+    const CANVAS: [u8; 4] = [100, 100, 100, 255];
+    const RED: Color = Color([255, 0, 0, 255]);
+
+    fn canvas() -> Vec<u8> {
+        CANVAS.repeat((WIDTH * HEIGHT) as usize)
+    }
+
+    fn drawn(annotations: &[Annotation]) -> Vec<u8> {
+        let mut rgba = canvas();
+        draw(&mut rgba, WIDTH, HEIGHT, annotations);
+        rgba
+    }
+
+    fn pixel(rgba: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * WIDTH + x) * 4) as usize;
+        rgba[i..i + 4].try_into().unwrap()
+    }
+
+    // Pixels no longer the canvas color.
+    fn painted(rgba: &[u8]) -> usize {
+        rgba.chunks_exact(4).filter(|px| *px != CANVAS).count()
+    }
+
+    fn count(rgba: &[u8], color: [u8; 4]) -> usize {
+        rgba.chunks_exact(4).filter(|px| *px == color).count()
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32, stroke_width: u32) -> Annotation {
+        Annotation::Rect { x, y, width, height, color: Some(RED), stroke_width: Some(stroke_width) }
+    }
+
+    fn arrow(from: (i32, i32), to: (i32, i32), stroke_width: u32) -> Annotation {
+        Annotation::Arrow {
+            from: Point { x: from.0, y: from.1 },
+            to: Point { x: to.0, y: to.1 },
+            color: Some(RED),
+            stroke_width: Some(stroke_width),
+        }
+    }
+
+    fn label(x: i32, y: i32, text: &str) -> Annotation {
+        Annotation::Label { x, y, text: text.to_string(), color: None, background: None, size: Some(1) }
+    }
+
+    #[test]
+    fn a_rect_is_an_outline_inside_its_edges() {
+        let rgba = drawn(&[rect(5, 5, 10, 8, 2)]);
+        // 10x8, less the 6x4 inside.
+        assert_eq!(painted(&rgba), 56);
+        assert_eq!(count(&rgba, RED.0), 56);
+        assert_eq!(pixel(&rgba, 5, 5), RED.0);
+        assert_eq!(pixel(&rgba, 14, 12), RED.0);
+        assert_eq!(pixel(&rgba, 7, 7), CANVAS);
+        assert_eq!(pixel(&rgba, 15, 5), CANVAS);
+    }
+
+    #[test]
+    fn a_filled_rect_blends_every_pixel_once() {
+        let fill = Annotation::FilledRect { x: 2, y: 3, width: 6, height: 4, color: None };
+        let rgba = drawn(&[fill]);
+        // 100 at 96/255 toward the highlight fill.
+        let blended = [158, 143, 66, 255];
+        assert_eq!((painted(&rgba), count(&rgba, blended)), (24, 24));
+    }
+
+    #[test]
+    fn an_arrow_has_a_shaft_and_a_head() {
+        let rgba = drawn(&[arrow((5, 15), (35, 15), 2)]);
+        // A 2px shaft to the 12px head's base, then the head.
+        assert_eq!(painted(&rgba), 140);
+        // The tip, the shaft's start and the head's wings.
+        for (x, y) in [(35, 15), (5, 15), (24, 10), (24, 20)] {
+            assert_eq!(pixel(&rgba, x, y), RED.0, "({x}, {y})");
+        }
+        assert_eq!(pixel(&rgba, 10, 12), CANVAS);
+        assert_eq!(pixel(&rgba, 36, 15), CANVAS);
+    }
+
+    #[test]
+    fn a_label_is_text_on_a_box() {
+        let rgba = drawn(&[label(3, 4, "HI")]);
+        // Two glyphs and the gap between them, padded by 2 on every side.
+        let (box_width, box_height) = label_box(2, 1);
+        assert_eq!((box_width, box_height), (15, 11));
+        assert_eq!(painted(&rgba), 165);
+        // H lights 17 pixels and I 11.
+        assert_eq!(count(&rgba, LABEL_TEXT.0), 28);
+        assert_eq!(pixel(&rgba, 3, 4), [25, 25, 25, 255]);
+        assert_eq!(pixel(&rgba, 5, 6), LABEL_TEXT.0);
+    }
+
+    #[test]
+    fn shapes_off_the_edges_are_cut_off() {
+        // Only the 5x5 corner is on the image, less the 3x3 inside of it.
+        assert_eq!(painted(&drawn(&[rect(-5, -5, 10, 10, 2)])), 16);
+        let fill = |x, y| Annotation::FilledRect { x, y, width: 10, height: 10, color: Some(RED) };
+        assert_eq!(painted(&drawn(&[fill(35, 25)])), 25);
+        assert_eq!(painted(&drawn(&[fill(-20, 0), fill(0, 100), fill(i32::MAX, i32::MIN)])), 0);
+    }
+
+    #[test]
+    fn an_arrow_aimed_off_the_image_ends_at_its_edge() {
+        assert_eq!(drawn(&[arrow((5, 15), (500, 15), 2)]), drawn(&[arrow((5, 15), (39, 15), 2)]));
+        assert_eq!(drawn(&[arrow((-50, -50), (20, 20), 2)]), drawn(&[arrow((0, 0), (20, 20), 2)]));
+    }
+
+    #[test]
+    fn a_label_off_the_image_is_moved_back_on() {
+        let rgba = drawn(&[label(1000, 1000, "HI")]);
+        assert_eq!(painted(&rgba), 165);
+        // Its box is in the bottom-right corner.
+        assert_ne!(pixel(&rgba, WIDTH - 1, HEIGHT - 1), CANVAS);
+        assert_ne!(pixel(&rgba, WIDTH - 15, HEIGHT - 11), CANVAS);
+        assert_eq!(pixel(&rgba, WIDTH - 16, HEIGHT - 1), CANVAS);
+        assert_eq!(pixel(&rgba, WIDTH - 1, HEIGHT - 12), CANVAS);
+        assert_eq!(drawn(&[label(-7, -7, "HI")]), drawn(&[label(0, 0, "HI")]));
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(feature = "capture")]
+use base64::Engine;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::State;
+use ts_rs::TS;
+
+use crate::error::CommandResult;
+
+// Files above this are attached by reference only (no content or preview).
+const MAX_INLINE_BYTES: u64 = 25 * 1024 * 1024;
+const MAX_TEXT_CHARS: usize = 64 * 1024;
+#[cfg(feature = "capture")]
+const THUMBNAIL_SIZE: u32 = 256;
+// How much of a file we look at to decide whether it's text.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AttachmentPreview {
+    Text { text: String, truncated: bool },
+    Image { data_url: String, width: u32, height: u32 },
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct FileAttachment {
+    pub id: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub mime_type: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    // True when the file was too large to read and only its path is kept.
+    pub by_reference: bool,
+    pub preview: AttachmentPreview,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Attachment {
+    File(FileAttachment),
+}
+
+impl Attachment {
+    pub fn id(&self) -> &str {
+        match self {
+            Attachment::File(f) => &f.id,
+        }
+    }
+}
+
+// Attachments waiting to be sent with the next message.
+#[derive(Default)]
+pub struct AttachmentStore {
+    attachments: Mutex<HashMap<String, Attachment>>,
+    pending: Mutex<Vec<String>>,
+}
+
+impl AttachmentStore {
+    pub fn add_pending(&self, attachment: Attachment) {
+        let id = attachment.id().to_string();
+        if let Ok(mut all) = self.attachments.lock() {
+            all.insert(id.clone(), attachment);
+        }
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(id);
+        }
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id() -> String {
+    let millis = chrono::Utc::now().timestamp_millis();
+    format!("att-{millis:x}-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+pub fn ingest_file(path: &Path) -> anyhow::Result<Attachment> {
+    let meta = std::fs::metadata(path)?;
+    if !meta.is_file() {
+        anyhow::bail!("{} is not a file", path.display());
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let size = meta.len();
+    let mut mime_type = mime_from_extension(path).to_string();
+
+    let (by_reference, preview) = if size > MAX_INLINE_BYTES {
+        (true, AttachmentPreview::None)
+    } else if mime_type.starts_with("image/") {
+        (false, image_preview(path).unwrap_or(AttachmentPreview::None))
+    } else if let Some((text, truncated)) = read_text(path)? {
+        if mime_type == "application/octet-stream" {
+            mime_type = "text/plain".to_string();
+        }
+        (false, AttachmentPreview::Text { text, truncated })
+    } else {
+        (false, AttachmentPreview::None)
+    };
+
+    Ok(Attachment::File(FileAttachment {
+        id: next_id(),
+        name,
+        path: path.to_path_buf(),
+        mime_type,
+        size,
+        by_reference,
+        preview,
+    }))
+}
+
+// Returns None for binary content.
+fn read_text(path: &Path) -> anyhow::Result<Option<(String, bool)>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut head = vec![0u8; SNIFF_BYTES];
+    let n = file.read(&mut head)?;
+    head.truncate(n);
+    if head.contains(&0) {
+        return Ok(None);
+    }
+
+    // Read only as many bytes as the char limit could possibly need.
+    let mut bytes = head;
+    file.take((MAX_TEXT_CHARS * 4) as u64).read_to_end(&mut bytes)?;
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(t) => t.to_string(),
+        // A multi-byte char cut at the read boundary is fine; anything else isn't text.
+        Err(e) if e.error_len().is_none() => String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(),
+        Err(_) => return Ok(None),
+    };
+    let file_len = std::fs::metadata(path)?.len() as usize;
+    Ok(Some(match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((idx, _)) => (text[..idx].to_string(), true),
+        None => (text, bytes.len() < file_len),
+    }))
+}
+
+#[cfg(feature = "capture")]
+fn image_preview(path: &Path) -> anyhow::Result<AttachmentPreview> {
+    let img = image::open(path)?;
+    let thumb = img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut png = Vec::new();
+    thumb.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(AttachmentPreview::Image {
+        data_url: format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        ),
+        width: thumb.width(),
+        height: thumb.height(),
+    })
+}
+
+// Built without the `capture` feature there's no decoder, so images are
+// attached without a thumbnail.
+#[cfg(not(feature = "capture"))]
+fn image_preview(_path: &Path) -> anyhow::Result<AttachmentPreview> {
+    Ok(AttachmentPreview::None)
+}
+
+fn mime_from_extension(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "html" | "htm" => "text/html",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "rs" | "py" | "js" | "ts" | "c" | "cpp" | "h" | "java" | "go" | "toml" | "yaml" | "yml" => {
+            "text/plain"
+        }
+        _ => "application/octet-stream",
+    }
+}
+
+#[tauri::command]
+pub fn take_pending_attachments(state: State<'_, AttachmentStore>) -> CommandResult<Vec<Attachment>> {
+    let ids = std::mem::take(&mut *state.pending.lock()?);
+    let all = state.attachments.lock()?;
+    Ok(ids.iter().filter_map(|id| all.get(id).cloned()).collect())
+}
+
+#[tauri::command]
+pub fn remove_attachment(state: State<'_, AttachmentStore>, id: String) -> CommandResult<()> {
+    state.attachments.lock()?.remove(&id);
+    state.pending.lock()?.retain(|p| p != &id);
+    Ok(())
+}
@@ -0,0 +1,454 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::Sample;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::command_stats;
+use crate::error::CommandResult;
+use crate::events::{emit_event, GraviaEvent, RecordingLimitReached};
+use crate::settings::SharedSettings;
+
+const OUTPUT_SAMPLE_RATE: u32 = 16_000;
+const LEVEL_FRAME: Duration = Duration::from_millis(50);
+// ~15 `mic-level` events a second.
+const LEVEL_EMIT_INTERVAL: Duration = Duration::from_millis(66);
+const CLIP_THRESHOLD: f32 = 0.99;
+// `level` maps this range of dBFS onto 0..1.
+const LEVEL_FLOOR_DB: f32 = -60.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AudioError {
+    #[error("microphone access has not been allowed in settings")]
+    MicrophoneDisabled,
+    #[error("no audio input device is available")]
+    NoDevice,
+    #[error("input device `{0}` was not found")]
+    DeviceNotFound(String),
+    #[error("a recording is already in progress")]
+    AlreadyRecording,
+    #[error("no recording is in progress")]
+    NotRecording,
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl AudioError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AudioError::MicrophoneDisabled => "microphone_disabled",
+            AudioError::NoDevice => "no_device",
+            AudioError::DeviceNotFound(_) => "device_not_found",
+            AudioError::AlreadyRecording => "already_recording",
+            AudioError::NotRecording => "not_recording",
+            AudioError::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct AudioDevice {
+    // Device names are the only stable handle cpal gives us.
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct Recording {
+    pub path: PathBuf,
+    #[ts(type = "number")]
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct MicLevel {
+    // Linear, 0..1.
+    pub rms: f32,
+    pub peak: f32,
+    // RMS on a dB scale normalized to 0..1, which reads better as a meter.
+    pub level: f32,
+    pub clipping: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct MicSilence {
+    #[ts(type = "number")]
+    pub silent_ms: u64,
+}
+
+// Written by the audio callback once per level frame and read by the
+// recorder thread, so the callback never allocates or emits itself.
+#[derive(Default)]
+struct LevelMeter {
+    rms: AtomicU32,
+    peak: AtomicU32,
+    clipping: AtomicBool,
+    frames: AtomicU64,
+    // Frames whose RMS was above the silence threshold.
+    loud_frames: AtomicU64,
+}
+
+impl LevelMeter {
+    fn publish(&self, rms: f32, peak: f32, clipping: bool, loud: bool) {
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+        self.clipping.store(clipping, Ordering::Relaxed);
+        if loud {
+            self.loud_frames.fetch_add(1, Ordering::Relaxed);
+        }
+        self.frames.fetch_add(1, Ordering::Release);
+    }
+
+    fn read(&self) -> MicLevel {
+        let rms = f32::from_bits(self.rms.load(Ordering::Relaxed));
+        let peak = f32::from_bits(self.peak.load(Ordering::Relaxed));
+        let db = 20.0 * rms.max(1e-6).log10();
+        MicLevel {
+            rms,
+            peak,
+            level: ((db - LEVEL_FLOOR_DB) / -LEVEL_FLOOR_DB).clamp(0.0, 1.0),
+            clipping: self.clipping.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct RecordOptions {
+    device_id: Option<String>,
+    max: Duration,
+    silence_threshold: f32,
+    silence_duration: Duration,
+}
+
+// Mono samples at the device's rate, handed back by the capture thread.
+struct Captured {
+    samples: Vec<f32>,
+    sample_rate: u32,
+}
+
+struct ActiveRecording {
+    window: String,
+    stop: Sender<()>,
+    thread: JoinHandle<Captured>,
+}
+
+// cpal streams aren't Send, so each recording owns a thread that holds the
+// stream until told to stop.
+#[derive(Default)]
+pub struct AudioRecorder(Mutex<Option<ActiveRecording>>);
+
+impl AudioRecorder {
+    fn finish(&self) -> Result<Option<(String, Captured)>, AudioError> {
+        let active = self.0.lock().map_err(|e| AudioError::Failed(e.to_string()))?.take();
+        let Some(active) = active else { return Ok(None) };
+        let _ = active.stop.send(());
+        let captured = active
+            .thread
+            .join()
+            .map_err(|_| AudioError::Failed("recording thread panicked".into()))?;
+        Ok(Some((active.window, captured)))
+    }
+}
+
+fn find_device(device_id: Option<&str>) -> Result<cpal::Device, AudioError> {
+    let host = cpal::default_host();
+    match device_id {
+        None => host.default_input_device().ok_or(AudioError::NoDevice),
+        Some(id) => host
+            .input_devices()
+            .map_err(|e| AudioError::Failed(e.to_string()))?
+            .find(|d| d.name().is_ok_and(|n| n == id))
+            .ok_or_else(|| AudioError::DeviceNotFound(id.to_string())),
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    buffer: Arc<Mutex<Vec<f32>>>,
+    meter: Arc<LevelMeter>,
+    silence_threshold: f32,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let channels = usize::from(config.channels.max(1));
+    let frame_len = (config.sample_rate.0 as usize * LEVEL_FRAME.as_millis() as usize / 1000).max(1);
+    let (mut sum_sq, mut peak, mut count) = (0.0f32, 0.0f32, 0usize);
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            // The buffer is preallocated for the maximum duration, so pushing
+            // never reallocates on the audio thread.
+            let mut buf = buffer.try_lock().ok();
+            for frame in data.chunks(channels) {
+                let sum: f32 = frame.iter().map(|&s| s.to_sample::<f32>()).sum();
+                let sample = sum / frame.len() as f32;
+                if let Some(buf) = buf.as_mut().filter(|b| b.len() < b.capacity()) {
+                    buf.push(sample);
+                }
+
+                sum_sq += sample * sample;
+                peak = peak.max(sample.abs());
+                count += 1;
+                if count == frame_len {
+                    let rms = (sum_sq / count as f32).sqrt();
+                    meter.publish(rms, peak, peak >= CLIP_THRESHOLD, rms >= silence_threshold);
+                    (sum_sq, peak, count) = (0.0, 0.0, 0);
+                }
+            }
+        },
+        |e| tracing::warn!(error = %e, "audio input stream error"),
+        None,
+    )
+}
+
+// Opens the device and records until `stop` fires or `max` elapses, emitting
+// `mic-level` and `mic-silence` along the way. Setup errors are reported
+// through `ready` before anything is recorded.
+fn record(
+    app: AppHandle,
+    options: RecordOptions,
+    stop: mpsc::Receiver<()>,
+    ready: Sender<Result<(), AudioError>>,
+) -> Captured {
+    let RecordOptions { device_id, max, silence_threshold, silence_duration } = options;
+    let meter = Arc::new(LevelMeter::default());
+    let setup = || -> Result<(cpal::Stream, Arc<Mutex<Vec<f32>>>, u32), AudioError> {
+        let device = find_device(device_id.as_deref())?;
+        let supported = device
+            .default_input_config()
+            .map_err(|e| AudioError::Failed(e.to_string()))?;
+        let config = supported.config();
+        let sample_rate = config.sample_rate.0;
+        let capacity = sample_rate as usize * max.as_secs().max(1) as usize;
+        let buffer = Arc::new(Mutex::new(Vec::with_capacity(capacity)));
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => {
+                build_stream::<f32>(&device, &config, buffer.clone(), meter.clone(), silence_threshold)
+            }
+            cpal::SampleFormat::I16 => {
+                build_stream::<i16>(&device, &config, buffer.clone(), meter.clone(), silence_threshold)
+            }
+            cpal::SampleFormat::U16 => {
+                build_stream::<u16>(&device, &config, buffer.clone(), meter.clone(), silence_threshold)
+            }
+            other => return Err(AudioError::Failed(format!("unsupported sample format {other}"))),
+        }
+        .map_err(|e| AudioError::Failed(e.to_string()))?;
+        stream.play().map_err(|e| AudioError::Failed(e.to_string()))?;
+        Ok((stream, buffer, sample_rate))
+    };
+
+    let (stream, buffer, sample_rate) = match setup() {
+        Ok(parts) => {
+            let _ = ready.send(Ok(()));
+            parts
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return Captured { samples: Vec::new(), sample_rate: OUTPUT_SAMPLE_RATE };
+        }
+    };
+
+    let started = Instant::now();
+    let (mut seen_frames, mut seen_loud) = (0u64, 0u64);
+    let mut last_sound = started;
+    let mut silence_reported = false;
+    loop {
+        match stop.recv_timeout(LEVEL_EMIT_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if started.elapsed() >= max {
+            tracing::info!(?max, "recording hit its maximum duration");
+            emit_event(&app, GraviaEvent::AudioRecordingLimitReached(RecordingLimitReached { max_secs: max.as_secs() })).ok();
+            // Keep the thread (and its result) around until someone stops it.
+            let _ = stop.recv();
+            break;
+        }
+
+        let frames = meter.frames.load(Ordering::Acquire);
+        if frames == seen_frames {
+            continue;
+        }
+        seen_frames = frames;
+        emit_event(&app, GraviaEvent::MicLevel(meter.read())).ok();
+
+        let loud = meter.loud_frames.load(Ordering::Relaxed);
+        if loud != seen_loud {
+            seen_loud = loud;
+            last_sound = Instant::now();
+            silence_reported = false;
+        } else if !silence_reported && last_sound.elapsed() >= silence_duration {
+            silence_reported = true;
+            let silent_ms = last_sound.elapsed().as_millis() as u64;
+            emit_event(&app, GraviaEvent::MicSilence(MicSilence { silent_ms })).ok();
+        }
+    }
+    drop(stream);
+    let samples = buffer.lock().map(|mut b| std::mem::take(&mut *b)).unwrap_or_default();
+    Captured { samples, sample_rate }
+}
+
+// Linear interpolation; good enough for speech going to a recognizer.
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = f64::from(from) / f64::from(to);
+    let len = (samples.len() as f64 / ratio) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+fn write_wav(path: &std::path::Path, samples: &[f32]) -> Result<(), AudioError> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: OUTPUT_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).map_err(|e| AudioError::Failed(e.to_string()))?;
+    for &s in samples {
+        let value = (s.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+        writer.write_sample(value).map_err(|e| AudioError::Failed(e.to_string()))?;
+    }
+    writer.finalize().map_err(|e| AudioError::Failed(e.to_string()))
+}
+
+// Stops and discards a recording started from `window`, e.g. when it closes.
+pub fn cancel_for_window(app: &AppHandle, window: &str) {
+    let recorder = app.state::<AudioRecorder>();
+    let started_here = recorder
+        .0
+        .lock()
+        .map(|a| a.as_ref().is_some_and(|a| a.window == window))
+        .unwrap_or(false);
+    if started_here {
+        if let Err(e) = recorder.finish() {
+            tracing::warn!(error = %e, "failed to cancel recording");
+        }
+        tracing::info!(window, "recording discarded, window closed");
+    }
+}
+
+// Device enumeration can take a while on some drivers.
+#[tauri::command]
+pub async fn list_audio_devices() -> CommandResult<Vec<AudioDevice>> {
+    tauri::async_runtime::spawn_blocking(input_devices).await?
+}
+
+fn input_devices() -> CommandResult<Vec<AudioDevice>> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    let devices = host
+        .input_devices()
+        .map_err(|e| AudioError::Failed(e.to_string()))?
+        .filter_map(|d| d.name().ok())
+        .map(|name| AudioDevice {
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            id: name.clone(),
+            name,
+        })
+        .collect();
+    Ok(devices)
+}
+
+#[tauri::command]
+pub async fn start_audio_recording(
+    app: AppHandle,
+    window: tauri::Window,
+    device_id: Option<String>,
+) -> CommandResult<()> {
+    command_stats::track(&app, "start_audio_recording", async {
+        // Opening the device blocks until the recorder thread reports back.
+        let label = window.label().to_string();
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || begin_recording(app, label, device_id)).await?
+    })
+    .await
+}
+
+fn begin_recording(app: AppHandle, window: String, device_id: Option<String>) -> CommandResult<()> {
+    let (allowed, max_secs, options) = {
+        let settings = app.state::<SharedSettings>();
+        let settings = settings.0.lock()?;
+        let options = RecordOptions {
+            device_id,
+            max: Duration::from_secs(settings.max_recording_secs),
+            silence_threshold: settings.silence_threshold,
+            silence_duration: Duration::from_millis(settings.silence_duration_ms),
+        };
+        (settings.allow_microphone, settings.max_recording_secs, options)
+    };
+    if !allowed {
+        return Err(AudioError::MicrophoneDisabled.into());
+    }
+
+    let state = app.state::<AudioRecorder>();
+    let mut active = state.0.lock()?;
+    if active.is_some() {
+        return Err(AudioError::AlreadyRecording.into());
+    }
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let thread_app = app.clone();
+    let thread = std::thread::Builder::new()
+        .name("audio-recorder".into())
+        .spawn(move || record(thread_app, options, stop_rx, ready_tx))
+        .map_err(|e| AudioError::Failed(e.to_string()))?;
+    ready_rx
+        .recv()
+        .map_err(|_| AudioError::Failed("recording thread exited early".into()))??;
+
+    *active = Some(ActiveRecording { window, stop: stop_tx, thread });
+    tracing::info!(max_secs, "recording started");
+    Ok(())
+}
+
+// Writes the recording to a 16kHz mono WAV in the cache dir and returns its
+// path; the caller owns the file from then on.
+#[tauri::command]
+pub async fn stop_audio_recording(app: AppHandle) -> CommandResult<Recording> {
+    command_stats::track(&app, "stop_audio_recording", async {
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || save_recording(&app)).await?
+    })
+    .await
+}
+
+fn save_recording(app: &AppHandle) -> CommandResult<Recording> {
+    let started = Instant::now();
+    let (_, captured) = app.state::<AudioRecorder>().finish()?.ok_or(AudioError::NotRecording)?;
+    let samples = resample(&captured.samples, captured.sample_rate, OUTPUT_SAMPLE_RATE);
+
+    let dir = app.path().app_cache_dir()?.join("recordings");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("rec-{}.wav", chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+    write_wav(&path, &samples)?;
+
+    let duration_ms = samples.len() as u64 * 1000 / u64::from(OUTPUT_SAMPLE_RATE);
+    tracing::info!(duration_ms, encode_ms = started.elapsed().as_millis() as u64, "recording saved");
+    Ok(Recording { path, duration_ms, sample_rate: OUTPUT_SAMPLE_RATE })
+}
@@ -0,0 +1,208 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Notify};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use ts_rs::TS;
+
+use crate::command_stats;
+use crate::connectivity::ConnectivityMonitor;
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::lifecycle::SERVER_PORT;
+use crate::server::{self, ServerStatus, ServerSupervisor};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const MAX_QUEUED: usize = 256;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+// What `send_backend_message` does while the socket is down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum WhenDisconnected {
+    #[default]
+    Queue,
+    Error,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SocketError {
+    #[error("not connected to the backend")]
+    Disconnected,
+    #[error("too many messages are waiting for the backend")]
+    QueueFull,
+}
+
+impl SocketError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SocketError::Disconnected => "disconnected",
+            SocketError::QueueFull => "queue_full",
+        }
+    }
+}
+
+// Rust-owned connection to the server's chat socket. Outbound messages go
+// through a bounded channel, so whatever is sent while disconnected waits
+// there until the next connection.
+pub struct BackendSocket {
+    outbound: mpsc::Sender<String>,
+    state: Mutex<ConnectionState>,
+    // Signalled by the sidecar lifecycle so we react to restarts right away
+    // instead of waiting out the backoff.
+    server_ready: Notify,
+    server_stopped: Notify,
+}
+
+impl BackendSocket {
+    pub fn state(&self) -> ConnectionState {
+        self.state.lock().map(|s| *s).unwrap_or(ConnectionState::Disconnected)
+    }
+
+    pub fn notify_server_ready(&self) {
+        self.server_ready.notify_one();
+    }
+
+    // Only wakes a live connection; a stop seen while disconnected must not
+    // linger and cut the next connection short.
+    pub fn notify_server_stopped(&self) {
+        self.server_stopped.notify_waiters();
+    }
+}
+
+fn set_state(app: &AppHandle, state: ConnectionState) {
+    let socket = app.state::<BackendSocket>();
+    let Ok(mut current) = socket.state.lock() else { return };
+    if *current != state {
+        *current = state;
+        emit_event(app, GraviaEvent::BackendConnectionChanged(state)).ok();
+    }
+}
+
+pub fn start(app: &AppHandle) {
+    let (outbound, rx) = mpsc::channel(MAX_QUEUED);
+    app.manage(BackendSocket {
+        outbound,
+        state: Mutex::new(ConnectionState::Disconnected),
+        server_ready: Notify::new(),
+        server_stopped: Notify::new(),
+    });
+    tauri::async_runtime::spawn(run(app.clone(), rx));
+}
+
+async fn run(app: AppHandle, mut rx: mpsc::Receiver<String>) {
+    let url = format!("ws://127.0.0.1:{SERVER_PORT}/chat/ws");
+    let mut backoff = INITIAL_BACKOFF;
+    // With lazy start there's nothing to connect to until someone asks.
+    app.state::<ServerSupervisor>().wait_until_requested().await;
+    loop {
+        set_state(&app, ConnectionState::Connecting);
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((stream, _)) => {
+                tracing::info!(%url, "backend socket connected");
+                backoff = INITIAL_BACKOFF;
+                set_state(&app, ConnectionState::Connected);
+                if !pump(&app, stream, &mut rx).await {
+                    return;
+                }
+            }
+            Err(e) => tracing::debug!(%url, error = %e, "backend socket connect failed"),
+        }
+        set_state(&app, ConnectionState::Disconnected);
+
+        let wait = backoff;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        let socket = app.state::<BackendSocket>();
+        let connectivity = app.state::<ConnectivityMonitor>();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = socket.server_ready.notified() => backoff = INITIAL_BACKOFF,
+            _ = connectivity.wait_restored() => backoff = INITIAL_BACKOFF,
+        }
+    }
+}
+
+// Shuttles messages until the connection drops. Returns false once the
+// outbound channel is closed, i.e. the app is going away.
+async fn pump(app: &AppHandle, stream: WsStream, rx: &mut mpsc::Receiver<String>) -> bool {
+    let socket = app.state::<BackendSocket>();
+    let (mut write, mut read) = stream.split();
+    loop {
+        tokio::select! {
+            inbound = read.next() => match inbound {
+                Some(Ok(Message::Text(text))) => {
+                    // Forward JSON as JSON so listeners don't parse twice.
+                    let payload = serde_json::from_str::<serde_json::Value>(&text)
+                        .unwrap_or(serde_json::Value::String(text));
+                    emit_event(app, GraviaEvent::BackendMessage(payload)).ok();
+                }
+                Some(Ok(Message::Close(_))) | None => return true,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    tracing::warn!(error = %e, "backend socket read failed");
+                    return true;
+                }
+            },
+            outbound = rx.recv() => match outbound {
+                Some(text) => {
+                    if let Err(e) = write.send(Message::Text(text)).await {
+                        tracing::warn!(error = %e, "backend socket write failed, message dropped");
+                        return true;
+                    }
+                }
+                None => return false,
+            },
+            _ = socket.server_stopped.notified() => return true,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn send_backend_message(
+    app: AppHandle,
+    state: State<'_, BackendSocket>,
+    connectivity: State<'_, ConnectivityMonitor>,
+    payload: serde_json::Value,
+    when_disconnected: Option<WhenDisconnected>,
+) -> CommandResult<()> {
+    command_stats::track_sync(&app, "send_backend_message", || {
+        // Anything sent now would only time out upstream, so say so up front.
+        if connectivity.is_offline() {
+            return Err(GraviaError::Offline);
+        }
+        // First use under lazy start; the message queues until the server is up.
+        if app.state::<ServerSupervisor>().status() == ServerStatus::NotStartedLazy {
+            server::request_start(&app)?;
+        }
+        if state.state() != ConnectionState::Connected
+            && when_disconnected.unwrap_or_default() == WhenDisconnected::Error
+        {
+            return Err(SocketError::Disconnected.into());
+        }
+        state.outbound.try_send(payload.to_string()).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => GraviaError::from(SocketError::QueueFull),
+            mpsc::error::TrySendError::Closed(_) => GraviaError::internal("backend socket task has stopped"),
+        })
+    })
+}
+
+#[tauri::command]
+pub fn get_backend_connection_state(state: State<'_, BackendSocket>) -> ConnectionState {
+    state.state()
+}
@@ -0,0 +1,146 @@
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// Base64-encodes whatever is written to it, so an encoder can produce the
+// final string in one pass without an intermediate byte buffer. Input is
+// encoded in 3-byte groups; up to two trailing bytes wait in `pending` for
+// the next write or `finish`. The input is hashed on the way through, for
+// `finish_with_hash`.
+pub struct Base64Writer {
+    out: String,
+    hasher: Sha256,
+    pending: [u8; 3],
+    pending_len: usize,
+    // Spent encoding, as opposed to waiting on whatever feeds the writer.
+    busy: Duration,
+}
+
+impl Base64Writer {
+    // `expected_len` is a guess at the input size, to size the string.
+    pub fn with_capacity(expected_len: usize) -> Self {
+        Self {
+            out: String::with_capacity(base64::encoded_len(expected_len, true).unwrap_or(0)),
+            hasher: Sha256::new(),
+            pending: [0; 3],
+            pending_len: 0,
+            busy: Duration::ZERO,
+        }
+    }
+
+    // Raw bytes written so far.
+    pub fn input_len(&self) -> usize {
+        self.out.len() / 4 * 3 + self.pending_len
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.out.capacity()
+    }
+
+    // Time spent in base64 so far.
+    pub fn busy(&self) -> Duration {
+        self.busy
+    }
+
+    // Encodes the leftover bytes, with padding, and returns the string.
+    pub fn finish(self) -> String {
+        self.finish_with_hash().0
+    }
+
+    // As `finish`, along with the lowercase hex SHA-256 of the raw bytes.
+    pub fn finish_with_hash(mut self) -> (String, String) {
+        if self.pending_len > 0 {
+            base64::engine::general_purpose::STANDARD.encode_string(&self.pending[..self.pending_len], &mut self.out);
+        }
+        (self.out, format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+// Lowercase hex SHA-256 of `bytes`, as `finish_with_hash` gives it.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+impl Write for Base64Writer {
+    fn write(&mut self, mut buf: &[u8]) -> std::io::Result<usize> {
+        let written = buf.len();
+        let started = Instant::now();
+        let engine = &base64::engine::general_purpose::STANDARD;
+        self.hasher.update(buf);
+        if self.pending_len > 0 {
+            let take = (3 - self.pending_len).min(buf.len());
+            self.pending[self.pending_len..self.pending_len + take].copy_from_slice(&buf[..take]);
+            self.pending_len += take;
+            buf = &buf[take..];
+            if self.pending_len < 3 {
+                self.busy += started.elapsed();
+                return Ok(written);
+            }
+            engine.encode_string(self.pending, &mut self.out);
+            self.pending_len = 0;
+        }
+        // Whole groups encode without padding, so they can be appended as is.
+        let whole = buf.len() / 3 * 3;
+        engine.encode_string(&buf[..whole], &mut self.out);
+        let rest = &buf[whole..];
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+        self.busy += started.elapsed();
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::png::PngEncoder;
+    use image::{ExtendedColorType, ImageEncoder};
+
+    fn stream(bytes: &[u8], chunk: usize) -> (String, String) {
+        let mut writer = Base64Writer::with_capacity(bytes.len());
+        for piece in bytes.chunks(chunk) {
+            writer.write_all(piece).unwrap();
+        }
+        assert_eq!(writer.input_len(), bytes.len());
+        writer.finish_with_hash()
+    }
+
+    #[test]
+    fn png_streamed_straight_from_the_encoder_matches_standard_base64() {
+        let (width, height) = (13u32, 7u32);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| (i * 37 % 251) as u8).collect();
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).write_image(&rgba, width, height, ExtendedColorType::Rgba8).unwrap();
+        let mut writer = Base64Writer::with_capacity(0);
+        PngEncoder::new(&mut writer).write_image(&rgba, width, height, ExtendedColorType::Rgba8).unwrap();
+        let (base64, hash) = writer.finish_with_hash();
+        assert_eq!(base64, base64::engine::general_purpose::STANDARD.encode(&png));
+        assert_eq!(hash, content_hash(&png));
+    }
+
+    #[test]
+    fn every_tail_length_and_chunking_matches_standard_base64() {
+        // 30, 31 and 32 bytes leave 0, 1 and 2 bytes for the padded tail.
+        for len in [30, 31, 32] {
+            let bytes: Vec<u8> = (0..len as u8).map(|b| b.wrapping_mul(91)).collect();
+            let expected = base64::engine::general_purpose::STANDARD.encode(&bytes);
+            for chunk in 1..=7 {
+                let (base64, hash) = stream(&bytes, chunk);
+                assert_eq!(base64, expected, "len {len}, chunk {chunk}");
+                assert_eq!(hash, content_hash(&bytes));
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_written_finishes_empty() {
+        let (base64, hash) = Base64Writer::with_capacity(16).finish_with_hash();
+        assert_eq!(base64, "");
+        assert_eq!(hash, content_hash(&[]));
+    }
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use ts_rs::TS;
+
+#[cfg(feature = "capture")]
+const MAX_FREE_BUFFERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, TS)]
+#[ts(export)]
+pub struct PoolStats {
+    #[ts(type = "number")]
+    pub hits: u64,
+    #[ts(type = "number")]
+    pub misses: u64,
+    #[ts(type = "number")]
+    pub free_buffers: usize,
+    #[ts(type = "number")]
+    pub free_bytes: usize,
+}
+
+// Reusable byte buffers for the capture pipeline, so steady captures of a
+// large display don't allocate several megabytes each time. Buffers are
+// cleared, not freed, between uses; at most `MAX_FREE_BUFFERS` are kept and
+// `release` drops them all once captures stop.
+#[derive(Default)]
+pub struct BufferPool {
+    free: Mutex<Vec<Vec<u8>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BufferPool {
+    // An empty buffer with room for at least `capacity` bytes.
+    #[cfg(feature = "capture")]
+    pub fn take(&self, capacity: usize) -> Vec<u8> {
+        let reused = self.free.lock().ok().and_then(|mut free| {
+            // Smallest buffer that fits, else the largest one to grow.
+            let index = free
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.capacity() >= capacity)
+                .min_by_key(|(_, b)| b.capacity())
+                .or_else(|| free.iter().enumerate().max_by_key(|(_, b)| b.capacity()))
+                .map(|(i, _)| i)?;
+            Some(free.swap_remove(index))
+        });
+        match reused {
+            Some(mut buffer) if buffer.capacity() >= capacity => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buffer.clear();
+                buffer
+            }
+            Some(mut buffer) => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                buffer.clear();
+                buffer.reserve(capacity);
+                buffer
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(capacity)
+            }
+        }
+    }
+
+    // Hands a buffer back. When the pool is full the smallest buffer is
+    // dropped, so what stays matches the largest recent captures.
+    #[cfg(feature = "capture")]
+    pub fn give(&self, buffer: Vec<u8>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+        let Ok(mut free) = self.free.lock() else { return };
+        free.push(buffer);
+        if free.len() > MAX_FREE_BUFFERS {
+            if let Some(smallest) = free.iter().enumerate().min_by_key(|(_, b)| b.capacity()).map(|(i, _)| i) {
+                free.swap_remove(smallest);
+            }
+        }
+    }
+
+    #[cfg(feature = "capture")]
+    pub fn release(&self) {
+        if let Ok(mut free) = self.free.lock() {
+            free.clear();
+            free.shrink_to_fit();
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let (free_buffers, free_bytes) = self
+            .free
+            .lock()
+            .map(|free| (free.len(), free.iter().map(Vec::capacity).sum()))
+            .unwrap_or_default();
+        PoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            free_buffers,
+            free_bytes,
+        }
+    }
+}
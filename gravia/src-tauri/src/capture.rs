@@ -0,0 +1,2647 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tokio_util::sync::CancellationToken;
+use ts_rs::TS;
+
+use crate::buffer_pool::{BufferPool, PoolStats};
+use crate::capture_history::{CaptureHistory, StoredCapture};
+use crate::command_stats;
+use crate::error::{CommandResult, GraviaError, SettingsError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::foreground::{self, WindowRect};
+use crate::metrics::{self, Counter, Histogram};
+#[cfg(feature = "capture")]
+use crate::payload_guard::{self, PayloadLimit};
+use crate::screen_analysis::ScreenAnalysis;
+#[cfg(feature = "capture")]
+use crate::screen_hash;
+#[cfg(feature = "capture")]
+use crate::screen_grab::{grab_all_hiding_window, grab_burst_hiding_window, grab_hiding_window};
+use crate::settings::{self, SharedSettings};
+use crate::tray;
+use crate::window_list;
+
+// Pooled capture buffers are freed after this long without a capture.
+#[cfg(feature = "capture")]
+const POOL_IDLE_RELEASE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("screen capture is paused")]
+    Paused,
+    #[error("screen capture is not available in this build")]
+    Unavailable,
+    // macOS only grants it through System Settings > Privacy & Security >
+    // Screen Recording.
+    #[error("Gravia isn't allowed to record the screen")]
+    PermissionDenied,
+    #[error("no display was found")]
+    NoDisplay,
+    #[error("could not convert the captured pixels: {0}")]
+    ConversionFailed(String),
+    #[error("could not encode the capture: {0}")]
+    EncodeFailed(String),
+    #[error("capture {0} was not found or has been purged")]
+    CaptureNotFound(String),
+    #[error("screen {0} is not connected; list the screens again")]
+    ScreenNotFound(u32),
+    #[error("screen {0} was disconnected; list the screens again")]
+    ScreenRemoved(u32),
+    #[error("invalid capture region: {0}")]
+    InvalidRegion(String),
+    #[error("no window matches \"{0}\"")]
+    WindowNotFound(String),
+    #[error("\"{matcher}\" matches several windows: {}", .candidates.join(", "))]
+    AmbiguousWindow { matcher: String, candidates: Vec<String> },
+    #[error("too many captures, retry in {retry_after_ms}ms")]
+    RateLimited { retry_after_ms: u64 },
+    #[error("capture is {bytes} bytes encoded, over the {limit} byte limit; open it as a file instead")]
+    PayloadTooLarge { bytes: usize, limit: usize },
+    #[error("capture was cancelled")]
+    Cancelled,
+    // The window is shown again; the grab is left to finish on its own.
+    #[error("capture took longer than {timeout_ms}ms")]
+    Timeout { timeout_ms: u64 },
+    #[error("a capture stream is already running")]
+    StreamActive,
+    #[error("\"{0}\" is on the capture blocklist")]
+    BlockedApp(String),
+    // Nothing was captured, so Gravia isn't in the shot.
+    #[error("could not hide the window for the capture: {0}")]
+    WindowHideFailed(String),
+    // The window may still be hidden; showing it again from the tray or
+    // hotkey works.
+    #[error("could not show the window again after the capture: {0}")]
+    WindowRestoreFailed(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl CaptureError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CaptureError::Paused => "paused",
+            CaptureError::Unavailable => "unavailable",
+            CaptureError::PermissionDenied => "permission_denied",
+            CaptureError::NoDisplay => "no_display",
+            CaptureError::ConversionFailed(_) => "conversion_failed",
+            CaptureError::EncodeFailed(_) => "encode_failed",
+            CaptureError::CaptureNotFound(_) => "capture_not_found",
+            CaptureError::ScreenNotFound(_) => "screen_not_found",
+            CaptureError::ScreenRemoved(_) => "screen_removed",
+            CaptureError::InvalidRegion(_) => "invalid_region",
+            CaptureError::WindowNotFound(_) => "window_not_found",
+            CaptureError::AmbiguousWindow { .. } => "ambiguous_window",
+            CaptureError::RateLimited { .. } => "rate_limited",
+            CaptureError::PayloadTooLarge { .. } => "payload_too_large",
+            CaptureError::Cancelled => "cancelled",
+            CaptureError::Timeout { .. } => "timeout",
+            CaptureError::StreamActive => "stream_active",
+            CaptureError::BlockedApp(_) => "blocked_app",
+            CaptureError::WindowHideFailed(_) => "window_hide_failed",
+            CaptureError::WindowRestoreFailed(_) => "window_restore_failed",
+            CaptureError::Failed(_) => "failed",
+        }
+    }
+
+    // Failures that tend to clear up on their own, e.g. a grab right after
+    // the workstation is unlocked or while the display mode is switching.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            CaptureError::NoDisplay
+                | CaptureError::ConversionFailed(_)
+                | CaptureError::WindowHideFailed(_)
+                | CaptureError::Failed(_)
+        )
+    }
+}
+
+// A connected display. `id` stays the same across calls while the display
+// is connected, so it can be handed back to pick a screen.
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct ScreenInfo {
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+// How a capture was changed to fit under `max_ipc_payload_bytes`. The
+// full-size image stays in the capture history either way.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PayloadAdjustment {
+    #[ts(type = "number")]
+    pub original_bytes: usize,
+    #[ts(type = "number")]
+    pub bytes: usize,
+    pub scale: f32,
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    // Of the shrunk image; the capture's own `content_hash` stays that of
+    // the full-size one.
+    pub content_hash: String,
+}
+
+// One display from `capture_all_screens_base64`. A display that failed has
+// `error` set; one that succeeded has `capture_id` and, unless left out
+// of a classify response, `base64`.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ScreenCapture {
+    pub screen_id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub capture_id: Option<String>,
+    #[ts(type = "string | null")]
+    pub base64: Option<Arc<String>>,
+    pub format: CaptureFormat,
+    // Encoded image size, before base64.
+    #[ts(type = "number | null")]
+    pub bytes: Option<usize>,
+    // SHA-256 of the encoded image, in hex.
+    pub content_hash: Option<String>,
+    // The grabber that produced the image; `None` when the display failed.
+    pub backend: Option<CaptureBackend>,
+    pub downscale: Option<Downscale>,
+    #[ts(type = "string | null")]
+    pub thumbnail_base64: Option<Arc<String>>,
+    pub window_handling: WindowHandling,
+    pub adjustment: Option<PayloadAdjustment>,
+    // Looks the same as this display's previous capture.
+    pub unchanged: bool,
+    // Redaction rectangles that fell on this display.
+    #[ts(type = "number")]
+    pub redacted: usize,
+    pub analysis: Option<ScreenAnalysis>,
+    pub scale: Option<DisplayScale>,
+    pub budget: Option<ByteBudget>,
+    pub timings: Option<CaptureTimings>,
+    pub error: Option<String>,
+    // `CaptureError` code of `error`, e.g. `encode_failed`.
+    pub error_code: Option<String>,
+}
+
+// One frame of `capture_burst_base64`. A frame that failed has `error` set
+// and no `capture_id`.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct BurstFrame {
+    pub index: u8,
+    pub captured_at: DateTime<Utc>,
+    // Since the burst's first grab started.
+    #[ts(type = "number")]
+    pub offset_ms: u64,
+    pub capture_id: Option<String>,
+    #[ts(type = "string | null")]
+    pub base64: Option<Arc<String>>,
+    pub format: CaptureFormat,
+    #[ts(type = "number | null")]
+    pub bytes: Option<usize>,
+    pub content_hash: Option<String>,
+    pub downscale: Option<Downscale>,
+    pub adjustment: Option<PayloadAdjustment>,
+    // Looks the same as the frame before it.
+    pub unchanged: bool,
+    #[ts(type = "number")]
+    pub redacted: usize,
+    pub error: Option<String>,
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct BurstCapture {
+    // In the order taken.
+    pub frames: Vec<BurstFrame>,
+    pub window_handling: WindowHandling,
+    // Frames were left out to keep within `MAX_BURST_DURATION`, as grabs
+    // took longer than the interval.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export)]
+pub struct CapturePauseState {
+    pub enabled: bool,
+    pub until: Option<DateTime<Utc>>,
+}
+
+// Do-not-disturb switch for every capture path. `generation` lets an expiry
+// timer tell whether the pause it was started for is still the current one.
+#[derive(Default)]
+pub struct CapturePause(Mutex<(CapturePauseState, u64)>);
+
+impl CapturePause {
+    pub fn is_active(&self) -> bool {
+        self.snapshot().enabled
+    }
+
+    pub fn snapshot(&self) -> CapturePauseState {
+        let Ok(mut guard) = self.0.lock() else {
+            // Fail closed: a poisoned lock must not silently allow captures.
+            return CapturePauseState { enabled: true, until: None };
+        };
+        let (state, _) = &mut *guard;
+        if matches!(state.until, Some(until) if until <= Utc::now()) {
+            *state = CapturePauseState::default();
+        }
+        state.clone()
+    }
+}
+
+fn pause_changed(app: &AppHandle, state: &CapturePauseState) {
+    tray::set_capture_paused(app, state.enabled);
+    emit_event(app, GraviaEvent::CapturePausedChanged(state.clone())).ok();
+}
+
+pub fn set_paused(app: &AppHandle, enabled: bool, until: Option<DateTime<Utc>>) -> CapturePauseState {
+    let pause = app.state::<CapturePause>();
+    let (state, generation) = {
+        let Ok(mut guard) = pause.0.lock() else { return pause.snapshot() };
+        guard.0 = CapturePauseState { enabled, until: until.filter(|_| enabled) };
+        guard.1 += 1;
+        (guard.0.clone(), guard.1)
+    };
+    pause_changed(app, &state);
+
+    if let Some(until) = state.until {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Ok(wait) = (until - Utc::now()).to_std() {
+                tokio::time::sleep(wait).await;
+            }
+            let pause = app.state::<CapturePause>();
+            let expired = match pause.0.lock() {
+                Ok(mut guard) if guard.1 == generation => {
+                    guard.0 = CapturePauseState::default();
+                    true
+                }
+                _ => false,
+            };
+            if expired {
+                pause_changed(&app, &CapturePauseState::default());
+            }
+        });
+    }
+    state
+}
+
+#[tauri::command]
+pub fn set_capture_paused(
+    app: AppHandle,
+    enabled: bool,
+    until: Option<DateTime<Utc>>,
+) -> CapturePauseState {
+    set_paused(&app, enabled, until)
+}
+
+#[tauri::command]
+pub fn get_capture_paused(state: State<'_, CapturePause>) -> CapturePauseState {
+    state.snapshot()
+}
+
+// Apps whose windows are blacked out of every capture.
+#[tauri::command]
+pub fn get_capture_blocklist(state: State<'_, SharedSettings>) -> CommandResult<Vec<String>> {
+    Ok(state.0.lock()?.capture_blocklist.clone())
+}
+
+// Replaces the blocklist with `apps`, process names such as "KeePassXC" or
+// "1Password.exe", matched whole and ignoring case and `.exe`. Only Windows
+// can list other apps' windows; elsewhere the list is kept but has no
+// effect yet. Returns the list as saved.
+#[tauri::command]
+pub fn set_capture_blocklist(
+    app: AppHandle,
+    state: State<'_, SharedSettings>,
+    apps: Vec<String>,
+) -> CommandResult<Vec<String>> {
+    if apps.len() > MAX_BLOCKLIST_APPS {
+        return Err(SettingsError::InvalidValue {
+            key: "capture_blocklist",
+            reason: format!("at most {MAX_BLOCKLIST_APPS} apps"),
+        }
+        .into());
+    }
+    let mut blocklist: Vec<String> = Vec::with_capacity(apps.len());
+    for name in apps {
+        let name = name.trim();
+        if !name.is_empty() && !blocklist.iter().any(|b| b.eq_ignore_ascii_case(name)) {
+            blocklist.push(name.to_string());
+        }
+    }
+    let mut current = state.0.lock()?;
+    let mut updated = current.clone();
+    updated.capture_blocklist = blocklist;
+    settings::save(&app, &updated).map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+    *current = updated;
+    tracing::info!(apps = current.capture_blocklist.len(), "capture blocklist updated");
+    Ok(current.capture_blocklist.clone())
+}
+
+// What to do with a capture request that arrives inside the minimum interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitPolicy {
+    // Hand back the most recent capture instead of grabbing a new one.
+    #[default]
+    Reuse,
+    Error,
+}
+
+// Which screen grabber to try first for the primary display. `Auto` takes
+// DXGI desktop duplication on Windows. Whichever is preferred, a failed grab
+// falls back to the other one, and captures report the one that actually
+// ran. Other displays always use the standard grabber.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureBackend {
+    #[default]
+    Auto,
+    Dxgi,
+    Standard,
+}
+
+// How Gravia's own window was kept out of a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowHandling {
+    // Excluded from capture while staying on screen (Windows 10 2004+).
+    Excluded,
+    // Hidden for the capture and shown again, which can flicker and moves
+    // focus.
+    Hidden,
+    // Left alone because `hide_window` was off.
+    Untouched,
+}
+
+// Image format a capture is encoded in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl CaptureFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::Jpeg => "jpg",
+            CaptureFormat::Webp => "webp",
+        }
+    }
+}
+
+// A rectangle kept out of captures, in physical pixels on the virtual
+// desktop like `capture_region_base64` takes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct RedactRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Where on a capture the `stamp` goes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum StampCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+// How redacted rectangles are covered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactStyle {
+    // Solid black.
+    #[default]
+    Fill,
+    // Blocks coarse enough that text can't be read back.
+    Pixelate,
+}
+
+// How hard the PNG encoder compresses. `fast` takes a fraction of the time
+// of `default` on a 4K frame, for a somewhat larger file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+// The filter PNG rows are run through before compression. `adaptive`
+// picks one per row; the fixed ones are cheaper.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    #[default]
+    Adaptive,
+}
+
+// How a capture is taken and encoded. Every field is optional; the
+// `redact_regions` and `stamp_corner` settings and the capture blocklist
+// are merged in for each capture.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct CaptureOptions {
+    pub format: CaptureFormat,
+    // JPEG quality, 1-100, default 85. WebP is always lossless.
+    pub quality: Option<u8>,
+    // A capture larger than these is scaled down to fit before encoding,
+    // keeping its aspect ratio.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    // Drops color, which mostly just costs bytes for text such as terminals
+    // and editors.
+    pub grayscale: bool,
+    // Draws the mouse pointer into screen and region captures; window
+    // captures never show it.
+    pub include_cursor: bool,
+    // Adds a JPEG preview about 256px on its long edge, made from the same
+    // pixels without decoding the capture again.
+    pub thumbnail: bool,
+    // Reports the mean luminance, dominant colors and whether the capture is
+    // mostly blank, e.g. a locked or switched-off display.
+    pub analyze: bool,
+    // Scales a capture of a scaled display down to its logical size, e.g.
+    // half on each side at 200%, on top of any `max_width`/`max_height`.
+    // Captures without a display scale are left as they are.
+    pub logical_resolution: bool,
+    // PNG encoder effort and row filter, default `default` and `adaptive`;
+    // classify's auto-captures use `fast` unless told otherwise. Other
+    // formats ignore them.
+    pub png_compression: Option<PngCompression>,
+    pub png_filter: Option<PngFilter>,
+    // Caps the base64 length. A capture over it is encoded again, at lower
+    // JPEG quality first and then smaller, until it fits or its long edge is
+    // down to 320px; `budget` says what it took.
+    #[ts(type = "number | null")]
+    pub max_bytes: Option<usize>,
+    // Differing perceptual hash bits (0-64, default 3) within which a capture
+    // counts as `unchanged` from the last one of the same target. Classify
+    // leaves an unchanged image out unless `include_unchanged` is set.
+    pub unchanged_distance: Option<u32>,
+    pub include_unchanged: bool,
+    // Covered in the pixels before anything is encoded; parts off the
+    // captured area are ignored. Window captures aren't redacted.
+    pub redact_regions: Vec<RedactRect>,
+    pub redact_style: RedactStyle,
+    // Labels the capture with when it was taken, the display and the Gravia
+    // version, sized to the image so it reads the same at any resolution.
+    pub stamp: bool,
+    // The `stamp_corner` setting by default.
+    pub stamp_corner: Option<StampCorner>,
+    // Filled in from the capture blocklist; not something callers pass.
+    // Those apps' windows are blacked out of every capture.
+    #[serde(skip)]
+    #[ts(skip)]
+    pub blocked_apps: Vec<String>,
+    // Set by the capture stream, whose frames leave part of the rate limit
+    // for interactive captures. Not something callers pass either.
+    #[serde(skip)]
+    #[ts(skip)]
+    pub background: bool,
+    // Override the settings of the same purpose: whether Gravia's window is
+    // kept out of the capture at all, and how long to wait once it reports
+    // hidden (capped at 2s) when it has to be hidden rather than excluded.
+    pub hide_window: Option<bool>,
+    #[ts(type = "number | null")]
+    pub hide_delay_ms: Option<u64>,
+    // Tries (1-5, default 3) for failures that tend to clear up; 1 turns
+    // retrying off. Permission and other lasting failures are never retried.
+    pub retry_attempts: Option<u32>,
+    // Wait before the first retry (default 100, at most 2000), doubling
+    // after each up to three times that.
+    #[ts(type = "number | null")]
+    pub retry_backoff_ms: Option<u64>,
+    // Always take a fresh capture rather than one from the last
+    // `capture_cache_ttl_ms`.
+    pub force: bool,
+}
+
+// The display a capture came from, at the capture's full size before any
+// downscale. `scale_factor` is the display's physical pixels per logical
+// (CSS) pixel, so a logical position maps into the image by multiplying
+// with `width / logical_width` of the image as sent. Each display of a
+// mixed-DPI setup has its own factor; virtual desktop and window captures
+// have none.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct DisplayScale {
+    pub scale_factor: f32,
+    pub physical_width: u32,
+    pub physical_height: u32,
+    pub logical_width: u32,
+    pub logical_height: u32,
+}
+
+impl DisplayScale {
+    #[cfg(feature = "capture")]
+    pub fn new(scale_factor: f32, physical_width: u32, physical_height: u32) -> Self {
+        // Some platforms report 0 for a display they know nothing about.
+        let scale_factor = if scale_factor > 0.0 { scale_factor } else { 1.0 };
+        let logical = |px: u32| ((px as f64 / scale_factor as f64).round() as u32).max(1);
+        DisplayScale {
+            scale_factor,
+            physical_width,
+            physical_height,
+            logical_width: logical(physical_width),
+            logical_height: logical(physical_height),
+        }
+    }
+}
+
+// How a capture was encoded again to fit its `max_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct ByteBudget {
+    #[ts(type = "number")]
+    pub max_bytes: usize,
+    // Base64 length of the first encoding.
+    #[ts(type = "number")]
+    pub original_bytes: usize,
+    #[ts(type = "number")]
+    pub bytes: usize,
+    // Of the image before the budget, i.e. after any `max_width` and
+    // `max_height`; `downscale` covers both.
+    pub scale: f32,
+    // JPEG quality used; `None` for other formats.
+    pub quality: Option<u8>,
+    pub attempts: u32,
+    // False when even the smallest size allowed didn't fit.
+    pub fits: bool,
+}
+
+// How a capture was scaled down to fit `max_width`/`max_height`, so
+// positions in the image can be mapped back to the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct Downscale {
+    pub original_width: u32,
+    pub original_height: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[cfg(feature = "capture")]
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+// Redaction rectangles accepted per call, on top of the setting's.
+const MAX_REDACT_REGIONS: usize = 64;
+// Apps the capture blocklist holds.
+const MAX_BLOCKLIST_APPS: usize = 64;
+// Bounds and defaults of the `retry_attempts` and `retry_backoff_ms`
+// options.
+const MAX_RETRY_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+const MAX_RETRY_BACKOFF_MS: u64 = 2000;
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 100;
+// Smallest `max_bytes`; below it barely a thumbnail fits.
+const MIN_BUDGET_BYTES: usize = 16 * 1024;
+
+impl CaptureOptions {
+    #[cfg(feature = "capture")]
+    pub fn jpeg_quality(&self) -> u8 {
+        self.quality.unwrap_or(DEFAULT_JPEG_QUALITY)
+    }
+
+    // Just the fields that change the image, for telling whether an earlier
+    // capture can stand in for a new one.
+    pub fn encoding(&self) -> CaptureOptions {
+        CaptureOptions {
+            hide_window: None,
+            hide_delay_ms: None,
+            retry_attempts: None,
+            retry_backoff_ms: None,
+            force: false,
+            background: false,
+            unchanged_distance: None,
+            include_unchanged: false,
+            ..self.clone()
+        }
+    }
+
+    #[cfg(feature = "capture")]
+    pub fn unchanged_distance(&self) -> u32 {
+        self.unchanged_distance.unwrap_or(screen_hash::DEFAULT_UNCHANGED_DISTANCE)
+    }
+
+    pub fn resolve(options: Option<CaptureOptions>) -> Result<CaptureOptions, GraviaError> {
+        let options = options.unwrap_or_default();
+        if matches!(options.quality, Some(q) if q == 0 || q > 100) {
+            return Err(GraviaError::InvalidArgument("quality must be between 1 and 100".into()));
+        }
+        if options.max_width == Some(0) || options.max_height == Some(0) {
+            return Err(GraviaError::InvalidArgument("max_width and max_height must be at least 1".into()));
+        }
+        if options.max_bytes.is_some_and(|b| b < MIN_BUDGET_BYTES) {
+            return Err(GraviaError::InvalidArgument(format!("max_bytes must be at least {MIN_BUDGET_BYTES}")));
+        }
+        if options.unchanged_distance.is_some_and(|d| d > 64) {
+            return Err(GraviaError::InvalidArgument("unchanged_distance must be between 0 and 64".into()));
+        }
+        if options.retry_attempts.is_some_and(|a| a == 0 || a > MAX_RETRY_ATTEMPTS) {
+            return Err(GraviaError::InvalidArgument(format!(
+                "retry_attempts must be between 1 and {MAX_RETRY_ATTEMPTS}"
+            )));
+        }
+        if options.retry_backoff_ms.is_some_and(|ms| ms > MAX_RETRY_BACKOFF_MS) {
+            return Err(GraviaError::InvalidArgument(format!(
+                "retry_backoff_ms must be at most {MAX_RETRY_BACKOFF_MS}"
+            )));
+        }
+        if options.redact_regions.len() > MAX_REDACT_REGIONS {
+            return Err(GraviaError::InvalidArgument(format!("at most {MAX_REDACT_REGIONS} redact_regions")));
+        }
+        Ok(options)
+    }
+
+    // Adds the rectangles from the `redact_regions` setting and the capture
+    // blocklist, which apply to every capture, and the stamp corner unless
+    // one was asked for. Left uncapped: they're the user's own standing
+    // rules.
+    fn with_settings(&self, app: &AppHandle) -> CaptureOptions {
+        let mut options = self.clone();
+        if let Ok(settings) = app.state::<SharedSettings>().0.lock() {
+            options.redact_regions.extend(settings.redact_regions.iter().copied());
+            options.blocked_apps = settings.capture_blocklist.clone();
+            options.stamp_corner.get_or_insert(settings.stamp_corner);
+        }
+        options
+    }
+}
+
+// Size of the image behind a base64 string.
+pub fn decoded_len(base64: &str) -> usize {
+    let padding = base64.bytes().rev().take_while(|&b| b == b'=').count();
+    (base64.len() / 4 * 3).saturating_sub(padding)
+}
+
+// What a capture covers. Recent captures are only reused for the same
+// target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureTarget {
+    // The first display.
+    Default,
+    Screen(u32),
+    // Every display stitched into one image.
+    VirtualDesktop,
+    Region(ScreenArea),
+    // One window by native handle, rendered even if covered.
+    Window(u64),
+}
+
+// A rectangle on one display, relative to its origin and in the units the
+// display reports its bounds in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenArea {
+    pub screen_id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// A region capture. The rectangle is in physical pixels on the virtual
+// desktop, after clamping to the display it starts on; `scale_factor` is
+// that display's physical pixels per logical pixel.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct RegionCapture {
+    pub capture_id: String,
+    #[ts(type = "string")]
+    pub base64: Arc<String>,
+    pub format: CaptureFormat,
+    // Encoded image size, before base64.
+    #[ts(type = "number")]
+    pub bytes: usize,
+    // SHA-256 of the encoded image, in hex. A payload shrunk to fit the IPC
+    // limit has its own in `adjustment`.
+    pub content_hash: String,
+    pub screen_id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    // Set when the requested rectangle ran past the display and was cut.
+    pub clamped: bool,
+    pub downscale: Option<Downscale>,
+    // Set when the `thumbnail` option asked for one.
+    #[ts(type = "string | null")]
+    pub thumbnail_base64: Option<Arc<String>>,
+    pub window_handling: WindowHandling,
+    pub adjustment: Option<PayloadAdjustment>,
+    // A recent capture of the same area handed back instead of a new one.
+    pub from_cache: bool,
+    // Looks the same as the previous capture of the same area.
+    pub unchanged: bool,
+    #[ts(type = "number")]
+    pub redacted: usize,
+    // Set when the `analyze` option asked for it.
+    pub analysis: Option<ScreenAnalysis>,
+    pub scale: Option<DisplayScale>,
+    pub budget: Option<ByteBudget>,
+    // `None` when `from_cache` is set.
+    pub timings: Option<CaptureTimings>,
+}
+
+impl RegionCapture {
+    // `shot` of the rectangle `[x, y, width, height]` on `screen`, in
+    // physical pixels.
+    fn from_shot(shot: CapturedScreen, screen: &ScreenInfo, [x, y, w, h]: [i64; 4], clamped: bool) -> Self {
+        RegionCapture {
+            capture_id: shot.id,
+            bytes: decoded_len(&shot.base64),
+            content_hash: shot.content_hash,
+            base64: shot.base64,
+            format: shot.format,
+            screen_id: screen.id,
+            x: x as i32,
+            y: y as i32,
+            width: w as u32,
+            height: h as u32,
+            scale_factor: screen.scale_factor,
+            clamped,
+            downscale: shot.downscale,
+            thumbnail_base64: shot.thumbnail,
+            analysis: shot.analysis,
+            scale: shot.scale,
+            budget: shot.budget,
+            timings: shot.timings,
+            window_handling: shot.window_handling,
+            adjustment: shot.adjustment,
+            from_cache: shot.from_cache,
+            unchanged: shot.unchanged,
+            redacted: shot.redacted,
+        }
+    }
+}
+
+// A foreground window capture: the window's part of one display plus which
+// app it belongs to.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ForegroundWindowCapture {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub region: RegionCapture,
+    pub title: String,
+    pub process_name: String,
+    pub pid: u32,
+}
+
+// macOS reports display bounds in points; elsewhere they are physical
+// pixels.
+pub(crate) fn layout_units_per_pixel(screen: &ScreenInfo) -> f64 {
+    if cfg!(target_os = "macos") {
+        1.0 / screen.scale_factor.max(1.0) as f64
+    } else {
+        1.0
+    }
+}
+
+// The cursor comes in physical pixels at the primary display's scale; this
+// puts it in the layout units of display bounds, which on macOS are points.
+pub(crate) fn cursor_layout_position(screens: &[ScreenInfo], (x, y): (f64, f64)) -> (f64, f64) {
+    let primary = screens.iter().find(|s| s.is_primary).or(screens.first());
+    let per_px = primary.map_or(1.0, layout_units_per_pixel);
+    (x * per_px, y * per_px)
+}
+
+// A display's bounds as `[x, y, width, height]` in physical pixels.
+fn screen_bounds_px(screen: &ScreenInfo) -> [i64; 4] {
+    let per_px = layout_units_per_pixel(screen);
+    let px = |units: f64| (units / per_px).round() as i64;
+    [px(screen.x as f64), px(screen.y as f64), px(screen.width as f64), px(screen.height as f64)]
+}
+
+// The part of `rect` on `screen`, as the area to grab and the rectangle
+// covered in physical pixels. `None` if they don't overlap.
+fn clip_to_screen(screen: &ScreenInfo, [x, y, w, h]: [i64; 4]) -> Option<(ScreenArea, [i64; 4])> {
+    let [left, top, sw, sh] = screen_bounds_px(screen);
+    let (x0, y0) = (x.max(left), y.max(top));
+    let (x1, y1) = ((x + w).min(left + sw), (y + h).min(top + sh));
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+    let per_px = layout_units_per_pixel(screen);
+    let units = |px: i64| (px as f64 * per_px).round();
+    let area = ScreenArea {
+        screen_id: screen.id,
+        x: units(x0 - left) as i32,
+        y: units(y0 - top) as i32,
+        width: units(x1 - x0).max(1.0) as u32,
+        height: units(y1 - y0).max(1.0) as u32,
+    };
+    Some((area, [x0, y0, x1 - x0, y1 - y0]))
+}
+
+// Finds the display the rectangle starts on and clamps the rectangle to it.
+fn resolve_region(
+    screens: &[ScreenInfo],
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) -> Result<(ScreenArea, &ScreenInfo, [i64; 4]), CaptureError> {
+    if width == 0 || height == 0 {
+        return Err(CaptureError::InvalidRegion("width and height must be at least 1".into()));
+    }
+    let (x, y) = (x as i64, y as i64);
+    let screen = screens
+        .iter()
+        .find(|s| {
+            let [left, top, w, h] = screen_bounds_px(s);
+            x >= left && x < left + w && y >= top && y < top + h
+        })
+        .ok_or_else(|| CaptureError::InvalidRegion(format!("({x}, {y}) is not on any screen")))?;
+    let (area, covered) = clip_to_screen(screen, [x, y, width as i64, height as i64])
+        .ok_or_else(|| CaptureError::InvalidRegion(format!("({x}, {y}) is not on any screen")))?;
+    Ok((area, screen, covered))
+}
+
+// The display under `cursor`, and the cursor in that display's physical
+// pixels.
+fn screen_under_cursor(screens: &[ScreenInfo], cursor: (f64, f64)) -> Option<(&ScreenInfo, (i32, i32))> {
+    let (x, y) = cursor_layout_position(screens, cursor);
+    let screen = screens.iter().find(|s| {
+        let (left, top) = (s.x as f64, s.y as f64);
+        let (right, bottom) = (left + s.width as f64, top + s.height as f64);
+        x >= left && x < right && y >= top && y < bottom
+    })?;
+    let per_px = layout_units_per_pixel(screen);
+    Some((screen, ((x / per_px).round() as i32, (y / per_px).round() as i32)))
+}
+
+// For a window that may span displays: the display holding most of it, and
+// the part of the window on that display.
+fn resolve_window(screens: &[ScreenInfo], rect: WindowRect) -> Result<(ScreenArea, &ScreenInfo, [i64; 4]), CaptureError> {
+    let rect = [rect.x as i64, rect.y as i64, rect.width as i64, rect.height as i64];
+    screens
+        .iter()
+        .filter_map(|s| clip_to_screen(s, rect).map(|(area, covered)| (area, s, covered)))
+        .max_by_key(|(_, _, [_, _, w, h])| w * h)
+        .ok_or_else(|| CaptureError::InvalidRegion("the window is not on any screen".into()))
+}
+
+impl CaptureTarget {
+    pub fn screen(screen_id: Option<u32>) -> Self {
+        screen_id.map_or(CaptureTarget::Default, CaptureTarget::Screen)
+    }
+}
+
+// How fast real screen grabs may go: up to `burst` back to back, with one
+// more allowed every `interval`. A zero interval turns the limit off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub interval: Duration,
+    pub burst: u32,
+    // Tokens this caller must leave in the bucket for everyone else.
+    pub reserve: u32,
+}
+
+impl RateLimit {
+    // From the `min_capture_interval_ms` and `capture_burst` settings.
+    pub fn from_settings(app: &AppHandle) -> Self {
+        app.state::<SharedSettings>()
+            .0
+            .lock()
+            .map(|s| RateLimit {
+                interval: Duration::from_millis(s.min_capture_interval_ms),
+                burst: s.capture_burst,
+                reserve: 0,
+            })
+            .unwrap_or(RateLimit { interval: Duration::from_millis(500), burst: 4, reserve: 0 })
+    }
+
+    // The same limit for background captures such as the capture stream,
+    // which leave a token for interactive ones. With a burst of 1 there's
+    // nothing to spare.
+    pub fn in_background(self) -> Self {
+        RateLimit { reserve: BACKGROUND_RESERVE.min(self.burst.saturating_sub(1)), ..self }
+    }
+}
+
+const BACKGROUND_RESERVE: u32 = 1;
+
+// A token bucket holding up to `burst` grabs. Time is passed in rather than
+// read, so the arithmetic doesn't depend on the clock.
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: RateLimit, now: Instant) -> Self {
+        TokenBucket { tokens: limit.burst.max(1) as f64, refilled: now }
+    }
+
+    // Takes a token as of `now`, or says how long until one is back, leaving
+    // `limit.reserve` behind.
+    fn take(&mut self, limit: RateLimit, now: Instant) -> Result<(), Duration> {
+        if limit.interval.is_zero() {
+            return Ok(());
+        }
+        let earned = now.saturating_duration_since(self.refilled).as_secs_f64() / limit.interval.as_secs_f64();
+        self.tokens = (self.tokens + earned).min(limit.burst.max(1) as f64);
+        self.refilled = now;
+        let needed = 1.0 + limit.reserve as f64;
+        if self.tokens >= needed {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(limit.interval.mul_f64(needed - self.tokens))
+        }
+    }
+
+    fn give_back(&mut self, limit: RateLimit) {
+        self.tokens = (self.tokens + 1.0).min(limit.burst.max(1) as f64);
+    }
+}
+
+// A wait from `TokenBucket::take` as reported in `RateLimited`, rounded up
+// so retrying after it never comes back throttled.
+fn retry_after_ms(wait: Duration) -> u64 {
+    (wait.as_secs_f64() * 1000.0).ceil() as u64
+}
+
+// Sent whenever the rate limit turns a capture away, so a frontend stuck
+// in a capture loop shows up.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CaptureThrottled {
+    #[ts(type = "number")]
+    pub retry_after_ms: u64,
+    // The most recent capture was handed back instead, per `Reuse`.
+    pub reused: bool,
+}
+
+// The rate limit on real screen grabs, shared by every entry point. The
+// lock is held across the grab so concurrent requests queue up instead of
+// hiding and re-showing the window over each other; it's an async lock
+// because the grab awaits.
+#[derive(Default)]
+pub struct CaptureRateLimiter(tokio::sync::Mutex<Option<TokenBucket>>);
+
+impl CaptureRateLimiter {
+    pub async fn run<T, G, F, R>(
+        &self,
+        app: &AppHandle,
+        limit: RateLimit,
+        policy: RateLimitPolicy,
+        cancel: &CancellationToken,
+        grab: G,
+        reuse_latest: R,
+    ) -> Result<T, CaptureError>
+    where
+        G: FnOnce() -> F,
+        F: Future<Output = Result<T, CaptureError>>,
+        R: FnOnce() -> Option<T>,
+    {
+        let throttled = |throttled| {
+            emit_event(app, GraviaEvent::CaptureThrottled(throttled)).ok();
+        };
+        let paused = || app.state::<CapturePause>().is_active();
+        self.run_reporting(limit, policy, cancel, paused, grab, reuse_latest, throttled).await
+    }
+
+    // `run`, asking `is_paused` whether captures are paused and handing a
+    // turned-away capture's `CaptureThrottled` to `on_throttled` instead of
+    // the frontend.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_reporting<T, P, G, F, R, N>(
+        &self,
+        limit: RateLimit,
+        policy: RateLimitPolicy,
+        cancel: &CancellationToken,
+        is_paused: P,
+        grab: G,
+        reuse_latest: R,
+        on_throttled: N,
+    ) -> Result<T, CaptureError>
+    where
+        P: Fn() -> bool,
+        G: FnOnce() -> F,
+        F: Future<Output = Result<T, CaptureError>>,
+        R: FnOnce() -> Option<T>,
+        N: FnOnce(CaptureThrottled),
+    {
+        let mut bucket = tokio::select! {
+            bucket = self.0.lock() => bucket,
+            _ = cancel.cancelled() => return Err(CaptureError::Cancelled),
+        };
+        // Callers check before queueing, but a pause can start while they
+        // wait on the lock, e.g. behind a slow grab.
+        if is_paused() {
+            return Err(CaptureError::Paused);
+        }
+        let now = Instant::now();
+        let taken = bucket.get_or_insert_with(|| TokenBucket::full(limit, now)).take(limit, now);
+        if let Err(wait) = taken {
+            let retry_after_ms = retry_after_ms(wait);
+            let reused = if policy == RateLimitPolicy::Reuse { reuse_latest() } else { None };
+            tracing::warn!(retry_after_ms, reused = reused.is_some(), "capture rate limited");
+            on_throttled(CaptureThrottled { retry_after_ms, reused: reused.is_some() });
+            return reused.ok_or(CaptureError::RateLimited { retry_after_ms });
+        }
+        let result = grab().await;
+        // A cancelled grab gives its token back.
+        if matches!(result, Err(CaptureError::Cancelled)) {
+            if let Some(bucket) = bucket.as_mut() {
+                bucket.give_back(limit);
+            }
+        }
+        result
+    }
+}
+
+// Captures in flight by operation id, so `cancel_capture` can reach them.
+#[derive(Default)]
+pub struct CaptureOperations(Mutex<HashMap<String, CancellationToken>>);
+
+// Registered for as long as the operation runs.
+pub struct CaptureOperation<'a> {
+    operations: &'a CaptureOperations,
+    id: String,
+    pub token: CancellationToken,
+}
+
+impl CaptureOperations {
+    // Callers may pick the id so they can cancel before the command returns.
+    pub fn begin(&self, id: Option<String>) -> CaptureOperation<'_> {
+        let id = id.unwrap_or_else(|| format!("op-{}", NEXT_OPERATION.fetch_add(1, Ordering::Relaxed)));
+        let token = CancellationToken::new();
+        if let Ok(mut ops) = self.0.lock() {
+            ops.insert(id.clone(), token.clone());
+        }
+        CaptureOperation { operations: self, id, token }
+    }
+}
+
+impl Drop for CaptureOperation<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut ops) = self.operations.0.lock() {
+            ops.remove(&self.id);
+        }
+    }
+}
+
+static NEXT_OPERATION: AtomicU64 = AtomicU64::new(1);
+
+#[tauri::command]
+pub fn cancel_capture(state: State<'_, CaptureOperations>, operation_id: String) -> CommandResult<()> {
+    let token = state.0.lock()?.get(&operation_id).cloned();
+    let token = token.ok_or_else(|| GraviaError::NotFound(format!("capture operation {operation_id}")))?;
+    tracing::info!(%operation_id, "capture cancelled");
+    token.cancel();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn capture_screenshot_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<Arc<String>> {
+    command_stats::track(window.app_handle(), "capture_screenshot_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let shot = capture_hiding_window(
+            &window,
+            CaptureTarget::Default,
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(shot.base64)
+    })
+    .await
+}
+
+// `capture_screenshot_base64` as raw image bytes over binary IPC, about a
+// quarter smaller than base64 and with nothing to decode in JS. The IPC
+// payload limit doesn't apply. `get_capture_info` describes the capture.
+#[tauri::command]
+pub async fn capture_screenshot_raw(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<tauri::ipc::Response> {
+    command_stats::track(window.app_handle(), "capture_screenshot_raw", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let shot = capture_unguarded(
+            &window,
+            CaptureTarget::Default,
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        let bytes = window
+            .state::<CaptureHistory>()
+            .image_bytes(&shot.id)
+            .ok_or_else(|| CaptureError::CaptureNotFound(shot.id.clone()))?;
+        Ok(tauri::ipc::Response::new(bytes))
+    })
+    .await
+}
+
+// Like `capture_screenshot_base64`, for the display `screen_id` from
+// `list_screens`.
+#[tauri::command]
+pub async fn capture_screen_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    screen_id: u32,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<Arc<String>> {
+    command_stats::track(window.app_handle(), "capture_screen_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let shot = capture_hiding_window(
+            &window,
+            CaptureTarget::Screen(screen_id),
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(shot.base64)
+    })
+    .await
+}
+
+// Captures the display under the mouse cursor, or the primary one when the
+// cursor can't be located.
+#[tauri::command]
+pub async fn capture_active_monitor_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<Arc<String>> {
+    command_stats::track(window.app_handle(), "capture_active_monitor_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let target = CaptureTarget::screen(active_screen_id(window.app_handle()).await);
+        let shot = capture_hiding_window(
+            &window,
+            target,
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(shot.base64)
+    })
+    .await
+}
+
+// Captures a rectangle given in physical pixels on the virtual desktop. A
+// rectangle running past the edge of the display it starts on is clamped
+// to that display; one that doesn't start on any display is rejected.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_region_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<RegionCapture> {
+    command_stats::track(window.app_handle(), "capture_region_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let screens = list_screens().await?;
+        let (area, screen, [x, y, w, h]) = resolve_region(&screens, x, y, width, height)?;
+        let clamped = (w, h) != (width as i64, height as i64);
+        let shot = capture_hiding_window(
+            &window,
+            CaptureTarget::Region(area),
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(RegionCapture::from_shot(shot, screen, [x, y, w, h], clamped))
+    })
+    .await
+}
+
+// Captures the window the user is working in, cropped to its frame. With
+// Gravia itself in front, that's the window behind it. A window spanning
+// displays is cut to the display holding most of it, with `clamped` set.
+#[tauri::command]
+pub async fn capture_foreground_window_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<ForegroundWindowCapture> {
+    command_stats::track(window.app_handle(), "capture_foreground_window_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        // Read before hiding, while our window still has focus to step past.
+        let (app_info, rect) = foreground::foreground_window()?;
+        let screens = list_screens().await?;
+        let (area, screen, [x, y, w, h]) = resolve_window(&screens, rect)?;
+        let clamped = (w, h) != (rect.width as i64, rect.height as i64);
+        let shot = capture_hiding_window(
+            &window,
+            CaptureTarget::Region(area),
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(ForegroundWindowCapture {
+            region: RegionCapture::from_shot(shot, screen, [x, y, w, h], clamped),
+            title: app_info.title,
+            process_name: app_info.process_name,
+            pid: app_info.pid,
+        })
+    })
+    .await
+}
+
+// The window under the mouse cursor, or the display it's on when it's over
+// the desktop.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CursorWindowCapture {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub region: RegionCapture,
+    // `None` when the whole display was captured.
+    pub app: Option<foreground::ForegroundApp>,
+}
+
+// Captures the display under the mouse cursor cropped to the window the
+// cursor is over, without capturing the window on its own. Over the
+// desktop, or where windows can't be looked up, it's the whole display. A
+// window reaching onto other displays is cut to the cursor's, with
+// `clamped` set.
+#[tauri::command]
+pub async fn capture_window_under_cursor_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<CursorWindowCapture> {
+    command_stats::track(window.app_handle(), "capture_window_under_cursor_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        // Read before hiding; our own window is skipped either way.
+        let cursor = window
+            .app_handle()
+            .cursor_position()
+            .map_err(|e| CaptureError::Failed(format!("cursor position unavailable: {e}")))?;
+        let screens = list_screens().await?;
+        let (screen, (px, py)) = screen_under_cursor(&screens, (cursor.x, cursor.y))
+            .ok_or_else(|| CaptureError::InvalidRegion("the cursor is not on any screen".into()))?;
+        let under_cursor = match foreground::window_at(px, py) {
+            Ok(found) => found,
+            Err(e) => {
+                tracing::debug!(error = %e, "window under the cursor unknown, capturing the display");
+                None
+            }
+        };
+        let cropped = under_cursor.and_then(|(app_info, rect)| {
+            let rect_px = [rect.x as i64, rect.y as i64, rect.width as i64, rect.height as i64];
+            let (area, covered) = clip_to_screen(screen, rect_px)?;
+            let clamped = (covered[2], covered[3]) != (rect.width as i64, rect.height as i64);
+            Some((CaptureTarget::Region(area), covered, clamped, Some(app_info)))
+        });
+        let (target, [x, y, w, h], clamped, app_info) =
+            cropped.unwrap_or_else(|| (CaptureTarget::Screen(screen.id), screen_bounds_px(screen), false, None));
+        let shot =
+            capture_hiding_window(&window, target, &options, on_rate_limit.unwrap_or_default(), &operation.token)
+                .await?;
+        Ok(CursorWindowCapture {
+            region: RegionCapture::from_shot(shot, screen, [x, y, w, h], clamped),
+            app: app_info,
+        })
+    })
+    .await
+}
+
+// A window picked by name.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct WindowCapture {
+    pub capture_id: String,
+    #[ts(type = "string")]
+    pub base64: Arc<String>,
+    pub format: CaptureFormat,
+    #[ts(type = "number")]
+    pub bytes: usize,
+    pub content_hash: String,
+    pub title: String,
+    pub process_name: String,
+    pub pid: u32,
+    pub downscale: Option<Downscale>,
+    #[ts(type = "string | null")]
+    pub thumbnail_base64: Option<Arc<String>>,
+    pub window_handling: WindowHandling,
+    pub adjustment: Option<PayloadAdjustment>,
+    pub from_cache: bool,
+    pub unchanged: bool,
+    // Always 0; windows are captured on their own, not as a desktop area,
+    // and blocklisted apps' windows can't be captured at all.
+    #[ts(type = "number")]
+    pub redacted: usize,
+    pub analysis: Option<ScreenAnalysis>,
+    pub budget: Option<ByteBudget>,
+    pub timings: Option<CaptureTimings>,
+}
+
+// Captures the window whose title or process name contains `matcher`,
+// ignoring case, even if other windows cover it. Several matches fail with
+// `ambiguous_window`, listing their titles; a window of an app on the
+// capture blocklist fails with `blocked_app`.
+#[tauri::command]
+pub async fn capture_window_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    matcher: String,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<WindowCapture> {
+    command_stats::track(window.app_handle(), "capture_window_base64", async {
+        if matcher.trim().is_empty() {
+            return Err(GraviaError::InvalidArgument("matcher must not be empty".into()));
+        }
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let windows = tauri::async_runtime::spawn_blocking(window_list::list_windows)
+            .await
+            .map_err(|e| CaptureError::Failed(e.to_string()))??;
+        let target = window_list::find(&windows, &matcher)?.clone();
+        let blocklist = window.state::<SharedSettings>().0.lock()?.capture_blocklist.clone();
+        if window_list::belongs_to_any(&target, &blocklist) {
+            return Err(CaptureError::BlockedApp(target.process_name).into());
+        }
+        let shot = capture_hiding_window(
+            &window,
+            CaptureTarget::Window(target.id),
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(WindowCapture {
+            capture_id: shot.id,
+            bytes: decoded_len(&shot.base64),
+            content_hash: shot.content_hash,
+            base64: shot.base64,
+            format: shot.format,
+            title: target.title,
+            process_name: target.process_name,
+            pid: target.pid,
+            downscale: shot.downscale,
+            thumbnail_base64: shot.thumbnail,
+            analysis: shot.analysis,
+            budget: shot.budget,
+            timings: shot.timings,
+            window_handling: shot.window_handling,
+            adjustment: shot.adjustment,
+            from_cache: shot.from_cache,
+            unchanged: shot.unchanged,
+            redacted: shot.redacted,
+        })
+    })
+    .await
+}
+
+// Captures every display as one image laid out like the desktop, with
+// black where no display covers it.
+#[tauri::command]
+pub async fn capture_virtual_desktop_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    on_rate_limit: Option<RateLimitPolicy>,
+    operation_id: Option<String>,
+) -> CommandResult<Arc<String>> {
+    command_stats::track(window.app_handle(), "capture_virtual_desktop_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let shot = capture_hiding_window(
+            &window,
+            CaptureTarget::VirtualDesktop,
+            &options,
+            on_rate_limit.unwrap_or_default(),
+            &operation.token,
+        )
+        .await?;
+        Ok(shot.base64)
+    })
+    .await
+}
+
+// Captures every connected display. A display that fails gets an error
+// entry instead of failing the call.
+#[tauri::command]
+pub async fn capture_all_screens_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    options: Option<CaptureOptions>,
+    operation_id: Option<String>,
+) -> CommandResult<Vec<ScreenCapture>> {
+    command_stats::track(window.app_handle(), "capture_all_screens_base64", async {
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        Ok(capture_all_hiding_window(&window, &options, &operation.token).await?)
+    })
+    .await
+}
+
+// Frames a burst takes at most, and how long from its first grab until its
+// last may start.
+const MAX_BURST_FRAMES: u8 = 10;
+pub(crate) const MAX_BURST_DURATION: Duration = Duration::from_secs(5);
+
+// Captures `frames` frames of one display, `interval_ms` apart, with the
+// window kept out of the shot once for the whole burst, e.g. to catch a
+// flicker or a stuck progress bar. The display is `screen_id`, else the
+// one under the cursor. Each frame is recorded in the history and reports
+// whether it differs from the one before. The last frame is due within
+// `MAX_BURST_DURATION` of the first; frames that slip past it are left out
+// with `truncated` set. Never served from the cache; the `thumbnail` and
+// `analyze` options don't apply.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_burst_base64(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    frames: u8,
+    interval_ms: u64,
+    screen_id: Option<u32>,
+    options: Option<CaptureOptions>,
+    operation_id: Option<String>,
+) -> CommandResult<BurstCapture> {
+    command_stats::track(window.app_handle(), "capture_burst_base64", async {
+        if frames == 0 || frames > MAX_BURST_FRAMES {
+            return Err(GraviaError::InvalidArgument(format!("frames must be between 1 and {MAX_BURST_FRAMES}")));
+        }
+        let interval = Duration::from_millis(interval_ms);
+        if interval.saturating_mul(frames as u32 - 1) > MAX_BURST_DURATION {
+            return Err(GraviaError::InvalidArgument(format!(
+                "a burst spans at most {}ms",
+                MAX_BURST_DURATION.as_millis()
+            )));
+        }
+        let options = CaptureOptions::resolve(options)?;
+        let operation = operations.begin(operation_id);
+        let screen_id = match screen_id {
+            Some(id) => Some(id),
+            None => active_screen_id(window.app_handle()).await,
+        };
+        let target = CaptureTarget::screen(screen_id);
+        Ok(capture_burst_hiding_window(&window, target, frames, interval, &options, &operation.token).await?)
+    })
+    .await
+}
+
+// `capture_hiding_window` for a burst. It counts as one capture towards the
+// rate limit, and a rate-limited call fails.
+async fn capture_burst_hiding_window(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    frames: u8,
+    interval: Duration,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+) -> Result<BurstCapture, CaptureError> {
+    if window.state::<CapturePause>().is_active() {
+        return Err(CaptureError::Paused);
+    }
+    let options = &options.with_settings(window.app_handle());
+    let app = window.app_handle();
+    let mut burst = window
+        .state::<CaptureRateLimiter>()
+        .run(
+            app,
+            RateLimit::from_settings(app),
+            RateLimitPolicy::Error,
+            cancel,
+            || async {
+                let started = Instant::now();
+                let result = grab_burst_hiding_window(window, target, frames, interval, options, cancel).await;
+                if let Ok(burst) = &result {
+                    for frame in &burst.frames {
+                        let taken = frame.error.is_none();
+                        metrics::increment(app, if taken { Counter::CapturesTaken } else { Counter::CaptureFailures });
+                    }
+                    metrics::observe(app, Histogram::CaptureLatencyMs, started.elapsed().as_secs_f64() * 1000.0);
+                }
+                result
+            },
+            || None,
+        )
+        .await
+        .inspect_err(|e| {
+            if !matches!(e, CaptureError::RateLimited { .. } | CaptureError::Cancelled) {
+                metrics::increment(app, Counter::CaptureFailures);
+            }
+        })?;
+    let history = window.state::<CaptureHistory>();
+    for frame in &mut burst.frames {
+        let (Some(id), Some(base64)) = (frame.capture_id.clone(), frame.base64.take()) else { continue };
+        let Some(mut shot) = history.captured(&id) else {
+            // Already pushed out of the history, so it can't be shrunk.
+            frame.base64 = Some(base64);
+            continue;
+        };
+        shot.unchanged = frame.unchanged;
+        match guard_payload(app, shot, cancel).await {
+            Ok(shot) => {
+                frame.bytes = Some(decoded_len(&shot.base64));
+                frame.base64 = Some(shot.base64);
+                frame.format = shot.format;
+                frame.adjustment = shot.adjustment;
+            }
+            Err(CaptureError::Cancelled) => return Err(CaptureError::Cancelled),
+            Err(e) => {
+                frame.error_code = Some(e.code().to_string());
+                frame.error = Some(e.to_string());
+            }
+        }
+    }
+    Ok(burst)
+}
+
+// `capture_hiding_window` for every display at once. There's no single
+// recent capture to reuse, so a rate-limited call fails.
+pub async fn capture_all_hiding_window(
+    window: &tauri::Window,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+) -> Result<Vec<ScreenCapture>, CaptureError> {
+    if window.state::<CapturePause>().is_active() {
+        return Err(CaptureError::Paused);
+    }
+    let options = &options.with_settings(window.app_handle());
+    let app = window.app_handle();
+    let mut captures = window
+        .state::<CaptureRateLimiter>()
+        .run(
+            app,
+            RateLimit::from_settings(app),
+            RateLimitPolicy::Error,
+            cancel,
+            || async {
+                let started = Instant::now();
+                let result = grab_all_hiding_window(window, options, cancel).await;
+                if let Ok(captures) = &result {
+                    for capture in captures {
+                        let counter = if capture.error.is_none() { Counter::CapturesTaken } else { Counter::CaptureFailures };
+                        metrics::increment(app, counter);
+                    }
+                    metrics::observe(app, Histogram::CaptureLatencyMs, started.elapsed().as_secs_f64() * 1000.0);
+                }
+                result
+            },
+            || None,
+        )
+        .await
+        .inspect_err(|e| {
+            if !matches!(e, CaptureError::RateLimited { .. } | CaptureError::Cancelled) {
+                metrics::increment(app, Counter::CaptureFailures);
+            }
+        })?;
+    let history = window.state::<CaptureHistory>();
+    for capture in &mut captures {
+        let (Some(id), Some(base64)) = (capture.capture_id.clone(), capture.base64.take()) else { continue };
+        let Some(mut shot) = history.captured(&id) else {
+            // Already pushed out of the history, so it can't be shrunk.
+            capture.base64 = Some(base64);
+            continue;
+        };
+        shot.unchanged = capture.unchanged;
+        shot.timings = capture.timings;
+        match guard_payload(app, shot, cancel).await {
+            Ok(shot) => {
+                capture.bytes = Some(decoded_len(&shot.base64));
+                capture.base64 = Some(shot.base64);
+                capture.format = shot.format;
+                capture.adjustment = shot.adjustment;
+            }
+            Err(CaptureError::Cancelled) => return Err(CaptureError::Cancelled),
+            Err(e) => {
+                capture.error_code = Some(e.code().to_string());
+                capture.error = Some(e.to_string());
+            }
+        }
+    }
+    Ok(captures)
+}
+
+// Every capture entry point funnels through here, so this is where the
+// user's pause switch and the capture rate limit are enforced. Cancelling
+// `cancel` ends it with `Cancelled` at the next step, with the window shown
+// again if it was hidden.
+pub async fn capture_hiding_window(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    policy: RateLimitPolicy,
+    cancel: &CancellationToken,
+) -> Result<CapturedScreen, CaptureError> {
+    capture_with_attempts(window, target, options, policy, cancel).await.0
+}
+
+// `capture_hiding_window`, along with how many grabs were tried whether it
+// worked or not. 0 means none were, e.g. a capture from the cache.
+pub async fn capture_with_attempts(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    policy: RateLimitPolicy,
+    cancel: &CancellationToken,
+) -> (Result<CapturedScreen, CaptureError>, u32) {
+    let (shot, attempts) = capture_counting_attempts(window, target, options, policy, cancel).await;
+    match shot {
+        Ok(shot) => (guard_payload(window.app_handle(), shot, cancel).await, attempts),
+        Err(e) => (Err(e), attempts),
+    }
+}
+
+// `capture_hiding_window` without the IPC payload limit, for callers that
+// don't send the capture as base64.
+pub(crate) async fn capture_unguarded(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    policy: RateLimitPolicy,
+    cancel: &CancellationToken,
+) -> Result<CapturedScreen, CaptureError> {
+    capture_counting_attempts(window, target, options, policy, cancel).await.0
+}
+
+async fn capture_counting_attempts(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    policy: RateLimitPolicy,
+    cancel: &CancellationToken,
+) -> (Result<CapturedScreen, CaptureError>, u32) {
+    let attempts = AtomicU32::new(0);
+    let result = capture_retrying(window, target, options, policy, cancel, &attempts).await;
+    (result, attempts.into_inner())
+}
+
+async fn capture_retrying(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    policy: RateLimitPolicy,
+    cancel: &CancellationToken,
+    attempts: &AtomicU32,
+) -> Result<CapturedScreen, CaptureError> {
+    if window.state::<CapturePause>().is_active() {
+        return Err(CaptureError::Paused);
+    }
+    let cache_ttl = window
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| Duration::from_millis(s.capture_cache_ttl_ms))
+        .unwrap_or(Duration::from_secs(5));
+    let app = window.app_handle();
+    let limit = RateLimit::from_settings(app);
+    let limit = if options.background { limit.in_background() } else { limit };
+    let history = window.state::<CaptureHistory>();
+    // Merged before the cache lookup, so a capture taken before the setting
+    // changed can't stand in.
+    let options = &options.with_settings(app);
+    // Mid-conversation the screen rarely changes between messages, so a
+    // recent capture of the same target stands in without touching the
+    // window.
+    if !options.force && !cache_ttl.is_zero() {
+        if let Some(shot) = history.latest_within(cache_ttl, target, options) {
+            metrics::increment(app, Counter::CapturesReused);
+            tracing::debug!(capture_id = %shot.id, "capture served from cache");
+            return Ok(shot);
+        }
+    }
+    window
+        .state::<CaptureRateLimiter>()
+        .run(
+            app,
+            limit,
+            policy,
+            cancel,
+            || async {
+                let started = Instant::now();
+                let grab = || grab_hiding_window(window, target, options, cancel);
+                let paused = || window.state::<CapturePause>().is_active();
+                let result = with_retries(options, cancel, paused, attempts, grab).await;
+                if result.is_ok() {
+                    metrics::increment(app, Counter::CapturesTaken);
+                    metrics::observe(app, Histogram::CaptureLatencyMs, started.elapsed().as_secs_f64() * 1000.0);
+                }
+                result
+            },
+            || {
+                let latest = history.latest_within(limit.interval, target, options);
+                if latest.is_some() {
+                    metrics::increment(app, Counter::CapturesReused);
+                }
+                latest
+            },
+        )
+        .await
+        .inspect_err(|e| {
+            let expected = matches!(
+                e,
+                CaptureError::RateLimited { .. }
+                    | CaptureError::Cancelled
+                    | CaptureError::Paused
+                    | CaptureError::ScreenNotFound(_)
+                    | CaptureError::ScreenRemoved(_)
+            );
+            if !expected {
+                metrics::increment(app, Counter::CaptureFailures);
+            }
+        })
+}
+
+// Runs `grab` until it works, fails in a way that won't clear up by
+// itself, or has been tried `retry_attempts` times, counting each try in
+// `attempts`. The wait between tries doubles from `retry_backoff_ms`. No
+// try starts once `is_paused` says captures were paused.
+async fn with_retries<T, P, F, Fut>(
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+    is_paused: P,
+    attempts: &AtomicU32,
+    mut grab: F,
+) -> Result<T, CaptureError>
+where
+    P: Fn() -> bool,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, CaptureError>>,
+{
+    let max_attempts = options.retry_attempts.unwrap_or(DEFAULT_RETRY_ATTEMPTS);
+    let first_backoff = Duration::from_millis(options.retry_backoff_ms.unwrap_or(DEFAULT_RETRY_BACKOFF_MS));
+    let mut backoff = first_backoff;
+    loop {
+        if is_paused() {
+            return Err(CaptureError::Paused);
+        }
+        let attempt = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+        match grab().await {
+            Err(e) if e.is_transient() && attempt < max_attempts => {
+                tracing::warn!(attempt, max_attempts, error = %e, "capture failed, retrying");
+                tokio::select! {
+                    _ = cancel.cancelled() => return Err(CaptureError::Cancelled),
+                    _ = tokio::time::sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(first_backoff * 3);
+            }
+            result => return result,
+        }
+    }
+}
+
+// Large displays can produce a base64 string big enough to stall the
+// webview when returned over IPC.
+#[cfg(feature = "capture")]
+async fn guard_payload(
+    app: &AppHandle,
+    mut shot: CapturedScreen,
+    cancel: &CancellationToken,
+) -> Result<CapturedScreen, CaptureError> {
+    let limit = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| PayloadLimit { max_bytes: s.max_ipc_payload_bytes, auto_shrink: s.auto_shrink })
+        .map_err(|e| CaptureError::Failed(e.to_string()))?;
+    if shot.base64.len() <= limit.max_bytes {
+        return Ok(shot);
+    }
+    let encoded = app
+        .state::<CaptureHistory>()
+        .image_bytes(&shot.id)
+        .ok_or_else(|| CaptureError::CaptureNotFound(shot.id.clone()))?;
+    let base64 = shot.base64.clone();
+    let cancel = cancel.clone();
+    let (base64, adjustment) =
+        tauri::async_runtime::spawn_blocking(move || payload_guard::fit(&encoded, base64, limit, &cancel))
+            .await
+            .map_err(|e| CaptureError::Failed(e.to_string()))??;
+    shot.base64 = base64;
+    if adjustment.is_some() {
+        metrics::increment(app, Counter::PayloadsShrunk);
+        shot.format = CaptureFormat::Jpeg;
+    }
+    shot.adjustment = adjustment;
+    Ok(shot)
+}
+
+pub struct CapturedScreen {
+    pub id: String,
+    // Shared with the capture history, so handing it out doesn't copy it.
+    pub base64: Arc<String>,
+    // Of the image in the history, so not changed by a payload adjustment.
+    pub content_hash: String,
+    pub monitor: String,
+    pub backend: CaptureBackend,
+    pub format: CaptureFormat,
+    pub downscale: Option<Downscale>,
+    pub thumbnail: Option<Arc<String>>,
+    pub window_handling: WindowHandling,
+    // Set when `base64` was shrunk to fit the IPC payload limit.
+    pub adjustment: Option<PayloadAdjustment>,
+    // Taken earlier and handed back from the history.
+    pub from_cache: bool,
+    // Within the options' `unchanged_distance` of the target's last capture.
+    pub unchanged: bool,
+    // Redaction rectangles that fell on the capture.
+    pub redacted: usize,
+    pub analysis: Option<ScreenAnalysis>,
+    pub scale: Option<DisplayScale>,
+    pub budget: Option<ByteBudget>,
+    // `None` for a capture handed back from the history.
+    pub timings: Option<CaptureTimings>,
+}
+
+impl CapturedScreen {
+    // A capture as the history holds it. Whether it looked unchanged and its
+    // timings aren't kept there, so they're left unset.
+    pub fn from_entry(entry: &StoredCapture) -> Self {
+        CapturedScreen {
+            id: entry.id.clone(),
+            base64: entry.base64.clone(),
+            content_hash: entry.content_hash.clone(),
+            monitor: entry.monitor.clone(),
+            backend: entry.backend,
+            format: entry.options.format,
+            downscale: entry.downscale,
+            thumbnail: entry.thumbnail.clone(),
+            window_handling: entry.window_handling,
+            adjustment: None,
+            from_cache: false,
+            unchanged: false,
+            redacted: entry.redacted,
+            analysis: entry.analysis.clone(),
+            scale: entry.scale,
+            budget: entry.budget,
+            timings: None,
+        }
+    }
+}
+
+// Where a fresh capture's time went, in milliseconds. The image is encoded
+// straight into its base64 string, so `base64_ms` is the share of that pass
+// spent on base64 and `encode_ms` the rest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct CaptureTimings {
+    // Until Gravia's window was off screen or out of captures.
+    #[ts(type = "number")]
+    pub hide_ms: u64,
+    // The settle delay after that.
+    #[ts(type = "number")]
+    pub settle_ms: u64,
+    // Reading the raw pixels.
+    #[ts(type = "number")]
+    pub grab_ms: u64,
+    // Turning them into RGBA.
+    #[ts(type = "number")]
+    pub convert_ms: u64,
+    // Cursor, redaction, downscale, thumbnail, hash and analysis.
+    #[ts(type = "number")]
+    pub process_ms: u64,
+    #[ts(type = "number")]
+    pub encode_ms: u64,
+    #[ts(type = "number")]
+    pub base64_ms: u64,
+    // The whole capture, including the parts not broken out. For a capture
+    // of every display, the whole call.
+    #[ts(type = "number")]
+    pub total_ms: u64,
+}
+
+// Sent as `capture-timing` after each fresh capture, for charting.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CaptureTiming {
+    pub capture_id: String,
+    pub monitor: String,
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub timings: CaptureTimings,
+}
+
+// Channel order of raw pixels as a grabber hands them over. Frames are
+// converted to RGBA once, right after the grab, and nothing later sees
+// any other order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelFormat {
+    #[default]
+    Rgba,
+    Bgra,
+    // BGRA whose alpha byte is undefined, as in GDI bitmaps. Made opaque.
+    Bgrx,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CapturePerfStats {
+    #[ts(type = "number")]
+    pub captures: u64,
+    // Time spent waiting for the window to leave the screen.
+    #[ts(type = "number")]
+    pub last_hide_wait_ms: u64,
+    #[ts(type = "number")]
+    pub last_grab_ms: u64,
+    #[ts(type = "number")]
+    pub last_encode_ms: u64,
+    // Largest amount of capture memory held at once during the last
+    // grab and encode.
+    #[ts(type = "number")]
+    pub last_peak_bytes: usize,
+    // `auto` until the first capture.
+    pub last_backend: CaptureBackend,
+    // Channel order the last frame arrived in before it was converted to
+    // RGBA, for telling a grabber's output apart when colors look off.
+    pub last_source_format: PixelFormat,
+    pub pool: PoolStats,
+}
+
+// How one capture backend has been doing since launch.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct BackendDiagnostics {
+    pub backend: CaptureBackend,
+    // Built in and supported on this platform.
+    pub available: bool,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    #[ts(type = "number")]
+    pub failures: u64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CaptureDiagnostics {
+    // From settings; the other backend is the fallback.
+    pub preferred: CaptureBackend,
+    pub backends: Vec<BackendDiagnostics>,
+}
+
+impl BackendDiagnostics {
+    fn new(backend: CaptureBackend) -> Self {
+        let available = match backend {
+            CaptureBackend::Standard => cfg!(feature = "capture"),
+            CaptureBackend::Dxgi => cfg!(all(target_os = "windows", feature = "capture")),
+            CaptureBackend::Auto => false,
+        };
+        BackendDiagnostics {
+            backend,
+            available,
+            last_success_at: None,
+            last_error: None,
+            last_error_at: None,
+            failures: 0,
+        }
+    }
+}
+
+// Buffers and timings for the grab/encode pipeline. `generation` lets the
+// idle timer tell whether another capture happened since it was started.
+#[derive(Default)]
+pub struct CapturePerf {
+    pub(crate) pool: BufferPool,
+    pub(crate) captures: AtomicU64,
+    pub(crate) last_hide_wait_ms: AtomicU64,
+    pub(crate) last_grab_ms: AtomicU64,
+    pub(crate) last_encode_ms: AtomicU64,
+    #[cfg(feature = "capture")]
+    pub(crate) last_png_len: AtomicUsize,
+    pub(crate) last_peak_bytes: AtomicUsize,
+    pub(crate) last_backend: Mutex<CaptureBackend>,
+    pub(crate) last_source_format: Mutex<PixelFormat>,
+    // Per backend, in the order they were first tried.
+    backend_health: Mutex<Vec<BackendDiagnostics>>,
+    #[cfg(feature = "capture")]
+    generation: AtomicU64,
+}
+
+#[cfg(feature = "capture")]
+impl CapturePerf {
+    // Notes how a grab through `backend` went, for `get_capture_diagnostics`.
+    pub(crate) fn record_backend(&self, backend: CaptureBackend, result: Result<(), String>) {
+        let Ok(mut health) = self.backend_health.lock() else {
+            return;
+        };
+        let entry = match health.iter().position(|d| d.backend == backend) {
+            Some(i) => &mut health[i],
+            None => {
+                health.push(BackendDiagnostics::new(backend));
+                health.last_mut().expect("just pushed")
+            }
+        };
+        match result {
+            Ok(()) => entry.last_success_at = Some(Utc::now()),
+            Err(error) => {
+                entry.last_error = Some(error);
+                entry.last_error_at = Some(Utc::now());
+                entry.failures += 1;
+            }
+        }
+    }
+}
+
+// Frees the pooled buffers once captures have stopped for a while.
+#[cfg(feature = "capture")]
+pub(crate) fn release_pool_when_idle(app: &AppHandle) {
+    let generation = app.state::<CapturePerf>().generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(POOL_IDLE_RELEASE).await;
+        let perf = app.state::<CapturePerf>();
+        if perf.generation.load(Ordering::SeqCst) == generation {
+            perf.pool.release();
+            tracing::debug!("capture buffers released");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_capture_perf_stats(state: State<'_, CapturePerf>) -> CapturePerfStats {
+    CapturePerfStats {
+        captures: state.captures.load(Ordering::Relaxed),
+        last_hide_wait_ms: state.last_hide_wait_ms.load(Ordering::Relaxed),
+        last_grab_ms: state.last_grab_ms.load(Ordering::Relaxed),
+        last_encode_ms: state.last_encode_ms.load(Ordering::Relaxed),
+        last_peak_bytes: state.last_peak_bytes.load(Ordering::Relaxed),
+        last_backend: state.last_backend.lock().map(|b| *b).unwrap_or_default(),
+        last_source_format: state.last_source_format.lock().map(|f| *f).unwrap_or_default(),
+        pool: state.pool.stats(),
+    }
+}
+
+// Whether each capture backend can run here, and its last success and
+// error. The primary display falls back from one backend to the other, so
+// errors here don't necessarily mean a capture failed.
+#[tauri::command]
+pub fn get_capture_diagnostics(
+    settings: State<'_, SharedSettings>,
+    perf: State<'_, CapturePerf>,
+) -> CommandResult<CaptureDiagnostics> {
+    let preferred = settings.0.lock()?.capture_backend;
+    let health = perf.backend_health.lock()?;
+    let backends = [CaptureBackend::Dxgi, CaptureBackend::Standard]
+        .into_iter()
+        .map(|backend| {
+            health.iter().find(|d| d.backend == backend).cloned().unwrap_or_else(|| BackendDiagnostics::new(backend))
+        })
+        .collect();
+    Ok(CaptureDiagnostics { preferred, backends })
+}
+
+// Connected displays, left to right then top to bottom. An empty list means
+// none were found, e.g. on a headless session.
+#[tauri::command]
+pub async fn list_screens() -> CommandResult<Vec<ScreenInfo>> {
+    let screens = tauri::async_runtime::spawn_blocking(enumerate_screens)
+        .await
+        .map_err(|e| CaptureError::Failed(e.to_string()))??;
+    if screens.is_empty() {
+        tracing::warn!("no screens found");
+    }
+    Ok(screens)
+}
+
+#[cfg(feature = "capture")]
+fn enumerate_screens() -> Result<Vec<ScreenInfo>, CaptureError> {
+    crate::screen_grab::list_screens().map_err(|e| CaptureError::Failed(format!("could not list screens: {e}")))
+}
+
+#[cfg(not(feature = "capture"))]
+fn enumerate_screens() -> Result<Vec<ScreenInfo>, CaptureError> {
+    Err(CaptureError::Unavailable)
+}
+
+// The display under the mouse cursor, else the primary one. Listing the
+// displays goes to the OS, so it runs on a blocking thread.
+#[cfg(feature = "capture")]
+pub async fn active_screen_id(app: &AppHandle) -> Option<u32> {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || crate::screen_grab::active_screen_id(&app)).await.ok().flatten()
+}
+
+#[cfg(not(feature = "capture"))]
+pub async fn active_screen_id(_app: &AppHandle) -> Option<u32> {
+    None
+}
+
+// Built without the `capture` feature: every capture path reports that
+// instead of touching the window.
+#[cfg(not(feature = "capture"))]
+async fn grab_hiding_window(
+    _window: &tauri::Window,
+    _target: CaptureTarget,
+    _options: &CaptureOptions,
+    _cancel: &CancellationToken,
+) -> Result<CapturedScreen, CaptureError> {
+    Err(CaptureError::Unavailable)
+}
+
+#[cfg(not(feature = "capture"))]
+async fn grab_all_hiding_window(
+    _window: &tauri::Window,
+    _options: &CaptureOptions,
+    _cancel: &CancellationToken,
+) -> Result<Vec<ScreenCapture>, CaptureError> {
+    Err(CaptureError::Unavailable)
+}
+
+#[cfg(not(feature = "capture"))]
+async fn guard_payload(
+    _app: &AppHandle,
+    shot: CapturedScreen,
+    _cancel: &CancellationToken,
+) -> Result<CapturedScreen, CaptureError> {
+    Ok(shot)
+}
+
+#[cfg(not(feature = "capture"))]
+async fn grab_burst_hiding_window(
+    _window: &tauri::Window,
+    _target: CaptureTarget,
+    _frames: u8,
+    _interval: Duration,
+    _options: &CaptureOptions,
+    _cancel: &CancellationToken,
+) -> Result<BurstCapture, CaptureError> {
+    Err(CaptureError::Unavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    const LIMIT: RateLimit = RateLimit { interval: Duration::from_millis(500), burst: 4, reserve: 0 };
+
+    #[test]
+    fn a_full_bucket_allows_a_burst_then_throttles() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(LIMIT, start);
+        for _ in 0..LIMIT.burst {
+            assert_eq!(bucket.take(LIMIT, start), Ok(()));
+        }
+        assert_eq!(bucket.take(LIMIT, start), Err(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn tokens_come_back_one_per_interval() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(LIMIT, start);
+        for _ in 0..LIMIT.burst {
+            bucket.take(LIMIT, start).unwrap();
+        }
+        assert_eq!(bucket.take(LIMIT, start + Duration::from_millis(250)), Err(Duration::from_millis(250)));
+        assert_eq!(bucket.take(LIMIT, start + Duration::from_millis(500)), Ok(()));
+        assert_eq!(bucket.take(LIMIT, start + Duration::from_millis(500)), Err(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn refill_stops_at_the_burst() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(LIMIT, start);
+        bucket.take(LIMIT, start).unwrap();
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..LIMIT.burst {
+            assert_eq!(bucket.take(LIMIT, later), Ok(()));
+        }
+        assert!(bucket.take(LIMIT, later).is_err());
+    }
+
+    #[test]
+    fn a_given_back_token_can_be_taken_again() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(LIMIT, start);
+        for _ in 0..LIMIT.burst {
+            bucket.take(LIMIT, start).unwrap();
+        }
+        bucket.give_back(LIMIT);
+        assert_eq!(bucket.take(LIMIT, start), Ok(()));
+    }
+
+    #[test]
+    fn a_background_capture_leaves_a_token_for_interactive_ones() {
+        let start = Instant::now();
+        let background = LIMIT.in_background();
+        let mut bucket = TokenBucket::full(LIMIT, start);
+        for _ in 1..LIMIT.burst {
+            assert_eq!(bucket.take(background, start), Ok(()));
+        }
+        assert_eq!(bucket.take(background, start), Err(Duration::from_millis(500)));
+        assert_eq!(bucket.take(LIMIT, start), Ok(()));
+        assert_eq!(bucket.take(background, start), Err(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn a_burst_of_one_has_nothing_to_reserve() {
+        let single = RateLimit { interval: Duration::from_millis(500), burst: 1, reserve: 0 };
+        assert_eq!(single.in_background().reserve, 0);
+        let start = Instant::now();
+        assert_eq!(TokenBucket::full(single, start).take(single.in_background(), start), Ok(()));
+    }
+
+    #[test]
+    fn a_zero_interval_never_throttles() {
+        let off = RateLimit { interval: Duration::ZERO, burst: 1, reserve: 0 };
+        let now = Instant::now();
+        let mut bucket = TokenBucket::full(off, now);
+        for _ in 0..100 {
+            assert_eq!(bucket.take(off, now), Ok(()));
+        }
+    }
+
+    // Every call after the burst is turned away without reaching the grab,
+    // however fast they come.
+    #[tokio::test]
+    async fn hammering_the_limiter_only_grabs_the_burst() {
+        let limiter = CaptureRateLimiter::default();
+        let limit = RateLimit { interval: Duration::from_secs(3600), burst: 3, reserve: 0 };
+        let cancel = CancellationToken::new();
+        let (grabs, throttled) = (&AtomicU32::new(0), &AtomicU32::new(0));
+        let calls = (0..20).map(|_| {
+            limiter.run_reporting(
+                limit,
+                RateLimitPolicy::Error,
+                &cancel,
+                || false,
+                move || async move { Ok::<_, CaptureError>(grabs.fetch_add(1, Ordering::SeqCst)) },
+                || None,
+                move |_| {
+                    throttled.fetch_add(1, Ordering::SeqCst);
+                },
+            )
+        });
+        let results = futures_util::future::join_all(calls).await;
+        assert_eq!(grabs.load(Ordering::SeqCst), 3);
+        assert_eq!(throttled.load(Ordering::SeqCst), 17);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+        assert!(results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .all(|e| matches!(e, CaptureError::RateLimited { retry_after_ms } if *retry_after_ms > 0)));
+    }
+
+    #[tokio::test]
+    async fn throttled_calls_reuse_the_latest_capture() {
+        let limiter = CaptureRateLimiter::default();
+        let limit = RateLimit { interval: Duration::from_secs(3600), burst: 1, reserve: 0 };
+        let cancel = CancellationToken::new();
+        let grabs = &AtomicU32::new(0);
+        let grab = move || async move { Ok::<_, CaptureError>(grabs.fetch_add(1, Ordering::SeqCst) + 1) };
+        let policy = RateLimitPolicy::Reuse;
+        let first = limiter.run_reporting(limit, policy, &cancel, || false, grab, || Some(0), |_| {}).await;
+        assert_eq!(first.unwrap(), 1);
+        let mut reported = None;
+        let second = limiter
+            .run_reporting(limit, policy, &cancel, || false, grab, || Some(0), |t| reported = Some(t.reused))
+            .await;
+        assert_eq!(second.unwrap(), 0);
+        assert_eq!(reported, Some(true));
+        assert_eq!(grabs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_grab_gives_its_token_back() {
+        let limiter = CaptureRateLimiter::default();
+        let limit = RateLimit { interval: Duration::from_secs(3600), burst: 1, reserve: 0 };
+        let cancel = CancellationToken::new();
+        let policy = RateLimitPolicy::Error;
+        let cancelled = || async { Err::<(), _>(CaptureError::Cancelled) };
+        let result = limiter.run_reporting(limit, policy, &cancel, || false, cancelled, || None, |_| {}).await;
+        assert!(matches!(result, Err(CaptureError::Cancelled)));
+        let ok = || async { Ok(()) };
+        let result = limiter.run_reporting(limit, policy, &cancel, || false, ok, || None, |_| {}).await;
+        assert!(result.is_ok());
+    }
+
+    // Paused while it waited behind another capture: it gets nowhere near
+    // the grab and leaves the token for after the pause.
+    #[tokio::test]
+    async fn a_pause_while_queued_stops_the_capture() {
+        let limiter = CaptureRateLimiter::default();
+        let limit = RateLimit { interval: Duration::from_secs(3600), burst: 1, reserve: 0 };
+        let cancel = CancellationToken::new();
+        let policy = RateLimitPolicy::Error;
+        let (paused, grabs) = (&AtomicBool::new(false), &AtomicU32::new(0));
+        let is_paused = || paused.load(Ordering::SeqCst);
+        let grab = || async {
+            grabs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+        let ahead = async {
+            let _held = limiter.0.lock().await;
+            tokio::task::yield_now().await;
+            paused.store(true, Ordering::SeqCst);
+        };
+        let queued = limiter.run_reporting(limit, policy, &cancel, is_paused, grab, || None, |_| {});
+        let (_, result) = tokio::join!(ahead, queued);
+        assert!(matches!(result, Err(CaptureError::Paused)));
+        assert_eq!(grabs.load(Ordering::SeqCst), 0);
+        paused.store(false, Ordering::SeqCst);
+        let result = limiter.run_reporting(limit, policy, &cancel, is_paused, grab, || None, |_| {}).await;
+        assert!(result.is_ok());
+        assert_eq!(grabs.load(Ordering::SeqCst), 1);
+    }
+
+    // The grab and encode run on a blocking thread, as in `grab_hiding_window`,
+    // so a slow capture leaves even a single-threaded runtime free to answer
+    // `get_server_status` meanwhile.
+    #[tokio::test]
+    async fn a_slow_capture_does_not_hold_up_other_commands() {
+        const GRAB: Duration = Duration::from_millis(400);
+        let limiter = CaptureRateLimiter::default();
+        let server = crate::server::ServerSupervisor::default();
+        let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+        let options = CaptureOptions::default();
+        let started = Instant::now();
+        let slow_grab = || async {
+            tauri::async_runtime::spawn_blocking(|| std::thread::sleep(GRAB))
+                .await
+                .map_err(|e| CaptureError::Failed(e.to_string()))
+        };
+        let capture = limiter.run_reporting(
+            LIMIT,
+            RateLimitPolicy::Error,
+            &cancel,
+            || false,
+            || with_retries(&options, &cancel, || false, &attempts, slow_grab),
+            || None,
+            |_| {},
+        );
+        let status = async {
+            // Lets the capture get going first.
+            tokio::task::yield_now().await;
+            (server.status(), started.elapsed())
+        };
+        let (captured, (status, answered_after)) = tokio::join!(capture, status);
+        assert!(captured.is_ok());
+        assert!(started.elapsed() >= GRAB);
+        assert_eq!(status, crate::server::ServerStatus::Stopped);
+        assert!(answered_after < GRAB / 4, "status waited {answered_after:?} on the capture");
+    }
+
+    // Fails with `error` for the first `failures` calls, then succeeds.
+    fn failing(
+        failures: u32,
+        error: fn() -> CaptureError,
+    ) -> impl FnMut() -> std::future::Ready<Result<u32, CaptureError>> {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            std::future::ready(if calls <= failures { Err(error()) } else { Ok(calls) })
+        }
+    }
+
+    fn retrying(attempts: Option<u32>, backoff_ms: Option<u64>) -> CaptureOptions {
+        CaptureOptions { retry_attempts: attempts, retry_backoff_ms: backoff_ms, ..CaptureOptions::default() }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_failures_are_retried_with_doubling_backoff() {
+        let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+        let started = tokio::time::Instant::now();
+        let grab = failing(u32::MAX, || CaptureError::NoDisplay);
+        let result = with_retries(&retrying(None, None), &cancel, || false, &attempts, grab).await;
+        assert!(matches!(result, Err(CaptureError::NoDisplay)));
+        assert_eq!(attempts.into_inner(), DEFAULT_RETRY_ATTEMPTS);
+        assert_eq!(started.elapsed(), Duration::from_millis(100 + 200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_retry_that_works_stops_retrying() {
+        let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+        let grab = failing(2, || CaptureError::Failed("device lost".into()));
+        let result = with_retries(&retrying(Some(5), Some(10)), &cancel, || false, &attempts, grab).await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts.into_inner(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_backoff_stops_growing_at_three_times_the_first() {
+        let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+        let started = tokio::time::Instant::now();
+        let grab = failing(u32::MAX, || CaptureError::NoDisplay);
+        let _ = with_retries(&retrying(Some(5), Some(100)), &cancel, || false, &attempts, grab).await;
+        assert_eq!(started.elapsed(), Duration::from_millis(100 + 200 + 300 + 300));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lasting_failures_are_not_retried() {
+        let lasting: [fn() -> CaptureError; 3] =
+            [|| CaptureError::PermissionDenied, || CaptureError::ScreenNotFound(2), || CaptureError::Cancelled];
+        for error in lasting {
+            let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+            let grab = failing(u32::MAX, error);
+            let result = with_retries(&retrying(Some(5), None), &cancel, || false, &attempts, grab).await;
+            assert!(result.is_err());
+            assert_eq!(attempts.into_inner(), 1);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_attempt_turns_retrying_off() {
+        let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+        let grab = failing(1, || CaptureError::NoDisplay);
+        let result = with_retries(&retrying(Some(1), None), &cancel, || false, &attempts, grab).await;
+        assert!(matches!(result, Err(CaptureError::NoDisplay)));
+        assert_eq!(attempts.into_inner(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_pause_between_attempts_ends_the_retries() {
+        let (cancel, attempts, paused) = (CancellationToken::new(), AtomicU32::new(0), AtomicBool::new(false));
+        let mut fail = failing(u32::MAX, || CaptureError::NoDisplay);
+        let grab = || {
+            // Paused while the first attempt runs.
+            paused.store(true, Ordering::SeqCst);
+            fail()
+        };
+        let is_paused = || paused.load(Ordering::SeqCst);
+        let result = with_retries(&retrying(Some(5), None), &cancel, is_paused, &attempts, grab).await;
+        assert!(matches!(result, Err(CaptureError::Paused)));
+        assert_eq!(attempts.into_inner(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelling_ends_the_backoff() {
+        let (cancel, attempts) = (CancellationToken::new(), AtomicU32::new(0));
+        cancel.cancel();
+        let grab = failing(u32::MAX, || CaptureError::NoDisplay);
+        let result = with_retries(&retrying(Some(5), None), &cancel, || false, &attempts, grab).await;
+        assert!(matches!(result, Err(CaptureError::Cancelled)));
+        assert_eq!(attempts.into_inner(), 1);
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_milliseconds() {
+        assert_eq!(retry_after_ms(Duration::ZERO), 0);
+        assert_eq!(retry_after_ms(Duration::from_millis(250)), 250);
+        assert_eq!(retry_after_ms(Duration::from_micros(1500)), 2);
+        assert_eq!(retry_after_ms(Duration::from_nanos(1)), 1);
+    }
+
+    // A 1920x1080 primary with a 1280x1024 display to its left, so the
+    // left one has a negative origin and there's a gap under the primary.
+    fn displays() -> Vec<ScreenInfo> {
+        let screen = |id, x, y, width, height| ScreenInfo {
+            id,
+            x,
+            y,
+            width,
+            height,
+            scale_factor: 1.0,
+            is_primary: id == 1,
+        };
+        vec![screen(1, 0, 0, 1920, 1080), screen(2, -1280, 0, 1280, 1024)]
+    }
+
+    fn area(screen_id: u32, x: i32, y: i32, width: u32, height: u32) -> ScreenArea {
+        ScreenArea { screen_id, x, y, width, height }
+    }
+
+    #[test]
+    fn regions_on_a_negative_origin_display_are_relative_to_it() {
+        let screens = displays();
+        let (found, screen, covered) = resolve_region(&screens, -1000, 100, 200, 100).unwrap();
+        assert_eq!(screen.id, 2);
+        assert_eq!(found, area(2, 280, 100, 200, 100));
+        assert_eq!(covered, [-1000, 100, 200, 100]);
+    }
+
+    #[test]
+    fn regions_are_clamped_to_the_display_they_start_on() {
+        let screens = displays();
+        // Starts on the left display and runs onto the primary.
+        let (found, _, covered) = resolve_region(&screens, -100, 50, 300, 100).unwrap();
+        assert_eq!(found, area(2, 1180, 50, 100, 100));
+        assert_eq!(covered, [-100, 50, 100, 100]);
+        // Starts on the primary and runs off its bottom right corner.
+        let (found, _, covered) = resolve_region(&screens, 1900, 1000, 100, 200).unwrap();
+        assert_eq!(found, area(1, 1900, 1000, 20, 80));
+        assert_eq!(covered, [1900, 1000, 20, 80]);
+        // Runs off the bottom of the shorter left display.
+        let (found, _, _) = resolve_region(&screens, -500, 1000, 100, 100).unwrap();
+        assert_eq!(found, area(2, 780, 1000, 100, 24));
+    }
+
+    #[test]
+    fn regions_starting_off_every_display_are_rejected() {
+        let screens = displays();
+        for (x, y) in [(-1281, 0), (-100, 1050), (1920, 0), (0, -1)] {
+            let result = resolve_region(&screens, x, y, 10, 10);
+            assert!(matches!(result, Err(CaptureError::InvalidRegion(_))), "({x}, {y})");
+        }
+        assert!(matches!(resolve_region(&screens, 0, 0, 0, 10), Err(CaptureError::InvalidRegion(_))));
+    }
+
+    // Positions given in layout units, turned into the physical pixels at
+    // the primary's scale the cursor comes in. The primary is Retina here,
+    // so on macOS its points and pixels differ.
+    #[test]
+    fn the_cursor_is_matched_in_layout_units() {
+        let mut screens = displays();
+        screens[0].scale_factor = 2.0;
+        let primary_per_px = layout_units_per_pixel(&screens[0]);
+        let cursor = |x: f64, y: f64| {
+            screen_under_cursor(&screens, (x / primary_per_px, y / primary_per_px)).map(|(s, px)| (s.id, px))
+        };
+        let px = |units: f64| (units / primary_per_px).round() as i32;
+        assert_eq!(cursor(1919.0, 1079.0), Some((1, (px(1919.0), px(1079.0)))));
+        assert_eq!(cursor(0.0, 0.0), Some((1, (0, 0))));
+        // The left display is 1x, so its points are pixels.
+        assert_eq!(cursor(-1.0, 0.0), Some((2, (-1, 0))));
+        assert_eq!(cursor(-1280.0, 1023.0), Some((2, (-1280, 1023))));
+        assert_eq!(cursor(-1281.0, 0.0), None);
+        assert_eq!(cursor(-100.0, 1050.0), None);
+        assert_eq!(cursor(1920.0, 0.0), None);
+    }
+
+    #[test]
+    fn windows_go_to_the_display_holding_most_of_them() {
+        let screens = displays();
+        let window = |x, y, width, height| WindowRect { x, y, width, height };
+        // 100 pixels on the left display, 300 on the primary.
+        let (found, screen, covered) = resolve_window(&screens, window(-100, 200, 400, 300)).unwrap();
+        assert_eq!(screen.id, 1);
+        assert_eq!(found, area(1, 0, 200, 300, 300));
+        assert_eq!(covered, [0, 200, 300, 300]);
+        // Mostly on the left display, hanging off its top.
+        let (found, _, _) = resolve_window(&screens, window(-800, -50, 900, 150)).unwrap();
+        assert_eq!(found, area(2, 480, 0, 800, 100));
+        // Entirely in the gap under the primary's left neighbour.
+        let result = resolve_window(&screens, window(-600, 1030, 200, 40));
+        assert!(matches!(result, Err(CaptureError::InvalidRegion(_))));
+    }
+}
@@ -0,0 +1,428 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+#[cfg(feature = "capture")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State};
+use serde::Serialize;
+use tauri_plugin_opener::OpenerExt;
+use ts_rs::TS;
+
+use crate::capture::{
+    self, ByteBudget, CaptureBackend, CaptureError, CaptureFormat, CaptureOptions, CaptureTarget, CapturedScreen,
+    DisplayScale, Downscale, WindowHandling,
+};
+use crate::command_stats;
+use crate::screen_analysis::ScreenAnalysis;
+use crate::error::CommandResult;
+
+#[cfg(feature = "capture")]
+const DEFAULT_CAPACITY: usize = 20;
+
+pub struct StoredCapture {
+    pub id: String,
+    pub captured_at: DateTime<Utc>,
+    pub monitor: String,
+    // Kept encoded, the form it's handed out in; the image bytes are
+    // decoded on the rare occasions they're needed.
+    pub base64: Arc<String>,
+    // SHA-256 of the image bytes, in hex.
+    pub content_hash: String,
+    pub backend: CaptureBackend,
+    pub target: CaptureTarget,
+    pub options: CaptureOptions,
+    pub width: u32,
+    pub height: u32,
+    pub downscale: Option<Downscale>,
+    pub thumbnail: Option<Arc<String>>,
+    pub window_handling: WindowHandling,
+    pub redacted: usize,
+    pub analysis: Option<ScreenAnalysis>,
+    pub scale: Option<DisplayScale>,
+    pub budget: Option<ByteBudget>,
+    // Set once the capture has been written to disk.
+    pub path: Option<PathBuf>,
+}
+
+// A fresh capture as handed to `record`.
+#[cfg(feature = "capture")]
+pub struct NewCapture {
+    pub base64: Arc<String>,
+    pub content_hash: String,
+    pub monitor: String,
+    pub backend: CaptureBackend,
+    pub target: CaptureTarget,
+    pub options: CaptureOptions,
+    pub width: u32,
+    pub height: u32,
+    pub downscale: Option<Downscale>,
+    pub thumbnail: Option<Arc<String>>,
+    pub window_handling: WindowHandling,
+    pub redacted: usize,
+    pub analysis: Option<ScreenAnalysis>,
+    pub scale: Option<DisplayScale>,
+    pub budget: Option<ByteBudget>,
+}
+
+// What `get_capture_bytes` would return for a capture, without the bytes.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CaptureInfo {
+    pub capture_id: String,
+    pub captured_at: DateTime<Utc>,
+    pub monitor: String,
+    pub format: CaptureFormat,
+    pub width: u32,
+    pub height: u32,
+    #[ts(type = "number")]
+    pub bytes: usize,
+    // SHA-256 of the image bytes, in hex; `get_screenshot` takes it in place
+    // of the id.
+    pub content_hash: String,
+    pub downscale: Option<Downscale>,
+    pub scale: Option<DisplayScale>,
+    pub budget: Option<ByteBudget>,
+}
+
+// One capture from the history with its image, for showing it again.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct Screenshot {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub info: CaptureInfo,
+    #[ts(type = "string")]
+    pub base64: Arc<String>,
+}
+
+// Recent captures kept in memory so later commands can refer to them by id.
+pub struct CaptureHistory {
+    captures: Mutex<VecDeque<StoredCapture>>,
+    #[cfg(feature = "capture")]
+    capacity: usize,
+    // Files written for memory-only captures, for the retention cleanup.
+    temp_files: Mutex<Vec<PathBuf>>,
+}
+
+impl Default for CaptureHistory {
+    fn default() -> Self {
+        Self {
+            captures: Mutex::new(VecDeque::new()),
+            #[cfg(feature = "capture")]
+            capacity: DEFAULT_CAPACITY,
+            temp_files: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(feature = "capture")]
+static NEXT_CAPTURE: AtomicU64 = AtomicU64::new(1);
+
+impl CaptureHistory {
+    #[cfg(feature = "capture")]
+    pub fn record(&self, capture: NewCapture) -> String {
+        let NewCapture {
+            base64,
+            content_hash,
+            monitor,
+            backend,
+            target,
+            options,
+            width,
+            height,
+            downscale,
+            thumbnail,
+            window_handling,
+            redacted,
+            analysis,
+            scale,
+            budget,
+        } = capture;
+        let captured_at = Utc::now();
+        let id = format!(
+            "cap-{}-{}",
+            captured_at.format("%Y%m%d%H%M%S"),
+            NEXT_CAPTURE.fetch_add(1, Ordering::Relaxed)
+        );
+        if let Ok(mut captures) = self.captures.lock() {
+            captures.push_back(StoredCapture {
+                id: id.clone(),
+                captured_at,
+                monitor,
+                base64,
+                content_hash,
+                backend,
+                target,
+                options,
+                width,
+                height,
+                downscale,
+                thumbnail,
+                window_handling,
+                redacted,
+                analysis,
+                scale,
+                budget,
+                path: None,
+            });
+            // The history holds the only lasting reference to an image, so
+            // an evicted one is freed once any response still carrying it
+            // has been sent.
+            while captures.len() > self.capacity {
+                captures.pop_front();
+            }
+        }
+        id
+    }
+
+    // Most recent capture, if it was taken within `max_age` for the same
+    // target and encoding.
+    pub fn latest_within(
+        &self,
+        max_age: std::time::Duration,
+        target: CaptureTarget,
+        options: &CaptureOptions,
+    ) -> Option<CapturedScreen> {
+        let captures = self.captures.lock().ok()?;
+        let latest = captures.back().filter(|c| c.target == target && c.options.encoding() == options.encoding())?;
+        let age = (Utc::now() - latest.captured_at).to_std().unwrap_or_default();
+        (age <= max_age).then(|| CapturedScreen {
+            from_cache: true,
+            // The very image handed out last time.
+            unchanged: true,
+            ..CapturedScreen::from_entry(latest)
+        })
+    }
+
+    // Capture `id` as it was recorded.
+    pub fn captured(&self, id: &str) -> Option<CapturedScreen> {
+        let captures = self.captures.lock().ok()?;
+        captures.iter().rev().find(|c| c.id == id).map(CapturedScreen::from_entry)
+    }
+
+    pub fn image_bytes(&self, id: &str) -> Option<Vec<u8>> {
+        let base64 = {
+            let captures = self.captures.lock().ok()?;
+            captures.iter().find(|c| c.id == id)?.base64.clone()
+        };
+        base64::engine::general_purpose::STANDARD.decode(base64.as_bytes()).ok()
+    }
+
+    // Describes capture `id`, or the most recent capture without one.
+    pub fn info(&self, id: Option<&str>) -> Result<CaptureInfo, CaptureError> {
+        self.screenshot(id).map(|s| s.info)
+    }
+
+    // Capture `id`, or the most recent capture without one. `id` may also be
+    // a content hash, which finds the latest capture with that image.
+    pub fn screenshot(&self, id: Option<&str>) -> Result<Screenshot, CaptureError> {
+        let captures = self.captures.lock().map_err(|e| CaptureError::Failed(e.to_string()))?;
+        let capture = match id {
+            Some(id) => captures.iter().rev().find(|c| c.id == id || c.content_hash == id),
+            None => captures.back(),
+        }
+        .ok_or_else(|| CaptureError::CaptureNotFound(id.unwrap_or("latest").to_string()))?;
+        Ok(Screenshot { info: describe(capture), base64: capture.base64.clone() })
+    }
+
+    // Every capture still held, newest first.
+    pub fn list(&self) -> Vec<CaptureInfo> {
+        self.captures.lock().map(|c| c.iter().rev().map(describe).collect()).unwrap_or_default()
+    }
+
+    // Drops every capture and returns how many there were. Files written
+    // for them are left to `cleanup_temp_files`.
+    pub fn clear(&self) -> usize {
+        self.captures.lock().map(|mut c| std::mem::take(&mut *c).len()).unwrap_or(0)
+    }
+
+    // Returns the on-disk path for a capture, writing it to `dir` first if it
+    // only exists in memory.
+    pub fn ensure_on_disk(&self, id: &str, dir: &std::path::Path) -> Result<PathBuf, CaptureError> {
+        let mut captures = self.captures.lock().map_err(|e| CaptureError::Failed(e.to_string()))?;
+        let capture = captures
+            .iter_mut()
+            .find(|c| c.id == id)
+            .ok_or_else(|| CaptureError::CaptureNotFound(id.to_string()))?;
+        if let Some(path) = capture.path.as_ref().filter(|p| p.exists()) {
+            return Ok(path.clone());
+        }
+
+        std::fs::create_dir_all(dir).map_err(|e| CaptureError::Failed(e.to_string()))?;
+        let path = dir.join(format!("{id}.{}", capture.options.format.extension()));
+        let image = base64::engine::general_purpose::STANDARD
+            .decode(capture.base64.as_bytes())
+            .map_err(|e| CaptureError::Failed(e.to_string()))?;
+        std::fs::write(&path, image).map_err(|e| CaptureError::Failed(e.to_string()))?;
+        capture.path = Some(path.clone());
+        if let Ok(mut temp) = self.temp_files.lock() {
+            temp.push(path.clone());
+        }
+        Ok(path)
+    }
+
+    pub fn take_temp_files(&self) -> Vec<PathBuf> {
+        self.temp_files.lock().map(|mut t| std::mem::take(&mut *t)).unwrap_or_default()
+    }
+}
+
+fn describe(capture: &StoredCapture) -> CaptureInfo {
+    CaptureInfo {
+        capture_id: capture.id.clone(),
+        captured_at: capture.captured_at,
+        monitor: capture.monitor.clone(),
+        format: capture.options.format,
+        width: capture.width,
+        height: capture.height,
+        bytes: capture::decoded_len(&capture.base64),
+        content_hash: capture.content_hash.clone(),
+        downscale: capture.downscale,
+        scale: capture.scale,
+        budget: capture.budget,
+    }
+}
+
+pub fn screenshots_dir(app: &AppHandle) -> Result<PathBuf, CaptureError> {
+    app.path()
+        .app_cache_dir()
+        .map(|d| d.join("screenshots"))
+        .map_err(|e| CaptureError::Failed(e.to_string()))
+}
+
+// Deletes files written for memory-only captures.
+pub fn cleanup_temp_files(app: &AppHandle) {
+    for path in app.state::<CaptureHistory>().take_temp_files() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::warn!(path = %path.display(), error = %e, "failed to remove temporary capture");
+        }
+    }
+}
+
+// Size, format and dimensions of capture `capture_id`, or of the most recent
+// capture when it's left out.
+#[tauri::command]
+pub async fn get_capture_info(app: AppHandle, capture_id: Option<String>) -> CommandResult<CaptureInfo> {
+    command_stats::track(&app, "get_capture_info", async {
+        Ok(app.state::<CaptureHistory>().info(capture_id.as_deref())?)
+    })
+    .await
+}
+
+// The captures taken this session that are still held, newest first,
+// without their images.
+#[tauri::command]
+pub fn list_screenshots(history: State<'_, CaptureHistory>) -> Vec<CaptureInfo> {
+    history.list()
+}
+
+// A capture from the history with its base64 image, e.g. for a gallery.
+// `capture_id` may be a `content_hash` instead.
+#[tauri::command]
+pub async fn get_screenshot(app: AppHandle, capture_id: String) -> CommandResult<Screenshot> {
+    command_stats::track(&app, "get_screenshot", async {
+        Ok(app.state::<CaptureHistory>().screenshot(Some(&capture_id))?)
+    })
+    .await
+}
+
+// Forgets every capture and deletes any files written for them. Returns
+// how many captures were dropped.
+#[tauri::command]
+pub async fn clear_screenshots(app: AppHandle) -> CommandResult<usize> {
+    command_stats::track(&app, "clear_screenshots", async {
+        let cleared = app.state::<CaptureHistory>().clear();
+        let cleanup_app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || cleanup_temp_files(&cleanup_app)).await?;
+        tracing::info!(cleared, "capture history cleared");
+        Ok(cleared)
+    })
+    .await
+}
+
+// A capture from the history as raw image bytes over binary IPC, e.g. the
+// one a classify response refers to by `capture_id`. Always the full-size
+// image, even if the base64 handed out was shrunk.
+#[tauri::command]
+pub async fn get_capture_bytes(app: AppHandle, capture_id: String) -> CommandResult<tauri::ipc::Response> {
+    command_stats::track(&app, "get_capture_bytes", async {
+        let history_app = app.clone();
+        let bytes = tauri::async_runtime::spawn_blocking(move || {
+            history_app.state::<CaptureHistory>().image_bytes(&capture_id).ok_or(CaptureError::CaptureNotFound(capture_id))
+        })
+        .await??;
+        Ok(tauri::ipc::Response::new(bytes))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn open_screenshot_externally(app: AppHandle, capture_id: String) -> CommandResult<PathBuf> {
+    command_stats::track(&app, "open_screenshot_externally", async {
+        let dir = screenshots_dir(&app)?;
+        let history_app = app.clone();
+        let path = tauri::async_runtime::spawn_blocking(move || {
+            history_app.state::<CaptureHistory>().ensure_on_disk(&capture_id, &dir)
+        })
+        .await??;
+        app.opener()
+            .open_path(path.to_string_lossy(), None::<&str>)
+            .map_err(|e| CaptureError::Failed(e.to_string()))?;
+        Ok(path)
+    })
+    .await
+}
+
+#[cfg(all(test, feature = "capture"))]
+mod tests {
+    use super::*;
+    use crate::base64_stream::{content_hash, Base64Writer};
+    use std::io::Write;
+
+    // Records `image` as a capture, hashed the way the capture pipeline does.
+    fn record(history: &CaptureHistory, image: &[u8]) -> String {
+        let mut writer = Base64Writer::with_capacity(image.len());
+        writer.write_all(image).unwrap();
+        let (base64, content_hash) = writer.finish_with_hash();
+        history.record(NewCapture {
+            base64: Arc::new(base64),
+            content_hash,
+            monitor: "test".to_string(),
+            backend: CaptureBackend::Standard,
+            target: CaptureTarget::Default,
+            options: CaptureOptions::default(),
+            width: 1,
+            height: 1,
+            downscale: None,
+            thumbnail: None,
+            window_handling: WindowHandling::Untouched,
+            redacted: 0,
+            analysis: None,
+            scale: None,
+            budget: None,
+        })
+    }
+
+    #[test]
+    fn a_screenshot_can_be_looked_up_by_its_content_hash() {
+        let history = CaptureHistory::default();
+        let first = record(&history, b"same image");
+        let other = record(&history, b"another image");
+        let again = record(&history, b"same image");
+
+        let hash = content_hash(b"same image");
+        assert_eq!(history.screenshot(Some(&first)).unwrap().info.content_hash, hash);
+        assert_eq!(history.screenshot(Some(&again)).unwrap().info.content_hash, hash);
+        // The latest capture with that image.
+        let by_hash = history.screenshot(Some(&hash)).unwrap();
+        assert_eq!(by_hash.info.capture_id, again);
+        assert_eq!(*by_hash.base64, base64::engine::general_purpose::STANDARD.encode(b"same image"));
+
+        let other_hash = content_hash(b"another image");
+        assert_eq!(history.info(Some(&other_hash)).unwrap().capture_id, other);
+        assert_eq!(history.screenshot(None).unwrap().info.capture_id, again);
+        let missing = history.screenshot(Some(&content_hash(b"never captured")));
+        assert!(matches!(missing, Err(CaptureError::CaptureNotFound(_))));
+    }
+}
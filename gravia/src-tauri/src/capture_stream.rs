@@ -0,0 +1,260 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use ts_rs::TS;
+
+use crate::capture::{self, CaptureError, CaptureFormat, CaptureOptions, CaptureTarget, RateLimitPolicy};
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::idle;
+use crate::power::{self, Throttle};
+
+const MIN_INTERVAL_MS: u64 = 500;
+// Frames sent but not yet acknowledged before new ones are dropped.
+const MAX_UNACKED_FRAMES: u64 = 2;
+
+// One frame of a capture stream. `base64` is left out when the screen is
+// unchanged since the previous frame, unless the stream's options include
+// unchanged captures.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ScreenshotFrame {
+    #[ts(type = "number")]
+    pub sequence: u64,
+    pub capture_id: String,
+    #[ts(type = "string | null")]
+    pub base64: Option<Arc<String>>,
+    pub format: CaptureFormat,
+    pub unchanged: bool,
+    // Frames skipped since the previous one: the frontend was behind, the
+    // user was idle, power saving paused the stream, or the capture was
+    // paused, rate limited or failed.
+    #[ts(type = "number")]
+    pub dropped: u64,
+}
+
+// What `get_capture_stream_status` reports about the running stream,
+// including any power throttling of its frame rate.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CaptureStreamStatus {
+    pub active: bool,
+    // As the stream was started with.
+    #[ts(type = "number | null")]
+    pub interval_ms: Option<u64>,
+    // Between frames once `throttle` is applied.
+    #[ts(type = "number | null")]
+    pub effective_interval_ms: Option<u64>,
+    // Frames are skipped while the user is idle.
+    pub idle_paused: bool,
+    // Frames are skipped altogether while `pause_optional` is set.
+    pub throttle: Throttle,
+}
+
+// The running stream, if any. Only one runs at a time.
+#[derive(Default)]
+pub struct CaptureStream(Mutex<Option<ActiveStream>>);
+
+struct ActiveStream {
+    id: u64,
+    window: String,
+    interval: Duration,
+    cancel: CancellationToken,
+    progress: Arc<StreamProgress>,
+}
+
+// Shared between the stream's task and the commands.
+#[derive(Default)]
+struct StreamProgress {
+    // Highest sequence number the frontend has acknowledged.
+    acked: AtomicU64,
+    idle_paused: AtomicBool,
+}
+
+static NEXT_STREAM: AtomicU64 = AtomicU64::new(1);
+
+impl CaptureStream {
+    fn stop(&self) -> bool {
+        let active = self.0.lock().ok().and_then(|mut s| s.take());
+        active.map(|s| s.cancel.cancel()).is_some()
+    }
+}
+
+// Captures the screen every `interval_ms` (at least 500) and emits each one
+// as `screenshot-frame`. Acknowledge frames with `ack_screenshot_frame`;
+// while two are unacknowledged new ones are dropped, so a busy frontend
+// doesn't build up a queue. Fails with `stream_active` if a stream is
+// already running.
+#[tauri::command]
+pub fn start_capture_stream(
+    window: tauri::Window,
+    state: State<'_, CaptureStream>,
+    interval_ms: u64,
+    options: Option<CaptureOptions>,
+) -> CommandResult<()> {
+    if interval_ms < MIN_INTERVAL_MS {
+        return Err(GraviaError::InvalidArgument(format!("interval_ms must be at least {MIN_INTERVAL_MS}")));
+    }
+    let mut options = CaptureOptions::resolve(options)?;
+    // Every frame should show the screen as it is now, without using up the
+    // rate limit interactive captures need.
+    options.force = true;
+    options.background = true;
+
+    let mut active = state.0.lock()?;
+    if active.is_some() {
+        return Err(CaptureError::StreamActive.into());
+    }
+    let id = NEXT_STREAM.fetch_add(1, Ordering::Relaxed);
+    let cancel = CancellationToken::new();
+    let progress = Arc::new(StreamProgress::default());
+    let interval = Duration::from_millis(interval_ms);
+    *active = Some(ActiveStream {
+        id,
+        window: window.label().to_string(),
+        interval,
+        cancel: cancel.clone(),
+        progress: progress.clone(),
+    });
+    tracing::info!(interval_ms, "capture stream started");
+    tauri::async_runtime::spawn(async move {
+        run(&window, interval, &options, &cancel, &progress).await;
+        let stream = window.state::<CaptureStream>();
+        if let Ok(mut active) = stream.0.lock() {
+            if active.as_ref().is_some_and(|s| s.id == id) {
+                *active = None;
+            }
+        }
+        tracing::info!("capture stream stopped");
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_capture_stream(state: State<'_, CaptureStream>) -> CommandResult<()> {
+    state.stop();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn ack_screenshot_frame(state: State<'_, CaptureStream>, sequence: u64) -> CommandResult<()> {
+    if let Some(active) = state.0.lock()?.as_ref() {
+        active.progress.acked.fetch_max(sequence, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_capture_stream_status(
+    app: AppHandle,
+    state: State<'_, CaptureStream>,
+) -> CommandResult<CaptureStreamStatus> {
+    let throttle = power::throttle(&app);
+    let active = state.0.lock()?;
+    let interval = active.as_ref().map(|s| s.interval);
+    Ok(CaptureStreamStatus {
+        active: active.is_some(),
+        interval_ms: interval.map(|i| i.as_millis() as u64),
+        effective_interval_ms: interval.map(|i| throttle.scale(i).as_millis() as u64),
+        idle_paused: active.as_ref().is_some_and(|s| s.progress.idle_paused.load(Ordering::Relaxed)),
+        throttle,
+    })
+}
+
+// Stops a stream started from `window`, e.g. when it closes.
+pub fn stop_for_window(app: &AppHandle, window: &str) {
+    let stream = app.state::<CaptureStream>();
+    let started_here = stream
+        .0
+        .lock()
+        .map(|s| s.as_ref().is_some_and(|s| s.window == window))
+        .unwrap_or(false);
+    if started_here && stream.stop() {
+        tracing::info!(window, "capture stream stopped, window closed");
+    }
+}
+
+async fn run(
+    window: &tauri::Window,
+    interval: Duration,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+    progress: &StreamProgress,
+) {
+    let throttle = power::throttle(window.app_handle());
+    let mut factor = throttle.interval_factor;
+    let mut ticker = frame_ticker(Instant::now(), throttle.scale(interval));
+    let (mut sequence, mut dropped) = (0u64, 0u64);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = cancel.cancelled() => return,
+        }
+        // On battery frames come further apart, from the next one on; power
+        // saver or a low battery stops them.
+        let throttle = power::throttle(window.app_handle());
+        if throttle.interval_factor != factor {
+            factor = throttle.interval_factor;
+            let period = throttle.scale(interval);
+            ticker = frame_ticker(Instant::now() + period, period);
+        }
+        if throttle.pause_optional {
+            dropped += 1;
+            continue;
+        }
+        // Nobody's watching the screen change.
+        let idle = idle::is_idle(window.app_handle());
+        progress.idle_paused.store(idle, Ordering::Relaxed);
+        if idle {
+            dropped += 1;
+            continue;
+        }
+        if sequence.saturating_sub(progress.acked.load(Ordering::Relaxed)) >= MAX_UNACKED_FRAMES {
+            dropped += 1;
+            continue;
+        }
+        let shot = match capture::capture_hiding_window(
+            window,
+            CaptureTarget::Default,
+            options,
+            RateLimitPolicy::Error,
+            cancel,
+        )
+        .await
+        {
+            Ok(shot) => shot,
+            Err(CaptureError::Cancelled) => return,
+            // Paused or rate limited frames are skipped; the stream carries on.
+            Err(e) => {
+                tracing::debug!(error = %e, "stream frame skipped");
+                dropped += 1;
+                continue;
+            }
+        };
+        sequence += 1;
+        let frame = ScreenshotFrame {
+            sequence,
+            capture_id: shot.id,
+            base64: (!shot.unchanged || options.include_unchanged).then_some(shot.base64),
+            format: shot.format,
+            unchanged: shot.unchanged,
+            dropped: std::mem::take(&mut dropped),
+        };
+        if let Err(e) = emit_event(window, GraviaEvent::ScreenshotFrame(frame)) {
+            tracing::warn!(error = %e, "failed to emit screenshot frame, stopping stream");
+            return;
+        }
+    }
+}
+
+// Ticks every `period` from `start`. A slow capture pushes the next one back
+// rather than bunching them up.
+fn frame_ticker(start: Instant, period: Duration) -> Interval {
+    let mut ticker = tokio::time::interval_at(start, period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker
+}
@@ -1,8 +1,11 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use chrono::{DateTime, Utc, Duration};
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct ChatMessage {
     pub role: String,          // "user" or "assistant"
     pub content: String,
@@ -10,7 +13,8 @@ pub struct ChatMessage {
     pub triggered_screenshot: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct ContextInfo {
     pub has_context: bool,
     pub context_type: Option<String>,
@@ -20,21 +24,255 @@ pub struct ContextInfo {
     pub context_strength: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Why the classifier leaned one way, as a stable code plus any values the
+// display string needs. `i18n` turns these into `reasoning`.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Reason {
+    pub code: String,
+    #[serde(default)]
+    pub args: HashMap<String, String>,
+}
+
+impl Reason {
+    pub fn new(code: &str) -> Self {
+        Self { code: code.to_string(), args: HashMap::new() }
+    }
+
+    pub fn with_arg(mut self, name: &str, value: &str) -> Self {
+        self.args.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+// What `classify_and_maybe_capture` does about a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Capture,
+    Skip,
+    // Too close to call; the frontend asks and answers with `confirm_capture`.
+    AskUser,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
 pub struct ClassificationResult {
     pub needs_screenshot: bool,
+    // `needs_screenshot` is false while this is `ask_user`
+    pub decision: Decision,
+    // set with `ask_user`, for `confirm_capture`
+    pub query_id: Option<String>,
+    // how sure of `needs_screenshot`, from the score margin (see
+    // `margin_confidence`), or 0.99 when an explicit phrase decided it
     pub confidence: f32,
     pub screenshot_score: i32,
     pub no_screenshot_score: i32,
+    pub reasons: Vec<Reason>,
+    // localized display strings for `reasons`, in the same order
     pub reasoning: Vec<String>,
+    // "screenshot", "no_screenshot", "ask_user" or "suppressed"
+    pub summary_code: String,
+    pub summary: String,
     pub context_info: ContextInfo,
+    // machine-readable code when a screenshot was wanted but not allowed
+    pub suppressed_reason: Option<String>,
+    // the query is about text on screen, such as an error to read out
+    pub text_oriented: bool,
+    // the explicit phrase that decided it, e.g. "take a screenshot", in
+    // which case the scores weren't compared
+    #[serde(rename = "override")]
+    #[ts(rename = "override")]
+    pub override_rule: Option<String>,
     // base64 screenshot if captured
     pub screenshot_base64: Option<String>,
 }
 
+// Confidence is a logistic over the score margin as a share of all the
+// evidence, `margin / (total + CONFIDENCE_PRIOR)`, so a point of margin
+// counts for less the more both sides scored: 1 vs 0 is about 0.79 but 8
+// vs 7 only 0.56. Ties are 0.5, 2 vs 0 is 0.88, 4 vs 0 0.94, and it's
+// capped at 0.95. A bigger margin never reads as less sure.
+const CONFIDENCE_PRIOR: f32 = 2.0;
+const CONFIDENCE_STEEPNESS: f32 = 4.0;
+const MIN_CONFIDENCE: f32 = 0.05;
+const MAX_CONFIDENCE: f32 = 0.95;
+// Explicit "take a screenshot" style commands leave nothing to weigh.
+const OVERRIDE_CONFIDENCE: f32 = 0.99;
+// Below this confidence a query that points at the screen at all is asked
+// about rather than decided. 0.6 asks on ties, and on one-point margins
+// once both sides have a lot of evidence; the classifier config's
+// `ask_below_confidence` changes it.
+pub const DEFAULT_ASK_BELOW: f32 = 0.6;
+// Unanswered asks kept for `confirm_capture`.
+const MAX_PENDING_ASKS: usize = 16;
+
+static NEXT_ASK_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_ask_id() -> String {
+    let millis = Utc::now().timestamp_millis();
+    format!("ask-{millis:x}-{}", NEXT_ASK_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+// How sure the classifier is of the side the scores come down on.
+fn margin_confidence(screenshot_score: i32, no_screenshot_score: i32) -> f32 {
+    let (screenshot_score, no_screenshot_score) = (screenshot_score.max(0), no_screenshot_score.max(0));
+    let margin = screenshot_score.abs_diff(no_screenshot_score) as f32;
+    let total = (screenshot_score + no_screenshot_score) as f32;
+    let share = margin / (total + CONFIDENCE_PRIOR);
+    let confidence = 1.0 / (1.0 + (-CONFIDENCE_STEEPNESS * share).exp());
+    confidence.clamp(MIN_CONFIDENCE, MAX_CONFIDENCE)
+}
+
+impl ClassificationResult {
+    // Re-derives the decision and its confidence after a score was adjusted.
+    // `ask_below` is the confidence under which a borderline query is asked
+    // about; one with no screen evidence at all is just skipped.
+    pub fn decide(&mut self, ask_below: f32) {
+        if self.override_rule.is_none() {
+            self.needs_screenshot = self.screenshot_score > self.no_screenshot_score;
+            self.confidence = margin_confidence(self.screenshot_score, self.no_screenshot_score);
+        }
+        let borderline = self.override_rule.is_none() && self.screenshot_score > 0 && self.confidence < ask_below;
+        self.decision = if borderline {
+            self.needs_screenshot = false;
+            Decision::AskUser
+        } else if self.needs_screenshot {
+            Decision::Capture
+        } else {
+            Decision::Skip
+        };
+        self.summary_code = match self.decision {
+            Decision::Capture => "screenshot",
+            Decision::Skip => "no_screenshot",
+            Decision::AskUser => "ask_user",
+        }
+        .to_string();
+    }
+}
+
+// The keyword lists the classifier matches on. `classifier_config` builds
+// them from these defaults and the user's classifier.json.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Keywords {
+    pub screenshot: Vec<String>,
+    pub no_screenshot: Vec<String>,
+    pub followup: Vec<String>,
+    pub ui: Vec<String>,
+    pub error: Vec<String>,
+    pub task: Vec<String>,
+    // Phrases that decide a query outright, skipping the score comparison.
+    pub force_capture: Vec<String>,
+    pub forbid_capture: Vec<String>,
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        let list = |words: &[&str]| words.iter().map(|w| w.to_string()).collect();
+        Self {
+            screenshot: list(&[
+                "this", "that", "these", "those", "current", "currently",
+                "right now", "now", "here", "there", "visible", "see",
+                "seeing", "shown", "showing", "button", "popup", "dialog",
+                "menu", "which", "where", "help me"
+            ]),
+            no_screenshot: list(&[
+                "explain", "what is", "how to", "difference between",
+                "history of", "write", "create", "generate", "timer",
+                "reminder", "weather"
+            ]),
+            followup: list(&[
+                "which", "what", "where", "should i", "do i", "how about",
+                "what about", "is this", "does this", "can i", "may i",
+                "next", "then", "now what", "ok", "okay", "like this",
+                "correct", "right"
+            ]),
+            ui: list(&[
+                "go to", "click", "select", "find the", "open the",
+                "settings", "menu", "button", "option", "panel"
+            ]),
+            error: list(&["error", "problem", "issue", "troubleshoot"]),
+            task: list(&["step", "next", "then", "after", "now"]),
+            force_capture: list(&[
+                "take a screenshot", "take screenshot", "capture my screen",
+                "capture the screen", "look at my screen", "look at the screen",
+                "see what i see", "see my screen"
+            ]),
+            forbid_capture: list(&[
+                "without a screenshot", "without screenshot", "no screenshot",
+                "text only", "don t look", "dont look", "do not look"
+            ]),
+        }
+    }
+}
+
+// Text split into lowercase words, so keywords match whole words and
+// phrases: "this" doesn't match "thistle", nor "now" match "know".
+struct Words(Vec<String>);
+
+impl Words {
+    fn new(text: &str) -> Self {
+        // Apostrophes split too, so "what's" still starts with "what".
+        Words(text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect())
+    }
+
+    // `phrase` is one or more words, matched in order.
+    fn has(&self, phrase: &str) -> bool {
+        self.find(phrase).next().is_some()
+    }
+
+    // Where `phrase` starts and ends (exclusive), at each place it occurs.
+    fn find<'a>(&'a self, phrase: &str) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let phrase: Vec<String> = phrase.split_whitespace().map(str::to_string).collect();
+        let len = phrase.len();
+        self.0
+            .windows(len)
+            .enumerate()
+            .filter(move |(_, w)| w.iter().zip(&phrase).all(|(word, key)| matches(word, key)))
+            .map(move |(start, _)| (start, start + len))
+    }
+
+    // Whether one of `phrases` has one of `negators` ending at most `gap`
+    // words before it, as in "don't take a screenshot".
+    fn negated(&self, phrases: &[&str], negators: &[&str], gap: usize) -> bool {
+        phrases.iter().flat_map(|p| self.find(p)).any(|(start, _)| {
+            negators.iter().flat_map(|n| self.find(n)).any(|(_, end)| end <= start && start - end <= gap)
+        })
+    }
+
+    fn starts_with(&self, phrase: &str) -> bool {
+        let phrase: Vec<&str> = phrase.split_whitespace().collect();
+        self.0.len() >= phrase.len() && self.0.iter().zip(&phrase).all(|(word, key)| matches(word, key))
+    }
+
+    fn has_any<S: AsRef<str>>(&self, phrases: &[S]) -> bool {
+        phrases.iter().any(|p| self.has(p.as_ref()))
+    }
+
+    fn count<S: AsRef<str>>(&self, phrases: &[S]) -> i32 {
+        phrases.iter().filter(|p| self.has(p.as_ref())).count() as i32
+    }
+}
+
+// A keyword as the matcher sees it, e.g. "Don't" becomes "don t".
+pub fn normalize_keyword(keyword: &str) -> String {
+    Words::new(keyword).0.join(" ")
+}
+
+// A keyword matches its plural too, e.g. "error" in "errors".
+fn matches(word: &str, keyword: &str) -> bool {
+    word == keyword || word.strip_suffix('s').is_some_and(|w| w == keyword || w.strip_suffix('e') == Some(keyword))
+}
+
 pub struct ContextualScreenshotClassifier {
     chat_history: VecDeque<ChatMessage>,
     max_history: usize,
+    // Bumped on every change to `chat_history`, so saves can tell it moved.
+    revision: u64,
+    keywords: Keywords,
+    ask_below: f32,
 }
 
 impl ContextualScreenshotClassifier {
@@ -42,6 +280,27 @@ impl ContextualScreenshotClassifier {
         Self {
             chat_history: VecDeque::new(),
             max_history,
+            revision: 0,
+            keywords: Keywords::default(),
+            ask_below: DEFAULT_ASK_BELOW,
+        }
+    }
+
+    pub fn set_keywords(&mut self, keywords: Keywords) {
+        self.keywords = keywords;
+    }
+
+    pub fn set_ask_below(&mut self, ask_below: f32) {
+        self.ask_below = ask_below;
+    }
+
+    // Fills in `triggered_screenshot` on the user message sent at `sent_at`,
+    // once it's known whether a screenshot was taken for it.
+    fn record_choice(&mut self, sent_at: DateTime<Utc>, captured: bool) {
+        let message = self.chat_history.iter_mut().rev().find(|m| m.role == "user" && m.timestamp == sent_at);
+        if let Some(message) = message {
+            message.triggered_screenshot = Some(captured);
+            self.revision += 1;
         }
     }
     
@@ -50,53 +309,70 @@ impl ContextualScreenshotClassifier {
         if self.chat_history.len() > self.max_history {
             self.chat_history.pop_front();
         }
+        self.revision += 1;
     }
     
     pub fn classify_with_context(&self, query: &str) -> ClassificationResult {
-        let query_lower = query.to_lowercase();
-        let mut screenshot_score = self.get_base_screenshot_score(&query_lower);
-        let mut no_screenshot_score = self.get_base_no_screenshot_score(&query_lower);
-        let mut reasoning = Vec::new();
+        let query = Words::new(query);
+        let mut screenshot_score = self.get_base_screenshot_score(&query);
+        let mut no_screenshot_score = self.get_base_no_screenshot_score(&query);
+        let mut reasons = Vec::new();
         let context_info = self.analyze_recent_context();
-        let mut confidence: f32 = 0.7;
         if context_info.has_context {
-            if self.is_contextual_followup(&query_lower) {
+            if self.is_contextual_followup(&query) {
                 screenshot_score += 3;
-                confidence += 0.2;
-                reasoning.push(format!(
-                    "Contextual follow-up after {}",
-                    context_info.context_type.as_ref().unwrap_or(&"unknown".to_string())
+                reasons.push(Reason::new("contextual_followup").with_arg(
+                    "context",
+                    context_info.context_type.as_deref().unwrap_or("unknown"),
                 ));
             }
             if context_info.assistant_gave_instructions {
                 let ambiguous_words = ["this", "that", "it", "here", "there"];
-                if ambiguous_words.iter().any(|&word| query_lower.contains(word)) {
+                if query.has_any(&ambiguous_words) {
                     screenshot_score += 2;
-                    confidence += 0.15;
-                    reasoning.push("Ambiguous reference with UI context".to_string());
+                    reasons.push(Reason::new("ambiguous_ui_reference"));
                 }
             }
             if context_info.user_in_middle_of_task {
                 screenshot_score += 1;
-                confidence += 0.1;
-                reasoning.push("Continuation of ongoing task".to_string());
+                reasons.push(Reason::new("ongoing_task"));
             }
         }
         let clear_general = ["explain", "what is", "how to", "definition", "history"];
-        if clear_general.iter().any(|&phrase| query_lower.contains(phrase)) {
+        if query.has_any(&clear_general) {
             no_screenshot_score += 2;
-            reasoning.push("Clear general knowledge query".to_string());
+            reasons.push(Reason::new("general_knowledge"));
+        }
+        // An explicit "don't look at my screen" outweighs everything that
+        // pointed at the screen.
+        if self.declines_screen_access(&query) {
+            no_screenshot_score += screenshot_score + 3;
+            reasons.push(Reason::new("declined_screen_access"));
         }
-        let needs_screenshot = screenshot_score > no_screenshot_score;
-        ClassificationResult {
-            needs_screenshot,
-            confidence: confidence.min(0.95),
+        // Explicit commands skip the score comparison altogether.
+        let explicit = self.explicit_override(&query);
+        if let Some((_, forced)) = explicit {
+            reasons.push(Reason::new(if forced { "explicit_request" } else { "explicit_refusal" }));
+        }
+        let mut result = ClassificationResult {
+            needs_screenshot: explicit.is_some_and(|(_, forced)| forced),
+            decision: Decision::Skip,
+            query_id: None,
+            confidence: OVERRIDE_CONFIDENCE,
             screenshot_score,
             no_screenshot_score,
-            reasoning,
+            reasons,
+            reasoning: Vec::new(),
+            summary_code: String::new(),
+            summary: String::new(),
             context_info,
+            suppressed_reason: None,
+            text_oriented: self.is_text_oriented(&query),
+            override_rule: explicit.map(|(phrase, _)| phrase.to_string()),
             screenshot_base64: None,
-        }
+        };
+        result.decide(self.ask_below);
+        result
     }
     
     fn analyze_recent_context(&self) -> ContextInfo {
@@ -116,20 +392,15 @@ impl ContextualScreenshotClassifier {
             context_strength: 0,
         };
         for msg in recent_messages {
-            let msg_lower = msg.content.to_lowercase();
+            let msg_words = Words::new(&msg.content);
             if msg.role == "assistant" {
-                let ui_patterns = [
-                    "go to", "click", "select", "find the", "open the",
-                    "settings", "menu", "button", "option", "panel"
-                ];
-                if ui_patterns.iter().any(|&pattern| msg_lower.contains(pattern)) {
+                if msg_words.has_any(&self.keywords.ui) {
                     context_info.has_context = true;
                     context_info.context_type = Some("ui_navigation".to_string());
                     context_info.assistant_gave_instructions = true;
                     context_info.context_strength += 2;
                 }
-                let error_patterns = ["error", "problem", "issue", "troubleshoot"];
-                if error_patterns.iter().any(|&pattern| msg_lower.contains(pattern)) {
+                if msg_words.has_any(&self.keywords.error) {
                     context_info.has_context = true;
                     context_info.context_type = Some("error_troubleshooting".to_string());
                     context_info.context_strength += 2;
@@ -139,8 +410,7 @@ impl ContextualScreenshotClassifier {
                 context_info.recent_screenshot = true;
                 context_info.context_strength += 1;
             }
-            let task_indicators = ["step", "next", "then", "after", "now"];
-            if task_indicators.iter().any(|&indicator| msg_lower.contains(indicator)) {
+            if msg_words.has_any(&self.keywords.task) {
                 context_info.user_in_middle_of_task = true;
                 context_info.context_strength += 1;
             }
@@ -148,76 +418,259 @@ impl ContextualScreenshotClassifier {
         context_info
     }
     
-    fn is_contextual_followup(&self, query: &str) -> bool {
-        let followup_patterns = [
-            "which", "what", "where", "should i", "do i", "how about",
-            "what about", "is this", "does this", "can i", "may i",
-            "next", "then", "now what", "ok", "okay", "like this",
-            "correct", "right"
-        ];
-        followup_patterns.iter().any(|&pattern| {
-            query.starts_with(pattern) || query.starts_with(&format!("^{}", pattern))
-        })
+    fn is_contextual_followup(&self, query: &Words) -> bool {
+        self.keywords.followup.iter().any(|pattern| query.starts_with(pattern))
     }
     
-    fn get_base_screenshot_score(&self, query: &str) -> i32 {
-        let screenshot_keywords = [
-            "this", "that", "these", "those", "current", "currently", 
-            "right now", "now", "here", "there", "visible", "see", 
-            "seeing", "shown", "showing", "button", "popup", "dialog", 
-            "menu", "which", "where", "help me"
+    // Only taking or looking at the screen counts as declined, so "I don't
+    // know which button" or "it's not showing on screen" still capture.
+    // "no" and "stop" only count right before the noun or "-ing" form, as in
+    // "no screenshot" or "stop looking", so "no, look at this" and "stop the
+    // app, then look" aren't taken as refusals.
+    fn declines_screen_access(&self, query: &Words) -> bool {
+        let screen_access = ["screenshot", "screen shot", "capture", "look", "looking", "peek"];
+        let negators = ["don t", "dont", "do not", "without", "no need to", "never"];
+        query.negated(&screen_access, &negators, 2)
+            || query.negated(&["screenshot", "screen shot", "looking", "peeking"], &["no"], 0)
+            || query.negated(&["looking", "capturing", "peeking"], &["stop"], 0)
+    }
+
+    // The phrase that forces the decision, and whether it forces a capture.
+    // Refusals go first, since "don't look at my screen" also contains
+    // "look at my screen".
+    fn explicit_override(&self, query: &Words) -> Option<(&str, bool)> {
+        let keywords = &self.keywords;
+        let forbidden = keywords.forbid_capture.iter().find(|p| query.has(p)).map(|p| (p.as_str(), false));
+        forbidden.or_else(|| keywords.force_capture.iter().find(|p| query.has(p)).map(|p| (p.as_str(), true)))
+    }
+
+    fn is_text_oriented(&self, query: &Words) -> bool {
+        let text_patterns = [
+            "read this", "read that", "read it", "read the", "read what",
+            "error", "warning", "exception", "stack trace", "traceback",
+            "message", "what does it say", "what it says", "text",
+            "wording", "typo", "spelling", "translate", "copy"
         ];
-        let mut score = 0;
-        for keyword in &screenshot_keywords {
-            if query.contains(keyword) {
-                score += 1;
-            }
-        }
+        query.has_any(&text_patterns)
+    }
+    
+    fn get_base_screenshot_score(&self, query: &Words) -> i32 {
+        let mut score = query.count(&self.keywords.screenshot);
         let strong_indicators = ["this", "that", "current"];
-        if strong_indicators.iter().any(|&word| query.contains(word)) {
+        if query.has_any(&strong_indicators) {
             score += 2;
         }
         score
     }
     
-    fn get_base_no_screenshot_score(&self, query: &str) -> i32 {
-        let no_screenshot_keywords = [
-            "explain", "what is", "how to", "difference between", 
-            "history of", "write", "create", "generate", "timer", 
-            "reminder", "weather"
-        ];
-        let mut score = 0;
-        for keyword in &no_screenshot_keywords {
-            if query.contains(keyword) {
-                score += 1;
-            }
-        }
-        score
+    fn get_base_no_screenshot_score(&self, query: &Words) -> i32 {
+        query.count(&self.keywords.no_screenshot)
     }
 }
 
 // Session manager for handling full conversation flow
 pub struct SessionManager {
     classifier: ContextualScreenshotClassifier,
+    // set when the user attached files; counts against a screenshot once
+    file_context_pending: bool,
+    // `ask_user` results not answered yet: query id, when the query was
+    // sent, and the query
+    pending_asks: VecDeque<(String, DateTime<Utc>, String)>,
 }
 
 impl SessionManager {
     pub fn new(max_history: usize) -> Self {
         Self {
             classifier: ContextualScreenshotClassifier::new(max_history),
+            file_context_pending: false,
+            pending_asks: VecDeque::new(),
         }
     }
     pub fn process_user_query(&mut self, query: &str) -> ClassificationResult {
-        let result = self.classifier.classify_with_context(query);
+        let mut result = self.classifier.classify_with_context(query);
+        if std::mem::take(&mut self.file_context_pending) {
+            result.no_screenshot_score += 3;
+            result.reasons.push(Reason::new("file_context"));
+            result.decide(self.classifier.ask_below);
+        }
+        let timestamp = Utc::now();
+        // An asked-about query is filled in by `record_capture`.
+        let asked = result.decision == Decision::AskUser;
+        if asked {
+            let id = next_ask_id();
+            if self.pending_asks.len() >= MAX_PENDING_ASKS {
+                self.pending_asks.pop_front();
+            }
+            self.pending_asks.push_back((id.clone(), timestamp, query.to_string()));
+            result.query_id = Some(id);
+        }
         let user_msg = ChatMessage {
             role: "user".to_string(),
             content: query.to_string(),
-            timestamp: Utc::now(),
-            triggered_screenshot: Some(result.needs_screenshot),
+            timestamp,
+            triggered_screenshot: (!asked).then_some(result.needs_screenshot),
         };
         self.classifier.add_message(user_msg);
         // update result with maybe later screenshot_base64 outside
         result
     }
     pub fn add_message(&mut self, msg: ChatMessage) { self.classifier.add_message(msg); }
+    pub fn set_keywords(&mut self, keywords: Keywords) { self.classifier.set_keywords(keywords); }
+    pub fn note_file_context(&mut self) { self.file_context_pending = true; }
+    pub fn set_ask_below(&mut self, ask_below: f32) { self.classifier.set_ask_below(ask_below); }
+    // Takes an `ask_user` result off the unanswered ones, returning when its
+    // query was sent and the query, or `None` for an unknown or already
+    // answered id. `record_capture` fills in what came of it.
+    pub fn take_ask(&mut self, query_id: &str) -> Option<(DateTime<Utc>, String)> {
+        let index = self.pending_asks.iter().position(|(id, _, _)| id == query_id)?;
+        let (_, sent_at, query) = self.pending_asks.remove(index)?;
+        Some((sent_at, query))
+    }
+    // Records whether a screenshot was taken for the query sent at `sent_at`.
+    pub fn record_capture(&mut self, sent_at: DateTime<Utc>, captured: bool) {
+        self.classifier.record_choice(sent_at, captured);
+    }
+    // The chat history, oldest first.
+    pub fn history(&self) -> Vec<ChatMessage> { self.classifier.chat_history.iter().cloned().collect() }
+    pub fn history_len(&self) -> usize { self.classifier.chat_history.len() }
+    // Changes whenever the chat history does.
+    pub fn history_revision(&self) -> u64 { self.classifier.revision }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_split_on_anything_but_letters_and_digits() {
+        let cases: &[(&str, &[&str])] = &[
+            ("What's THIS?", &["what", "s", "this"]),
+            ("click  the\tOK button", &["click", "the", "ok", "button"]),
+            ("step 2/3", &["step", "2", "3"]),
+            ("  ...  ", &[]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(Words::new(text).0, *expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn keywords_match_whole_words_and_plurals() {
+        let cases = [
+            ("history of thistles", "this", false),
+            ("is this right", "this", true),
+            ("I know", "now", false),
+            ("what now", "now", true),
+            ("two errors", "error", true),
+            ("the boxes", "box", true),
+            ("settings", "setting", true),
+            ("errors", "errors", true),
+            ("error", "errors", false),
+            ("please take a Screenshot!", "take a screenshot", true),
+            ("take the screenshot", "take a screenshot", false),
+            ("Don't look", "don t look", true),
+            ("what's this", "what", true),
+        ];
+        for (text, keyword, expected) in cases {
+            assert_eq!(Words::new(text).has(keyword), expected, "{keyword:?} in {text:?}");
+        }
+    }
+
+    #[test]
+    fn phrases_are_found_where_they_occur() {
+        let words = Words::new("look here, then look there");
+        assert_eq!(words.find("look").collect::<Vec<_>>(), [(0, 1), (3, 4)]);
+        assert_eq!(words.find("then look").collect::<Vec<_>>(), [(2, 4)]);
+        assert!(words.starts_with("look here"));
+        assert!(!words.starts_with("here"));
+    }
+
+    #[test]
+    fn only_refusing_the_screen_counts_as_declined() {
+        let classifier = ContextualScreenshotClassifier::new(10);
+        let cases = [
+            ("don't take a screenshot", true),
+            ("please do not look at my screen", true),
+            ("no screenshots please", true),
+            ("stop looking at my screen", true),
+            ("never peek at my screen", true),
+            ("there's no need to look", true),
+            ("answer without a screenshot", true),
+            ("I don't know which button", false),
+            ("it's not showing on screen", false),
+            ("No, look at this button", false),
+            ("stop the app then look at the dialog", false),
+            ("take a screenshot", false),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(classifier.declines_screen_access(&Words::new(query)), expected, "{query:?}");
+        }
+    }
+
+    #[test]
+    fn explicit_phrases_decide_without_the_scores() {
+        let classifier = ContextualScreenshotClassifier::new(10);
+        let forced = classifier.classify_with_context("take a screenshot and explain what is a closure");
+        assert!(forced.needs_screenshot);
+        assert_eq!(forced.decision, Decision::Capture);
+        assert_eq!(forced.override_rule.as_deref(), Some("take a screenshot"));
+        assert_eq!(forced.confidence, OVERRIDE_CONFIDENCE);
+
+        let forbidden = classifier.classify_with_context("text only: which button is this?");
+        assert!(!forbidden.needs_screenshot);
+        assert_eq!(forbidden.decision, Decision::Skip);
+        assert_eq!(forbidden.override_rule.as_deref(), Some("text only"));
+
+        // The refusal wins over the "look at my screen" inside it.
+        let refused = classifier.classify_with_context("don't look at my screen");
+        assert_eq!(refused.override_rule.as_deref(), Some("don t look"));
+        assert!(!refused.needs_screenshot);
+    }
+
+    #[test]
+    fn override_phrases_come_from_the_keyword_lists() {
+        let mut classifier = ContextualScreenshotClassifier::new(10);
+        let mut keywords = Keywords::default();
+        keywords.force_capture.push("peek at this".to_string());
+        keywords.forbid_capture.retain(|p| p != "text only");
+        classifier.set_keywords(keywords);
+
+        let forced = classifier.classify_with_context("peek at this for me");
+        assert_eq!(forced.override_rule.as_deref(), Some("peek at this"));
+        assert!(forced.needs_screenshot);
+        assert_eq!(classifier.classify_with_context("text only please").override_rule, None);
+    }
+
+    #[test]
+    fn a_bigger_margin_never_lowers_confidence() {
+        for other in 0..12 {
+            for score in other..24 {
+                assert!(margin_confidence(score + 1, other) >= margin_confidence(score, other), "{score} vs {other}");
+                assert!(margin_confidence(other, score + 1) >= margin_confidence(other, score), "{other} vs {score}");
+            }
+        }
+        assert_eq!(margin_confidence(3, 3), 0.5);
+        assert_eq!(margin_confidence(40, 0), MAX_CONFIDENCE);
+    }
+
+    #[test]
+    fn the_same_margin_means_less_with_more_evidence() {
+        for margin in 1..4 {
+            for other in 0..12 {
+                let less = margin_confidence(other + margin, other);
+                let more = margin_confidence(other + 1 + margin, other + 1);
+                assert!(more < less, "{margin} point margin over {other}");
+            }
+        }
+        assert!(margin_confidence(8, 7) < margin_confidence(1, 0));
+        assert!(margin_confidence(8, 7) < DEFAULT_ASK_BELOW);
+        assert!(margin_confidence(1, 0) > DEFAULT_ASK_BELOW);
+    }
+
+    #[test]
+    fn keywords_are_normalized_like_queries() {
+        assert_eq!(normalize_keyword("Don't"), "don t");
+        assert_eq!(normalize_keyword("  Take a   SCREENSHOT "), "take a screenshot");
+        assert_eq!(normalize_keyword("?!"), "");
+    }
 }
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::classifier::{normalize_keyword, Keywords, DEFAULT_ASK_BELOW};
+use crate::command_stats;
+use crate::error::{CommandResult, SettingsError};
+use crate::persist;
+use crate::SharedSession;
+
+pub const CONFIG_FILE: &str = "classifier.json";
+
+// Changes to one of the compiled-in keyword lists.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct KeywordEdits {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+impl KeywordEdits {
+    fn apply(&self, defaults: Vec<String>) -> Vec<String> {
+        let removed: Vec<String> = self.remove.iter().map(|k| normalize_keyword(k)).collect();
+        let mut list: Vec<String> = defaults.into_iter().filter(|k| !removed.contains(k)).collect();
+        for keyword in self.add.iter().map(|k| normalize_keyword(k)) {
+            if !keyword.is_empty() && !list.contains(&keyword) {
+                list.push(keyword);
+            }
+        }
+        list
+    }
+}
+
+// The contents of classifier.json: the user's additions to and removals
+// from each keyword and override phrase list. Lists left out keep their
+// defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    pub screenshot_keywords: KeywordEdits,
+    pub no_screenshot_keywords: KeywordEdits,
+    pub followup_patterns: KeywordEdits,
+    pub ui_patterns: KeywordEdits,
+    pub error_patterns: KeywordEdits,
+    pub task_indicators: KeywordEdits,
+    // Phrases that force or forbid a capture whatever the scores say.
+    pub force_capture_phrases: KeywordEdits,
+    pub forbid_capture_phrases: KeywordEdits,
+    // Queries classified with less confidence than this are asked about
+    // instead of captured or skipped; 0 never asks.
+    pub ask_below_confidence: f32,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            screenshot_keywords: KeywordEdits::default(),
+            no_screenshot_keywords: KeywordEdits::default(),
+            followup_patterns: KeywordEdits::default(),
+            ui_patterns: KeywordEdits::default(),
+            error_patterns: KeywordEdits::default(),
+            task_indicators: KeywordEdits::default(),
+            force_capture_phrases: KeywordEdits::default(),
+            forbid_capture_phrases: KeywordEdits::default(),
+            ask_below_confidence: DEFAULT_ASK_BELOW,
+        }
+    }
+}
+
+impl ClassifierConfig {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !(0.0..=1.0).contains(&self.ask_below_confidence) {
+            let reason = "must be between 0 and 1".to_string();
+            return Err(SettingsError::InvalidValue { key: "ask_below_confidence", reason });
+        }
+        Ok(())
+    }
+
+    pub fn keywords(&self) -> Keywords {
+        let defaults = Keywords::default();
+        Keywords {
+            screenshot: self.screenshot_keywords.apply(defaults.screenshot),
+            no_screenshot: self.no_screenshot_keywords.apply(defaults.no_screenshot),
+            followup: self.followup_patterns.apply(defaults.followup),
+            ui: self.ui_patterns.apply(defaults.ui),
+            error: self.error_patterns.apply(defaults.error),
+            task: self.task_indicators.apply(defaults.task),
+            force_capture: self.force_capture_phrases.apply(defaults.force_capture),
+            forbid_capture: self.forbid_capture_phrases.apply(defaults.forbid_capture),
+        }
+    }
+}
+
+// The edits as saved, and the lists they produce.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ClassifierConfigState {
+    pub config: ClassifierConfig,
+    pub keywords: Keywords,
+}
+
+// The config the live classifier is using.
+#[derive(Default)]
+pub struct CurrentClassifierConfig(Mutex<ClassifierConfig>);
+
+pub fn config_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app.path().app_config_dir()?.join(CONFIG_FILE))
+}
+
+pub fn current(app: &AppHandle) -> ClassifierConfig {
+    app.state::<CurrentClassifierConfig>().0.lock().map(|c| c.clone()).unwrap_or_default()
+}
+
+// Swaps `config` into the live session, under the same lock classify takes.
+pub fn apply(app: &AppHandle, config: ClassifierConfig) -> CommandResult<ClassifierConfigState> {
+    let keywords = config.keywords();
+    {
+        let state = app.state::<Arc<SharedSession>>();
+        let mut session = state.0.lock()?;
+        session.set_keywords(keywords.clone());
+        session.set_ask_below(config.ask_below_confidence.clamp(0.0, 1.0));
+    }
+    *app.state::<CurrentClassifierConfig>().0.lock()? = config.clone();
+    Ok(ClassifierConfigState { config, keywords })
+}
+
+// Loads classifier.json at startup. A missing or unreadable file leaves the
+// compiled-in lists.
+pub fn start(app: &AppHandle) {
+    let config = match config_path(app) {
+        Ok(path) if path.exists() => persist::read_json(app, &path).unwrap_or_else(|| {
+            tracing::warn!(path = %path.display(), "invalid classifier config and no usable backup, using defaults");
+            ClassifierConfig::default()
+        }),
+        Ok(_) => ClassifierConfig::default(),
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to resolve classifier config path");
+            ClassifierConfig::default()
+        }
+    };
+    if let Err(e) = apply(app, config) {
+        tracing::warn!(error = %e, "failed to apply classifier config");
+    }
+}
+
+#[tauri::command]
+pub async fn get_classifier_config(app: AppHandle) -> CommandResult<ClassifierConfigState> {
+    command_stats::track(&app, "get_classifier_config", async {
+        let config = app.state::<CurrentClassifierConfig>().0.lock()?.clone();
+        let keywords = config.keywords();
+        Ok(ClassifierConfigState { config, keywords })
+    })
+    .await
+}
+
+// Saves the edits to classifier.json and applies them straight away.
+#[tauri::command]
+pub async fn set_classifier_config(app: AppHandle, config: ClassifierConfig) -> CommandResult<ClassifierConfigState> {
+    command_stats::track(&app, "set_classifier_config", async {
+        config.validate()?;
+        persist::write_json_atomic(&config_path(&app)?, &config, true)
+            .map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+        apply(&app, config)
+    })
+    .await
+}
+
+// Re-reads classifier.json after an edit outside the app. Unlike startup, a
+// file that doesn't parse is an error and the current lists stay.
+#[tauri::command]
+pub async fn reload_classifier_config(app: AppHandle) -> CommandResult<ClassifierConfigState> {
+    command_stats::track(&app, "reload_classifier_config", async {
+        let config = match std::fs::read_to_string(config_path(&app)?) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .map_err(|e| SettingsError::InvalidValue { key: CONFIG_FILE, reason: e.to_string() })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ClassifierConfig::default(),
+            Err(e) => return Err(e.into()),
+        };
+        config.validate()?;
+        tracing::info!("classifier config reloaded");
+        apply(&app, config)
+    })
+    .await
+}
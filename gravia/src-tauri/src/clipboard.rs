@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::borrow::Cow;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::capture::{self, CaptureError, CaptureOperations, CaptureOptions, CaptureTarget, RateLimitPolicy};
+use crate::capture_history::CaptureHistory;
+use crate::command_stats;
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, ClipboardPermissionRequested, GraviaEvent};
+use crate::settings::{self, SharedSettings};
+
+// Another app holding the clipboard open usually lets go within a few
+// hundred milliseconds.
+const BUSY_RETRIES: u32 = 5;
+const BUSY_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardContentKind {
+    PlainText,
+    Code,
+    Url,
+    FileList,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ClipboardText {
+    pub text: String,
+    pub truncated: bool,
+    pub kind: ClipboardContentKind,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClipboardError {
+    #[error("clipboard access has not been allowed in settings")]
+    PermissionRequired,
+    #[error("the clipboard does not contain text")]
+    NotText,
+    #[error("the clipboard is empty")]
+    Empty,
+    #[error("another app is holding the clipboard; try again")]
+    Busy,
+    #[error("{0}")]
+    Os(String),
+}
+
+impl ClipboardError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClipboardError::PermissionRequired => "permission_required",
+            ClipboardError::NotText => "not_text",
+            ClipboardError::Empty => "empty",
+            ClipboardError::Busy => "busy",
+            ClipboardError::Os(_) => "os",
+        }
+    }
+}
+
+#[tauri::command]
+pub fn read_clipboard_text(
+    app: AppHandle,
+    state: State<'_, SharedSettings>,
+    max_len: usize,
+) -> CommandResult<ClipboardText> {
+    {
+        let mut current = state.0.lock()?;
+        if !current.allow_clipboard_read {
+            // Let the frontend ask the user once; after that the toggle in
+            // settings is the only way in.
+            if !current.clipboard_permission_prompted {
+                current.clipboard_permission_prompted = true;
+                if let Err(e) = settings::save(&app, &current) {
+                    tracing::warn!(error = %e, "failed to persist clipboard prompt flag");
+                }
+                emit_event(&app, GraviaEvent::ClipboardPermissionRequested(ClipboardPermissionRequested)).ok();
+            }
+            return Err(ClipboardError::PermissionRequired.into());
+        }
+    }
+
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| ClipboardError::Os(e.to_string()))?;
+    let text = match clipboard.get_text() {
+        Ok(t) => t,
+        Err(arboard::Error::ContentNotAvailable) => {
+            // Text is missing but something else may be there (an image, say).
+            let err = if clipboard.get_image().is_ok() {
+                ClipboardError::NotText
+            } else {
+                ClipboardError::Empty
+            };
+            return Err(err.into());
+        }
+        Err(e) => return Err(ClipboardError::Os(e.to_string()).into()),
+    };
+    if text.is_empty() {
+        return Err(ClipboardError::Empty.into());
+    }
+
+    let kind = detect_kind(&text);
+    let (text, truncated) = truncate_chars(text, max_len);
+    Ok(ClipboardText { text, truncated, kind })
+}
+
+// A screenshot placed on the clipboard.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CopiedScreenshot {
+    pub capture_id: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Puts capture `capture_id` on the clipboard as an image, ready to paste
+// into other apps. Without an id, captures the primary display first.
+#[tauri::command]
+pub async fn copy_screenshot_to_clipboard(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    capture_id: Option<String>,
+    operation_id: Option<String>,
+) -> CommandResult<CopiedScreenshot> {
+    command_stats::track(window.app_handle(), "copy_screenshot_to_clipboard", async {
+        let capture_id = match capture_id {
+            Some(id) => id,
+            None => {
+                let operation = operations.begin(operation_id);
+                let options = CaptureOptions::default();
+                let policy = RateLimitPolicy::Reuse;
+                let target = CaptureTarget::Default;
+                capture::capture_unguarded(&window, target, &options, policy, &operation.token).await?.id
+            }
+        };
+        let encoded = window
+            .state::<CaptureHistory>()
+            .image_bytes(&capture_id)
+            .ok_or_else(|| CaptureError::CaptureNotFound(capture_id.clone()))?;
+        let (width, height) = tauri::async_runtime::spawn_blocking(move || {
+            let (width, height, rgba) = decode_rgba(&encoded)?;
+            set_clipboard_image(width, height, &rgba)?;
+            Ok::<_, GraviaError>((width, height))
+        })
+        .await
+        .map_err(|e| ClipboardError::Os(e.to_string()))??;
+        tracing::info!(%capture_id, width, height, "screenshot copied to clipboard");
+        Ok(CopiedScreenshot { capture_id, width, height })
+    })
+    .await
+}
+
+#[cfg(feature = "capture")]
+fn decode_rgba(encoded: &[u8]) -> Result<(u32, u32, Vec<u8>), GraviaError> {
+    let image = image::load_from_memory(encoded).map_err(|e| ClipboardError::Os(e.to_string()))?.into_rgba8();
+    let (width, height) = image.dimensions();
+    Ok((width, height, image.into_raw()))
+}
+
+// Built without the `capture` feature there's no decoder; nothing reaches
+// here anyway, as the history stays empty.
+#[cfg(not(feature = "capture"))]
+fn decode_rgba(_encoded: &[u8]) -> Result<(u32, u32, Vec<u8>), GraviaError> {
+    Err(CaptureError::Unavailable.into())
+}
+
+// Writes a decoded capture as a bitmap, which arboard offers as CF_DIB (and
+// PNG) on Windows and as an image on other systems.
+fn set_clipboard_image(width: u32, height: u32, rgba: &[u8]) -> Result<(), ClipboardError> {
+    let data = arboard::ImageData { width: width as usize, height: height as usize, bytes: Cow::Borrowed(rgba) };
+
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| ClipboardError::Os(e.to_string()))?;
+    for attempt in 0..=BUSY_RETRIES {
+        match clipboard.set_image(data.clone()) {
+            Ok(()) => return Ok(()),
+            Err(arboard::Error::ClipboardOccupied) if attempt < BUSY_RETRIES => {
+                std::thread::sleep(BUSY_RETRY_DELAY);
+            }
+            Err(arboard::Error::ClipboardOccupied) => break,
+            Err(e) => return Err(ClipboardError::Os(e.to_string())),
+        }
+    }
+    Err(ClipboardError::Busy)
+}
+
+fn truncate_chars(text: String, max_len: usize) -> (String, bool) {
+    match text.char_indices().nth(max_len) {
+        Some((byte_idx, _)) => (text[..byte_idx].to_string(), true),
+        None => (text, false),
+    }
+}
+
+fn detect_kind(text: &str) -> ClipboardContentKind {
+    let trimmed = text.trim();
+    let lines: Vec<&str> = trimmed.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+
+    if !lines.is_empty() && lines.iter().all(|l| std::path::Path::new(l).is_absolute()) {
+        return ClipboardContentKind::FileList;
+    }
+    if lines.len() == 1 && !trimmed.contains(char::is_whitespace) {
+        let url_prefixes = ["http://", "https://", "www.", "ftp://", "file://"];
+        if url_prefixes.iter().any(|&p| trimmed.starts_with(p)) {
+            return ClipboardContentKind::Url;
+        }
+    }
+
+    let code_markers = [
+        "{", "};", "=>", "->", "fn ", "def ", "function ", "class ", "import ", "#include",
+        "let ", "const ", "return ", "</", "==",
+    ];
+    let marker_hits = code_markers.iter().filter(|&&m| trimmed.contains(m)).count();
+    let indented = lines.len() > 1
+        && text.lines().filter(|l| l.starts_with("    ") || l.starts_with('\t')).count() * 3
+            >= lines.len();
+    if marker_hits >= 2 || (marker_hits >= 1 && indented) {
+        return ClipboardContentKind::Code;
+    }
+    ClipboardContentKind::PlainText
+}
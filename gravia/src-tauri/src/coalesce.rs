@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+struct Entry<T> {
+    result: watch::Receiver<Option<T>>,
+    // None while the original request is still running.
+    finished_at: Option<Instant>,
+}
+
+// Folds identical requests arriving close together into one run. A request
+// whose key matches one that is in flight, or finished within `window`,
+// gets that run's result instead of running again.
+pub struct Coalescer<T> {
+    entries: Mutex<HashMap<String, Entry<T>>>,
+}
+
+impl<T> Default for Coalescer<T> {
+    fn default() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+}
+
+// Removes the entry if the leading run fails or is dropped, so waiting
+// requests run on their own rather than sharing a failure.
+struct LeaderGuard<'a, T> {
+    coalescer: &'a Coalescer<T>,
+    key: &'a str,
+    done: bool,
+}
+
+impl<T> Drop for LeaderGuard<'_, T> {
+    fn drop(&mut self) {
+        if !self.done {
+            if let Ok(mut entries) = self.coalescer.entries.lock() {
+                entries.remove(self.key);
+            }
+        }
+    }
+}
+
+impl<T: Clone> Coalescer<T> {
+    // Runs `run` unless an equivalent request can be reused. Returns the
+    // result and whether it came from another request.
+    pub async fn run<E, F, Fut>(&self, key: String, window: Duration, run: F) -> Result<(T, bool), E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let sender = {
+            let Ok(mut entries) = self.entries.lock() else { return run().await.map(|r| (r, false)) };
+            entries.retain(|_, e| e.finished_at.is_none_or(|t| t.elapsed() < window));
+            match entries.get(&key) {
+                Some(entry) => Err(entry.result.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    entries.insert(key.clone(), Entry { result: rx, finished_at: None });
+                    Ok(tx)
+                }
+            }
+        };
+
+        let tx = match sender {
+            Ok(tx) => tx,
+            Err(mut rx) => {
+                if let Ok(result) = rx.wait_for(Option::is_some).await {
+                    if let Some(result) = result.clone() {
+                        return Ok((result, true));
+                    }
+                }
+                // The original failed; run this one normally.
+                return run().await.map(|r| (r, false));
+            }
+        };
+
+        let mut guard = LeaderGuard { coalescer: self, key: &key, done: false };
+        let result = run().await?;
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(&key) {
+                entry.finished_at = Some(Instant::now());
+            }
+        }
+        guard.done = true;
+        tx.send_replace(Some(result.clone()));
+        Ok((result, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::join_all;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const WINDOW: Duration = Duration::from_secs(2);
+
+    // A request that takes a while and counts how often it really ran.
+    async fn slow(runs: &AtomicU32, result: Result<u32, &'static str>) -> Result<u32, &'static str> {
+        runs.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        result
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn identical_concurrent_requests_run_once() {
+        let (coalescer, runs) = (Coalescer::default(), AtomicU32::new(0));
+        let requests = (0..8).map(|_| coalescer.run("same".into(), WINDOW, || slow(&runs, Ok(7))));
+        let results = join_all(requests).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| matches!(r, Ok((7, _)))));
+        assert_eq!(results.iter().filter(|r| matches!(r, Ok((_, false)))).count(), 1);
+        // Within the window a later request is still served from that run.
+        assert_eq!(coalescer.run("same".into(), WINDOW, || slow(&runs, Ok(8))).await, Ok((7, true)));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn different_keys_run_separately() {
+        let (coalescer, runs) = (Coalescer::default(), AtomicU32::new(0));
+        let requests = ["a", "b", "a"].map(|key| coalescer.run(key.into(), WINDOW, || slow(&runs, Ok(1))));
+        join_all(requests).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_requests_run_themselves_when_the_first_fails() {
+        let (coalescer, runs) = (Coalescer::default(), AtomicU32::new(0));
+        let first = coalescer.run("same".into(), WINDOW, || slow(&runs, Err("grab failed")));
+        let second = coalescer.run("same".into(), WINDOW, || slow(&runs, Ok(3)));
+        let (first, second) = tokio::join!(first, second);
+        assert_eq!(first, Err("grab failed"));
+        assert_eq!(second, Ok((3, false)));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn finished_runs_are_not_reused_after_the_window() {
+        let (coalescer, runs) = (Coalescer::default(), AtomicU32::new(0));
+        coalescer.run("same".into(), Duration::ZERO, || slow(&runs, Ok(1))).await.unwrap();
+        let again = coalescer.run("same".into(), Duration::ZERO, || slow(&runs, Ok(2))).await;
+        assert_eq!(again, Ok((2, false)));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
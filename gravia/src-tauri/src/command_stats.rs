@@ -0,0 +1,122 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+
+// Latency percentiles come from the most recent calls only.
+const LATENCY_SAMPLES: usize = 256;
+
+#[derive(Default)]
+struct Stat {
+    calls: u64,
+    errors: BTreeMap<String, u64>,
+    latencies_us: VecDeque<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CommandStat {
+    pub command: String,
+    #[ts(type = "number")]
+    pub calls: u64,
+    #[ts(type = "Record<string, number>")]
+    pub errors: BTreeMap<String, u64>,
+    pub p50_us: u32,
+    pub p95_us: u32,
+}
+
+// Call counts, recent latencies and error codes per command. Recording is one
+// short lock and a push, so tracking costs a few microseconds per call.
+#[derive(Default)]
+pub struct CommandStats(Mutex<HashMap<&'static str, Stat>>);
+
+impl CommandStats {
+    fn record(&self, command: &'static str, elapsed: Duration, error: Option<&GraviaError>) {
+        let Ok(mut stats) = self.0.lock() else { return };
+        let stat = stats.entry(command).or_default();
+        stat.calls += 1;
+        if let Some(e) = error {
+            *stat.errors.entry(e.code()).or_default() += 1;
+        }
+        if stat.latencies_us.len() == LATENCY_SAMPLES {
+            stat.latencies_us.pop_front();
+        }
+        stat.latencies_us.push_back(elapsed.as_micros().min(u32::MAX as u128) as u32);
+    }
+
+    fn snapshot(&self) -> Vec<CommandStat> {
+        let Ok(stats) = self.0.lock() else { return Vec::new() };
+        let mut out: Vec<CommandStat> = stats
+            .iter()
+            .map(|(command, stat)| {
+                let mut sorted: Vec<u32> = stat.latencies_us.iter().copied().collect();
+                sorted.sort_unstable();
+                CommandStat {
+                    command: command.to_string(),
+                    calls: stat.calls,
+                    errors: stat.errors.clone(),
+                    p50_us: percentile(&sorted, 50),
+                    p95_us: percentile(&sorted, 95),
+                }
+            })
+            .collect();
+        out.sort_by(|a, b| a.command.cmp(&b.command));
+        out
+    }
+}
+
+fn percentile(sorted: &[u32], p: usize) -> u32 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted[((sorted.len() - 1) * p) / 100]
+}
+
+// Runs a command body and records how it went.
+pub async fn track<T, F>(app: &AppHandle, command: &'static str, body: F) -> CommandResult<T>
+where
+    F: Future<Output = CommandResult<T>>,
+{
+    let started = Instant::now();
+    let result = body.await;
+    app.state::<CommandStats>().record(command, started.elapsed(), result.as_ref().err());
+    result
+}
+
+pub fn track_sync<T>(app: &AppHandle, command: &'static str, body: impl FnOnce() -> CommandResult<T>) -> CommandResult<T> {
+    let started = Instant::now();
+    let result = body();
+    app.state::<CommandStats>().record(command, started.elapsed(), result.as_ref().err());
+    result
+}
+
+// Written to the log on exit so slow sessions can be looked at afterwards.
+pub fn log_summary(app: &AppHandle) {
+    for stat in app.state::<CommandStats>().snapshot() {
+        tracing::info!(
+            command = %stat.command,
+            calls = stat.calls,
+            p50_us = stat.p50_us,
+            p95_us = stat.p95_us,
+            errors = ?stat.errors,
+            "command stats"
+        );
+    }
+}
+
+#[tauri::command]
+pub fn get_command_stats(state: State<'_, CommandStats>) -> Vec<CommandStat> {
+    state.snapshot()
+}
+
+#[tauri::command]
+pub fn reset_command_stats(state: State<'_, CommandStats>) {
+    if let Ok(mut stats) = state.0.lock() {
+        stats.clear();
+    }
+}
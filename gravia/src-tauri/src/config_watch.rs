@@ -0,0 +1,175 @@
+use notify::{RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::classifier_config::{self, ClassifierConfig};
+use crate::events::{emit_event, GraviaEvent};
+use crate::http_client;
+use crate::logging;
+use crate::settings::{self, Settings, SharedSettings};
+
+// Editors often write a file several times in a row (truncate, write,
+// rename); wait for this much quiet before reading it.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ConfigReloaded {
+    pub file: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ConfigInvalid {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+// Watches settings.json and classifier.json for edits made outside the
+// app. If the watcher can't start, edits just need a restart as before.
+pub fn start(app: &AppHandle) {
+    let path = match settings::settings_path(app) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!(error = %e, "config watcher not started");
+            return;
+        }
+    };
+    let Some(dir) = path.parent().map(Path::to_path_buf) else { return };
+    // Both live in the app config dir.
+    let classifier_path = dir.join(classifier_config::CONFIG_FILE);
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!(dir = %dir.display(), error = %e, "config watcher not started");
+        return;
+    }
+
+    let app = app.clone();
+    let spawned = std::thread::Builder::new().name("config-watch".into()).spawn(move || {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                tracing::warn!(error = %e, "config watcher unavailable, edits need a restart");
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            tracing::warn!(dir = %dir.display(), error = %e, "config watcher unavailable, edits need a restart");
+            return;
+        }
+        let touches = |event: &notify::Result<notify::Event>, file: &Path| {
+            event.as_ref().is_ok_and(|e| e.paths.iter().any(|p| p.file_name() == file.file_name()))
+        };
+        while let Ok(event) = rx.recv() {
+            let mut settings_edited = touches(&event, &path);
+            let mut classifier_edited = touches(&event, &classifier_path);
+            if !settings_edited && !classifier_edited {
+                continue;
+            }
+            while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+                settings_edited |= touches(&event, &path);
+                classifier_edited |= touches(&event, &classifier_path);
+            }
+            if settings_edited {
+                reload_settings(&app, &path);
+            }
+            if classifier_edited {
+                reload_classifier(&app, &classifier_path);
+            }
+        }
+    });
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "failed to start config watcher");
+    }
+}
+
+// Keys in the file that `T` doesn't have; serde silently drops them, which
+// usually means a typo.
+fn unknown_keys<T: Default + Serialize>(raw: &serde_json::Value) -> Vec<String> {
+    let known = serde_json::to_value(T::default()).unwrap_or_default();
+    let (Some(raw), Some(known)) = (raw.as_object(), known.as_object()) else { return Vec::new() };
+    raw.keys()
+        .filter(|k| !known.contains_key(*k))
+        .map(|k| format!("unknown key `{k}` ignored"))
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// Reads an edited config file as `T`, along with its unknown keys. `None`
+// if it's gone (deleted or mid-rename; the next event will pick it up) or
+// invalid, which is reported and leaves the previous config in place.
+fn read_edited<T: DeserializeOwned + Default + Serialize>(app: &AppHandle, path: &Path) -> Option<(T, Vec<String>)> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let parsed = serde_json::from_str::<serde_json::Value>(&raw)
+        .and_then(|value| Ok((serde_json::from_value::<T>(value.clone())?, value)));
+    match parsed {
+        Ok((updated, value)) => Some((updated, unknown_keys::<T>(&value))),
+        Err(e) => {
+            report_invalid(app, file_name(path), e.line(), e.column(), e.to_string());
+            None
+        }
+    }
+}
+
+fn report_invalid(app: &AppHandle, file: String, line: usize, column: usize, message: String) {
+    tracing::warn!(%file, error = %message, "edited config is invalid, keeping the previous one");
+    emit_event(app, GraviaEvent::ConfigInvalid(ConfigInvalid { file, line, column, message })).ok();
+}
+
+fn reload_settings(app: &AppHandle, path: &Path) {
+    let file = file_name(path);
+    let Some((updated, mut warnings)) = read_edited::<Settings>(app, path) else { return };
+
+    let state = app.state::<SharedSettings>();
+    let Ok(mut current) = state.0.lock() else { return };
+    // Our own saves land here too.
+    if serde_json::to_value(&*current).ok() == serde_json::to_value(&updated).ok() {
+        return;
+    }
+    if current.proxy != updated.proxy {
+        http_client::rebuild(app, &updated.proxy);
+    }
+    if current.log_level != updated.log_level {
+        if let Err(e) = logging::apply_level(&updated.log_level) {
+            warnings.push(format!("log_level not applied: {e}"));
+        }
+    }
+    logging::set_streaming(updated.debug_log_stream);
+    *current = updated;
+    drop(current);
+
+    tracing::info!(%file, ?warnings, "config reloaded from disk");
+    emit_event(app, GraviaEvent::ConfigReloaded(ConfigReloaded { file, warnings })).ok();
+}
+
+fn reload_classifier(app: &AppHandle, path: &Path) {
+    let file = file_name(path);
+    let Some((updated, warnings)) = read_edited::<ClassifierConfig>(app, path) else { return };
+    if let Err(e) = updated.validate() {
+        // Line and column 0: the value is wrong, not the JSON.
+        report_invalid(app, file, 0, 0, e.to_string());
+        return;
+    }
+    // Our own saves land here too.
+    if serde_json::to_value(classifier_config::current(app)).ok() == serde_json::to_value(&updated).ok() {
+        return;
+    }
+    if let Err(e) = classifier_config::apply(app, updated) {
+        tracing::warn!(%file, error = %e, "failed to apply edited classifier config");
+        return;
+    }
+
+    tracing::info!(%file, ?warnings, "config reloaded from disk");
+    emit_event(app, GraviaEvent::ConfigReloaded(ConfigReloaded { file, warnings })).ok();
+}
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+use ts_rs::TS;
+
+use crate::error::CommandResult;
+use crate::events::{emit_event, GraviaEvent};
+use crate::http_client::HttpClient;
+use crate::power;
+use crate::settings::SharedSettings;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const ONLINE_INTERVAL: Duration = Duration::from_secs(60);
+// Checked more often while offline so we notice it coming back quickly.
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Connectivity {
+    Online,
+    Offline,
+    // The network answers, but not as the backend host would, e.g. a hotel
+    // login page redirecting every request.
+    CaptivePortalSuspected,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ConnectivityStatus {
+    pub state: Connectivity,
+    pub changed_at: DateTime<Utc>,
+}
+
+pub struct ConnectivityMonitor {
+    status: Mutex<ConnectivityStatus>,
+    // Poked by the OS watcher so we re-check right away.
+    os_changed: Notify,
+    // Signalled each time we go back online.
+    restored: Notify,
+}
+
+impl Default for ConnectivityMonitor {
+    fn default() -> Self {
+        Self {
+            // Optimistic until the first check, so startup isn't blocked on it.
+            status: Mutex::new(ConnectivityStatus { state: Connectivity::Online, changed_at: Utc::now() }),
+            os_changed: Notify::new(),
+            restored: Notify::new(),
+        }
+    }
+}
+
+impl ConnectivityMonitor {
+    pub fn state(&self) -> Connectivity {
+        self.status.lock().map(|s| s.state).unwrap_or(Connectivity::Online)
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.state() == Connectivity::Offline
+    }
+
+    // Resolves the next time connectivity comes back.
+    pub async fn wait_restored(&self) {
+        self.restored.notified().await;
+    }
+}
+
+fn set_state(app: &AppHandle, state: Connectivity) {
+    let monitor = app.state::<ConnectivityMonitor>();
+    let Ok(mut status) = monitor.status.lock() else { return };
+    if status.state == state {
+        return;
+    }
+    let was_online = status.state == Connectivity::Online;
+    *status = ConnectivityStatus { state, changed_at: Utc::now() };
+    tracing::info!(?state, "connectivity changed");
+    emit_event(app, GraviaEvent::ConnectivityChanged(status.clone())).ok();
+    if state == Connectivity::Online && !was_online {
+        monitor.restored.notify_waiters();
+    }
+}
+
+// A lightweight request to the host the backend talks to. Any answer from
+// that host counts as online; ending up somewhere else suggests a portal.
+async fn probe(app: &AppHandle) -> Connectivity {
+    let url = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.connectivity_probe_url.clone())
+        .unwrap_or_default();
+    let Ok(url) = reqwest::Url::parse(&url) else {
+        tracing::warn!(%url, "invalid connectivity probe URL, assuming online");
+        return Connectivity::Online;
+    };
+    let client = app.state::<HttpClient>().get();
+    match client.head(url.clone()).timeout(PROBE_TIMEOUT).send().await {
+        Ok(response) if response.url().host_str() != url.host_str() => Connectivity::CaptivePortalSuspected,
+        Ok(_) => Connectivity::Online,
+        Err(e) => {
+            tracing::debug!(error = %e, "connectivity probe failed");
+            Connectivity::Offline
+        }
+    }
+}
+
+async fn check(app: &AppHandle) -> Connectivity {
+    // Trust the OS when it says there's no network at all; it can't tell
+    // "connected" from "connected to something useful", so probe for that.
+    if platform::os_reports_connected() == Some(false) {
+        return Connectivity::Offline;
+    }
+    probe(app).await
+}
+
+pub fn start(app: &AppHandle) {
+    platform::watch(app.clone());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = check(&app).await;
+            set_state(&app, state);
+            let interval = match state {
+                Connectivity::Online => power::throttle(&app).scale(ONLINE_INTERVAL),
+                _ => OFFLINE_INTERVAL,
+            };
+            let monitor = app.state::<ConnectivityMonitor>();
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = monitor.os_changed.notified() => {}
+            }
+        }
+    });
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn os_changed(app: &AppHandle) {
+    app.state::<ConnectivityMonitor>().os_changed.notify_one();
+}
+
+#[tauri::command]
+pub fn get_connectivity(state: State<'_, ConnectivityMonitor>) -> CommandResult<ConnectivityStatus> {
+    Ok(state.status.lock()?.clone())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use tauri::AppHandle;
+    use windows::Win32::NetworkManagement::IpHelper::NotifyAddrChange;
+    use windows::Win32::Networking::NetworkListManager::{
+        INetworkListManager, NetworkListManager, NLM_CONNECTIVITY_DISCONNECTED,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+
+    // None when the Network List Manager can't be asked.
+    pub fn os_reports_connected() -> Option<bool> {
+        unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let nlm: INetworkListManager = CoCreateInstance(&NetworkListManager, None, CLSCTX_ALL).ok()?;
+            let connectivity = nlm.GetConnectivity().ok()?;
+            Some(connectivity != NLM_CONNECTIVITY_DISCONNECTED)
+        }
+    }
+
+    // NotifyAddrChange without a handle blocks until any adapter's address
+    // changes, which covers cables, Wi-Fi and VPNs coming and going.
+    pub fn watch(app: AppHandle) {
+        let spawned = std::thread::Builder::new().name("connectivity".into()).spawn(move || loop {
+            let result = unsafe { NotifyAddrChange(std::ptr::null_mut(), std::ptr::null()) };
+            if result != 0 {
+                tracing::warn!(code = result, "address change notifications unavailable");
+                return;
+            }
+            super::os_changed(&app);
+        });
+        if let Err(e) = spawned {
+            tracing::warn!(error = %e, "failed to start connectivity watcher");
+        }
+    }
+}
+
+// Elsewhere we rely on the periodic probe alone.
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use tauri::AppHandle;
+
+    pub fn os_reports_connected() -> Option<bool> {
+        None
+    }
+
+    pub fn watch(_app: AppHandle) {}
+}
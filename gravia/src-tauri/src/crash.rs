@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::persist;
+
+const MAX_REPORTS: usize = 10;
+const MAX_REPORT_BYTES: usize = 64 * 1024;
+const RUNNING_MARKER: &str = "running.marker";
+
+// Set once the app's log dir is known; panics before that go to the temp dir.
+static CRASH_DIR: OnceLock<PathBuf> = OnceLock::new();
+// Supplies the last few log lines for a report, once logging is up.
+static LOG_TAIL: OnceLock<fn() -> Vec<String>> = OnceLock::new();
+static DIRTY_PREVIOUS_SHUTDOWN: OnceLock<bool> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CrashReport {
+    pub timestamp: DateTime<Utc>,
+    pub app_version: String,
+    pub thread: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub log_tail: Vec<String>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CrashReportSummary {
+    pub file_name: String,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+}
+
+fn crash_dir() -> PathBuf {
+    CRASH_DIR
+        .get()
+        .cloned()
+        .unwrap_or_else(|| std::env::temp_dir().join("gravia-crashes"))
+}
+
+pub fn set_log_tail_provider(provider: fn() -> Vec<String>) {
+    let _ = LOG_TAIL.set(provider);
+}
+
+// Installed first thing in `run()`. Chains to the default hook so panics
+// still reach stderr.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic payload".to_string());
+        let report = CrashReport {
+            timestamp: Utc::now(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+            message,
+            location: info.location().map(|l| format!("{}:{}", l.file(), l.line())),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            log_tail: LOG_TAIL.get().map(|f| f()).unwrap_or_default(),
+        };
+        // A panic inside a panic hook aborts the process, so everything in
+        // here reports failures instead of unwrapping.
+        if let Err(e) = write_report(&report) {
+            eprintln!("Failed to write crash report: {e}");
+        }
+        default_hook(info);
+    }));
+}
+
+fn write_report(report: &CrashReport) -> std::io::Result<()> {
+    let dir = crash_dir();
+    std::fs::create_dir_all(&dir)?;
+    let mut json = serde_json::to_vec_pretty(report).map_err(std::io::Error::other)?;
+    if json.len() > MAX_REPORT_BYTES {
+        // Keep the report parseable by trimming the bulky fields first.
+        let mut trimmed = report.clone();
+        trimmed.log_tail.clear();
+        trimmed.backtrace = trimmed.backtrace.chars().take(MAX_REPORT_BYTES / 2).collect();
+        json = serde_json::to_vec_pretty(&trimmed).map_err(std::io::Error::other)?;
+    }
+    let name = format!("crash-{}.json", report.timestamp.format("%Y%m%d-%H%M%S%.3f"));
+    persist::write_atomic(&dir.join(name), &json)?;
+    prune_reports(&dir);
+    Ok(())
+}
+
+fn report_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names embed the timestamp, so lexical order is chronological.
+    files.sort();
+    files
+}
+
+fn prune_reports(dir: &Path) {
+    let files = report_files(dir);
+    let excess = files.len().saturating_sub(MAX_REPORTS);
+    for old in &files[..excess] {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+// Points crash reports at the app log dir and checks the dirty-shutdown
+// marker left by the previous session.
+pub fn init(app: &AppHandle) {
+    let Ok(log_dir) = app.path().app_log_dir() else { return };
+    let _ = CRASH_DIR.set(log_dir.join("crashes"));
+
+    let marker = log_dir.join(RUNNING_MARKER);
+    let _ = DIRTY_PREVIOUS_SHUTDOWN.set(marker.exists());
+    if let Err(e) = std::fs::create_dir_all(&log_dir).and_then(|_| std::fs::write(&marker, b"")) {
+        eprintln!("Failed to write running marker: {e}");
+    }
+}
+
+// Called on a clean exit so the next launch doesn't report a crash.
+pub fn mark_clean_shutdown(app: &AppHandle) {
+    if let Ok(log_dir) = app.path().app_log_dir() {
+        let _ = std::fs::remove_file(log_dir.join(RUNNING_MARKER));
+    }
+}
+
+#[tauri::command]
+pub fn previous_session_crashed() -> bool {
+    DIRTY_PREVIOUS_SHUTDOWN.get().copied().unwrap_or(false)
+}
+
+#[tauri::command]
+pub async fn get_last_crash_report() -> CommandResult<Option<CrashReport>> {
+    tauri::async_runtime::spawn_blocking(read_last_report).await?
+}
+
+fn read_last_report() -> CommandResult<Option<CrashReport>> {
+    let Some(latest) = report_files(&crash_dir()).pop() else { return Ok(None) };
+    let raw = std::fs::read_to_string(&latest)?;
+    serde_json::from_str(&raw).map(Some).map_err(GraviaError::internal)
+}
+
+#[tauri::command]
+pub async fn list_crash_reports() -> CommandResult<Vec<CrashReportSummary>> {
+    Ok(tauri::async_runtime::spawn_blocking(summarize_reports).await?)
+}
+
+fn summarize_reports() -> Vec<CrashReportSummary> {
+    report_files(&crash_dir())
+        .into_iter()
+        .rev()
+        .filter_map(|path| {
+            let raw = std::fs::read_to_string(&path).ok()?;
+            let report: CrashReport = serde_json::from_str(&raw).ok()?;
+            Some(CrashReportSummary {
+                file_name: path.file_name()?.to_string_lossy().into_owned(),
+                timestamp: report.timestamp,
+                message: report.message,
+            })
+        })
+        .collect()
+}
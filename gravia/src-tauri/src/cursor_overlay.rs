@@ -0,0 +1,248 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::borrow::Cow;
+
+use crate::capture::{self, ScreenInfo};
+
+// The classic arrow at 1x, drawn where the system cursor image can't be
+// read. `B` is the outline, `W` the fill; the hotspot is the top-left tip.
+const ARROW: [&str; 19] = [
+    "B           ",
+    "BB          ",
+    "BWB         ",
+    "BWWB        ",
+    "BWWWB       ",
+    "BWWWWB      ",
+    "BWWWWWB     ",
+    "BWWWWWWB    ",
+    "BWWWWWWWB   ",
+    "BWWWWWWWWB  ",
+    "BWWWWWWWWWB ",
+    "BWWWWWWBBBBB",
+    "BWWWBWWB    ",
+    "BWWBBWWB    ",
+    "BWB  BWWB   ",
+    "BB   BWWB   ",
+    "B     BWWB  ",
+    "      BWWB  ",
+    "       BB   ",
+];
+
+// The part of the desktop a frame shows, in the layout units displays
+// report their bounds in.
+#[derive(Debug, Clone, Copy)]
+pub struct DesktopRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// The pointer as it should appear in a capture.
+pub struct Cursor {
+    // Hotspot position in desktop layout units.
+    x: f64,
+    y: f64,
+    image: RgbaImage,
+    hotspot: (u32, u32),
+    // Layout units one pixel of `image` spans.
+    units_per_pixel: f64,
+}
+
+// The cursor at (`x`, `y`) on `screen`, with the system's cursor image
+// where it can be read and the built-in arrow otherwise. `None` when the
+// cursor is hidden, e.g. while typing or in a fullscreen game.
+pub fn locate(x: f64, y: f64, screen: &ScreenInfo) -> Option<Cursor> {
+    let units_per_px = capture::layout_units_per_pixel(screen);
+    match platform::system_cursor() {
+        Ok(Some((image, hotspot))) => Some(Cursor { x, y, image, hotspot, units_per_pixel: units_per_px }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::debug!(error = %e, "system cursor image unavailable, drawing the default arrow");
+            // The arrow is sized for 1x, so it grows with the display scale.
+            let units_per_pixel = screen.scale_factor.max(1.0) as f64 * units_per_px;
+            Some(Cursor { x, y, image: arrow(), hotspot: (0, 0), units_per_pixel })
+        }
+    }
+}
+
+fn arrow() -> RgbaImage {
+    RgbaImage::from_fn(ARROW[0].len() as u32, ARROW.len() as u32, |x, y| {
+        match ARROW[y as usize].as_bytes()[x as usize] {
+            b'B' => image::Rgba([0, 0, 0, 255]),
+            b'W' => image::Rgba([255, 255, 255, 255]),
+            _ => image::Rgba([0, 0, 0, 0]),
+        }
+    })
+}
+
+// Blends `cursor` onto a `width` x `height` RGBA frame showing `covers`.
+// The frame's own pixel density decides where and how large it lands, so
+// scaled displays and stitched desktops line up; a cursor outside `covers`
+// leaves the frame untouched.
+pub fn draw(rgba: &mut [u8], width: u32, height: u32, covers: DesktopRect, cursor: &Cursor) {
+    if covers.width <= 0.0 || covers.height <= 0.0 {
+        return;
+    }
+    let px_per_unit = width as f64 / covers.width;
+    let scale = cursor.units_per_pixel * px_per_unit;
+    let (cw, ch) = (
+        ((cursor.image.width() as f64 * scale).round() as u32).max(1),
+        ((cursor.image.height() as f64 * scale).round() as u32).max(1),
+    );
+    let px_per_unit_y = height as f64 / covers.height;
+    let left = ((cursor.x - covers.x) * px_per_unit - cursor.hotspot.0 as f64 * scale).round() as i64;
+    let top = ((cursor.y - covers.y) * px_per_unit_y - cursor.hotspot.1 as f64 * scale).round() as i64;
+    if left >= width as i64 || top >= height as i64 || left + cw as i64 <= 0 || top + ch as i64 <= 0 {
+        return;
+    }
+
+    let image = if (cw, ch) == cursor.image.dimensions() {
+        Cow::Borrowed(&cursor.image)
+    } else {
+        Cow::Owned(imageops::resize(&cursor.image, cw, ch, FilterType::Triangle))
+    };
+    for (cx, cy, px) in image.enumerate_pixels() {
+        let (x, y) = (left + cx as i64, top + cy as i64);
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            continue;
+        }
+        let alpha = px[3] as u32;
+        if alpha == 0 {
+            continue;
+        }
+        let at = (y as usize * width as usize + x as usize) * 4;
+        for c in 0..3 {
+            let dst = rgba[at + c] as u32;
+            rgba[at + c] = ((px[c] as u32 * alpha + dst * (255 - alpha)) / 255) as u8;
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use image::RgbaImage;
+    use windows::Win32::Foundation::{HWND, RECT};
+    use windows::Win32::Graphics::Gdi::{
+        CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, FillRect, GetDC, GetDIBits,
+        GetObjectW, GetStockObject, ReleaseDC, SelectObject, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+        BLACK_BRUSH, DIB_RGB_COLORS, GET_STOCK_OBJECT_FLAGS, HBRUSH, WHITE_BRUSH,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DrawIconEx, GetCursorInfo, GetIconInfo, CURSORINFO, CURSOR_SHOWING, DI_NORMAL, HICON, ICONINFO,
+    };
+
+    // The current cursor image as straight-alpha RGBA with its hotspot.
+    // `Ok(None)` when the cursor is hidden.
+    pub fn system_cursor() -> anyhow::Result<Option<(RgbaImage, (u32, u32))>> {
+        unsafe {
+            let mut info = CURSORINFO { cbSize: std::mem::size_of::<CURSORINFO>() as u32, ..Default::default() };
+            GetCursorInfo(&mut info)?;
+            if info.flags.0 & CURSOR_SHOWING.0 == 0 {
+                return Ok(None);
+            }
+            let icon = HICON(info.hCursor.0);
+            let mut icon_info = ICONINFO::default();
+            GetIconInfo(icon, &mut icon_info)?;
+            let monochrome = icon_info.hbmColor.is_invalid();
+            let mut bitmap = BITMAP::default();
+            let source = if monochrome { icon_info.hbmMask } else { icon_info.hbmColor };
+            let read = GetObjectW(
+                source,
+                std::mem::size_of::<BITMAP>() as i32,
+                Some(&mut bitmap as *mut BITMAP as *mut _),
+            );
+            let _ = DeleteObject(icon_info.hbmMask);
+            if !monochrome {
+                let _ = DeleteObject(icon_info.hbmColor);
+            }
+            if read == 0 {
+                anyhow::bail!("cursor bitmap could not be read");
+            }
+            let width = bitmap.bmWidth;
+            // A monochrome cursor's AND and XOR masks are stacked.
+            let height = if monochrome { bitmap.bmHeight / 2 } else { bitmap.bmHeight };
+
+            // Drawing over black and over white recovers the alpha, and
+            // also covers cursors that invert what's under them.
+            let on_black = render(icon, width, height, BLACK_BRUSH)?;
+            let on_white = render(icon, width, height, WHITE_BRUSH)?;
+            let image = RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+                let at = ((y * width as u32 + x) * 4) as usize;
+                let alpha = 255 - (on_white[at + 1] as i32 - on_black[at + 1] as i32).clamp(0, 255);
+                let channel = |c: usize| {
+                    if alpha == 0 {
+                        0
+                    } else {
+                        (on_black[at + c] as i32 * 255 / alpha).min(255) as u8
+                    }
+                };
+                // BGRA in, RGBA out.
+                image::Rgba([channel(2), channel(1), channel(0), alpha as u8])
+            });
+            Ok(Some((image, (icon_info.xHotspot, icon_info.yHotspot))))
+        }
+    }
+
+    // Draws the cursor over a solid background. Returns top-down BGRA.
+    unsafe fn render(
+        icon: HICON,
+        width: i32,
+        height: i32,
+        background: GET_STOCK_OBJECT_FLAGS,
+    ) -> anyhow::Result<Vec<u8>> {
+        let screen_dc = GetDC(HWND::default());
+        let mem_dc = CreateCompatibleDC(screen_dc);
+        let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+        let previous = SelectObject(mem_dc, bitmap);
+        let rect = RECT { left: 0, top: 0, right: width, bottom: height };
+        FillRect(mem_dc, &rect, HBRUSH(GetStockObject(background).0));
+        let drawn = DrawIconEx(mem_dc, 0, 0, icon, width, height, 0, HBRUSH::default(), DI_NORMAL);
+
+        let mut info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                // Negative height asks for rows top-down.
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        let rows = GetDIBits(
+            mem_dc,
+            bitmap,
+            0,
+            height as u32,
+            Some(pixels.as_mut_ptr() as *mut _),
+            &mut info,
+            DIB_RGB_COLORS,
+        );
+
+        SelectObject(mem_dc, previous);
+        let _ = DeleteObject(bitmap);
+        let _ = DeleteDC(mem_dc);
+        ReleaseDC(HWND::default(), screen_dc);
+
+        drawn?;
+        if rows == 0 {
+            anyhow::bail!("cursor could not be rendered");
+        }
+        Ok(pixels)
+    }
+}
+
+// Only Windows exposes the current cursor image. Elsewhere this always
+// fails, and `locate` draws the built-in arrow instead.
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use image::RgbaImage;
+
+    pub fn system_cursor() -> anyhow::Result<Option<(RgbaImage, (u32, u32))>> {
+        anyhow::bail!("reading the cursor image is not supported on this platform")
+    }
+}
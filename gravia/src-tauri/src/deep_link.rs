@@ -0,0 +1,115 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Url};
+use ts_rs::TS;
+
+use crate::error::CommandResult;
+use crate::events::{emit_event, GraviaEvent};
+use crate::tray;
+
+const SCHEME: &str = "gravia";
+const MAX_URL_LEN: usize = 4096;
+const MAX_TEXT_LEN: usize = 2000;
+const MAX_SESSION_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeepLinkIntent {
+    Ask { text: String, capture: bool },
+    OpenSession { id: String },
+    Capture,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeepLinkError {
+    #[error("deep link exceeds {MAX_URL_LEN} bytes")]
+    TooLong,
+    #[error("not a gravia:// link")]
+    WrongScheme,
+    #[error("invalid link: {0}")]
+    Malformed(String),
+    #[error("unknown action `{0}`")]
+    UnknownAction(String),
+}
+
+// Links received before the frontend is listening (cold start); drained
+// through `take_pending_deep_links`.
+#[derive(Default)]
+pub struct PendingDeepLinks(Mutex<Vec<DeepLinkIntent>>);
+
+pub fn parse(raw: &str) -> Result<DeepLinkIntent, DeepLinkError> {
+    if raw.len() > MAX_URL_LEN {
+        return Err(DeepLinkError::TooLong);
+    }
+    let url = Url::parse(raw).map_err(|e| DeepLinkError::Malformed(e.to_string()))?;
+    if url.scheme() != SCHEME {
+        return Err(DeepLinkError::WrongScheme);
+    }
+    // `gravia://ask?..` puts the action in the host slot; `gravia:ask?..` in the path.
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'))
+        .to_ascii_lowercase();
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+
+    match action.as_str() {
+        "ask" => {
+            let text: String = param("text").unwrap_or_default().chars().take(MAX_TEXT_LEN).collect();
+            let capture = matches!(param("capture").as_deref(), Some("1" | "true" | "yes"));
+            if text.trim().is_empty() && !capture {
+                return Err(DeepLinkError::Malformed("ask requires text or capture".into()));
+            }
+            Ok(DeepLinkIntent::Ask { text, capture })
+        }
+        "session" | "open-session" => {
+            let id = param("id").unwrap_or_default();
+            let valid = !id.is_empty()
+                && id.len() <= MAX_SESSION_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(DeepLinkError::Malformed("invalid session id".into()));
+            }
+            Ok(DeepLinkIntent::OpenSession { id })
+        }
+        "capture" => Ok(DeepLinkIntent::Capture),
+        other => Err(DeepLinkError::UnknownAction(other.chars().take(32).collect())),
+    }
+}
+
+// Focuses the main window and forwards each valid link as a `deep-link`
+// event. Invalid links are logged and dropped.
+pub fn handle_urls<I, S>(app: &AppHandle, urls: I, cold_start: bool)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    for raw in urls {
+        let raw = raw.as_ref();
+        if !raw.starts_with(SCHEME) {
+            continue;
+        }
+        match parse(raw) {
+            Ok(intent) if cold_start => {
+                if let Ok(mut pending) = app.state::<PendingDeepLinks>().0.lock() {
+                    pending.push(intent);
+                }
+            }
+            Ok(intent) => {
+                tray::show_main_window(app);
+                emit_event(app, GraviaEvent::DeepLink(intent)).ok();
+            }
+            Err(e) => tracing::warn!(error = %e, "rejected deep link"),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn take_pending_deep_links(state: State<'_, PendingDeepLinks>) -> CommandResult<Vec<DeepLinkIntent>> {
+    let mut pending = state.0.lock()?;
+    Ok(std::mem::take(&mut *pending))
+}
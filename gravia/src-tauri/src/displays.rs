@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::capture::{self, ScreenInfo};
+#[cfg(feature = "capture")]
+use crate::capture::{CaptureError, CaptureTarget};
+use crate::events::{emit_event, GraviaEvent};
+use crate::power;
+#[cfg(feature = "capture")]
+use crate::screen_hash::ScreenHashes;
+
+// Docking or undocking is noticed within this long.
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+// The displays as last listed, so plugging one in or out can be noticed and
+// a capture of one that's gone can say so.
+#[derive(Default)]
+pub struct DisplayWatch {
+    // `None` until the first listing.
+    screens: Mutex<Option<Vec<ScreenInfo>>>,
+    // Every display id listed since launch.
+    seen: Mutex<HashSet<u32>>,
+    recheck: Notify,
+}
+
+pub fn start(app: &AppHandle) {
+    if !cfg!(feature = "capture") {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            refresh(&app).await;
+            let watch = app.state::<DisplayWatch>();
+            tokio::select! {
+                _ = tokio::time::sleep(power::throttle(&app).scale(POLL_INTERVAL)) => {}
+                _ = watch.recheck.notified() => {}
+            }
+        }
+    });
+}
+
+// Lists the displays again and, when one was added, removed or moved,
+// drops state tied to the old layout and emits `displays-changed` with the
+// new list.
+async fn refresh(app: &AppHandle) {
+    let screens = match capture::list_screens().await {
+        Ok(screens) => screens,
+        Err(e) => {
+            tracing::debug!(error = %e, "listing displays failed");
+            return;
+        }
+    };
+    let watch = app.state::<DisplayWatch>();
+    if let Ok(mut seen) = watch.seen.lock() {
+        seen.extend(screens.iter().map(|s| s.id));
+    }
+    let previous = {
+        let Ok(mut last) = watch.screens.lock() else { return };
+        if last.as_ref() == Some(&screens) {
+            return;
+        }
+        last.replace(screens.clone())
+    };
+    // The first listing is the starting point, not a change.
+    let Some(previous) = previous else { return };
+
+    let removed: Vec<u32> = previous.iter().map(|s| s.id).filter(|id| !screens.iter().any(|s| s.id == *id)).collect();
+    let added: Vec<u32> = screens.iter().map(|s| s.id).filter(|id| !previous.iter().any(|s| s.id == *id)).collect();
+    tracing::info!(?added, ?removed, count = screens.len(), "displays changed");
+    #[cfg(feature = "capture")]
+    {
+        let hashes = app.state::<ScreenHashes>();
+        for id in &removed {
+            hashes.forget(CaptureTarget::Screen(*id));
+        }
+        // Desktop duplication holds on to the output it was opened for.
+        #[cfg(target_os = "windows")]
+        crate::dxgi_capture::reset();
+    }
+    emit_event(app, GraviaEvent::DisplaysChanged(screens)).ok();
+}
+
+// The error for a capture of display `id`, which isn't connected:
+// `screen_removed` when it was earlier this session. Also has the displays
+// listed again, so the frontend hears about the change right away.
+#[cfg(feature = "capture")]
+pub fn screen_missing(app: &AppHandle, id: u32) -> CaptureError {
+    let watch = app.state::<DisplayWatch>();
+    watch.recheck.notify_one();
+    if watch.seen.lock().is_ok_and(|seen| seen.contains(&id)) {
+        CaptureError::ScreenRemoved(id)
+    } else {
+        CaptureError::ScreenNotFound(id)
+    }
+}
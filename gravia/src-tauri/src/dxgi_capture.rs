@@ -0,0 +1,267 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use windows::core::Interface;
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::{
+    D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_CPU_ACCESS_READ,
+    D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_SDK_VERSION,
+    D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
+};
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_MODE_ROTATION_IDENTITY, DXGI_MODE_ROTATION_UNSPECIFIED,
+    DXGI_SAMPLE_DESC,
+};
+use windows::Win32::Graphics::Dxgi::{
+    IDXGIDevice, IDXGIOutput1, IDXGIOutputDuplication, IDXGIResource, DXGI_ERROR_ACCESS_LOST,
+    DXGI_ERROR_NOT_FOUND, DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_DESC, DXGI_OUTDUPL_FRAME_INFO,
+};
+
+use crate::buffer_pool::BufferPool;
+use crate::capture::PixelFormat;
+use crate::pixel_format;
+
+// How long to wait for the desktop to present a new frame. An unchanged
+// desktop presents nothing, in which case the previous frame is reused.
+const FRAME_TIMEOUT_MS: u32 = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DxgiError {
+    // The session was invalidated (mode change, secure desktop, fullscreen
+    // switch) and has to be recreated.
+    #[error("desktop duplication access lost")]
+    AccessLost,
+    #[error("desktop duplication unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl From<windows::core::Error> for DxgiError {
+    fn from(e: windows::core::Error) -> Self {
+        if e.code() == DXGI_ERROR_ACCESS_LOST {
+            DxgiError::AccessLost
+        } else {
+            DxgiError::Unavailable(e.message())
+        }
+    }
+}
+
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    pub monitor: String,
+    // The desktop's own channel order, before conversion.
+    pub source_format: PixelFormat,
+    // Spent copying the frame off the GPU mapping into RGBA.
+    pub convert: Duration,
+}
+
+struct Session {
+    // Owns the GPU resources below; never used directly after opening.
+    _device: ID3D11Device,
+    context: ID3D11DeviceContext,
+    duplication: IDXGIOutputDuplication,
+    // Holds the last frame copied off the GPU, so it can be read again when
+    // nothing has changed since.
+    staging: ID3D11Texture2D,
+    has_frame: bool,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    monitor: String,
+}
+
+// D3D11 devices are free-threaded and the session is only touched under
+// `SESSION`'s lock.
+unsafe impl Send for Session {}
+
+// Kept between captures; creating a duplication costs far more than
+// grabbing a frame from one.
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+impl Session {
+    // Duplicates the primary output of the default adapter.
+    fn open() -> Result<Self, DxgiError> {
+        let mut device = None;
+        let mut context = None;
+        unsafe {
+            D3D11CreateDevice(
+                None,
+                D3D_DRIVER_TYPE_HARDWARE,
+                HMODULE::default(),
+                D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                None,
+                D3D11_SDK_VERSION,
+                Some(&mut device),
+                None,
+                Some(&mut context),
+            )?;
+        }
+        let device: ID3D11Device = device.ok_or_else(|| DxgiError::Unavailable("no D3D11 device".into()))?;
+        let context = context.ok_or_else(|| DxgiError::Unavailable("no D3D11 context".into()))?;
+
+        let adapter = unsafe { device.cast::<IDXGIDevice>()?.GetAdapter()? };
+        let mut index = 0;
+        let (output, desc) = loop {
+            let output = match unsafe { adapter.EnumOutputs(index) } {
+                Ok(output) => output,
+                Err(e) if e.code() == DXGI_ERROR_NOT_FOUND => {
+                    return Err(DxgiError::Unavailable("primary display is not on the default adapter".into()));
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let desc = unsafe { output.GetDesc()? };
+            // The primary display is the one at the desktop origin.
+            if desc.AttachedToDesktop.as_bool() && desc.DesktopCoordinates.left == 0 && desc.DesktopCoordinates.top == 0 {
+                break (output, desc);
+            }
+            index += 1;
+        };
+        // Rotated displays duplicate in panel orientation; leave those to the
+        // standard path rather than rotating here.
+        if desc.Rotation != DXGI_MODE_ROTATION_IDENTITY && desc.Rotation != DXGI_MODE_ROTATION_UNSPECIFIED {
+            return Err(DxgiError::Unavailable("display is rotated".into()));
+        }
+
+        let duplication = unsafe { output.cast::<IDXGIOutput1>()?.DuplicateOutput(&device)? };
+        let mut dupl_desc = DXGI_OUTDUPL_DESC::default();
+        unsafe { duplication.GetDesc(&mut dupl_desc) };
+        let width = dupl_desc.ModeDesc.Width;
+        let height = dupl_desc.ModeDesc.Height;
+        // Duplication hands over the desktop in its own format, which the
+        // staging copy has to match. HDR desktops come as float and are
+        // left to the standard path.
+        let format = match dupl_desc.ModeDesc.Format {
+            DXGI_FORMAT_B8G8R8A8_UNORM => PixelFormat::Bgra,
+            DXGI_FORMAT_R8G8B8A8_UNORM => PixelFormat::Rgba,
+            other => return Err(DxgiError::Unavailable(format!("unsupported desktop format {}", other.0))),
+        };
+
+        let staging_desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: dupl_desc.ModeDesc.Format,
+            SampleDesc: DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+            Usage: D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            MiscFlags: 0,
+        };
+        let mut staging = None;
+        unsafe { device.CreateTexture2D(&staging_desc, None, Some(&mut staging))? };
+        let staging = staging.ok_or_else(|| DxgiError::Unavailable("no staging texture".into()))?;
+
+        tracing::debug!(width, height, ?format, "desktop duplication session opened");
+        Ok(Self {
+            _device: device,
+            context,
+            duplication,
+            staging,
+            has_frame: false,
+            width,
+            height,
+            format,
+            monitor: format!("primary display ({width}x{height})"),
+        })
+    }
+
+    // Copies the next desktop frame into the staging texture, or keeps the
+    // previous one if the desktop hasn't changed.
+    fn acquire(&mut self) -> Result<(), DxgiError> {
+        let mut info = DXGI_OUTDUPL_FRAME_INFO::default();
+        let mut resource: Option<IDXGIResource> = None;
+        match unsafe { self.duplication.AcquireNextFrame(FRAME_TIMEOUT_MS, &mut info, &mut resource) } {
+            Ok(()) => {}
+            Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT && self.has_frame => return Ok(()),
+            Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT => {
+                return Err(DxgiError::Unavailable("no frame presented yet".into()));
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let copied = (|| {
+            // DRM-protected windows come through blacked out; the standard
+            // path is asked instead.
+            if info.ProtectedContentMaskedOut.as_bool() {
+                return Err(DxgiError::Unavailable("protected content on screen".into()));
+            }
+            let texture = resource
+                .ok_or_else(|| DxgiError::Unavailable("no desktop resource".into()))?
+                .cast::<ID3D11Texture2D>()?;
+            unsafe { self.context.CopyResource(&self.staging, &texture) };
+            Ok(())
+        })();
+        unsafe { self.duplication.ReleaseFrame().ok() };
+        copied?;
+        self.has_frame = true;
+        Ok(())
+    }
+
+    // Reads the staging texture as tightly packed RGBA, top row first like
+    // the standard path.
+    fn read(&self, pool: &BufferPool, parallel: bool) -> Result<(Vec<u8>, Duration), DxgiError> {
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        unsafe { self.context.Map(&self.staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))? };
+        let row_bytes = self.width as usize * 4;
+        let pitch = mapped.RowPitch as usize;
+        let mut rgba = pool.take(row_bytes * self.height as usize);
+        rgba.resize(row_bytes * self.height as usize, 0);
+        // SAFETY: the mapping covers `RowPitch` bytes for each of `height`
+        // rows until Unmap below.
+        let src = unsafe { std::slice::from_raw_parts(mapped.pData as *const u8, pitch * self.height as usize) };
+        let started = Instant::now();
+        pixel_format::copy_rows_to_rgba(src, pitch, &mut rgba, row_bytes, self.format, parallel);
+        let convert = started.elapsed();
+        unsafe { self.context.Unmap(&self.staging, 0) };
+        Ok((rgba, convert))
+    }
+}
+
+// Closes the session, so the next grab duplicates whatever output is
+// primary by then.
+pub fn reset() {
+    if let Ok(mut session) = SESSION.lock() {
+        *session = None;
+    }
+}
+
+// Grabs the primary display through DXGI desktop duplication. The session is
+// opened on first use and reopened once if access was lost; any error means
+// the caller should use the standard path for this capture.
+pub fn grab(pool: &BufferPool, parallel: bool) -> Result<Frame, DxgiError> {
+    let mut session = SESSION.lock().map_err(|e| DxgiError::Unavailable(e.to_string()))?;
+    for attempt in 0..2 {
+        if session.is_none() {
+            *session = Some(Session::open()?);
+        }
+        let Some(current) = session.as_mut() else { continue };
+        match current.acquire() {
+            Ok(()) => {
+                let (rgba, convert) = current.read(pool, parallel)?;
+                return Ok(Frame {
+                    width: current.width,
+                    height: current.height,
+                    rgba,
+                    monitor: current.monitor.clone(),
+                    source_format: current.format,
+                    convert,
+                });
+            }
+            Err(DxgiError::AccessLost) if attempt == 0 => {
+                tracing::debug!("desktop duplication access lost, reopening");
+                *session = None;
+            }
+            Err(e) => {
+                // A protected-content or timeout miss doesn't break the
+                // session; anything else might.
+                if matches!(e, DxgiError::AccessLost) {
+                    *session = None;
+                }
+                return Err(e);
+            }
+        }
+    }
+    Err(DxgiError::AccessLost)
+}
@@ -0,0 +1,502 @@
+use serde::{Serialize, Serializer};
+use ts_rs::TS;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::actions::ActionError;
+use crate::audio::AudioError;
+use crate::backend_socket::SocketError;
+use crate::capture::CaptureError;
+use crate::clipboard::ClipboardError;
+use crate::foreground::ForegroundError;
+use crate::ocr::OcrError;
+use crate::print_screen::ShortcutError;
+use crate::screenshot_file::SaveError;
+use crate::secrets::SecretError;
+use crate::speech::TtsError;
+use crate::updater::UpdateError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("query must not be empty")]
+    EmptyQuery,
+    #[error("failed to save the session: {0}")]
+    SaveFailed(String),
+}
+
+impl SessionError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SessionError::EmptyQuery => "empty_query",
+            SessionError::SaveFailed(_) => "save_failed",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    #[error("failed to start the backend server: {0}")]
+    SpawnFailed(String),
+    #[error("the backend server exited before it was ready")]
+    ExitedEarly,
+    #[error("the backend server was not ready after {secs}s")]
+    StartTimeout { secs: u64 },
+}
+
+impl ServerError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServerError::SpawnFailed(_) => "spawn_failed",
+            ServerError::ExitedEarly => "exited_early",
+            ServerError::StartTimeout { .. } => "start_timeout",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("invalid value for {key}: {reason}")]
+    InvalidValue { key: &'static str, reason: String },
+    #[error("failed to save settings: {0}")]
+    SaveFailed(String),
+}
+
+impl SettingsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SettingsError::InvalidValue { .. } => "invalid_value",
+            SettingsError::SaveFailed(_) => "save_failed",
+        }
+    }
+}
+
+// The one error type every command returns. Serialized for the frontend as
+// `{ code, message, details }`, where `code` is stable and safe to match on.
+#[derive(Debug, thiserror::Error)]
+pub enum GraviaError {
+    #[error(transparent)]
+    Capture(CaptureError),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    #[error(transparent)]
+    Server(#[from] ServerError),
+    #[error(transparent)]
+    Settings(#[from] SettingsError),
+    #[error(transparent)]
+    Clipboard(ClipboardError),
+    #[error(transparent)]
+    Foreground(#[from] ForegroundError),
+    #[error(transparent)]
+    Update(#[from] UpdateError),
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+    #[error(transparent)]
+    Socket(#[from] SocketError),
+    #[error(transparent)]
+    Audio(AudioError),
+    #[error(transparent)]
+    Tts(#[from] TtsError),
+    #[error(transparent)]
+    Action(#[from] ActionError),
+    #[error(transparent)]
+    Shortcut(#[from] ShortcutError),
+    #[error(transparent)]
+    Save(#[from] SaveError),
+    #[error(transparent)]
+    Ocr(#[from] OcrError),
+    #[error("not supported on this platform")]
+    Unsupported,
+    #[error("you're offline")]
+    Offline,
+    #[error("too many requests, retry in {retry_after_ms}ms")]
+    RateLimited { retry_after_ms: u64 },
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("internal error (ref {id})")]
+    Internal { id: String },
+}
+
+static NEXT_ERROR_ID: AtomicU64 = AtomicU64::new(1);
+
+impl GraviaError {
+    // Hides the underlying error from the UI but logs it under a short id the
+    // user can quote.
+    pub fn internal(err: impl std::fmt::Display) -> Self {
+        let id = format!(
+            "E{:x}{:02}",
+            chrono::Utc::now().timestamp() & 0xffff_ffff,
+            NEXT_ERROR_ID.fetch_add(1, Ordering::Relaxed) % 100
+        );
+        tracing::error!(error_id = %id, error = %err, "internal error");
+        GraviaError::Internal { id }
+    }
+
+    pub fn code(&self) -> String {
+        match self {
+            GraviaError::Capture(e) => format!("capture.{}", e.code()),
+            GraviaError::Session(e) => format!("session.{}", e.code()),
+            GraviaError::Server(e) => format!("server.{}", e.code()),
+            GraviaError::Settings(e) => format!("settings.{}", e.code()),
+            GraviaError::Clipboard(e) => format!("clipboard.{}", e.code()),
+            GraviaError::Foreground(e) => format!("foreground.{}", e.code()),
+            GraviaError::Update(e) => format!("update.{}", e.code()),
+            GraviaError::Secret(e) => format!("secret.{}", e.code()),
+            GraviaError::Socket(e) => format!("socket.{}", e.code()),
+            GraviaError::Audio(e) => format!("audio.{}", e.code()),
+            GraviaError::Tts(e) => format!("tts.{}", e.code()),
+            GraviaError::Action(e) => format!("action.{}", e.code()),
+            GraviaError::Shortcut(e) => format!("shortcut.{}", e.code()),
+            GraviaError::Save(e) => format!("save.{}", e.code()),
+            GraviaError::Ocr(e) => format!("ocr.{}", e.code()),
+            GraviaError::Unsupported => "unsupported".to_string(),
+            GraviaError::Offline => "offline".to_string(),
+            GraviaError::RateLimited { .. } => "rate_limited".to_string(),
+            GraviaError::InvalidArgument(_) => "invalid_argument".to_string(),
+            GraviaError::PermissionDenied(_) => "permission_denied".to_string(),
+            GraviaError::NotFound(_) => "not_found".to_string(),
+            GraviaError::Internal { .. } => "internal".to_string(),
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            GraviaError::RateLimited { retry_after_ms } => {
+                Some(serde_json::json!({ "retry_after_ms": retry_after_ms }))
+            }
+            GraviaError::Internal { id } => Some(serde_json::json!({ "id": id })),
+            GraviaError::Capture(CaptureError::CaptureNotFound(id)) => {
+                Some(serde_json::json!({ "capture_id": id }))
+            }
+            GraviaError::Capture(CaptureError::AmbiguousWindow { candidates, .. }) => {
+                Some(serde_json::json!({ "candidates": candidates }))
+            }
+            GraviaError::Capture(CaptureError::PermissionDenied) => {
+                Some(serde_json::json!({ "permission": "screen_recording" }))
+            }
+            GraviaError::Capture(CaptureError::ScreenNotFound(id) | CaptureError::ScreenRemoved(id)) => {
+                Some(serde_json::json!({ "screen_id": id }))
+            }
+            GraviaError::Capture(CaptureError::PayloadTooLarge { bytes, limit }) => {
+                Some(serde_json::json!({ "bytes": bytes, "limit": limit }))
+            }
+            GraviaError::Settings(SettingsError::InvalidValue { key, .. }) => {
+                Some(serde_json::json!({ "key": key }))
+            }
+            GraviaError::Shortcut(ShortcutError::InUse { holder }) => {
+                Some(serde_json::json!({ "holder": holder }))
+            }
+            GraviaError::Save(e) => Some(serde_json::json!({ "path": e.path() })),
+            _ => None,
+        }
+    }
+}
+
+// What a `GraviaError` looks like on the wire, exported to the frontend
+// under the same name.
+#[derive(Serialize, TS)]
+#[ts(export, rename = "GraviaError")]
+struct ErrorPayload {
+    code: String,
+    message: String,
+    #[ts(type = "Record<string, unknown> | null")]
+    details: Option<serde_json::Value>,
+}
+
+impl Serialize for GraviaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+            details: self.details(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<CaptureError> for GraviaError {
+    fn from(e: CaptureError) -> Self {
+        match e {
+            CaptureError::RateLimited { retry_after_ms } => GraviaError::RateLimited { retry_after_ms },
+            other => GraviaError::Capture(other),
+        }
+    }
+}
+
+impl From<ClipboardError> for GraviaError {
+    fn from(e: ClipboardError) -> Self {
+        match e {
+            ClipboardError::PermissionRequired => {
+                GraviaError::PermissionDenied("clipboard access has not been allowed in settings".into())
+            }
+            other => GraviaError::Clipboard(other),
+        }
+    }
+}
+
+impl From<AudioError> for GraviaError {
+    fn from(e: AudioError) -> Self {
+        match e {
+            AudioError::MicrophoneDisabled => {
+                GraviaError::PermissionDenied("microphone access has not been allowed in settings".into())
+            }
+            other => GraviaError::Audio(other),
+        }
+    }
+}
+
+impl From<anyhow::Error> for GraviaError {
+    fn from(e: anyhow::Error) -> Self {
+        GraviaError::internal(e)
+    }
+}
+
+impl From<std::io::Error> for GraviaError {
+    fn from(e: std::io::Error) -> Self {
+        GraviaError::internal(e)
+    }
+}
+
+impl From<tauri::Error> for GraviaError {
+    fn from(e: tauri::Error) -> Self {
+        GraviaError::internal(e)
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for GraviaError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        GraviaError::internal(e)
+    }
+}
+
+pub type CommandResult<T> = Result<T, GraviaError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn wire(err: impl Into<GraviaError>) -> Value {
+        serde_json::to_value(err.into()).unwrap()
+    }
+
+    #[test]
+    fn domain_errors_are_namespaced_with_their_details() {
+        assert_eq!(
+            wire(CaptureError::CaptureNotFound("cap-1".into())),
+            json!({
+                "code": "capture.capture_not_found",
+                "message": "capture cap-1 was not found or has been purged",
+                "details": { "capture_id": "cap-1" },
+            })
+        );
+        assert_eq!(
+            wire(CaptureError::PayloadTooLarge { bytes: 9000, limit: 4000 }),
+            json!({
+                "code": "capture.payload_too_large",
+                "message": "capture is 9000 bytes encoded, over the 4000 byte limit; open it as a file instead",
+                "details": { "bytes": 9000, "limit": 4000 },
+            })
+        );
+        assert_eq!(
+            wire(SettingsError::InvalidValue { key: "log_level", reason: "unknown level".into() }),
+            json!({
+                "code": "settings.invalid_value",
+                "message": "invalid value for log_level: unknown level",
+                "details": { "key": "log_level" },
+            })
+        );
+        assert_eq!(
+            wire(SessionError::EmptyQuery),
+            json!({ "code": "session.empty_query", "message": "query must not be empty", "details": null })
+        );
+    }
+
+    #[test]
+    fn shared_conditions_keep_one_code_whatever_raised_them() {
+        assert_eq!(
+            wire(CaptureError::RateLimited { retry_after_ms: 250 }),
+            json!({
+                "code": "rate_limited",
+                "message": "too many requests, retry in 250ms",
+                "details": { "retry_after_ms": 250 },
+            })
+        );
+        assert_eq!(
+            wire(ClipboardError::PermissionRequired),
+            json!({
+                "code": "permission_denied",
+                "message": "permission denied: clipboard access has not been allowed in settings",
+                "details": null,
+            })
+        );
+        assert_eq!(
+            wire(GraviaError::Unsupported),
+            json!({ "code": "unsupported", "message": "not supported on this platform", "details": null })
+        );
+    }
+
+    // Named by an exhaustive match, so a new variant doesn't compile until
+    // it's listed here and given a row in the snapshot table.
+    fn variant(err: &GraviaError) -> &'static str {
+        match err {
+            GraviaError::Capture(_) => "Capture",
+            GraviaError::Session(_) => "Session",
+            GraviaError::Server(_) => "Server",
+            GraviaError::Settings(_) => "Settings",
+            GraviaError::Clipboard(_) => "Clipboard",
+            GraviaError::Foreground(_) => "Foreground",
+            GraviaError::Update(_) => "Update",
+            GraviaError::Secret(_) => "Secret",
+            GraviaError::Socket(_) => "Socket",
+            GraviaError::Audio(_) => "Audio",
+            GraviaError::Tts(_) => "Tts",
+            GraviaError::Action(_) => "Action",
+            GraviaError::Shortcut(_) => "Shortcut",
+            GraviaError::Save(_) => "Save",
+            GraviaError::Ocr(_) => "Ocr",
+            GraviaError::Unsupported => "Unsupported",
+            GraviaError::Offline => "Offline",
+            GraviaError::RateLimited { .. } => "RateLimited",
+            GraviaError::InvalidArgument(_) => "InvalidArgument",
+            GraviaError::PermissionDenied(_) => "PermissionDenied",
+            GraviaError::NotFound(_) => "NotFound",
+            GraviaError::Internal { .. } => "Internal",
+        }
+    }
+
+    // Arms in `variant`. With a row each and no variant twice, the table
+    // covers them all.
+    const VARIANTS: usize = 22;
+
+    #[test]
+    fn every_variant_keeps_its_wire_shape() {
+        let payload =
+            |code: &str, message: &str, details: Value| json!({ "code": code, "message": message, "details": details });
+        let table: [(GraviaError, Value); VARIANTS] = [
+            (
+                CaptureError::ScreenNotFound(2).into(),
+                payload(
+                    "capture.screen_not_found",
+                    "screen 2 is not connected; list the screens again",
+                    json!({ "screen_id": 2 }),
+                ),
+            ),
+            (
+                SessionError::SaveFailed("disk full".into()).into(),
+                payload("session.save_failed", "failed to save the session: disk full", Value::Null),
+            ),
+            (
+                ServerError::StartTimeout { secs: 30 }.into(),
+                payload("server.start_timeout", "the backend server was not ready after 30s", Value::Null),
+            ),
+            (
+                SettingsError::SaveFailed("read-only".into()).into(),
+                payload("settings.save_failed", "failed to save settings: read-only", Value::Null),
+            ),
+            (
+                ClipboardError::Busy.into(),
+                payload("clipboard.busy", "another app is holding the clipboard; try again", Value::Null),
+            ),
+            (
+                ForegroundError::Minimized.into(),
+                payload("foreground.window_minimized", "the foreground window is minimized", Value::Null),
+            ),
+            (
+                UpdateError::SignatureInvalid.into(),
+                payload("update.signature_invalid", "the update's signature could not be verified", Value::Null),
+            ),
+            (
+                SecretError::KeyringLocked.into(),
+                payload("secret.keyring_locked", "the system keyring is locked", Value::Null),
+            ),
+            (
+                SocketError::QueueFull.into(),
+                payload("socket.queue_full", "too many messages are waiting for the backend", Value::Null),
+            ),
+            (
+                AudioError::NoDevice.into(),
+                payload("audio.no_device", "no audio input device is available", Value::Null),
+            ),
+            (
+                TtsError::VoiceNotFound("Alex".into()).into(),
+                payload("tts.voice_not_found", "voice `Alex` was not found", Value::Null),
+            ),
+            (
+                ActionError::NotAllowed("file:///etc".into()).into(),
+                payload("action.not_allowed", "action not allowed: file:///etc", Value::Null),
+            ),
+            (
+                ShortcutError::InUse { holder: Some("Snipping Tool".into()) }.into(),
+                payload(
+                    "shortcut.in_use",
+                    "PrintScreen is already taken by Snipping Tool",
+                    json!({ "holder": "Snipping Tool" }),
+                ),
+            ),
+            (
+                SaveError::FileExists("/tmp/shot.png".into()).into(),
+                payload("save.file_exists", "/tmp/shot.png already exists", json!({ "path": "/tmp/shot.png" })),
+            ),
+            (
+                OcrError::TimedOut(30).into(),
+                payload("ocr.timed_out", "text recognition took longer than 30s", Value::Null),
+            ),
+            (GraviaError::Unsupported, payload("unsupported", "not supported on this platform", Value::Null)),
+            (GraviaError::Offline, payload("offline", "you're offline", Value::Null)),
+            (
+                GraviaError::RateLimited { retry_after_ms: 250 },
+                payload("rate_limited", "too many requests, retry in 250ms", json!({ "retry_after_ms": 250 })),
+            ),
+            (
+                GraviaError::InvalidArgument("interval_ms must be at least 500".into()),
+                payload("invalid_argument", "invalid argument: interval_ms must be at least 500", Value::Null),
+            ),
+            (
+                GraviaError::PermissionDenied("screen recording".into()),
+                payload("permission_denied", "permission denied: screen recording", Value::Null),
+            ),
+            (
+                GraviaError::NotFound("capture operation op-7".into()),
+                payload("not_found", "capture operation op-7 not found", Value::Null),
+            ),
+            (
+                GraviaError::Internal { id: "E1a2b3c01".into() },
+                payload("internal", "internal error (ref E1a2b3c01)", json!({ "id": "E1a2b3c01" })),
+            ),
+        ];
+        let mut seen = std::collections::BTreeSet::new();
+        for (err, expected) in table {
+            let name = variant(&err);
+            assert!(seen.insert(name), "{name} has two rows");
+            assert_eq!(wire(err), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn the_exported_binding_matches_the_wire_shape() {
+        assert_eq!(
+            ErrorPayload::decl(),
+            "type GraviaError = { code: string, message: string, details: Record<string, unknown> | null, };"
+        );
+        let mut keys: Vec<String> = match wire(GraviaError::Offline) {
+            Value::Object(fields) => fields.keys().cloned().collect(),
+            other => panic!("not an object: {other}"),
+        };
+        keys.sort();
+        assert_eq!(keys, ["code", "details", "message"]);
+    }
+
+    #[test]
+    fn internal_errors_hide_the_cause_behind_an_id() {
+        let payload = wire(GraviaError::internal("disk on fire"));
+        let id = payload["details"]["id"].as_str().unwrap().to_string();
+        assert!(id.starts_with('E'));
+        assert_eq!(
+            payload,
+            json!({ "code": "internal", "message": format!("internal error (ref {id})"), "details": { "id": id } })
+        );
+    }
+}
@@ -0,0 +1,467 @@
+use serde::Serialize;
+use tauri::{Emitter, Runtime};
+use ts_rs::TS;
+
+use crate::actions::ActionExecuted;
+use crate::attachments::Attachment;
+use crate::audio::{MicLevel, MicSilence};
+use crate::backend_socket::ConnectionState;
+use crate::capture::{CapturePauseState, CaptureThrottled, CaptureTiming, ScreenInfo};
+use crate::capture_stream::ScreenshotFrame;
+use crate::config_watch::{ConfigInvalid, ConfigReloaded};
+use crate::connectivity::ConnectivityStatus;
+use crate::deep_link::DeepLinkIntent;
+use crate::foreground::ForegroundApp;
+use crate::idle::IdleEvent;
+use crate::logging::LogEntry;
+use crate::notifications::AutoCaptureNotice;
+use crate::persist::PersistenceRecovered;
+use crate::power::PowerState;
+use crate::print_screen::PrintScreenPressed;
+use crate::quick_ask::{QuickAskPrefill, QuickAskSubmission};
+use crate::speech::SpeakingState;
+use crate::theme::SystemTheme;
+use crate::updater::DownloadProgress;
+use crate::window_focus::FocusChanged;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ServerReady {
+    // True when we found a server from an earlier run instead of starting one.
+    pub already_running: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct RecordingLimitReached {
+    #[ts(type = "number")]
+    pub max_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CloseRequested;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ClipboardPermissionRequested;
+
+// Every event the native side sends to the frontend. Serializes as just the
+// payload; `name` gives the event name. Add new events here rather than
+// calling `emit` directly so the two sides can't drift apart.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+#[serde(untagged)]
+pub enum GraviaEvent {
+    ServerReady(ServerReady),
+    BackendConnectionChanged(ConnectionState),
+    BackendMessage(serde_json::Value),
+    ConnectivityChanged(ConnectivityStatus),
+    PowerStateChanged(PowerState),
+    ConfigReloaded(ConfigReloaded),
+    ConfigInvalid(ConfigInvalid),
+    PersistenceRecovered(PersistenceRecovered),
+    SystemThemeChanged(SystemTheme),
+    UserIdle(IdleEvent),
+    UserActive(IdleEvent),
+    ForegroundAppChanged(ForegroundApp),
+    WindowFocusChanged(FocusChanged),
+    CloseRequested(CloseRequested),
+    DeepLink(DeepLinkIntent),
+    PrintScreenPressed(PrintScreenPressed),
+    CapturePausedChanged(CapturePauseState),
+    // The new `list_screens` after a display was plugged in, unplugged or
+    // rearranged.
+    DisplaysChanged(Vec<ScreenInfo>),
+    ScreenshotFrame(ScreenshotFrame),
+    CaptureTiming(CaptureTiming),
+    CaptureThrottled(CaptureThrottled),
+    ScrollToMessage(AutoCaptureNotice),
+    FilesDropped(Vec<Attachment>),
+    ClipboardPermissionRequested(ClipboardPermissionRequested),
+    QuickAskPrefill(QuickAskPrefill),
+    QuickAskSubmitted(QuickAskSubmission),
+    ActionExecuted(ActionExecuted),
+    MicLevel(MicLevel),
+    MicSilence(MicSilence),
+    AudioRecordingLimitReached(RecordingLimitReached),
+    SpeakingState(SpeakingState),
+    UpdateDownloadProgress(DownloadProgress),
+    AppLog(LogEntry),
+}
+
+impl GraviaEvent {
+    // No wildcard arm, so a new variant without a name doesn't compile.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GraviaEvent::ServerReady(_) => "server-ready",
+            GraviaEvent::BackendConnectionChanged(_) => "backend-connection-changed",
+            GraviaEvent::BackendMessage(_) => "backend-message",
+            GraviaEvent::ConnectivityChanged(_) => "connectivity-changed",
+            GraviaEvent::PowerStateChanged(_) => "power-state-changed",
+            GraviaEvent::ConfigReloaded(_) => "config-reloaded",
+            GraviaEvent::ConfigInvalid(_) => "config-invalid",
+            GraviaEvent::PersistenceRecovered(_) => "persistence-recovered",
+            GraviaEvent::SystemThemeChanged(_) => "system-theme-changed",
+            GraviaEvent::UserIdle(_) => "user-idle",
+            GraviaEvent::UserActive(_) => "user-active",
+            GraviaEvent::ForegroundAppChanged(_) => "foreground-app-changed",
+            GraviaEvent::WindowFocusChanged(_) => "window-focus-changed",
+            GraviaEvent::CloseRequested(_) => "close-requested",
+            GraviaEvent::DeepLink(_) => "deep-link",
+            GraviaEvent::PrintScreenPressed(_) => "print-screen-pressed",
+            GraviaEvent::CapturePausedChanged(_) => "capture-paused-changed",
+            GraviaEvent::DisplaysChanged(_) => "displays-changed",
+            GraviaEvent::ScreenshotFrame(_) => "screenshot-frame",
+            GraviaEvent::CaptureTiming(_) => "capture-timing",
+            GraviaEvent::CaptureThrottled(_) => "capture-throttled",
+            GraviaEvent::ScrollToMessage(_) => "scroll-to-message",
+            GraviaEvent::FilesDropped(_) => "files-dropped",
+            GraviaEvent::ClipboardPermissionRequested(_) => "clipboard-permission-requested",
+            GraviaEvent::QuickAskPrefill(_) => "quick-ask-prefill",
+            GraviaEvent::QuickAskSubmitted(_) => "quick-ask-submitted",
+            GraviaEvent::ActionExecuted(_) => "action-executed",
+            GraviaEvent::MicLevel(_) => "mic-level",
+            GraviaEvent::MicSilence(_) => "mic-silence",
+            GraviaEvent::AudioRecordingLimitReached(_) => "audio-recording-limit-reached",
+            GraviaEvent::SpeakingState(_) => "speaking-state",
+            GraviaEvent::UpdateDownloadProgress(_) => "update-download-progress",
+            GraviaEvent::AppLog(_) => "app-log",
+        }
+    }
+}
+
+pub fn emit_event<R: Runtime>(emitter: &impl Emitter<R>, event: GraviaEvent) -> tauri::Result<()> {
+    emitter.emit(event.name(), &event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::OpenAction;
+    use crate::attachments::{AttachmentPreview, FileAttachment};
+    use crate::capture::{CaptureFormat, CaptureTimings};
+    use crate::connectivity::Connectivity;
+    use crate::power::PowerSource;
+    use crate::print_screen::PrintScreenAction;
+    use crate::theme::ThemeMode;
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use std::collections::{BTreeMap, HashSet};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    const AT: &str = "2026-01-02T03:04:05Z";
+
+    // One of every event, with its name and payload as the frontend sees it.
+    fn every_event() -> Vec<(GraviaEvent, &'static str, Value)> {
+        let at = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let screen = ScreenInfo { id: 1, x: -1920, y: 0, width: 1920, height: 1080, scale_factor: 1.5, is_primary: true };
+        let idle = IdleEvent { idle_seconds: 300, threshold_seconds: 240 };
+        vec![
+            (
+                GraviaEvent::ServerReady(ServerReady { already_running: true }),
+                "server-ready",
+                json!({ "already_running": true }),
+            ),
+            (
+                GraviaEvent::BackendConnectionChanged(ConnectionState::Disconnected),
+                "backend-connection-changed",
+                json!("disconnected"),
+            ),
+            (
+                GraviaEvent::BackendMessage(json!({ "type": "reply", "text": "hi" })),
+                "backend-message",
+                json!({ "type": "reply", "text": "hi" }),
+            ),
+            (
+                GraviaEvent::ConnectivityChanged(ConnectivityStatus {
+                    state: Connectivity::CaptivePortalSuspected,
+                    changed_at: at,
+                }),
+                "connectivity-changed",
+                json!({ "state": "captive_portal_suspected", "changed_at": AT }),
+            ),
+            (
+                GraviaEvent::PowerStateChanged(PowerState {
+                    source: PowerSource::Battery,
+                    battery_percent: Some(42),
+                    power_saver: true,
+                }),
+                "power-state-changed",
+                json!({ "source": "battery", "battery_percent": 42, "power_saver": true }),
+            ),
+            (
+                GraviaEvent::ConfigReloaded(ConfigReloaded {
+                    file: "settings.json".into(),
+                    warnings: vec!["unknown key `colour`".into()],
+                }),
+                "config-reloaded",
+                json!({ "file": "settings.json", "warnings": ["unknown key `colour`"] }),
+            ),
+            (
+                GraviaEvent::ConfigInvalid(ConfigInvalid {
+                    file: "classifier.json".into(),
+                    line: 3,
+                    column: 7,
+                    message: "expected `,`".into(),
+                }),
+                "config-invalid",
+                json!({ "file": "classifier.json", "line": 3, "column": 7, "message": "expected `,`" }),
+            ),
+            (
+                GraviaEvent::PersistenceRecovered(PersistenceRecovered {
+                    file: "session.json".into(),
+                    reason: "EOF while parsing".into(),
+                }),
+                "persistence-recovered",
+                json!({ "file": "session.json", "reason": "EOF while parsing" }),
+            ),
+            (
+                GraviaEvent::SystemThemeChanged(SystemTheme {
+                    mode: ThemeMode::Dark,
+                    taskbar_mode: ThemeMode::Light,
+                    accent_color: Some("#0078d4".into()),
+                    high_contrast: false,
+                }),
+                "system-theme-changed",
+                json!({ "mode": "dark", "taskbar_mode": "light", "accent_color": "#0078d4", "high_contrast": false }),
+            ),
+            (
+                GraviaEvent::UserIdle(idle.clone()),
+                "user-idle",
+                json!({ "idle_seconds": 300, "threshold_seconds": 240 }),
+            ),
+            (
+                GraviaEvent::UserActive(idle),
+                "user-active",
+                json!({ "idle_seconds": 300, "threshold_seconds": 240 }),
+            ),
+            (
+                GraviaEvent::ForegroundAppChanged(ForegroundApp {
+                    title: "notes.txt - Notepad".into(),
+                    process_name: "notepad.exe".into(),
+                    executable_path: r"C:\Windows\notepad.exe".into(),
+                    pid: 4321,
+                    is_gravia: false,
+                }),
+                "foreground-app-changed",
+                json!({
+                    "title": "notes.txt - Notepad",
+                    "process_name": "notepad.exe",
+                    "executable_path": r"C:\Windows\notepad.exe",
+                    "pid": 4321,
+                    "is_gravia": false,
+                }),
+            ),
+            (
+                GraviaEvent::WindowFocusChanged(FocusChanged { focused: true }),
+                "window-focus-changed",
+                json!({ "focused": true }),
+            ),
+            (GraviaEvent::CloseRequested(CloseRequested), "close-requested", Value::Null),
+            (
+                GraviaEvent::DeepLink(DeepLinkIntent::Ask { text: "what's this?".into(), capture: true }),
+                "deep-link",
+                json!({ "type": "ask", "text": "what's this?", "capture": true }),
+            ),
+            (
+                GraviaEvent::PrintScreenPressed(PrintScreenPressed { action: PrintScreenAction::RegionSelect }),
+                "print-screen-pressed",
+                json!({ "action": "region_select" }),
+            ),
+            (
+                GraviaEvent::CapturePausedChanged(CapturePauseState { enabled: true, until: Some(at) }),
+                "capture-paused-changed",
+                json!({ "enabled": true, "until": AT }),
+            ),
+            (
+                GraviaEvent::DisplaysChanged(vec![screen]),
+                "displays-changed",
+                json!([{
+                    "id": 1,
+                    "x": -1920,
+                    "y": 0,
+                    "width": 1920,
+                    "height": 1080,
+                    "scale_factor": 1.5,
+                    "is_primary": true,
+                }]),
+            ),
+            (
+                GraviaEvent::ScreenshotFrame(ScreenshotFrame {
+                    sequence: 7,
+                    capture_id: "cap-7".into(),
+                    base64: Some(Arc::new("iVBORw0KGgo=".into())),
+                    format: CaptureFormat::Jpeg,
+                    unchanged: false,
+                    dropped: 2,
+                }),
+                "screenshot-frame",
+                json!({
+                    "sequence": 7,
+                    "capture_id": "cap-7",
+                    "base64": "iVBORw0KGgo=",
+                    "format": "jpeg",
+                    "unchanged": false,
+                    "dropped": 2,
+                }),
+            ),
+            (
+                GraviaEvent::CaptureTiming(CaptureTiming {
+                    capture_id: "cap-8".into(),
+                    monitor: "DISPLAY1".into(),
+                    timings: CaptureTimings {
+                        hide_ms: 1,
+                        settle_ms: 2,
+                        grab_ms: 3,
+                        convert_ms: 4,
+                        process_ms: 5,
+                        encode_ms: 6,
+                        base64_ms: 7,
+                        total_ms: 28,
+                    },
+                }),
+                "capture-timing",
+                // The timings are flattened into the event.
+                json!({
+                    "capture_id": "cap-8",
+                    "monitor": "DISPLAY1",
+                    "hide_ms": 1,
+                    "settle_ms": 2,
+                    "grab_ms": 3,
+                    "convert_ms": 4,
+                    "process_ms": 5,
+                    "encode_ms": 6,
+                    "base64_ms": 7,
+                    "total_ms": 28,
+                }),
+            ),
+            (
+                GraviaEvent::CaptureThrottled(CaptureThrottled { retry_after_ms: 250, reused: true }),
+                "capture-throttled",
+                json!({ "retry_after_ms": 250, "reused": true }),
+            ),
+            (
+                GraviaEvent::ScrollToMessage(AutoCaptureNotice {
+                    session_id: Some("s-1".into()),
+                    message_id: None,
+                    captured_at: at,
+                    monitor: "DISPLAY2".into(),
+                }),
+                "scroll-to-message",
+                json!({ "session_id": "s-1", "message_id": null, "captured_at": AT, "monitor": "DISPLAY2" }),
+            ),
+            (
+                GraviaEvent::FilesDropped(vec![Attachment::File(FileAttachment {
+                    id: "att-1".into(),
+                    name: "todo.md".into(),
+                    path: PathBuf::from("/home/me/todo.md"),
+                    mime_type: "text/markdown".into(),
+                    size: 12,
+                    by_reference: false,
+                    preview: AttachmentPreview::Text { text: "- ship it".into(), truncated: false },
+                })]),
+                "files-dropped",
+                json!([{
+                    "type": "file",
+                    "id": "att-1",
+                    "name": "todo.md",
+                    "path": "/home/me/todo.md",
+                    "mime_type": "text/markdown",
+                    "size": 12,
+                    "by_reference": false,
+                    "preview": { "kind": "text", "text": "- ship it", "truncated": false },
+                }]),
+            ),
+            (
+                GraviaEvent::ClipboardPermissionRequested(ClipboardPermissionRequested),
+                "clipboard-permission-requested",
+                Value::Null,
+            ),
+            (
+                GraviaEvent::QuickAskPrefill(QuickAskPrefill { prefill: Some("explain".into()), capture_id: None }),
+                "quick-ask-prefill",
+                json!({ "prefill": "explain", "capture_id": null }),
+            ),
+            (
+                GraviaEvent::QuickAskSubmitted(QuickAskSubmission {
+                    query: "explain this".into(),
+                    capture_id: Some("cap-9".into()),
+                }),
+                "quick-ask-submitted",
+                json!({ "query": "explain this", "capture_id": "cap-9" }),
+            ),
+            (
+                GraviaEvent::ActionExecuted(ActionExecuted {
+                    action: OpenAction::OpenUrl { url: "https://example.com".into() },
+                    target: "https://example.com/".into(),
+                }),
+                "action-executed",
+                json!({
+                    "action": { "kind": "open_url", "url": "https://example.com" },
+                    "target": "https://example.com/",
+                }),
+            ),
+            (
+                GraviaEvent::MicLevel(MicLevel { rms: 0.25, peak: 0.5, level: 0.75, clipping: false }),
+                "mic-level",
+                json!({ "rms": 0.25, "peak": 0.5, "level": 0.75, "clipping": false }),
+            ),
+            (
+                GraviaEvent::MicSilence(MicSilence { silent_ms: 1500 }),
+                "mic-silence",
+                json!({ "silent_ms": 1500 }),
+            ),
+            (
+                GraviaEvent::AudioRecordingLimitReached(RecordingLimitReached { max_secs: 120 }),
+                "audio-recording-limit-reached",
+                json!({ "max_secs": 120 }),
+            ),
+            (
+                GraviaEvent::SpeakingState(SpeakingState::Finished { id: 3, interrupted: true }),
+                "speaking-state",
+                json!({ "state": "finished", "id": 3, "interrupted": true }),
+            ),
+            (
+                GraviaEvent::UpdateDownloadProgress(DownloadProgress { downloaded: 1024, total: None }),
+                "update-download-progress",
+                json!({ "downloaded": 1024, "total": null }),
+            ),
+            (
+                GraviaEvent::AppLog(LogEntry {
+                    timestamp: at,
+                    level: "WARN".into(),
+                    target: "gravia::capture".into(),
+                    message: "slow grab".into(),
+                    fields: BTreeMap::from([("session_id".to_string(), "s-1".to_string())]),
+                }),
+                "app-log",
+                json!({
+                    "timestamp": AT,
+                    "level": "WARN",
+                    "target": "gravia::capture",
+                    "message": "slow grab",
+                    "fields": { "session_id": "s-1" },
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn every_event_has_its_name_and_wire_payload() {
+        for (event, name, payload) in every_event() {
+            assert_eq!(event.name(), name);
+            assert_eq!(serde_json::to_value(&event).unwrap(), payload, "{name}");
+        }
+    }
+
+    #[test]
+    fn the_table_covers_every_event_once() {
+        let events = every_event();
+        let names: HashSet<_> = events.iter().map(|(event, _, _)| event.name()).collect();
+        assert_eq!(names.len(), events.len(), "an event is listed twice");
+        // Bump along with a new `GraviaEvent` variant and its entry above.
+        assert_eq!(names.len(), 33);
+    }
+}
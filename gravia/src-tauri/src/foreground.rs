@@ -0,0 +1,391 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+use ts_rs::TS;
+
+use crate::error::CommandResult;
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct ForegroundApp {
+    pub title: String,
+    pub process_name: String,
+    pub executable_path: String,
+    pub pid: u32,
+    pub is_gravia: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ForegroundError {
+    #[error("foreground app detection is not supported on this platform")]
+    Unsupported,
+    #[error("no window is currently in the foreground")]
+    NoForegroundWindow,
+    #[error("the foreground window is minimized")]
+    Minimized,
+    #[error("foreground watch is already running")]
+    AlreadyWatching,
+    #[error("{0}")]
+    Os(String),
+}
+
+impl ForegroundError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ForegroundError::Unsupported => "unsupported",
+            ForegroundError::NoForegroundWindow => "no_foreground_window",
+            ForegroundError::Minimized => "window_minimized",
+            ForegroundError::AlreadyWatching => "already_watching",
+            ForegroundError::Os(_) => "os",
+        }
+    }
+}
+
+// A window's on-screen bounds in physical pixels on the virtual desktop.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// The window the user is working in and its bounds. When that's Gravia
+// itself, which a capture is about to hide, it's the window below it.
+pub fn foreground_window() -> Result<(ForegroundApp, WindowRect), ForegroundError> {
+    platform::foreground_window()
+}
+
+// The topmost window, other than Gravia's own, under a point in physical
+// desktop pixels and its bounds. `None` over the desktop or the taskbar.
+pub fn window_at(x: i32, y: i32) -> Result<Option<(ForegroundApp, WindowRect)>, ForegroundError> {
+    platform::window_at(x, y)
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) use platform::{app_for_window, window_bounds};
+
+// Thread id of the WinEvent hook's message loop while a watch is active.
+pub struct ForegroundWatcher(Mutex<Option<u32>>);
+
+impl ForegroundWatcher {
+    pub fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+}
+
+#[tauri::command]
+pub fn get_foreground_app() -> CommandResult<ForegroundApp> {
+    Ok(platform::foreground_app()?)
+}
+
+// Emits `foreground-app-changed` whenever the active app settles on a new
+// window, at most once per `debounce_ms` (default 250ms).
+#[tauri::command]
+pub fn start_foreground_app_watch(
+    app: AppHandle,
+    state: State<'_, ForegroundWatcher>,
+    debounce_ms: Option<u64>,
+) -> CommandResult<()> {
+    let mut watch = state.0.lock()?;
+    if watch.is_some() {
+        return Err(ForegroundError::AlreadyWatching.into());
+    }
+    let debounce = std::time::Duration::from_millis(debounce_ms.unwrap_or(250));
+    *watch = Some(platform::start_watch(app, debounce)?);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_foreground_app_watch(state: State<'_, ForegroundWatcher>) -> CommandResult<()> {
+    let mut watch = state.0.lock()?;
+    if let Some(thread_id) = watch.take() {
+        platform::stop_watch(thread_id);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{ForegroundApp, ForegroundError, WindowRect};
+    use crate::events::{emit_event, GraviaEvent};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tauri::AppHandle;
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HWND, LPARAM, RECT, WPARAM};
+    use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS};
+    use windows::Win32::System::Threading::{
+        GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DispatchMessageW, GetForegroundWindow, GetMessageW, GetShellWindow, GetTopWindow, GetWindow, GetWindowRect,
+        GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindowVisible, PostThreadMessageW,
+        TranslateMessage, EVENT_SYSTEM_FOREGROUND, GW_HWNDNEXT, MSG, WINEVENT_OUTOFCONTEXT, WM_QUIT,
+    };
+
+    // Stops the z-order walk on a desktop with an absurd number of windows.
+    const MAX_WINDOWS_WALKED: usize = 512;
+
+    // The WinEvent callback has no user-data pointer, so it reaches the
+    // debouncer through this slot. The sender is tagged with the watch it
+    // belongs to: a stopped watch's hook thread can still be winding down
+    // after the next watch has filled the slot, and must leave that one be.
+    static HOOK_TX: Mutex<Option<(u64, Sender<()>)>> = Mutex::new(None);
+    static NEXT_WATCH: AtomicU64 = AtomicU64::new(1);
+
+    // Empties the slot if it still holds `watch`'s sender.
+    fn clear_hook_tx(watch: u64) {
+        if let Ok(mut guard) = HOOK_TX.lock() {
+            if guard.as_ref().is_some_and(|(owner, _)| *owner == watch) {
+                guard.take();
+            }
+        }
+    }
+
+    pub fn foreground_app() -> Result<ForegroundApp, ForegroundError> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd.0.is_null() {
+            return Err(ForegroundError::NoForegroundWindow);
+        }
+        app_for_window(hwnd)
+    }
+
+    pub fn foreground_window() -> Result<(ForegroundApp, WindowRect), ForegroundError> {
+        let mut hwnd = unsafe { GetForegroundWindow() };
+        if hwnd.0.is_null() {
+            return Err(ForegroundError::NoForegroundWindow);
+        }
+        let own_pid = std::process::id();
+        let mut walked = 0;
+        while !is_user_window(hwnd, own_pid) {
+            walked += 1;
+            hwnd = unsafe { GetWindow(hwnd, GW_HWNDNEXT) }.map_err(|_| ForegroundError::NoForegroundWindow)?;
+            if hwnd.0.is_null() || walked > MAX_WINDOWS_WALKED {
+                return Err(ForegroundError::NoForegroundWindow);
+            }
+        }
+        if unsafe { IsIconic(hwnd) }.as_bool() {
+            return Err(ForegroundError::Minimized);
+        }
+
+        Ok((app_for_window(hwnd)?, window_bounds(hwnd)?))
+    }
+
+    // Walks the z-order from the top rather than asking WindowFromPoint,
+    // which would stop at our own window.
+    pub fn window_at(x: i32, y: i32) -> Result<Option<(ForegroundApp, WindowRect)>, ForegroundError> {
+        let own_pid = std::process::id();
+        let desktop = unsafe { GetShellWindow() };
+        let mut hwnd = unsafe { GetTopWindow(HWND::default()) }.map_err(|e| ForegroundError::Os(e.to_string()))?;
+        for _ in 0..MAX_WINDOWS_WALKED {
+            if hwnd.0.is_null() || hwnd == desktop {
+                break;
+            }
+            if is_user_window(hwnd, own_pid) && !unsafe { IsIconic(hwnd) }.as_bool() {
+                let rect = window_bounds(hwnd)?;
+                let (x, y) = (x as i64, y as i64);
+                let (left, top) = (rect.x as i64, rect.y as i64);
+                if x >= left && x < left + rect.width as i64 && y >= top && y < top + rect.height as i64 {
+                    return Ok(Some((app_for_window(hwnd)?, rect)));
+                }
+            }
+            match unsafe { GetWindow(hwnd, GW_HWNDNEXT) } {
+                Ok(next) => hwnd = next,
+                Err(_) => break,
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn window_bounds(hwnd: HWND) -> Result<WindowRect, ForegroundError> {
+        // The extended frame bounds leave out the invisible resize borders
+        // that GetWindowRect includes.
+        let mut rect = RECT::default();
+        let extended = unsafe {
+            DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                &mut rect as *mut RECT as *mut _,
+                std::mem::size_of::<RECT>() as u32,
+            )
+        };
+        if extended.is_err() {
+            unsafe { GetWindowRect(hwnd, &mut rect) }.map_err(|e| ForegroundError::Os(e.to_string()))?;
+        }
+        Ok(WindowRect {
+            x: rect.left,
+            y: rect.top,
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+        })
+    }
+
+    // A visible, titled, uncloaked window that isn't ours. Cloaked windows
+    // are suspended UWP apps and windows on other virtual desktops.
+    fn is_user_window(hwnd: HWND, own_pid: u32) -> bool {
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+        if pid == own_pid || !unsafe { IsWindowVisible(hwnd) }.as_bool() || unsafe { GetWindowTextLengthW(hwnd) } == 0 {
+            return false;
+        }
+        let mut cloaked = 0u32;
+        let read = unsafe {
+            DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_CLOAKED,
+                &mut cloaked as *mut u32 as *mut _,
+                std::mem::size_of::<u32>() as u32,
+            )
+        };
+        read.is_err() || cloaked == 0
+    }
+
+    pub fn app_for_window(hwnd: HWND) -> Result<ForegroundApp, ForegroundError> {
+        let title = unsafe {
+            let len = GetWindowTextLengthW(hwnd);
+            let mut buf = vec![0u16; len.max(0) as usize + 1];
+            let copied = GetWindowTextW(hwnd, &mut buf);
+            String::from_utf16_lossy(&buf[..copied.max(0) as usize])
+        };
+
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+        if pid == 0 {
+            return Err(ForegroundError::Os("failed to resolve window process".into()));
+        }
+
+        let executable_path = unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)
+                .map_err(|e| ForegroundError::Os(e.to_string()))?;
+            let mut buf = vec![0u16; 1024];
+            let mut size = buf.len() as u32;
+            let result = QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(buf.as_mut_ptr()),
+                &mut size,
+            );
+            let _ = CloseHandle(handle);
+            result.map_err(|e| ForegroundError::Os(e.to_string()))?;
+            String::from_utf16_lossy(&buf[..size as usize])
+        };
+        let process_name = std::path::Path::new(&executable_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(ForegroundApp {
+            title,
+            process_name,
+            executable_path,
+            pid,
+            is_gravia: pid == std::process::id(),
+        })
+    }
+
+    unsafe extern "system" fn on_foreground_changed(
+        _hook: HWINEVENTHOOK,
+        _event: u32,
+        _hwnd: HWND,
+        _id_object: i32,
+        _id_child: i32,
+        _thread: u32,
+        _time: u32,
+    ) {
+        if let Ok(guard) = HOOK_TX.lock() {
+            if let Some((_, tx)) = guard.as_ref() {
+                let _ = tx.send(());
+            }
+        }
+    }
+
+    pub fn start_watch(app: AppHandle, debounce: Duration) -> Result<u32, ForegroundError> {
+        let (tx, rx) = mpsc::channel::<()>();
+        let watch = NEXT_WATCH.fetch_add(1, Ordering::Relaxed);
+        *HOOK_TX.lock().map_err(|e| ForegroundError::Os(e.to_string()))? = Some((watch, tx));
+
+        std::thread::spawn(move || {
+            let mut last: Option<ForegroundApp> = None;
+            while rx.recv().is_ok() {
+                // Swallow the burst of events a single alt-tab produces.
+                while rx.recv_timeout(debounce).is_ok() {}
+                let Ok(current) = foreground_app() else { continue };
+                if last.as_ref() != Some(&current) {
+                    emit_event(&app, GraviaEvent::ForegroundAppChanged(current.clone())).ok();
+                    last = Some(current);
+                }
+            }
+        });
+
+        let (id_tx, id_rx) = mpsc::channel::<Result<u32, ForegroundError>>();
+        std::thread::spawn(move || unsafe {
+            let hook = SetWinEventHook(
+                EVENT_SYSTEM_FOREGROUND,
+                EVENT_SYSTEM_FOREGROUND,
+                None,
+                Some(on_foreground_changed),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT,
+            );
+            if hook.0.is_null() {
+                let _ = id_tx.send(Err(ForegroundError::Os("SetWinEventHook failed".into())));
+                clear_hook_tx(watch);
+                return;
+            }
+            let _ = id_tx.send(Ok(GetCurrentThreadId()));
+
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+            let _ = UnhookWinEvent(hook);
+            // Dropping the sender ends the debounce thread.
+            clear_hook_tx(watch);
+        });
+
+        id_rx
+            .recv()
+            .map_err(|e| ForegroundError::Os(e.to_string()))?
+    }
+
+    pub fn stop_watch(thread_id: u32) {
+        if let Err(e) = unsafe { PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) } {
+            tracing::warn!(error = %e, "failed to stop foreground watch");
+        }
+    }
+}
+
+// Foreground tracking is Windows-only. Elsewhere every call fails with
+// `Unsupported` (`foreground.unsupported` on the wire), so callers and the
+// UI can tell it apart from there being no foreground window.
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use super::{ForegroundApp, ForegroundError, WindowRect};
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    pub fn foreground_app() -> Result<ForegroundApp, ForegroundError> {
+        Err(ForegroundError::Unsupported)
+    }
+
+    pub fn foreground_window() -> Result<(ForegroundApp, WindowRect), ForegroundError> {
+        Err(ForegroundError::Unsupported)
+    }
+
+    pub fn window_at(_x: i32, _y: i32) -> Result<Option<(ForegroundApp, WindowRect)>, ForegroundError> {
+        Err(ForegroundError::Unsupported)
+    }
+
+    pub fn start_watch(_app: AppHandle, _debounce: Duration) -> Result<u32, ForegroundError> {
+        Err(ForegroundError::Unsupported)
+    }
+
+    pub fn stop_watch(_thread_id: u32) {}
+}
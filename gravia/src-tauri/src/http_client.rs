@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::error::Error as _;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Url};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::secrets;
+use crate::settings::{ProxyMode, ProxySettings};
+
+const PROXY_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The one client every Rust-side HTTP call goes through, rebuilt whenever
+// the proxy settings change. `reqwest::Client` is a cheap handle to clone.
+pub struct HttpClient(Mutex<reqwest::Client>);
+
+impl HttpClient {
+    pub fn new(proxy: &ProxySettings) -> Self {
+        Self(Mutex::new(build_client(proxy)))
+    }
+
+    pub fn get(&self) -> reqwest::Client {
+        self.0.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+}
+
+// Manual proxy URL with the keyring password filled in, if one is set.
+pub fn proxy_url(proxy: &ProxySettings) -> Option<Url> {
+    if proxy.mode != ProxyMode::Manual {
+        return None;
+    }
+    let mut url = match Url::parse(proxy.url.as_deref()?) {
+        Ok(url) => url,
+        Err(e) => {
+            tracing::warn!(error = %e, "invalid proxy URL, ignoring it");
+            return None;
+        }
+    };
+    if let Some(username) = proxy.username.as_deref().filter(|u| !u.is_empty()) {
+        let password = secrets::read("proxy_password").unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "failed to read proxy password");
+            None
+        });
+        if url.set_username(username).is_err() || url.set_password(password.as_deref()).is_err() {
+            tracing::warn!("proxy URL cannot carry credentials");
+        }
+    }
+    Some(url)
+}
+
+fn build_client(proxy: &ProxySettings) -> reqwest::Client {
+    let builder = reqwest::Client::builder();
+    let builder = match proxy.mode {
+        // reqwest picks up the environment and OS proxy configuration itself.
+        ProxyMode::System => builder,
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::Manual => match proxy_url(proxy).map(|u| reqwest::Proxy::all(u.as_str())) {
+            Some(Ok(p)) => builder.proxy(p),
+            Some(Err(e)) => {
+                tracing::warn!(error = %e, "unusable proxy URL, connecting directly");
+                builder.no_proxy()
+            }
+            None => builder.no_proxy(),
+        },
+    };
+    builder.build().unwrap_or_else(|e| {
+        tracing::error!(error = %e, "failed to build HTTP client, using defaults");
+        reqwest::Client::new()
+    })
+}
+
+pub fn rebuild(app: &AppHandle, proxy: &ProxySettings) {
+    if let Ok(mut client) = app.state::<HttpClient>().0.lock() {
+        *client = build_client(proxy);
+    }
+    tracing::info!(mode = ?proxy.mode, "HTTP client rebuilt");
+}
+
+// Proxy variables for the sidecar so the Python server goes the same way.
+// In system mode it simply inherits ours.
+pub fn sidecar_env(proxy: &ProxySettings) -> Vec<(&'static str, String)> {
+    let value = match proxy.mode {
+        ProxyMode::System => return Vec::new(),
+        ProxyMode::None => return vec![("NO_PROXY", "*".to_string()), ("no_proxy", "*".to_string())],
+        ProxyMode::Manual => match proxy_url(proxy) {
+            Some(url) => url.to_string(),
+            None => return Vec::new(),
+        },
+    };
+    ["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY", "http_proxy", "https_proxy", "all_proxy"]
+        .into_iter()
+        .map(|var| (var, value.clone()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyFailure {
+    Dns,
+    Connect,
+    AuthRequired,
+    Timeout,
+    Other,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ProxyTestOutcome {
+    Reachable { status: u16 },
+    Failed { reason: ProxyFailure, message: String },
+}
+
+fn classify(e: &reqwest::Error) -> ProxyFailure {
+    // hyper only exposes these as messages somewhere down the source chain.
+    let mut source = e.source();
+    while let Some(err) = source {
+        let text = err.to_string().to_lowercase();
+        if text.contains("dns error") || text.contains("failed to lookup address") {
+            return ProxyFailure::Dns;
+        }
+        if text.contains("407") || text.contains("authentication required") {
+            return ProxyFailure::AuthRequired;
+        }
+        source = err.source();
+    }
+    if e.is_timeout() {
+        ProxyFailure::Timeout
+    } else if e.is_connect() {
+        ProxyFailure::Connect
+    } else {
+        ProxyFailure::Other
+    }
+}
+
+#[tauri::command]
+pub async fn test_proxy_connection(
+    state: State<'_, HttpClient>,
+    url: String,
+) -> CommandResult<ProxyTestOutcome> {
+    let url = Url::parse(&url).map_err(|e| GraviaError::InvalidArgument(format!("url: {e}")))?;
+    let client = state.get();
+    let outcome = match client.get(url).timeout(PROXY_TEST_TIMEOUT).send().await {
+        Ok(resp) if resp.status() == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED => {
+            ProxyTestOutcome::Failed {
+                reason: ProxyFailure::AuthRequired,
+                message: "the proxy requires authentication".to_string(),
+            }
+        }
+        Ok(resp) => ProxyTestOutcome::Reachable { status: resp.status().as_u16() },
+        Err(e) => ProxyTestOutcome::Failed { reason: classify(&e), message: e.to_string() },
+    };
+    tracing::info!(?outcome, "proxy connection test");
+    Ok(outcome)
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tauri::{AppHandle, State};
+
+use crate::classifier::ClassificationResult;
+use crate::error::{CommandResult, SettingsError};
+use crate::settings::{self, SharedSettings};
+
+const FALLBACK_LOCALE: &str = "en";
+
+// Catalogs are compiled in; add a language by dropping a JSON file in
+// `locales/` and listing it here.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("hi", include_str!("../locales/hi.json")),
+];
+
+type Catalog = HashMap<String, String>;
+
+fn catalogs() -> &'static HashMap<&'static str, Catalog> {
+    static PARSED: OnceLock<HashMap<&'static str, Catalog>> = OnceLock::new();
+    PARSED.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .filter_map(|(locale, raw)| match serde_json::from_str(raw) {
+                Ok(catalog) => Some((*locale, catalog)),
+                Err(e) => {
+                    tracing::error!(locale, error = %e, "invalid message catalog");
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+// `hi-IN` falls back to `hi`, then to English.
+fn resolve(locale: &str) -> Option<&'static Catalog> {
+    let catalogs = catalogs();
+    catalogs
+        .get(locale)
+        .or_else(|| locale.split(['-', '_']).next().and_then(|lang| catalogs.get(lang)))
+}
+
+pub fn is_supported(locale: &str) -> bool {
+    resolve(locale).is_some()
+}
+
+fn lookup(locale: &str, key: &str) -> Option<&'static String> {
+    resolve(locale)
+        .and_then(|c| c.get(key))
+        .or_else(|| catalogs().get(FALLBACK_LOCALE).and_then(|c| c.get(key)))
+}
+
+// Looks up `key` and fills `{name}` placeholders from `args`. Missing keys
+// fall back to English, then to the key itself.
+pub fn translate(locale: &str, key: &str, args: &HashMap<String, String>) -> String {
+    let Some(message) = lookup(locale, key) else {
+        tracing::warn!(locale, key, "missing message");
+        return key.to_string();
+    };
+    args.iter().fold(message.clone(), |text, (name, value)| {
+        text.replace(&format!("{{{name}}}"), value)
+    })
+}
+
+// Fills the display strings of a result from its reason and summary codes.
+pub fn localize_classification(result: &mut ClassificationResult, locale: &str) {
+    result.reasoning = result
+        .reasons
+        .iter()
+        .map(|reason| {
+            let args = reason
+                .args
+                .iter()
+                .map(|(name, value)| {
+                    // Argument values that are codes themselves get translated too.
+                    let display = lookup(locale, &format!("{name}.{value}")).unwrap_or(value);
+                    (name.clone(), display.clone())
+                })
+                .collect();
+            translate(locale, &format!("reason.{}", reason.code), &args)
+        })
+        .collect();
+    result.summary = translate(locale, &format!("summary.{}", result.summary_code), &HashMap::new());
+}
+
+#[tauri::command]
+pub fn set_locale(
+    app: AppHandle,
+    state: State<'_, SharedSettings>,
+    locale: String,
+) -> CommandResult<()> {
+    if !is_supported(&locale) {
+        return Err(SettingsError::InvalidValue {
+            key: "locale",
+            reason: format!("no messages for `{locale}`"),
+        }
+        .into());
+    }
+    let mut current = state.0.lock()?;
+    current.locale = locale;
+    settings::save(&app, &current).map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+    Ok(())
+}
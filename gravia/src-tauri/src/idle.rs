@@ -0,0 +1,138 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::power::{self, Throttle};
+use crate::settings::SharedSettings;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// What `get_idle_status` reports, including any power throttling of the poll.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct IdleStatus {
+    pub supported: bool,
+    pub idle: bool,
+    #[ts(type = "number")]
+    pub idle_seconds: u64,
+    #[ts(type = "number")]
+    pub poll_interval_ms: u64,
+    pub throttle: Throttle,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct IdleEvent {
+    #[ts(type = "number")]
+    pub idle_seconds: u64,
+    #[ts(type = "number")]
+    pub threshold_seconds: u64,
+}
+
+// Shared view of the user's input activity. Periodic work checks `is_idle`
+// on each tick and skips it while the user is away.
+#[derive(Default)]
+pub struct IdleMonitor {
+    idle: AtomicBool,
+    idle_seconds: AtomicU64,
+}
+
+impl IdleMonitor {
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Relaxed)
+    }
+}
+
+pub fn is_idle(app: &AppHandle) -> bool {
+    app.state::<Arc<IdleMonitor>>().is_idle()
+}
+
+pub fn start(app: &AppHandle, monitor: Arc<IdleMonitor>) {
+    if !platform::SUPPORTED {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(power::throttle(&app).scale(POLL_INTERVAL));
+        let Some(idle_seconds) = platform::seconds_since_last_input() else { continue };
+        monitor.idle_seconds.store(idle_seconds, Ordering::Relaxed);
+
+        let threshold_seconds = app
+            .state::<SharedSettings>()
+            .0
+            .lock()
+            .map(|s| s.idle_threshold_secs)
+            .unwrap_or(300);
+        let now_idle = idle_seconds >= threshold_seconds;
+        if now_idle != monitor.is_idle() {
+            monitor.idle.store(now_idle, Ordering::Relaxed);
+            let payload = IdleEvent { idle_seconds, threshold_seconds };
+            let event = if now_idle { GraviaEvent::UserIdle(payload) } else { GraviaEvent::UserActive(payload) };
+            emit_event(&app, event).ok();
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_idle_seconds() -> CommandResult<u64> {
+    match platform::seconds_since_last_input() {
+        Some(seconds) => Ok(seconds),
+        None if platform::SUPPORTED => Err(GraviaError::internal("failed to read the last input time")),
+        None => Err(GraviaError::Unsupported),
+    }
+}
+
+#[tauri::command]
+pub fn get_idle_status(app: AppHandle, state: State<'_, Arc<IdleMonitor>>) -> IdleStatus {
+    let throttle = power::throttle(&app);
+    IdleStatus {
+        supported: platform::SUPPORTED,
+        idle: state.is_idle(),
+        idle_seconds: state.idle_seconds.load(Ordering::Relaxed),
+        poll_interval_ms: throttle.scale(POLL_INTERVAL).as_millis() as u64,
+        throttle,
+    }
+}
+
+#[tauri::command]
+pub fn is_user_idle(state: State<'_, Arc<IdleMonitor>>) -> bool {
+    state.is_idle()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub const SUPPORTED: bool = true;
+
+    // None when the last input time can't be read.
+    pub fn seconds_since_last_input() -> Option<u64> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // A failed read says nothing about activity, so it mustn't read as
+        // "just active".
+        if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+            return None;
+        }
+        // Both are 32-bit tick counts, so wrapping_sub survives the 49-day rollover.
+        let elapsed_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        Some(u64::from(elapsed_ms) / 1000)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    pub const SUPPORTED: bool = false;
+
+    pub fn seconds_since_last_input() -> Option<u64> {
+        None
+    }
+}
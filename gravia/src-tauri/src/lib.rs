@@ -1,25 +1,86 @@
-#[tauri::command]
-fn capture_screenshot_base64(window: tauri::Window) -> Result<String, String> {
-    // Hide window to avoid capturing app UI
-    if let Err(e) = window.hide() { eprintln!("Failed to hide window before screenshot: {e}"); }
-    std::thread::sleep(std::time::Duration::from_millis(150));
-    let result = capture_primary_screen_base64();
-    if let Err(e) = window.show() { eprintln!("Failed to show window after screenshot: {e}"); }
-    if let Err(e) = window.set_focus() { eprintln!("Failed to refocus window: {e}"); }
-    result.map_err(|e| e.to_string())
-}
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod actions;
+mod annotate;
+mod attachments;
+mod audio;
+mod backend_socket;
+#[cfg(feature = "capture")]
+mod base64_stream;
+mod buffer_pool;
+mod capture;
+mod capture_history;
+mod capture_stream;
 mod classifier;
+mod classifier_config;
+mod clipboard;
+mod coalesce;
+mod command_stats;
+mod config_watch;
+mod connectivity;
+mod crash;
+#[cfg(feature = "capture")]
+mod cursor_overlay;
+mod deep_link;
+mod displays;
+#[cfg(all(target_os = "windows", feature = "capture"))]
+mod dxgi_capture;
+mod error;
+mod events;
+mod foreground;
+mod http_client;
+mod i18n;
+mod idle;
+mod lifecycle;
+mod logging;
+mod metrics;
+mod notifications;
+mod ocr;
+#[cfg(feature = "capture")]
+mod payload_guard;
+mod persist;
+#[cfg(feature = "capture")]
+mod pixel_format;
+mod power;
+mod print_screen;
+mod quick_ask;
+#[cfg(feature = "capture")]
+mod redaction;
+mod saved_screenshots;
+#[cfg(feature = "capture")]
+mod screen_grab;
+mod screen_analysis;
+#[cfg(feature = "capture")]
+mod screen_hash;
+mod screenshot_file;
+mod secrets;
+mod server;
+mod session_store;
+mod settings;
+mod speech;
+mod theme;
+mod tray;
+mod updater;
+mod window_focus;
+#[cfg(feature = "capture")]
+mod window_hider;
+mod window_list;
 
-use classifier::{ChatMessage, SessionManager, ClassificationResult};
+use capture::CapturePause;
+use classifier::{ChatMessage, Decision, SessionManager, ClassificationResult};
+use error::{CommandResult, GraviaError, SessionError};
+use events::{emit_event, GraviaEvent};
+use notifications::{AutoCaptureNotice, SharedNotifier};
+use settings::SharedSettings;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::{Mutex, Arc};
-use tauri::{State, Manager, Listener, Emitter};
-use tauri_plugin_shell::process::CommandEvent;
-use tauri_plugin_shell::ShellExt;
+use std::time::Duration;
+use tauri::{State, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use ts_rs::TS;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, TS)]
+#[ts(export)]
 pub struct FrontendChatMessage {
     pub role: String,
     pub content: String,
@@ -27,13 +88,102 @@ pub struct FrontendChatMessage {
     pub triggered_screenshot: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
 pub struct ClassifyResponse {
     pub classification: ClassificationResult,
-    pub screenshot_base64: Option<String>,
+    // Left out when `inline_screenshot` is false; `capture_id` still
+    // refers to the capture.
+    #[ts(type = "string | null")]
+    pub screenshot_base64: Option<Arc<String>>,
+    pub screenshot_format: Option<capture::CaptureFormat>,
+    // Encoded image size, before base64.
+    #[ts(type = "number | null")]
+    pub screenshot_bytes: Option<usize>,
+    // SHA-256 of the screenshot in the capture history, in hex, for
+    // spotting an image already sent. A shrunk `screenshot_base64` has its
+    // own in `payload_adjustment`.
+    pub content_hash: Option<String>,
+    pub screenshot_downscale: Option<capture::Downscale>,
+    // Small JPEG preview when `capture_options.thumbnail` is set; kept even
+    // when the screenshot itself is left out.
+    #[ts(type = "string | null")]
+    pub thumbnail_base64: Option<Arc<String>>,
+    pub capture_backend: Option<capture::CaptureBackend>,
+    // How Gravia's window was kept out of the auto-capture.
+    pub window_handling: Option<capture::WindowHandling>,
+    pub capture_id: Option<String>,
+    pub payload_adjustment: Option<capture::PayloadAdjustment>,
+    // Every display, when `capture_all` was asked for. The first one that
+    // succeeded is also in `screenshot_base64`.
+    pub screenshots: Option<Vec<capture::ScreenCapture>>,
+    // The screenshot is a recent capture reused within the cache TTL.
+    pub screenshot_from_cache: bool,
+    // The screen looks as it did for the previous capture. Its base64 is
+    // left out unless `capture_options.include_unchanged` is set, so the
+    // frontend can reuse the image it already has.
+    pub screenshot_unchanged: bool,
+    // Redaction rectangles, the `redact_regions` setting's included, that
+    // fell on the screenshot.
+    #[ts(type = "number")]
+    pub screenshot_redacted: usize,
+    pub screenshot_analysis: Option<screen_analysis::ScreenAnalysis>,
+    // The captured display's scale factor and sizes, for mapping overlay
+    // positions in CSS pixels onto the screenshot.
+    pub screenshot_scale: Option<capture::DisplayScale>,
+    // Set when the screenshot was shrunk or recompressed to fit
+    // `max_bytes`, with the scale and quality it ended up at.
+    pub screenshot_budget: Option<capture::ByteBudget>,
+    // Where the screenshot's time went; `None` when it came from the cache.
+    pub capture_timings: Option<capture::CaptureTimings>,
+    // Grabs tried for the screenshot, failed ones and retries included; 0
+    // when none was taken or it came from the cache. Not counted for
+    // `capture_all`.
+    pub capture_attempts: u32,
+    // The screenshot is nearly one solid color or nearly black, e.g. a
+    // locked or switched-off display, so there's little to describe.
+    pub screenshot_blank: bool,
+    // Text read off the screenshot, per `screen_text_mode`. With `instead`
+    // the image is left out whenever this has any text.
+    pub screen_text: Option<ocr::ScreenText>,
+    // True when this is another identical request's result.
+    pub coalesced: bool,
 }
 
-struct SharedSession(Mutex<SessionManager>);
+// Long edge auto-captures are scaled down to by default. Vision models
+// downscale anything larger themselves, so sending more is wasted upload.
+const AUTO_CAPTURE_MAX_EDGE: u32 = 1568;
+
+pub(crate) struct SharedSession(pub(crate) Mutex<SessionManager>);
+
+// Duplicate classify calls from a misfiring debounce share one run.
+#[derive(Default)]
+struct ClassifyCoalescer(coalesce::Coalescer<ClassifyResponse>);
+
+fn coalesce_key(session_id: Option<&str>, query: &str) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    format!("{}\n{query}", session_id.unwrap_or_default())
+}
+
+// `capture_options` with the defaults for a screenshot taken for a query:
+// unset `max_width`/`max_height` become `AUTO_CAPTURE_MAX_EDGE` and an unset
+// `max_bytes` the `auto_capture_max_bytes` setting.
+fn query_capture_options(
+    capture_options: Option<capture::CaptureOptions>,
+    default_budget: usize,
+    stamp_allowed: bool,
+) -> CommandResult<capture::CaptureOptions> {
+    let mut options = capture::CaptureOptions::resolve(capture_options)?;
+    options.max_width.get_or_insert(AUTO_CAPTURE_MAX_EDGE);
+    options.max_height.get_or_insert(AUTO_CAPTURE_MAX_EDGE);
+    // Encoding time is most of an auto-capture's latency.
+    options.png_compression.get_or_insert(capture::PngCompression::Fast);
+    if default_budget > 0 {
+        options.max_bytes.get_or_insert(default_budget);
+    }
+    options.stamp &= stamp_allowed;
+    Ok(options)
+}
 
 fn map_frontend_messages(msgs: Vec<FrontendChatMessage>) -> Vec<ChatMessage> {
     msgs.into_iter().map(|m| ChatMessage {
@@ -44,95 +194,394 @@ fn map_frontend_messages(msgs: Vec<FrontendChatMessage>) -> Vec<ChatMessage> {
     }).collect()
 }
 
+// Turns dropped paths into pending attachments off the UI thread, then tells
+// the frontend and the classifier about them.
+fn handle_file_drop(app: tauri::AppHandle, paths: Vec<std::path::PathBuf>) {
+    std::thread::spawn(move || {
+        let mut dropped = Vec::new();
+        for path in paths {
+            match attachments::ingest_file(&path) {
+                Ok(attachment) => {
+                    app.state::<attachments::AttachmentStore>().add_pending(attachment.clone());
+                    dropped.push(attachment);
+                }
+                Err(e) => tracing::warn!(path = %path.display(), error = %e, "failed to attach dropped file"),
+            }
+        }
+        if dropped.is_empty() {
+            return;
+        }
+        if let Ok(mut session) = app.state::<Arc<SharedSession>>().0.lock() {
+            session.note_file_context();
+        }
+        emit_event(&app, GraviaEvent::FilesDropped(dropped)).ok();
+    });
+}
+
 #[tauri::command]
-fn classify_and_maybe_capture(
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip_all, fields(session_id = ?session_id, message_id = ?message_id))]
+async fn classify_and_maybe_capture(
     state: State<'_, Arc<SharedSession>>,
+    app: tauri::AppHandle,
     window: tauri::Window,
     recent_messages: Vec<FrontendChatMessage>,
     query: String,
-) -> Result<ClassifyResponse, String> {
-    let mut session = state.0.lock().map_err(|e| e.to_string())?;
-    for msg in map_frontend_messages(recent_messages) {
-        session.add_message(msg);
-    }
-
-    let result = session.process_user_query(&query);
-
-    // If the classifier says we need a screenshot, capture here.
-    let mut screenshot_b64: Option<String> = None;
-    if result.needs_screenshot {
-        match capture_screenshot_base64(window.clone()) {
-            Ok(b64) => screenshot_b64 = Some(b64),
-            Err(e) => eprintln!("Auto screenshot capture failed: {e}"),
+    session_id: Option<String>,
+    message_id: Option<String>,
+    capture_operation_id: Option<String>,
+    // Display to auto-capture, from `list_screens`; the one under the
+    // cursor otherwise.
+    screen_id: Option<u32>,
+    // Capture every display instead of one.
+    capture_all: Option<bool>,
+    // Unset `max_width`/`max_height` default to `AUTO_CAPTURE_MAX_EDGE`,
+    // and an unset `max_bytes` to the `auto_capture_max_bytes` setting.
+    // `stamp` is dropped with the `stamp_auto_captures` setting off.
+    capture_options: Option<capture::CaptureOptions>,
+    // False leaves the base64 out of the response; fetch the capture with
+    // `get_capture_bytes` and `capture_id` instead.
+    inline_screenshot: Option<bool>,
+    // For queries about text on screen, also read the text off the
+    // screenshot into `screen_text`, or send it instead of the image.
+    screen_text_mode: Option<ocr::ScreenTextMode>,
+) -> CommandResult<ClassifyResponse> {
+    let (window_ms, default_budget, stamp_allowed) = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| (s.classify_coalesce_window_ms, s.auto_capture_max_bytes, s.stamp_auto_captures))
+        .unwrap_or((1000, 0, true));
+    let key = coalesce_key(session_id.as_deref(), &query);
+    let coalescer = app.state::<ClassifyCoalescer>();
+    let classify = async {
+        if query.trim().is_empty() {
+            return Err(GraviaError::from(SessionError::EmptyQuery));
         }
-    }
-
-    Ok(ClassifyResponse { classification: result, screenshot_base64: screenshot_b64 })
-}
+        let mut options = query_capture_options(capture_options, default_budget, stamp_allowed)?;
+        // Needed to flag a blank screenshot.
+        options.analyze = true;
+        // The session lock is released before the capture awaits below.
+        let mut result = {
+            let mut session = state.0.lock()?;
+            for msg in map_frontend_messages(recent_messages) {
+                session.add_message(msg);
+            }
+            session.process_user_query(&query)
+        };
+        session_store::mark_dirty(&app);
+        tracing::info!(
+            needs_screenshot = result.needs_screenshot,
+            decision = ?result.decision,
+            screenshot_score = result.screenshot_score,
+            no_screenshot_score = result.no_screenshot_score,
+            "query classified"
+        );
+        metrics::increment(&app, metrics::Counter::Classifications);
+        metrics::observe(&app, metrics::Histogram::ClassifierConfidence, f64::from(result.confidence));
+        if result.needs_screenshot {
+            metrics::increment(&app, metrics::Counter::ScreenshotsWanted);
+        }
+        // Paused, there's nothing to capture or ask about.
+        if result.decision != Decision::Skip && app.state::<CapturePause>().is_active() {
+            // An ask already queued is settled here, so it doesn't crowd out
+            // real ones or leave the query's `triggered_screenshot` unset.
+            if let Some(id) = result.query_id.take() {
+                let mut session = state.0.lock()?;
+                if let Some((sent_at, _)) = session.take_ask(&id) {
+                    session.record_capture(sent_at, false);
+                    session_store::mark_dirty(&app);
+                }
+            }
+            result.needs_screenshot = false;
+            result.decision = Decision::Skip;
+            result.suppressed_reason = Some("capture_paused".to_string());
+            result.reasons.push(classifier::Reason::new("capture_paused"));
+            result.summary_code = "suppressed".to_string();
+        }
+        let locale = app
+            .state::<SharedSettings>()
+            .0
+            .lock()
+            .map(|s| s.locale.clone())
+            .unwrap_or_default();
+        i18n::localize_classification(&mut result, &locale);
 
-fn capture_primary_screen_base64() -> anyhow::Result<String> {
-    use screenshots::Screen;
-    use image::{ImageBuffer, Rgba};
-    use base64::Engine;
-
-    let screens = Screen::all()?;
-    let screen = screens
-        .into_iter()
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("No screen found"))?;
-    let shot = screen.capture()?;
-    let width = shot.width();
-    let height = shot.height();
-    let raw = shot.into_raw(); // Raw pixel buffer from crate
-
-    // Platform-specific channel order handling.
-    // Windows: buffer is BGRA. Others: already RGBA.
-    #[cfg(target_os = "windows")]
-    let rgba: Vec<u8> = {
-        let mut out = Vec::with_capacity(raw.len());
-        for px in raw.chunks_exact(4) {
-            // BGRA -> RGBA swap: B=px[0], G=px[1], R=px[2], A=px[3]
-            out.push(px[0]); // R (from B)
-            out.push(px[1]); // G
-            out.push(px[2]); // B (from R)
-            out.push(px[3]); // A
-        }
-        out
-    };
+        // If the classifier says we need a screenshot, capture here. Builds
+        // without the `capture` feature still report `needs_screenshot`.
+        let mut screenshot_b64: Option<Arc<String>> = None;
+        let mut capture_id: Option<String> = None;
+        let mut content_hash = None;
+        let mut payload_adjustment = None;
+        let mut capture_backend = None;
+        let mut screenshots = None;
+        let mut screenshot_format = None;
+        let mut screenshot_downscale = None;
+        let mut thumbnail_base64 = None;
+        let mut window_handling = None;
+        let mut screenshot_from_cache = false;
+        let mut screenshot_unchanged = false;
+        let mut screenshot_redacted = 0;
+        let mut screenshot_analysis = None;
+        let mut screenshot_scale = None;
+        let mut screenshot_budget = None;
+        let mut capture_timings = None;
+        let mut capture_attempts = 0;
+        if result.needs_screenshot && cfg!(feature = "capture") && capture_all.unwrap_or(false) {
+            let operations = app.state::<capture::CaptureOperations>();
+            let operation = operations.begin(capture_operation_id);
+            match capture::capture_all_hiding_window(&window, &options, &operation.token).await {
+                Ok(captures) => {
+                    notifications::notify_auto_capture(&app, AutoCaptureNotice {
+                        session_id,
+                        message_id,
+                        captured_at: Utc::now(),
+                        monitor: format!("{} displays", captures.len()),
+                    });
+                    if let Some(first) = captures.iter().find(|c| c.base64.is_some()) {
+                        screenshot_b64 = first.base64.clone();
+                        capture_id = first.capture_id.clone();
+                        content_hash = first.content_hash.clone();
+                        screenshot_format = Some(first.format);
+                        screenshot_downscale = first.downscale;
+                        thumbnail_base64 = first.thumbnail_base64.clone();
+                        screenshot_unchanged = first.unchanged;
+                        screenshot_redacted = first.redacted;
+                        screenshot_analysis = first.analysis.clone();
+                        screenshot_scale = first.scale;
+                        screenshot_budget = first.budget;
+                        capture_timings = first.timings;
+                        window_handling = Some(first.window_handling);
+                        payload_adjustment = first.adjustment.clone();
+                        capture_backend = first.backend;
+                    }
+                    screenshots = Some(captures);
+                }
+                Err(e) => tracing::warn!(error = %e, "auto screenshot capture failed"),
+            }
+        } else if result.needs_screenshot && cfg!(feature = "capture") {
+            let operations = app.state::<capture::CaptureOperations>();
+            let operation = operations.begin(capture_operation_id);
+            let screen_id = match screen_id {
+                Some(id) => Some(id),
+                None => capture::active_screen_id(&app).await,
+            };
+            let target = capture::CaptureTarget::screen(screen_id);
+            let policy = capture::RateLimitPolicy::Reuse;
+            let (shot, attempts) =
+                capture::capture_with_attempts(&window, target, &options, policy, &operation.token).await;
+            capture_attempts = attempts;
+            match shot {
+                Ok(shot) => {
+                    notifications::notify_auto_capture(&app, AutoCaptureNotice {
+                        session_id,
+                        message_id,
+                        captured_at: Utc::now(),
+                        monitor: shot.monitor,
+                    });
+                    screenshot_b64 = Some(shot.base64);
+                    capture_id = Some(shot.id);
+                    content_hash = Some(shot.content_hash);
+                    screenshot_format = Some(shot.format);
+                    screenshot_downscale = shot.downscale;
+                    thumbnail_base64 = shot.thumbnail;
+                    window_handling = Some(shot.window_handling);
+                    payload_adjustment = shot.adjustment;
+                    capture_backend = Some(shot.backend);
+                    screenshot_from_cache = shot.from_cache;
+                    screenshot_unchanged = shot.unchanged;
+                    screenshot_redacted = shot.redacted;
+                    screenshot_analysis = shot.analysis;
+                    screenshot_scale = shot.scale;
+                    screenshot_budget = shot.budget;
+                    capture_timings = shot.timings;
+                }
+                Err(e) => tracing::warn!(error = %e, attempts, "auto screenshot capture failed"),
+            }
+        }
 
-    #[cfg(not(target_os = "windows"))]
-    let rgba: Vec<u8> = raw;
+        // A no-op unless `save_screenshots` is on.
+        let saved = capture_id.iter().chain(screenshots.iter().flatten().filter_map(|c| c.capture_id.as_ref()));
+        for id in saved {
+            saved_screenshots::set_query(&app, id, &query);
+        }
 
-    let img: ImageBuffer<Rgba<u8>, _> =
-        ImageBuffer::from_vec(width, height, rgba)
-            .ok_or_else(|| anyhow::anyhow!("Failed to create image buffer"))?;
+        let screenshot_bytes = screenshot_b64.as_ref().map(|b| capture::decoded_len(b));
+        if !inline_screenshot.unwrap_or(true) {
+            screenshot_b64 = None;
+            for capture in screenshots.iter_mut().flatten() {
+                capture.base64 = None;
+            }
+        }
+        if !options.include_unchanged {
+            if screenshot_unchanged {
+                screenshot_b64 = None;
+            }
+            for capture in screenshots.iter_mut().flatten().filter(|c| c.unchanged) {
+                capture.base64 = None;
+            }
+        }
 
-    let mut png_bytes: Vec<u8> = Vec::new();
-    {
-        let dynimg = image::DynamicImage::ImageRgba8(img);
-        dynimg.write_to(
-            &mut std::io::Cursor::new(&mut png_bytes),
-            image::ImageFormat::Png,
-        )?;
-    }
+        let text_mode = screen_text_mode.unwrap_or_default();
+        let wants_text = text_mode != ocr::ScreenTextMode::Off && result.text_oriented;
+        let mut screen_text = None;
+        if let Some(id) = capture_id.as_deref().filter(|_| wants_text) {
+            match ocr::read_capture(&app, id).await {
+                Ok(text) => {
+                    if text_mode == ocr::ScreenTextMode::Instead && !text.text.is_empty() {
+                        screenshot_b64 = None;
+                        for capture in screenshots.iter_mut().flatten() {
+                            capture.base64 = None;
+                        }
+                    }
+                    screen_text = Some(text);
+                }
+                Err(e) => tracing::warn!(error = %e, "reading text off the screenshot failed"),
+            }
+        }
 
-    Ok(base64::engine::general_purpose::STANDARD.encode(png_bytes))
+        Ok::<_, GraviaError>(ClassifyResponse {
+            classification: result,
+            screenshot_bytes,
+            content_hash,
+            screenshot_base64: screenshot_b64,
+            screenshot_format,
+            screenshot_downscale,
+            thumbnail_base64,
+            capture_id,
+            capture_backend,
+            window_handling,
+            payload_adjustment,
+            screenshots,
+            screenshot_from_cache,
+            screenshot_unchanged,
+            screenshot_redacted,
+            screenshot_blank: screenshot_analysis.as_ref().is_some_and(|a| a.is_mostly_blank),
+            screenshot_analysis,
+            screenshot_scale,
+            screenshot_budget,
+            capture_timings,
+            capture_attempts,
+            screen_text,
+            coalesced: false,
+        })
+    };
+    command_stats::track(&app, "classify_and_maybe_capture", async {
+        let (mut response, coalesced) =
+            coalescer.0.run(key, Duration::from_millis(window_ms), || classify).await?;
+        if coalesced {
+            tracing::info!("duplicate classify request coalesced");
+            response.coalesced = true;
+        }
+        Ok(response)
+    })
+    .await
 }
 
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ConfirmedCapture {
+    pub capture_id: String,
+    #[ts(type = "string")]
+    pub screenshot_base64: Arc<String>,
+    pub screenshot_format: capture::CaptureFormat,
+    pub content_hash: String,
+    pub monitor: String,
+}
 
-
+// Answers an `ask_user` classification. An approved one is captured with
+// the same defaults as an auto-capture, and whether a screenshot was
+// actually taken is recorded in the session history as the query's
+// `triggered_screenshot`. `None` when declined, or in builds without the
+// `capture` feature.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn confirm_capture(
+    state: State<'_, Arc<SharedSession>>,
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    session_query_id: String,
+    approved: bool,
+    capture_operation_id: Option<String>,
+    screen_id: Option<u32>,
+    capture_options: Option<capture::CaptureOptions>,
+) -> CommandResult<Option<ConfirmedCapture>> {
+    command_stats::track(&app, "confirm_capture", async {
+        let (default_budget, stamp_allowed) = app
+            .state::<SharedSettings>()
+            .0
+            .lock()
+            .map(|s| (s.auto_capture_max_bytes, s.stamp_auto_captures))
+            .unwrap_or((0, true));
+        // Checked first, so bad options don't use up the answer.
+        let options = query_capture_options(capture_options, default_budget, stamp_allowed)?;
+        let ask = state.0.lock()?.take_ask(&session_query_id);
+        let Some((sent_at, query)) = ask else {
+            return Err(GraviaError::NotFound(format!("pending query {session_query_id}")));
+        };
+        tracing::info!(approved, "capture confirmation answered");
+        if !approved || !cfg!(feature = "capture") {
+            state.0.lock()?.record_capture(sent_at, false);
+            session_store::mark_dirty(&app);
+            return Ok(None);
+        }
+        let operations = app.state::<capture::CaptureOperations>();
+        let operation = operations.begin(capture_operation_id);
+        let screen_id = match screen_id {
+            Some(id) => Some(id),
+            None => capture::active_screen_id(&app).await,
+        };
+        let target = capture::CaptureTarget::screen(screen_id);
+        let policy = capture::RateLimitPolicy::Reuse;
+        let shot = capture::capture_hiding_window(&window, target, &options, policy, &operation.token).await;
+        state.0.lock()?.record_capture(sent_at, shot.is_ok());
+        session_store::mark_dirty(&app);
+        let shot = shot?;
+        saved_screenshots::set_query(&app, &shot.id, &query);
+        Ok(Some(ConfirmedCapture {
+            capture_id: shot.id,
+            screenshot_base64: shot.base64,
+            screenshot_format: shot.format,
+            content_hash: shot.content_hash,
+            monitor: shot.monitor,
+        }))
+    })
+    .await
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    crash::install_panic_hook();
     let session = Arc::new(SharedSession(Mutex::new(SessionManager::new(200))));
     tauri::Builder::default()
     .manage(session)
-    .plugin(tauri_plugin_single_instance::init(|_app, _args, _cwd| {}))
+    .manage(ClassifyCoalescer::default())
+    .manage(classifier_config::CurrentClassifierConfig::default())
+    .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+        tray::show_main_window(app);
+        deep_link::handle_urls(app, args, false);
+    }))
+    .plugin(tauri_plugin_deep_link::init())
     .on_window_event(|window, event| match event {
         tauri::WindowEvent::CloseRequested { api, .. } => {
-            window.hide().unwrap();
-            api.prevent_close();
+            audio::cancel_for_window(window.app_handle(), window.label());
+            capture_stream::stop_for_window(window.app_handle(), window.label());
+            if !lifecycle::handle_close_requested(window) {
+                api.prevent_close();
+            }
+        }
+        tauri::WindowEvent::Destroyed => {
+            audio::cancel_for_window(window.app_handle(), window.label());
+            capture_stream::stop_for_window(window.app_handle(), window.label());
+        }
+        tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+            handle_file_drop(window.app_handle().clone(), paths.clone());
+        }
+        tauri::WindowEvent::Focused(focused) => {
+            quick_ask::handle_focus_changed(window, *focused);
+            window_focus::handle_focus_changed(window, *focused);
         }
+        tauri::WindowEvent::ThemeChanged(_) => theme::refresh(window.app_handle()),
         _ => {}
     })
     .plugin(tauri_plugin_process::init())
@@ -145,69 +594,261 @@ pub fn run() {
     .plugin(tauri_plugin_websocket::init())
     .plugin(tauri_plugin_autostart::Builder::new().build())
     .plugin(tauri_plugin_opener::init())
-    .invoke_handler(tauri::generate_handler![classify_and_maybe_capture, capture_screenshot_base64])
+    .plugin(tauri_plugin_notification::init())
+    .plugin(tauri_plugin_updater::Builder::new().build())
+    .manage(SharedNotifier::new())
+    .manage(foreground::ForegroundWatcher::new())
+    .manage(Arc::new(idle::IdleMonitor::default()))
+    .manage(theme::ThemeState::new())
+    .manage(deep_link::PendingDeepLinks::default())
+    .manage(lifecycle::AppLifecycle::default())
+    .manage(lifecycle::Sidecar::default())
+    .manage(server::ServerSupervisor::default())
+    .manage(CapturePause::default())
+    .manage(capture::CaptureRateLimiter::default())
+    .manage(capture::CapturePerf::default())
+    .manage(capture::CaptureOperations::default())
+    .manage(quick_ask::PendingQuickAsk::default())
+    .manage(attachments::AttachmentStore::default())
+    .manage(capture_history::CaptureHistory::default())
+    .manage(saved_screenshots::SavedScreenshots::default())
+    .manage(displays::DisplayWatch::default())
+    .manage(capture_stream::CaptureStream::default())
+    .manage(updater::UpdaterState::default())
+    .manage(audio::AudioRecorder::default())
+    .manage(window_focus::WindowFocus::default())
+    .manage(power::PowerMonitor::default())
+    .manage(connectivity::ConnectivityMonitor::default())
+    .manage(metrics::UsageMetrics::default())
+    .manage(command_stats::CommandStats::default())
+    .manage(persist::Recoveries::default())
+    .manage(session_store::SessionStore::default())
+    .invoke_handler(tauri::generate_handler![
+        classify_and_maybe_capture,
+        confirm_capture,
+        session_store::get_session_stats,
+        session_store::flush_sessions_now,
+        classifier_config::get_classifier_config,
+        classifier_config::set_classifier_config,
+        classifier_config::reload_classifier_config,
+        capture::capture_screenshot_base64,
+        capture::capture_screenshot_raw,
+        capture::capture_screen_base64,
+        capture::capture_active_monitor_base64,
+        capture::capture_all_screens_base64,
+        capture::capture_burst_base64,
+        capture::capture_virtual_desktop_base64,
+        capture::capture_region_base64,
+        capture::capture_foreground_window_base64,
+        capture::capture_window_under_cursor_base64,
+        capture::capture_window_base64,
+        capture::cancel_capture,
+        capture::list_screens,
+        capture::set_capture_paused,
+        capture::get_capture_paused,
+        capture::get_capture_blocklist,
+        capture::set_capture_blocklist,
+        capture::get_capture_perf_stats,
+        capture::get_capture_diagnostics,
+        settings::get_settings,
+        settings::update_settings,
+        notifications::set_capture_notifications_muted,
+        notifications::open_auto_capture_notice,
+        foreground::get_foreground_app,
+        foreground::start_foreground_app_watch,
+        foreground::stop_foreground_app_watch,
+        http_client::test_proxy_connection,
+        clipboard::read_clipboard_text,
+        clipboard::copy_screenshot_to_clipboard,
+        idle::get_idle_seconds,
+        idle::is_user_idle,
+        idle::get_idle_status,
+        power::get_power_state,
+        connectivity::get_connectivity,
+        actions::execute_open_action,
+        print_screen::get_print_screen_binding,
+        print_screen::set_print_screen_binding,
+        theme::get_system_theme,
+        deep_link::take_pending_deep_links,
+        i18n::set_locale,
+        lifecycle::quit_app,
+        lifecycle::restart_app,
+        lifecycle::resolve_close,
+        server::ensure_server_running,
+        server::get_server_status,
+        quick_ask::open_quick_ask,
+        quick_ask::close_quick_ask,
+        quick_ask::submit_quick_ask,
+        quick_ask::take_quick_ask_prefill,
+        attachments::take_pending_attachments,
+        attachments::remove_attachment,
+        audio::list_audio_devices,
+        audio::start_audio_recording,
+        audio::stop_audio_recording,
+        backend_socket::send_backend_message,
+        backend_socket::get_backend_connection_state,
+        capture_history::open_screenshot_externally,
+        capture_history::get_capture_info,
+        capture_history::get_capture_bytes,
+        capture_history::list_screenshots,
+        capture_history::get_screenshot,
+        capture_history::clear_screenshots,
+        saved_screenshots::list_saved_screenshots,
+        saved_screenshots::delete_saved_screenshot,
+        annotate::annotate_screenshot,
+        ocr::extract_screen_text,
+        capture_stream::start_capture_stream,
+        capture_stream::stop_capture_stream,
+        capture_stream::ack_screenshot_frame,
+        capture_stream::get_capture_stream_status,
+        screenshot_file::save_screenshot,
+        crash::previous_session_crashed,
+        crash::get_last_crash_report,
+        crash::list_crash_reports,
+        logging::get_recent_logs,
+        logging::get_recent_app_logs,
+        logging::set_log_level,
+        metrics::get_usage_metrics,
+        metrics::export_usage_metrics,
+        metrics::get_metrics_status,
+        command_stats::get_command_stats,
+        command_stats::reset_command_stats,
+        persist::take_persistence_warnings,
+        speech::speak_text,
+        speech::stop_speaking,
+        speech::list_voices,
+        secrets::set_secret,
+        secrets::get_secret_exists,
+        secrets::delete_secret,
+        updater::check_for_updates,
+        updater::install_update,
+        window_focus::set_compact_mode,
+    ])
          .setup(|app| {
-            let window = app.get_webview_window("main").unwrap();
-            let shell = app.shell();
-            let window_for_spawn = window.clone();
-
-            // Check if server.exe is already running
-            #[cfg(target_os = "windows")]
-            let already_running = std::process::Command::new("tasklist")
-                .args(["/FI", "IMAGENAME eq server.exe"])
-                .output()
-                .map(|o| String::from_utf8_lossy(&o.stdout).contains("server.exe"))
-                .unwrap_or(false);
-
-            if already_running {
-                println!("server.exe already running, skipping sidecar startup");
-                window_for_spawn.emit("server-ready", true).ok();
-                return Ok(());
+            crash::init(app.handle());
+            // Logging takes its level from the settings, so anything wrong
+            // with them is logged once it's up.
+            let (loaded, load_warning) = settings::load(app.handle());
+            logging::init(app.handle(), &loaded.log_level);
+            if let Some(warning) = load_warning {
+                warning.log();
             }
+            logging::set_streaming(loaded.debug_log_stream);
+            app.manage(http_client::HttpClient::new(&loaded.proxy));
+            app.manage(SharedSettings(Mutex::new(loaded)));
+            #[cfg(feature = "capture")]
+            app.manage(screen_hash::ScreenHashes::default());
+            config_watch::start(app.handle());
+            classifier_config::start(app.handle());
+            metrics::start(app.handle());
+            session_store::start(app.handle());
+            power::start(app.handle());
+            idle::start(app.handle(), app.state::<Arc<idle::IdleMonitor>>().inner().clone());
+            let system_theme = theme::start(app.handle());
+            tray::build(app.handle(), system_theme.taskbar_mode == theme::ThemeMode::Light)?;
+            connectivity::start(app.handle());
+            displays::start(app.handle());
+            saved_screenshots::start(app.handle());
+            print_screen::start(app.handle());
+            backend_socket::start(app.handle());
+            server::start(app.handle())?;
+            speech::start(app.handle());
 
-            // Spawn sidecar
-            let sidecar = shell.sidecar("server").unwrap();
-            let (mut rx, child) = sidecar.spawn().expect("Failed to spawn server.exe");
-            let child = Arc::new(Mutex::new(Some(child)));
-
-
-            tauri::async_runtime::spawn(async move {
-                let mut server_started = false;
-
-                while let Some(event) = rx.recv().await {
-                    match event {
-                        CommandEvent::Stdout(line_bytes) => {
-                            let line = String::from_utf8_lossy(&line_bytes);
-                            println!("server stdout: {}", line);
-
-                            if line.contains("Server started successfully") && !server_started {
-                                server_started = true;
-                                window_for_spawn.emit("server-ready", true).ok();
-                                println!("Server is ready!");
-                            }
-                        }
-                        CommandEvent::Stderr(err_bytes) => {
-                            eprintln!("server stderr: {}", String::from_utf8_lossy(&err_bytes));
-                        }
-                        CommandEvent::Terminated(code) => {
-                            println!("server.exe exited with code {:?}", code);
-                        }
-                        _ => {}
-                    }
-                }
-            });
-
-            // Kill server.exe on Tauri exit
-            let child_clone = Arc::clone(&child);
-            app.listen("app-close", move |_event| {
-                println!("Killing server.exe...");
-                if let Some(c) = child_clone.lock().unwrap().take() {
-                    let _ = c.kill();
-                }
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                tracing::warn!(error = %e, "failed to register gravia:// scheme");
+            }
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                deep_link::handle_urls(app.handle(), urls.iter().map(|u| u.as_str()), true);
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                deep_link::handle_urls(&handle, event.urls().iter().map(|u| u.as_str()), false);
             });
 
             Ok(())
         })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while running tauri application")
+    .run(|app, event| {
+        if let tauri::RunEvent::Exit = event {
+            session_store::flush_on_exit(app);
+            metrics::flush(app);
+            command_stats::log_summary(app);
+            crash::mark_clean_shutdown(app);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Part of `npm run bindings`. Exports what every command takes or returns
+    // and every event carries, each with the types it uses, so none of them
+    // relies on having `#[ts(export)]` itself. A type here that doesn't
+    // derive `TS` won't compile; list new command types as they're added.
+    #[test]
+    fn export_bindings_for_every_command_and_event() {
+        fn export<T: TS + 'static>() {
+            T::export_all().unwrap_or_else(|e| panic!("could not export {}: {e}", T::name()));
+        }
+        export::<GraviaEvent>();
+        export::<actions::OpenAction>();
+        export::<annotate::Annotation>();
+        export::<attachments::Attachment>();
+        export::<audio::AudioDevice>();
+        export::<audio::Recording>();
+        export::<backend_socket::ConnectionState>();
+        export::<backend_socket::WhenDisconnected>();
+        export::<capture::BurstCapture>();
+        export::<capture::CaptureDiagnostics>();
+        export::<capture::CaptureOptions>();
+        export::<capture::CapturePauseState>();
+        export::<capture::CapturePerfStats>();
+        export::<capture::CursorWindowCapture>();
+        export::<capture::ForegroundWindowCapture>();
+        export::<capture::RateLimitPolicy>();
+        export::<capture::RegionCapture>();
+        export::<capture::ScreenCapture>();
+        export::<capture::ScreenInfo>();
+        export::<capture::WindowCapture>();
+        export::<capture_history::CaptureInfo>();
+        export::<capture_history::Screenshot>();
+        export::<capture_stream::CaptureStreamStatus>();
+        export::<classifier_config::ClassifierConfig>();
+        export::<classifier_config::ClassifierConfigState>();
+        export::<clipboard::ClipboardText>();
+        export::<clipboard::CopiedScreenshot>();
+        export::<command_stats::CommandStat>();
+        export::<connectivity::ConnectivityStatus>();
+        export::<crash::CrashReport>();
+        export::<crash::CrashReportSummary>();
+        export::<deep_link::DeepLinkIntent>();
+        export::<foreground::ForegroundApp>();
+        export::<http_client::ProxyTestOutcome>();
+        export::<idle::IdleStatus>();
+        export::<lifecycle::CloseAction>();
+        export::<logging::LogEntry>();
+        export::<metrics::DailyMetrics>();
+        export::<metrics::MetricsStatus>();
+        export::<notifications::AutoCaptureNotice>();
+        export::<ocr::ScreenText>();
+        export::<ocr::ScreenTextMode>();
+        export::<persist::PersistenceRecovered>();
+        export::<power::PowerState>();
+        export::<print_screen::PrintScreenAction>();
+        export::<print_screen::PrintScreenStatus>();
+        export::<quick_ask::QuickAskPrefill>();
+        export::<saved_screenshots::SavedScreenshot>();
+        export::<server::ServerStatus>();
+        export::<session_store::SessionStats>();
+        export::<settings::Settings>();
+        export::<speech::VoiceInfo>();
+        export::<theme::SystemTheme>();
+        export::<updater::UpdateInfo>();
+        export::<ClassifyResponse>();
+        export::<ConfirmedCapture>();
+        export::<FrontendChatMessage>();
+    }
 }
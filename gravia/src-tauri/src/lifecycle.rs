@@ -0,0 +1,202 @@
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_shell::process::CommandChild;
+use ts_rs::TS;
+
+use crate::capture_history;
+use crate::error::{CommandResult, SettingsError};
+use crate::events::{emit_event, CloseRequested, GraviaEvent};
+use crate::session_store;
+use crate::settings::{self, CloseBehavior, SharedSettings};
+
+// Where the backend server listens.
+pub const SERVER_PORT: u16 = 5089;
+const SIDECAR_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const QUIT_WATCHDOG: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub struct AppLifecycle {
+    exiting: AtomicBool,
+}
+
+impl AppLifecycle {
+    pub fn is_exiting(&self) -> bool {
+        self.exiting.load(Ordering::SeqCst)
+    }
+}
+
+// The spawned backend process, if we own one, and whether it has exited.
+#[derive(Default)]
+pub struct Sidecar {
+    child: Mutex<Option<CommandChild>>,
+    terminated: Mutex<bool>,
+    terminated_cv: Condvar,
+    updating: AtomicBool,
+}
+
+// Held while the server binary is being replaced; app updates wait for it.
+pub struct SidecarUpdateGuard<'a>(&'a Sidecar);
+
+impl Drop for SidecarUpdateGuard<'_> {
+    fn drop(&mut self) {
+        self.0.updating.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Sidecar {
+    pub fn set_child(&self, child: CommandChild) {
+        if let Ok(mut slot) = self.child.lock() {
+            *slot = Some(child);
+        }
+        if let Ok(mut done) = self.terminated.lock() {
+            *done = false;
+        }
+    }
+
+    pub fn mark_terminated(&self) {
+        if let Ok(mut slot) = self.child.lock() {
+            slot.take();
+        }
+        if let Ok(mut done) = self.terminated.lock() {
+            *done = true;
+            self.terminated_cv.notify_all();
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn begin_update(&self) -> Option<SidecarUpdateGuard<'_>> {
+        (!self.updating.swap(true, Ordering::SeqCst)).then_some(SidecarUpdateGuard(self))
+    }
+
+    pub fn is_updating(&self) -> bool {
+        self.updating.load(Ordering::SeqCst)
+    }
+
+    // Kills the sidecar and waits up to `timeout` for it to report exit.
+    pub fn shutdown(&self, timeout: Duration) {
+        let child = self.child.lock().ok().and_then(|mut slot| slot.take());
+        let Some(child) = child else { return };
+        tracing::info!(target: "sidecar", "killing server.exe");
+        if let Err(e) = child.kill() {
+            tracing::error!(target: "sidecar", error = %e, "failed to kill server.exe");
+            return;
+        }
+        let Ok(done) = self.terminated.lock() else { return };
+        let result = self.terminated_cv.wait_timeout_while(done, timeout, |done| !*done);
+        if matches!(result, Ok((_, r)) if r.timed_out()) {
+            tracing::warn!(target: "sidecar", ?timeout, "server.exe did not exit in time");
+        }
+    }
+}
+
+// Runs cleanup off the calling thread and exits (or restarts) when done.
+// Calls made while a quit is already in progress are ignored.
+pub fn shutdown(app: &AppHandle, restart: bool) {
+    shutdown_then(app, restart, |_| {});
+}
+
+// Like `shutdown`, but runs `before_exit` once cleanup has finished, e.g. to
+// hand over to an installer. Returns false if a quit was already under way.
+pub fn shutdown_then<F>(app: &AppHandle, restart: bool, before_exit: F) -> bool
+where
+    F: FnOnce(&AppHandle) + Send + 'static,
+{
+    let lifecycle = app.state::<AppLifecycle>();
+    if lifecycle.exiting.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+
+    std::thread::spawn(|| {
+        std::thread::sleep(QUIT_WATCHDOG);
+        tracing::error!(timeout = ?QUIT_WATCHDOG, "quit cleanup timed out, forcing exit");
+        std::process::exit(1);
+    });
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Ok(current) = app.state::<SharedSettings>().0.lock() {
+            if let Err(e) = settings::save(&app, &current) {
+                tracing::error!(error = %e, "failed to flush settings on quit");
+            }
+        }
+        session_store::flush_on_exit(&app);
+        app.state::<Sidecar>().shutdown(SIDECAR_SHUTDOWN_TIMEOUT);
+        capture_history::cleanup_temp_files(&app);
+        before_exit(&app);
+        if restart {
+            app.restart();
+        } else {
+            app.exit(0);
+        }
+    });
+    true
+}
+
+#[tauri::command]
+pub fn quit_app(app: AppHandle) {
+    shutdown(&app, false);
+}
+
+#[tauri::command]
+pub fn restart_app(app: AppHandle) {
+    shutdown(&app, true);
+}
+
+// Decides what the main window's close button does. Returns true when the
+// close should go ahead (other windows, or the app is on its way out).
+pub fn handle_close_requested(window: &tauri::Window) -> bool {
+    let app = window.app_handle();
+    if window.label() != "main" || app.state::<AppLifecycle>().is_exiting() {
+        return true;
+    }
+    let behavior = app
+        .try_state::<SharedSettings>()
+        .and_then(|s| s.0.lock().ok().map(|s| s.on_close))
+        .unwrap_or(CloseBehavior::Hide);
+    match behavior {
+        CloseBehavior::Hide => {
+            if let Err(e) = window.hide() { tracing::warn!(error = %e, "failed to hide window on close"); }
+        }
+        CloseBehavior::Quit => shutdown(app, false),
+        CloseBehavior::Ask => {
+            emit_event(window, GraviaEvent::CloseRequested(CloseRequested)).ok();
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseAction {
+    Hide,
+    Quit,
+}
+
+// Answer to a `close-requested` prompt. With `remember`, the choice becomes
+// the new `on_close` setting.
+#[tauri::command]
+pub fn resolve_close(
+    app: AppHandle,
+    window: tauri::Window,
+    state: State<'_, SharedSettings>,
+    action: CloseAction,
+    remember: Option<bool>,
+) -> CommandResult<()> {
+    if remember.unwrap_or(false) {
+        let mut current = state.0.lock()?;
+        current.on_close = match action {
+            CloseAction::Hide => CloseBehavior::Hide,
+            CloseAction::Quit => CloseBehavior::Quit,
+        };
+        settings::save(&app, &current).map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+    }
+    match action {
+        CloseAction::Hide => window.hide()?,
+        CloseAction::Quit => shutdown(&app, false),
+    }
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager, State};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+use ts_rs::TS;
+
+use crate::crash;
+use crate::error::{CommandResult, GraviaError, SettingsError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::settings::{self, SharedSettings};
+
+const RING_CAPACITY: usize = 2000;
+const CRASH_TAIL_LINES: usize = 50;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    // Fields recorded with the message and on its enclosing spans, so e.g.
+    // everything under one `session_id` can be filtered together.
+    pub fields: BTreeMap<String, String>,
+}
+
+static RING: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+// Set by `debug_log_stream`; app-side entries are then also emitted live.
+static STREAM: AtomicBool = AtomicBool::new(false);
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+thread_local! {
+    // Emitting can itself log; don't stream those.
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+}
+
+// Keeps the file writer's background thread alive for the app's lifetime.
+pub struct LogGuard(#[allow(dead_code)] WorkerGuard);
+
+#[derive(Default)]
+struct EntryVisitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for EntryVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            self.fields.insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+}
+
+// A span's fields, kept so its events can carry them.
+struct SpanFields(BTreeMap<String, String>);
+
+// Copies every event into the in-memory ring behind `get_recent_logs`.
+struct RingLayer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for RingLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut visitor = EntryVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.fields));
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                    fields.extend(span_fields.0.clone());
+                }
+            }
+        }
+        let mut visitor = EntryVisitor::default();
+        event.record(&mut visitor);
+        fields.extend(visitor.fields);
+        let entry = LogEntry {
+            timestamp: Utc::now(),
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message,
+            fields,
+        };
+        if STREAM.load(Ordering::Relaxed) && is_app_entry(&entry) {
+            stream(&entry);
+        }
+        if let Ok(mut ring) = RING.lock() {
+            ring.push_back(entry);
+            while ring.len() > RING_CAPACITY {
+                ring.pop_front();
+            }
+        }
+    }
+}
+
+// Everything except the sidecar's forwarded output.
+fn is_app_entry(entry: &LogEntry) -> bool {
+    entry.target != "sidecar"
+}
+
+fn stream(entry: &LogEntry) {
+    let Some(app) = APP.get() else { return };
+    if EMITTING.with(|e| e.replace(true)) {
+        return;
+    }
+    emit_event(app, GraviaEvent::AppLog(entry.clone())).ok();
+    EMITTING.with(|e| e.set(false));
+}
+
+pub fn set_streaming(enabled: bool) {
+    STREAM.store(enabled, Ordering::Relaxed);
+}
+
+// The panic hook may fire while this thread holds the ring lock, so only
+// try it.
+fn crash_tail() -> Vec<String> {
+    let Ok(ring) = RING.try_lock() else { return Vec::new() };
+    ring.iter()
+        .rev()
+        .take(CRASH_TAIL_LINES)
+        .rev()
+        .map(|e| {
+            let fields: Vec<String> = e.fields.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            format!("{} {} {}: {} {}", e.timestamp.to_rfc3339(), e.level, e.target, e.message, fields.join(" "))
+        })
+        .collect()
+}
+
+fn parse_filter(level: &str) -> Result<EnvFilter, SettingsError> {
+    EnvFilter::try_new(level).map_err(|e| SettingsError::InvalidValue {
+        key: "log_level",
+        reason: e.to_string(),
+    })
+}
+
+pub fn init(app: &AppHandle, level: &str) {
+    let _ = APP.set(app.clone());
+    let filter = parse_filter(level).unwrap_or_else(|e| {
+        eprintln!("{e}, falling back to info");
+        EnvFilter::new("info")
+    });
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    let file_layer = match app.path().app_log_dir() {
+        Ok(dir) => {
+            let appender = tracing_appender::rolling::daily(dir, "gravia.log");
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            app.manage(LogGuard(guard));
+            Some(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false))
+        }
+        Err(e) => {
+            eprintln!("No log directory, file logging disabled: {e}");
+            None
+        }
+    };
+
+    let result = tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(file_layer)
+        .with(RingLayer)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to install tracing subscriber: {e}");
+        return;
+    }
+    let _ = FILTER_HANDLE.set(handle);
+    crash::set_log_tail_provider(crash_tail);
+}
+
+#[tauri::command]
+pub fn get_recent_logs(limit: Option<usize>, level: Option<String>) -> CommandResult<Vec<LogEntry>> {
+    recent(limit, level, |_| true)
+}
+
+// Like `get_recent_logs` without the sidecar's output, for the debug panel.
+#[tauri::command]
+pub fn get_recent_app_logs(limit: Option<usize>, min_level: Option<String>) -> CommandResult<Vec<LogEntry>> {
+    recent(limit, min_level, is_app_entry)
+}
+
+fn recent(
+    limit: Option<usize>,
+    level: Option<String>,
+    include: impl Fn(&LogEntry) -> bool,
+) -> CommandResult<Vec<LogEntry>> {
+    // Entries at `level` or more severe.
+    let min_level = match level {
+        Some(l) => Some(
+            l.parse::<Level>()
+                .map_err(|e| GraviaError::InvalidArgument(format!("level: {e}")))?,
+        ),
+        None => None,
+    };
+    let ring = RING.lock()?;
+    let mut entries: Vec<LogEntry> = ring
+        .iter()
+        .rev()
+        .filter(|e| include(e))
+        .filter(|e| match min_level {
+            Some(min) => e.level.parse::<Level>().is_ok_and(|l| l <= min),
+            None => true,
+        })
+        .take(limit.unwrap_or(200))
+        .cloned()
+        .collect();
+    entries.reverse();
+    Ok(entries)
+}
+
+// Checks a level as `apply_level` would read it, without applying it.
+pub fn check_level(level: &str) -> Result<(), SettingsError> {
+    parse_filter(level).map(|_| ())
+}
+
+// Swaps the live filter without touching the saved settings.
+pub fn apply_level(level: &str) -> CommandResult<()> {
+    let filter = parse_filter(level)?;
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| GraviaError::internal("logging is not initialized"))?;
+    handle.reload(filter).map_err(GraviaError::internal)
+}
+
+// Accepts a bare level (`debug`) or a full filter (`info,gravia_lib=trace`).
+#[tauri::command]
+pub fn set_log_level(
+    app: AppHandle,
+    state: State<'_, SharedSettings>,
+    level: String,
+) -> CommandResult<()> {
+    apply_level(&level)?;
+
+    let mut current = state.0.lock()?;
+    current.log_level = level;
+    settings::save(&app, &current).map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+    tracing::info!(level = %current.log_level, "log level changed");
+    Ok(())
+}
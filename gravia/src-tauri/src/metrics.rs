@@ -0,0 +1,226 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::idle;
+use crate::persist;
+use crate::power::{self, Throttle};
+use crate::settings::SharedSettings;
+
+const METRICS_FILE: &str = "usage_metrics.json";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(300);
+const RETAIN_DAYS: i64 = 90;
+
+// Everything we count. Metrics are keyed by these and hold numbers only, so
+// there's no way to record message text or image data through this module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Counter {
+    Classifications,
+    ScreenshotsWanted,
+    CapturesTaken,
+    CapturesReused,
+    CaptureFailures,
+    PayloadsShrunk,
+    ServerStarts,
+    ServerStartFailures,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Histogram {
+    ClassifierConfidence,
+    CaptureLatencyMs,
+}
+
+impl Histogram {
+    // Upper bounds of each bucket; the last bucket takes everything above.
+    fn bounds(self) -> &'static [f64] {
+        match self {
+            Histogram::ClassifierConfidence => &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0],
+            Histogram::CaptureLatencyMs => &[50.0, 100.0, 200.0, 400.0, 800.0, 1600.0, 3200.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct HistogramData {
+    // `buckets[i]` counts values up to `bounds[i]`, plus one overflow bucket.
+    #[ts(type = "number[]")]
+    pub buckets: Vec<u64>,
+    #[ts(type = "number")]
+    pub count: u64,
+    pub sum: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct DailyMetrics {
+    #[ts(type = "string")]
+    pub date: NaiveDate,
+    // `as` rather than `type`, so the binding imports the key type; `u32`
+    // just makes the counts plain numbers.
+    #[ts(as = "BTreeMap<Counter, u32>")]
+    pub counters: BTreeMap<Counter, u64>,
+    pub histograms: BTreeMap<Histogram, HistogramData>,
+}
+
+impl DailyMetrics {
+    fn new(date: NaiveDate) -> Self {
+        Self { date, counters: BTreeMap::new(), histograms: BTreeMap::new() }
+    }
+}
+
+// Daily aggregates, flushed to disk every few minutes and on exit.
+#[derive(Default)]
+pub struct UsageMetrics {
+    days: Mutex<BTreeMap<NaiveDate, DailyMetrics>>,
+    dirty: AtomicBool,
+    idle_paused: AtomicBool,
+}
+
+// What `get_metrics_status` reports about collection and the periodic flush,
+// including any power throttling of it.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct MetricsStatus {
+    pub enabled: bool,
+    // Flushes are skipped while the user is idle or `throttle` has
+    // `pause_optional` set; exit still flushes.
+    pub idle_paused: bool,
+    #[ts(type = "number")]
+    pub flush_interval_ms: u64,
+    pub throttle: Throttle,
+}
+
+impl UsageMetrics {
+    fn update(&self, f: impl FnOnce(&mut DailyMetrics)) {
+        let today = Local::now().date_naive();
+        let Ok(mut days) = self.days.lock() else { return };
+        f(days.entry(today).or_insert_with(|| DailyMetrics::new(today)));
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    fn recent(&self, days: u32) -> Vec<DailyMetrics> {
+        let since = Local::now().date_naive() - chrono::Duration::days(i64::from(days.max(1)) - 1);
+        self.days
+            .lock()
+            .map(|d| d.range(since..).map(|(_, m)| m.clone()).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.collect_usage_metrics)
+        .unwrap_or(false)
+}
+
+pub fn increment(app: &AppHandle, counter: Counter) {
+    if !enabled(app) {
+        return;
+    }
+    app.state::<UsageMetrics>().update(|day| *day.counters.entry(counter).or_default() += 1);
+}
+
+pub fn observe(app: &AppHandle, histogram: Histogram, value: f64) {
+    if !enabled(app) || !value.is_finite() {
+        return;
+    }
+    let bounds = histogram.bounds();
+    let bucket = bounds.iter().position(|b| value <= *b).unwrap_or(bounds.len());
+    app.state::<UsageMetrics>().update(|day| {
+        let data = day.histograms.entry(histogram).or_default();
+        data.buckets.resize(bounds.len() + 1, 0);
+        data.buckets[bucket] += 1;
+        data.count += 1;
+        data.sum += value;
+    });
+}
+
+fn metrics_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(METRICS_FILE))
+}
+
+// Loads saved aggregates and starts the periodic flush.
+pub fn start(app: &AppHandle) {
+    let saved: Vec<DailyMetrics> = metrics_path(app)
+        .ok()
+        .and_then(|path| persist::read_json(app, &path))
+        .unwrap_or_default();
+    if let Ok(mut days) = app.state::<UsageMetrics>().days.lock() {
+        days.extend(saved.into_iter().map(|m| (m.date, m)));
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(power::throttle(&app).scale(FLUSH_INTERVAL)).await;
+            let idle = idle::is_idle(&app);
+            app.state::<UsageMetrics>().idle_paused.store(idle, Ordering::Relaxed);
+            if !idle && !power::throttle(&app).pause_optional {
+                flush(&app);
+            }
+        }
+    });
+}
+
+// Writes the aggregates if anything changed, dropping days past retention.
+pub fn flush(app: &AppHandle) {
+    let metrics = app.state::<UsageMetrics>();
+    if !metrics.dirty.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let cutoff = Local::now().date_naive() - chrono::Duration::days(RETAIN_DAYS);
+    let snapshot: Vec<DailyMetrics> = match metrics.days.lock() {
+        Ok(mut days) => {
+            days.retain(|date, _| *date >= cutoff);
+            days.values().cloned().collect()
+        }
+        Err(_) => return,
+    };
+    let result = metrics_path(app).and_then(|path| persist::write_json_atomic(&path, &snapshot, false));
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "failed to save usage metrics");
+    }
+}
+
+#[tauri::command]
+pub fn get_usage_metrics(state: State<'_, UsageMetrics>, days: Option<u32>) -> Vec<DailyMetrics> {
+    state.recent(days.unwrap_or(7))
+}
+
+#[tauri::command]
+pub fn get_metrics_status(app: AppHandle, state: State<'_, UsageMetrics>) -> MetricsStatus {
+    let throttle = power::throttle(&app);
+    MetricsStatus {
+        enabled: enabled(&app),
+        idle_paused: state.idle_paused.load(Ordering::Relaxed),
+        flush_interval_ms: throttle.scale(FLUSH_INTERVAL).as_millis() as u64,
+        throttle,
+    }
+}
+
+// Writes everything retained to `path` so the user can look at or share it.
+#[tauri::command]
+pub async fn export_usage_metrics(state: State<'_, UsageMetrics>, path: PathBuf) -> CommandResult<()> {
+    let all = state.recent(RETAIN_DAYS as u32);
+    tauri::async_runtime::spawn_blocking(move || {
+        let json = serde_json::to_string_pretty(&all).map_err(GraviaError::internal)?;
+        std::fs::write(&path, json)
+            .map_err(|e| GraviaError::InvalidArgument(format!("can't write {}: {e}", path.display())))
+    })
+    .await?
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::settings::SharedSettings;
+
+// Payload carried by an auto-capture notification so a click can bring the
+// user back to the message that caused it.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct AutoCaptureNotice {
+    pub session_id: Option<String>,
+    pub message_id: Option<String>,
+    pub captured_at: DateTime<Utc>,
+    pub monitor: String,
+}
+
+#[derive(Default)]
+pub struct NotifierState {
+    muted_sessions: HashSet<String>,
+    last_notice: Option<AutoCaptureNotice>,
+}
+
+pub struct SharedNotifier(pub Mutex<NotifierState>);
+
+impl SharedNotifier {
+    pub fn new() -> Self {
+        Self(Mutex::new(NotifierState::default()))
+    }
+}
+
+pub fn notify_auto_capture(app: &AppHandle, notice: AutoCaptureNotice) {
+    let enabled = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.notify_on_auto_capture)
+        .unwrap_or(true);
+    if !enabled {
+        return;
+    }
+
+    // Showing the toast goes out to the OS, so it happens after the lock is
+    // released rather than holding up mute changes and notice lookups.
+    {
+        let notifier = app.state::<SharedNotifier>();
+        let Ok(mut state) = notifier.0.lock() else { return };
+        if let Some(sid) = &notice.session_id {
+            if state.muted_sessions.contains(sid) {
+                return;
+            }
+        }
+        state.last_notice = Some(notice.clone());
+    }
+
+    let local_time = notice.captured_at.with_timezone(&Local).format("%H:%M:%S");
+    let result = app
+        .notification()
+        .builder()
+        .title("Gravia captured your screen")
+        .body(format!("Automatic screenshot of {} at {}", notice.monitor, local_time))
+        .extra("session_id", &notice.session_id)
+        .extra("message_id", &notice.message_id)
+        .show();
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "failed to show auto-capture notification");
+    }
+}
+
+#[tauri::command]
+pub fn set_capture_notifications_muted(
+    state: State<'_, SharedNotifier>,
+    session_id: String,
+    muted: bool,
+) -> CommandResult<()> {
+    let mut state = state.0.lock()?;
+    if muted {
+        state.muted_sessions.insert(session_id);
+    } else {
+        state.muted_sessions.remove(&session_id);
+    }
+    Ok(())
+}
+
+// Desktop toasts don't report clicks back to the app, so the click path is
+// routed through this command (and the notification's `extra` payload on
+// platforms that do). Focuses the main window and asks the frontend to
+// scroll to the message behind the given notice, or the most recent one.
+#[tauri::command]
+pub fn open_auto_capture_notice(
+    app: AppHandle,
+    state: State<'_, SharedNotifier>,
+    notice: Option<AutoCaptureNotice>,
+) -> CommandResult<()> {
+    let notice = match notice {
+        Some(n) => Some(n),
+        None => state.0.lock()?.last_notice.clone(),
+    };
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| GraviaError::NotFound("main window".into()))?;
+    window.show()?;
+    window.unminimize()?;
+    window.set_focus()?;
+    if let Some(notice) = notice {
+        emit_event(&window, GraviaEvent::ScrollToMessage(notice))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::capture::{self, CaptureFormat, CaptureOperations, CaptureOptions, CaptureTarget, Downscale, RateLimitPolicy};
+use crate::capture_history::{self, CaptureHistory};
+use crate::command_stats;
+use crate::error::{CommandResult, GraviaError};
+
+// A full desktop at native size reads in a few seconds; well past that the
+// engine is stuck.
+const OCR_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, thiserror::Error)]
+pub enum OcrError {
+    #[error("text recognition is not available in this build")]
+    Unavailable,
+    #[error("tesseract is not installed or not on the PATH")]
+    EngineMissing,
+    #[error("text recognition took longer than {0}s")]
+    TimedOut(u64),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl OcrError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OcrError::Unavailable => "unavailable",
+            OcrError::EngineMissing => "engine_missing",
+            OcrError::TimedOut(_) => "timed_out",
+            OcrError::Failed(_) => "failed",
+        }
+    }
+}
+
+// What classify does with the screenshot of a query about text on screen,
+// such as "read this error".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenTextMode {
+    // Just the image.
+    #[default]
+    Off,
+    // The image and the text read off it.
+    Alongside,
+    // The text in place of the image, once any was found.
+    Instead,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct OcrWord {
+    pub text: String,
+    // Bounds in the capture's pixels, after any downscale.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // 0-100.
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ScreenText {
+    pub capture_id: String,
+    // One line per recognized line, with a blank line between blocks.
+    pub text: String,
+    pub words: Vec<OcrWord>,
+    // Maps word bounds back to the screen when the capture was scaled down.
+    pub downscale: Option<Downscale>,
+}
+
+// Captures `screen_id`, or the display under the cursor, and reads the text
+// on it, with each word's bounds and confidence. The capture stays full
+// size unless `options` cap it, as small text is the first thing lost.
+// Needs the tesseract executable; fails with `engine_missing` without it.
+#[tauri::command]
+pub async fn extract_screen_text(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    screen_id: Option<u32>,
+    options: Option<CaptureOptions>,
+    operation_id: Option<String>,
+) -> CommandResult<ScreenText> {
+    command_stats::track(window.app_handle(), "extract_screen_text", async {
+        if !cfg!(feature = "ocr") {
+            return Err(OcrError::Unavailable.into());
+        }
+        let mut options = CaptureOptions::resolve(options)?;
+        // Lossless keeps letter edges sharp.
+        options.format = CaptureFormat::Png;
+        let operation = operations.begin(operation_id);
+        let screen_id = match screen_id {
+            Some(id) => Some(id),
+            None => capture::active_screen_id(window.app_handle()).await,
+        };
+        let target = CaptureTarget::screen(screen_id);
+        let policy = RateLimitPolicy::Reuse;
+        let shot = capture::capture_unguarded(&window, target, &options, policy, &operation.token).await?;
+        read_capture(window.app_handle(), &shot.id).await
+    })
+    .await
+}
+
+// Reads the text off capture `capture_id` from the history.
+pub async fn read_capture(app: &AppHandle, capture_id: &str) -> CommandResult<ScreenText> {
+    let dir = capture_history::screenshots_dir(app)?;
+    let history_app = app.clone();
+    let id = capture_id.to_string();
+    let recognize = tauri::async_runtime::spawn_blocking(move || {
+        let history = history_app.state::<CaptureHistory>();
+        // The engine reads from a file; it's removed with the history's
+        // other temporary files.
+        let path = history.ensure_on_disk(&id, &dir)?;
+        let downscale = history.info(Some(&id))?.downscale;
+        let (text, words) = engine::recognize(&path)?;
+        Ok::<_, GraviaError>(ScreenText { capture_id: id, text, words, downscale })
+    });
+    // The engine can't be interrupted; a late result is dropped.
+    let started = std::time::Instant::now();
+    let screen_text = tokio::time::timeout(OCR_TIMEOUT, recognize)
+        .await
+        .map_err(|_| OcrError::TimedOut(OCR_TIMEOUT.as_secs()))???;
+    tracing::info!(
+        capture_id,
+        words = screen_text.words.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "screen text extracted"
+    );
+    Ok(screen_text)
+}
+
+#[cfg(feature = "ocr")]
+mod engine {
+    use super::{OcrError, OcrWord};
+    use rusty_tesseract::{Args, Image, TessError};
+    use std::path::Path;
+
+    // Words below this confidence (0-100) are mostly icons and window
+    // edges read as letters.
+    const MIN_WORD_CONFIDENCE: f32 = 30.0;
+
+    pub fn recognize(path: &Path) -> Result<(String, Vec<OcrWord>), OcrError> {
+        let image = Image::from_path(path).map_err(|e| OcrError::Failed(e.to_string()))?;
+        let data = rusty_tesseract::image_to_data(&image, &Args::default()).map_err(|e| match e {
+            TessError::TesseractNotFoundError => OcrError::EngineMissing,
+            e => OcrError::Failed(e.to_string()),
+        })?;
+
+        let (mut text, mut words) = (String::new(), Vec::new());
+        let mut last_line = None;
+        for entry in &data.data {
+            let word = entry.text.trim();
+            if word.is_empty() || entry.conf < MIN_WORD_CONFIDENCE {
+                continue;
+            }
+            let line = (entry.block_num, entry.par_num, entry.line_num);
+            match last_line {
+                Some((block, _, _)) if block != line.0 => text.push_str("\n\n"),
+                Some(last) if last != line => text.push('\n'),
+                Some(_) => text.push(' '),
+                None => {}
+            }
+            last_line = Some(line);
+            text.push_str(word);
+            words.push(OcrWord {
+                text: word.to_string(),
+                x: entry.left.max(0) as u32,
+                y: entry.top.max(0) as u32,
+                width: entry.width.max(0) as u32,
+                height: entry.height.max(0) as u32,
+                confidence: entry.conf,
+            });
+        }
+        Ok((text, words))
+    }
+}
+
+#[cfg(not(feature = "ocr"))]
+mod engine {
+    use super::{OcrError, OcrWord};
+    use std::path::Path;
+
+    pub fn recognize(_path: &Path) -> Result<(String, Vec<OcrWord>), OcrError> {
+        Err(OcrError::Unavailable)
+    }
+}
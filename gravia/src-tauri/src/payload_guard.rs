@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::{CaptureError, CaptureFormat, CaptureOptions, PayloadAdjustment};
+use crate::screen_grab;
+
+// The JPEG quality a shrink starts from; the budget lowers it further before
+// making the image smaller.
+const SHRINK_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadLimit {
+    pub max_bytes: usize,
+    pub auto_shrink: bool,
+}
+
+// Checks an encoded capture against the limit. Oversized ones are either
+// rejected or re-encoded from `encoded` as a JPEG through the same byte
+// budget as the `max_bytes` capture option. If that can't make it fit, the
+// error carries the size of the smallest attempt.
+pub fn fit(
+    encoded: &[u8],
+    base64: Arc<String>,
+    limit: PayloadLimit,
+    cancel: &CancellationToken,
+) -> Result<(Arc<String>, Option<PayloadAdjustment>), CaptureError> {
+    let original_bytes = base64.len();
+    if original_bytes <= limit.max_bytes {
+        return Ok((base64, None));
+    }
+    if !limit.auto_shrink {
+        return Err(CaptureError::PayloadTooLarge { bytes: original_bytes, limit: limit.max_bytes });
+    }
+    drop(base64);
+    if cancel.is_cancelled() {
+        return Err(CaptureError::Cancelled);
+    }
+
+    let image = image::load_from_memory(encoded).map_err(|e| CaptureError::Failed(e.to_string()))?.into_rgba8();
+    let (width, height) = image.dimensions();
+    let options = CaptureOptions { format: CaptureFormat::Jpeg, quality: Some(SHRINK_QUALITY), ..Default::default() };
+    let fitted = screen_grab::fit_budget(image.as_raw(), width, height, &options, limit.max_bytes, original_bytes)
+        .map_err(|e| CaptureError::Failed(e.to_string()))?;
+    match fitted.base64 {
+        Some((base64, content_hash)) if fitted.budget.fits => {
+            let adjustment = PayloadAdjustment {
+                original_bytes,
+                bytes: base64.len(),
+                scale: fitted.budget.scale,
+                width: fitted.width,
+                height: fitted.height,
+                format: "jpeg".to_string(),
+                content_hash,
+            };
+            Ok((Arc::new(base64), Some(adjustment)))
+        }
+        _ => Err(CaptureError::PayloadTooLarge { bytes: fitted.budget.bytes, limit: limit.max_bytes }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    // A PNG of random pixels, which doesn't compress, and its base64.
+    fn noise_png(width: u32, height: u32) -> (Vec<u8>, Arc<String>) {
+        let mut state = 0x2545_f491_u32;
+        let image = RgbImage::from_fn(width, height, |_, _| {
+            let mut channel = || {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            };
+            image::Rgb([channel(), channel(), channel()])
+        });
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        let base64 = base64::engine::general_purpose::STANDARD.encode(&png);
+        (png, Arc::new(base64))
+    }
+
+    fn limit(max_bytes: usize, auto_shrink: bool) -> PayloadLimit {
+        PayloadLimit { max_bytes, auto_shrink }
+    }
+
+    #[test]
+    fn a_capture_within_the_limit_is_passed_through() {
+        let (png, base64) = noise_png(64, 64);
+        let limit = limit(base64.len(), true);
+        let (fitted, adjustment) = fit(&png, base64.clone(), limit, &CancellationToken::new()).unwrap();
+        assert!(Arc::ptr_eq(&fitted, &base64));
+        assert!(adjustment.is_none());
+    }
+
+    #[test]
+    fn an_oversized_capture_is_rejected_without_auto_shrink() {
+        let (png, base64) = noise_png(128, 128);
+        let original = base64.len();
+        let result = fit(&png, base64, limit(original / 2, false), &CancellationToken::new());
+        let Err(CaptureError::PayloadTooLarge { bytes, limit }) = result else {
+            panic!("expected PayloadTooLarge, got {result:?}");
+        };
+        assert_eq!((bytes, limit), (original, original / 2));
+    }
+
+    #[test]
+    fn an_oversized_capture_is_shrunk_to_a_jpeg_that_fits() {
+        let (png, base64) = noise_png(640, 480);
+        let original = base64.len();
+        // Too small for a full-size JPEG of noise even at the lowest quality.
+        let max_bytes = 60_000;
+        let (fitted, adjustment) = fit(&png, base64, limit(max_bytes, true), &CancellationToken::new()).unwrap();
+        let adjustment = adjustment.unwrap();
+        assert!(fitted.len() <= max_bytes);
+        assert_eq!((adjustment.original_bytes, adjustment.bytes), (original, fitted.len()));
+        assert!(adjustment.scale < 1.0 && adjustment.width < 640 && adjustment.height < 480);
+        assert_eq!(adjustment.format, "jpeg");
+
+        let jpeg = base64::engine::general_purpose::STANDARD.decode(fitted.as_bytes()).unwrap();
+        assert_eq!(image::guess_format(&jpeg).unwrap(), ImageFormat::Jpeg);
+        let decoded = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (adjustment.width, adjustment.height));
+        assert_eq!(adjustment.content_hash, crate::base64_stream::content_hash(&jpeg));
+    }
+
+    #[test]
+    fn a_capture_that_never_fits_reports_the_smallest_attempt() {
+        let (png, base64) = noise_png(640, 480);
+        let original = base64.len();
+        // Smaller than any JPEG's headers.
+        let result = fit(&png, base64, limit(64, true), &CancellationToken::new());
+        let Err(CaptureError::PayloadTooLarge { bytes, limit }) = result else {
+            panic!("expected PayloadTooLarge, got {result:?}");
+        };
+        assert_eq!(limit, 64);
+        assert!(bytes > limit && bytes < original / 10, "{bytes} bytes");
+    }
+
+    #[test]
+    fn a_cancelled_shrink_stops() {
+        let (png, base64) = noise_png(64, 64);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = fit(&png, base64, limit(64, true), &cancel);
+        assert!(matches!(result, Err(CaptureError::Cancelled)));
+    }
+}
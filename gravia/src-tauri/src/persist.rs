@@ -0,0 +1,189 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::events::{emit_event, GraviaEvent};
+
+// A file that failed to parse and was loaded from its backup instead.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PersistenceRecovered {
+    pub file: String,
+    pub reason: String,
+}
+
+// Recoveries from startup, kept until the frontend asks, since they happen
+// before it's listening for events.
+#[derive(Default)]
+pub struct Recoveries(Mutex<Vec<PersistenceRecovered>>);
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+// Writes `bytes` to a temp file next to `path`, syncs it, and renames it over
+// `path`, so a crash leaves either the old or the new contents. The previous
+// contents are kept as `<name>.bak`, but only if they parse as JSON, so a
+// damaged file never replaces a good backup.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    match std::fs::read(path) {
+        Ok(previous) if serde_json::from_slice::<serde::de::IgnoredAny>(&previous).is_ok() => {
+            if let Err(e) = std::fs::write(backup_path(path), previous) {
+                tracing::warn!(path = %path.display(), error = %e, "failed to refresh backup");
+            }
+        }
+        Ok(_) => tracing::warn!(path = %path.display(), "previous contents damaged, backup kept"),
+        Err(_) => {}
+    }
+    std::fs::rename(&tmp, path)?;
+    // The rename itself only survives a crash once the directory is synced.
+    #[cfg(unix)]
+    std::fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+pub fn write_json_atomic<T: Serialize>(path: &Path, value: &T, pretty: bool) -> anyhow::Result<()> {
+    let json = if pretty { serde_json::to_vec_pretty(value)? } else { serde_json::to_vec(value)? };
+    write_atomic(path, &json)?;
+    Ok(())
+}
+
+// Reads `path`, falling back to its backup if the primary can't be read or
+// doesn't parse. A missing file is taken as deliberate and not recovered.
+pub fn read_json<T: DeserializeOwned>(app: &AppHandle, path: &Path) -> Option<T> {
+    let (value, damaged) = read_with_backup(path)?;
+    let Some(reason) = damaged else { return Some(value) };
+
+    let file = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+    tracing::warn!(%file, %reason, "loaded backup instead of a damaged file");
+    let recovered = PersistenceRecovered { file, reason };
+    if let Ok(mut pending) = app.state::<Recoveries>().0.lock() {
+        pending.push(recovered.clone());
+    }
+    emit_event(app, GraviaEvent::PersistenceRecovered(recovered)).ok();
+    Some(value)
+}
+
+// The value for `read_json`, along with why the primary was passed over
+// when it came from the backup.
+fn read_with_backup<T: DeserializeOwned>(path: &Path) -> Option<(T, Option<String>)> {
+    let primary = match std::fs::read_to_string(path) {
+        Ok(raw) => match serde_json::from_str(&raw) {
+            Ok(value) => return Some((value, None)),
+            Err(e) => e.to_string(),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => e.to_string(),
+    };
+    let raw = std::fs::read_to_string(backup_path(path)).ok()?;
+    let value = serde_json::from_str(&raw).ok()?;
+    Some((value, Some(primary)))
+}
+
+#[tauri::command]
+pub fn take_persistence_warnings(state: State<'_, Recoveries>) -> Vec<PersistenceRecovered> {
+    state.0.lock().map(|mut p| std::mem::take(&mut *p)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn saved_twice(dir: &Path) -> PathBuf {
+        let path = dir.join("settings.json");
+        write_json_atomic(&path, &json!({ "version": 1 }), false).unwrap();
+        write_json_atomic(&path, &json!({ "version": 2 }), true).unwrap();
+        path
+    }
+
+    #[test]
+    fn writes_replace_the_file_and_keep_the_previous_as_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_twice(dir.path());
+        let (value, damaged) = read_with_backup::<Value>(&path).unwrap();
+        assert_eq!((value, damaged), (json!({ "version": 2 }), None));
+        let backup: Value = serde_json::from_slice(&std::fs::read(backup_path(&path)).unwrap()).unwrap();
+        assert_eq!(backup, json!({ "version": 1 }));
+        assert!(!dir.path().join("settings.json.tmp").exists());
+    }
+
+    #[test]
+    fn a_truncated_file_is_recovered_from_its_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_twice(dir.path());
+        let full = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &full[..full.len() / 2]).unwrap();
+        let (value, damaged) = read_with_backup::<Value>(&path).unwrap();
+        assert_eq!(value, json!({ "version": 1 }));
+        assert!(damaged.is_some());
+    }
+
+    #[test]
+    fn a_corrupt_file_is_recovered_from_its_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_twice(dir.path());
+        for garbage in [&b"\xff\xfe\x00binary"[..], b"", b"{\"version\": 2,,}"] {
+            std::fs::write(&path, garbage).unwrap();
+            let (value, damaged) = read_with_backup::<Value>(&path).unwrap();
+            assert_eq!(value, json!({ "version": 1 }));
+            assert!(damaged.is_some());
+        }
+    }
+
+    // Wherever the file is cut off, loading finds the last complete save,
+    // and saving over the damage leaves that save as the backup.
+    #[test]
+    fn truncation_at_any_offset_recovers_the_last_consistent_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let full = std::fs::read(saved_twice(dir.path())).unwrap();
+        for len in 0..full.len() {
+            let path = saved_twice(dir.path());
+            std::fs::write(&path, &full[..len]).unwrap();
+            let (value, damaged) = read_with_backup::<Value>(&path).unwrap();
+            assert_eq!(value, json!({ "version": 1 }), "cut at {len}");
+            assert!(damaged.is_some());
+
+            write_json_atomic(&path, &json!({ "version": 3 }), true).unwrap();
+            let backup: Value = serde_json::from_slice(&std::fs::read(backup_path(&path)).unwrap()).unwrap();
+            assert_eq!(backup, json!({ "version": 1 }), "cut at {len}");
+            std::fs::write(&path, &full[..len]).unwrap();
+            assert_eq!(read_with_backup::<Value>(&path).unwrap().0, json!({ "version": 1 }), "cut at {len}");
+        }
+    }
+
+    #[test]
+    fn nothing_is_recovered_without_a_usable_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_twice(dir.path());
+        std::fs::write(&path, "{").unwrap();
+        std::fs::write(backup_path(&path), "{").unwrap();
+        assert!(read_with_backup::<Value>(&path).is_none());
+        std::fs::remove_file(backup_path(&path)).unwrap();
+        assert!(read_with_backup::<Value>(&path).is_none());
+    }
+
+    #[test]
+    fn a_missing_file_is_not_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_twice(dir.path());
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_with_backup::<Value>(&path).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::capture::PixelFormat;
+
+// Bytes handed to each rayon task; a multiple of the pixel size.
+const PARALLEL_CHUNK: usize = 64 * 1024;
+
+// Puts one pixel of `format` into RGBA order.
+fn convert(format: PixelFormat, px: &mut [u8]) {
+    match format {
+        PixelFormat::Rgba => {}
+        PixelFormat::Bgra => px.swap(0, 2),
+        PixelFormat::Bgrx => {
+            px.swap(0, 2);
+            px[3] = 255;
+        }
+    }
+}
+
+// Converts `pixels` from `format` to RGBA in place, across the rayon pool
+// when `parallel` is set. Both paths produce identical output.
+pub fn to_rgba_in_place(pixels: &mut [u8], format: PixelFormat, parallel: bool) {
+    if format == PixelFormat::Rgba {
+        return;
+    }
+    let convert_chunk = |chunk: &mut [u8]| chunk.chunks_exact_mut(4).for_each(|px| convert(format, px));
+    if parallel {
+        use rayon::prelude::*;
+        pixels.par_chunks_mut(PARALLEL_CHUNK).for_each(convert_chunk);
+        return;
+    }
+    convert_chunk(pixels);
+}
+
+// Copies rows of `row_bytes` that start every `pitch` bytes in `src` into
+// `dst` as tightly packed RGBA, as GPU mappings pad their rows.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn copy_rows_to_rgba(
+    src: &[u8],
+    pitch: usize,
+    dst: &mut [u8],
+    row_bytes: usize,
+    format: PixelFormat,
+    parallel: bool,
+) {
+    let copy_row = |(dst, src): (&mut [u8], &[u8])| {
+        dst.copy_from_slice(&src[..row_bytes]);
+        dst.chunks_exact_mut(4).for_each(|px| convert(format, px));
+    };
+    if parallel {
+        use rayon::prelude::*;
+        dst.par_chunks_exact_mut(row_bytes).zip(src.par_chunks(pitch)).for_each(copy_row);
+        return;
+    }
+    dst.chunks_exact_mut(row_bytes).zip(src.chunks(pitch)).for_each(copy_row);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two pixels: opaque red and half-transparent blue, in `format`'s order.
+    fn red_and_blue(format: PixelFormat) -> Vec<u8> {
+        match format {
+            PixelFormat::Rgba => vec![255, 0, 0, 255, 0, 0, 255, 128],
+            PixelFormat::Bgra => vec![0, 0, 255, 255, 255, 0, 0, 128],
+            // The alpha bytes are whatever GDI left there.
+            PixelFormat::Bgrx => vec![0, 0, 255, 7, 255, 0, 0, 0],
+        }
+    }
+
+    #[test]
+    fn every_format_converts_to_rgba() {
+        let cases = [
+            (PixelFormat::Rgba, vec![255, 0, 0, 255, 0, 0, 255, 128]),
+            (PixelFormat::Bgra, vec![255, 0, 0, 255, 0, 0, 255, 128]),
+            (PixelFormat::Bgrx, vec![255, 0, 0, 255, 0, 0, 255, 255]),
+        ];
+        for (format, expected) in cases {
+            for parallel in [false, true] {
+                let mut pixels = red_and_blue(format);
+                to_rgba_in_place(&mut pixels, format, parallel);
+                assert_eq!(pixels, expected, "{format:?}, parallel {parallel}");
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_conversion_matches_serial_across_chunks() {
+        // Spans several rayon chunks and ends partway into one.
+        let mut serial: Vec<u8> = (0..PARALLEL_CHUNK * 2 + 12).map(|i| (i % 253) as u8).collect();
+        let mut parallel = serial.clone();
+        to_rgba_in_place(&mut serial, PixelFormat::Bgrx, false);
+        to_rgba_in_place(&mut parallel, PixelFormat::Bgrx, true);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn padded_rows_are_packed_and_converted() {
+        // 2x2 BGRA with 4 bytes of padding after each row, as a GPU mapping
+        // might hand it over.
+        let (row_bytes, pitch) = (8, 12);
+        let src = [
+            0, 0, 255, 255, 255, 0, 0, 255, 9, 9, 9, 9, //
+            0, 255, 0, 255, 10, 20, 30, 40, 9, 9, 9, 9,
+        ];
+        let expected = [255, 0, 0, 255, 0, 0, 255, 255, 0, 255, 0, 255, 30, 20, 10, 40];
+        for parallel in [false, true] {
+            let mut dst = vec![0u8; row_bytes * 2];
+            copy_rows_to_rgba(&src, pitch, &mut dst, row_bytes, PixelFormat::Bgra, parallel);
+            assert_eq!(dst, expected, "parallel {parallel}");
+        }
+    }
+
+    #[test]
+    fn unpadded_rows_copy_straight_through() {
+        // The last row of a mapping may stop right after its pixels.
+        let src = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut dst = vec![0u8; 8];
+        copy_rows_to_rgba(&src, 4, &mut dst, 4, PixelFormat::Rgba, false);
+        assert_eq!(dst, src);
+    }
+}
@@ -0,0 +1,413 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::events::{emit_event, GraviaEvent};
+use crate::settings::SharedSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct PowerState {
+    pub source: PowerSource,
+    pub battery_percent: Option<u8>,
+    pub power_saver: bool,
+}
+
+impl Default for PowerState {
+    fn default() -> Self {
+        Self { source: PowerSource::Unknown, battery_percent: None, power_saver: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleReason {
+    PowerSaver,
+    LowBattery,
+    OnBattery,
+}
+
+// How much background work should back off right now. Subsystems multiply
+// their normal interval by `interval_factor`; optional work stops when
+// `pause_optional` is set. Status commands include it so users can see why.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct Throttle {
+    pub interval_factor: u32,
+    pub pause_optional: bool,
+    pub reason: Option<ThrottleReason>,
+}
+
+impl Throttle {
+    const NONE: Throttle = Throttle { interval_factor: 1, pause_optional: false, reason: None };
+
+    pub fn scale(&self, interval: std::time::Duration) -> std::time::Duration {
+        interval * self.interval_factor
+    }
+}
+
+#[derive(Default)]
+pub struct PowerMonitor(Mutex<PowerState>);
+
+pub fn current(app: &AppHandle) -> PowerState {
+    app.state::<PowerMonitor>().0.lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+pub fn throttle(app: &AppHandle) -> Throttle {
+    let (enabled, low_battery) = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| (s.throttle_on_battery, s.low_battery_percent))
+        .unwrap_or((true, 20));
+    if !enabled {
+        return Throttle::NONE;
+    }
+    let state = current(app);
+    let low = state.source == PowerSource::Battery
+        && state.battery_percent.is_some_and(|p| p <= low_battery);
+    if state.power_saver {
+        Throttle { interval_factor: 4, pause_optional: true, reason: Some(ThrottleReason::PowerSaver) }
+    } else if low {
+        Throttle { interval_factor: 4, pause_optional: true, reason: Some(ThrottleReason::LowBattery) }
+    } else if state.source == PowerSource::Battery {
+        Throttle { interval_factor: 2, pause_optional: false, reason: Some(ThrottleReason::OnBattery) }
+    } else {
+        Throttle::NONE
+    }
+}
+
+// Re-reads the power status and emits `power-state-changed` if it moved.
+#[cfg_attr(not(any(target_os = "windows", target_os = "linux", target_os = "macos")), allow(dead_code))]
+pub fn refresh(app: &AppHandle) {
+    let Some(state) = platform::read_state() else { return };
+    let monitor = app.state::<PowerMonitor>();
+    let Ok(mut last) = monitor.0.lock() else { return };
+    if *last == state {
+        return;
+    }
+    tracing::info!(source = ?state.source, percent = ?state.battery_percent, power_saver = state.power_saver, "power state changed");
+    *last = state.clone();
+    emit_event(app, GraviaEvent::PowerStateChanged(state)).ok();
+}
+
+pub fn start(app: &AppHandle) {
+    if let Some(state) = platform::read_state() {
+        if let Ok(mut last) = app.state::<PowerMonitor>().0.lock() {
+            *last = state;
+        }
+    }
+    platform::watch(app.clone());
+}
+
+#[tauri::command]
+pub fn get_power_state(state: State<'_, PowerMonitor>) -> PowerState {
+    state.0.lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{PowerSource, PowerState};
+    use std::ffi::c_void;
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tauri::AppHandle;
+    use windows::core::GUID;
+    use windows::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+    use windows::Win32::System::Power::{
+        GetSystemPowerStatus, PowerSettingRegisterNotification, DEVICE_NOTIFY_CALLBACK,
+        DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, SYSTEM_POWER_STATUS,
+    };
+    use windows::Win32::System::SystemServices::{
+        GUID_ACDC_POWER_SOURCE, GUID_BATTERY_PERCENTAGE_REMAINING, GUID_POWER_SAVING_STATUS,
+    };
+
+    // Catches anything the notifications miss (and covers systems where
+    // registering fails).
+    const FALLBACK_POLL: Duration = Duration::from_secs(60);
+
+    // The notification callback only gets a context pointer, so it reaches
+    // the refresh thread through this slot.
+    static NOTIFY_TX: Mutex<Option<Sender<()>>> = Mutex::new(None);
+
+    pub fn read_state() -> Option<PowerState> {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+        let source = match status.ACLineStatus {
+            0 => PowerSource::Battery,
+            1 => PowerSource::Ac,
+            _ => PowerSource::Unknown,
+        };
+        Some(PowerState {
+            source,
+            battery_percent: (status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent),
+            power_saver: status.SystemStatusFlag == 1,
+        })
+    }
+
+    unsafe extern "system" fn on_power_setting(_context: *const c_void, _kind: u32, _setting: *const c_void) -> u32 {
+        if let Ok(guard) = NOTIFY_TX.lock() {
+            if let Some(tx) = guard.as_ref() {
+                let _ = tx.send(());
+            }
+        }
+        0
+    }
+
+    fn register(setting: &GUID) {
+        // Lives for the rest of the process, as the registration does.
+        let params: &'static mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS = Box::leak(Box::new(
+            DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+                Callback: Some(on_power_setting),
+                Context: std::ptr::null_mut(),
+            },
+        ));
+        let mut handle: *mut c_void = std::ptr::null_mut();
+        let status = unsafe {
+            PowerSettingRegisterNotification(
+                setting,
+                DEVICE_NOTIFY_CALLBACK,
+                HANDLE(params as *mut _ as *mut c_void),
+                &mut handle,
+            )
+        };
+        if status != ERROR_SUCCESS {
+            tracing::warn!(?setting, code = status.0, "failed to register power notification");
+        }
+    }
+
+    pub fn watch(app: AppHandle) {
+        let (tx, rx) = mpsc::channel::<()>();
+        if let Ok(mut slot) = NOTIFY_TX.lock() {
+            *slot = Some(tx);
+        }
+        for setting in [&GUID_ACDC_POWER_SOURCE, &GUID_BATTERY_PERCENTAGE_REMAINING, &GUID_POWER_SAVING_STATUS] {
+            register(setting);
+        }
+        std::thread::spawn(move || loop {
+            match rx.recv_timeout(FALLBACK_POLL) {
+                Ok(()) | Err(mpsc::RecvTimeoutError::Timeout) => super::refresh(&app),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        });
+    }
+}
+
+// Reads the kernel's power_supply class, which UPower itself reports
+// from, so there's no D-Bus dependency. Nothing notifies on change without
+// D-Bus, so it's polled.
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{PowerSource, PowerState};
+    use std::path::Path;
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    const POLL: Duration = Duration::from_secs(30);
+    const SUPPLIES: &str = "/sys/class/power_supply";
+    // Set to low-power by power-profiles-daemon's power saver profile.
+    const PLATFORM_PROFILE: &str = "/sys/firmware/acpi/platform_profile";
+
+    fn read(path: &Path) -> Option<String> {
+        std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+    }
+
+    pub fn read_state() -> Option<PowerState> {
+        let mut state = read_supplies(Path::new(SUPPLIES))?;
+        state.power_saver = read(Path::new(PLATFORM_PROFILE)).as_deref() == Some("low-power");
+        Some(state)
+    }
+
+    fn read_supplies(root: &Path) -> Option<PowerState> {
+        let mut external = None;
+        let mut discharging = false;
+        let mut capacities = Vec::new();
+        for entry in std::fs::read_dir(root).ok()?.flatten() {
+            let dir = entry.path();
+            // Mice, headsets and the like report their batteries too.
+            if read(&dir.join("scope")).as_deref() == Some("Device") {
+                continue;
+            }
+            match read(&dir.join("type")).as_deref() {
+                Some("Mains" | "USB") => {
+                    let online = read(&dir.join("online")).as_deref() == Some("1");
+                    external = Some(external.unwrap_or(false) || online);
+                }
+                Some("Battery") => {
+                    discharging |= read(&dir.join("status")).as_deref() == Some("Discharging");
+                    capacities.extend(read(&dir.join("capacity")).and_then(|c| c.parse::<u32>().ok()));
+                }
+                _ => {}
+            }
+        }
+        // Without an adapter entry, a battery that isn't discharging is on
+        // external power.
+        let source = match external {
+            Some(true) => PowerSource::Ac,
+            Some(false) => PowerSource::Battery,
+            None if capacities.is_empty() => PowerSource::Unknown,
+            None if discharging => PowerSource::Battery,
+            None => PowerSource::Ac,
+        };
+        let battery_percent = (!capacities.is_empty())
+            .then(|| capacities.iter().sum::<u32>() / capacities.len() as u32)
+            .map(|p| p.min(100) as u8);
+        Some(PowerState { source, battery_percent, power_saver: false })
+    }
+
+    pub fn watch(app: AppHandle) {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL);
+            super::refresh(&app);
+        });
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn supply(root: &Path, name: &str, files: &[(&str, &str)]) {
+            let dir = root.join(name);
+            std::fs::create_dir(&dir).unwrap();
+            for (file, value) in files {
+                std::fs::write(dir.join(file), format!("{value}\n")).unwrap();
+            }
+        }
+
+        #[test]
+        fn a_laptop_on_its_adapter_is_on_ac() {
+            let root = tempfile::tempdir().unwrap();
+            supply(root.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+            supply(root.path(), "BAT0", &[("type", "Battery"), ("status", "Charging"), ("capacity", "64")]);
+            let state = read_supplies(root.path()).unwrap();
+            assert_eq!((state.source, state.battery_percent), (PowerSource::Ac, Some(64)));
+        }
+
+        #[test]
+        fn an_unplugged_laptop_is_on_battery_ignoring_peripherals() {
+            let root = tempfile::tempdir().unwrap();
+            supply(root.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+            supply(root.path(), "BAT0", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "30")]);
+            supply(root.path(), "BAT1", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "50")]);
+            supply(root.path(), "hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("capacity", "5")]);
+            let state = read_supplies(root.path()).unwrap();
+            assert_eq!((state.source, state.battery_percent), (PowerSource::Battery, Some(40)));
+        }
+
+        #[test]
+        fn without_an_adapter_entry_the_battery_status_decides() {
+            let root = tempfile::tempdir().unwrap();
+            supply(root.path(), "BAT0", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "80")]);
+            assert_eq!(read_supplies(root.path()).unwrap().source, PowerSource::Battery);
+            std::fs::write(root.path().join("BAT0/status"), "Full\n").unwrap();
+            assert_eq!(read_supplies(root.path()).unwrap().source, PowerSource::Ac);
+        }
+
+        #[test]
+        fn a_desktop_without_supplies_is_unknown() {
+            let root = tempfile::tempdir().unwrap();
+            let state = read_supplies(root.path()).unwrap();
+            assert_eq!((state.source, state.battery_percent), (PowerSource::Unknown, None));
+        }
+    }
+}
+
+// Reads `pmset`, which reports what IOKit's power sources do, rather than
+// linking IOKit. Polled like Linux.
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{PowerSource, PowerState};
+    use std::process::Command;
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    const POLL: Duration = Duration::from_secs(30);
+
+    fn pmset(args: &[&str]) -> Option<String> {
+        let output = Command::new("/usr/bin/pmset").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    pub fn read_state() -> Option<PowerState> {
+        let batteries = pmset(&["-g", "batt"])?;
+        let settings = pmset(&["-g"]).unwrap_or_default();
+        Some(parse(&batteries, &settings))
+    }
+
+    // `batteries` looks like:
+    //   Now drawing from 'Battery Power'
+    //    -InternalBattery-0 (id=4653155)	83%; discharging; 5:12 remaining present: true
+    // and `settings` has a ` lowpowermode 1` line while Low Power Mode is on.
+    fn parse(batteries: &str, settings: &str) -> PowerState {
+        let source = if batteries.contains("'AC Power'") {
+            PowerSource::Ac
+        } else if batteries.contains("'Battery Power'") {
+            PowerSource::Battery
+        } else {
+            PowerSource::Unknown
+        };
+        let battery_percent = batteries
+            .lines()
+            .find(|line| line.contains("-InternalBattery-"))
+            .and_then(|line| line.split('\t').nth(1))
+            .and_then(|status| status.split('%').next())
+            .and_then(|percent| percent.trim().parse::<u8>().ok())
+            .filter(|percent| *percent <= 100);
+        let power_saver = settings.lines().any(|line| {
+            let mut words = line.split_whitespace();
+            words.next() == Some("lowpowermode") && words.next() == Some("1")
+        });
+        PowerState { source, battery_percent, power_saver }
+    }
+
+    pub fn watch(app: AppHandle) {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL);
+            super::refresh(&app);
+        });
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn reads_the_source_charge_and_low_power_mode() {
+            let batteries = "Now drawing from 'Battery Power'\n \
+                             -InternalBattery-0 (id=4653155)\t83%; discharging; 5:12 remaining present: true\n";
+            let state = parse(batteries, " lowpowermode         1\n powernap             0\n");
+            assert_eq!(state, PowerState { source: PowerSource::Battery, battery_percent: Some(83), power_saver: true });
+        }
+
+        #[test]
+        fn a_mac_without_a_battery_is_on_ac() {
+            let state = parse("Now drawing from 'AC Power'\n", " lowpowermode         0\n");
+            assert_eq!(state, PowerState { source: PowerSource::Ac, battery_percent: None, power_saver: false });
+        }
+    }
+}
+
+// Other Unixes report no power state, so nothing is ever throttled there.
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+    use super::PowerState;
+    use tauri::AppHandle;
+
+    pub fn read_state() -> Option<PowerState> {
+        None
+    }
+
+    pub fn watch(_app: AppHandle) {}
+}
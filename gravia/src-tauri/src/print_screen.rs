@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Shortcut, ShortcutState};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, SettingsError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::settings::{self, SharedSettings};
+use crate::tray;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintScreenAction {
+    #[default]
+    RegionSelect,
+    FullScreen,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShortcutError {
+    #[error("PrintScreen is already taken{}", .holder.as_deref().map(|h| format!(" by {h}")).unwrap_or_default())]
+    InUse { holder: Option<String> },
+    #[error("failed to change the PrintScreen binding: {0}")]
+    Failed(String),
+}
+
+impl ShortcutError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ShortcutError::InUse { .. } => "in_use",
+            ShortcutError::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PrintScreenStatus {
+    pub bound: bool,
+    pub action: PrintScreenAction,
+    // Something the OS routes PrintScreen to first, e.g. Snipping Tool. It
+    // can win even when our registration succeeds.
+    pub conflict: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PrintScreenPressed {
+    pub action: PrintScreenAction,
+}
+
+fn shortcut() -> Shortcut {
+    Shortcut::new(None, Code::PrintScreen)
+}
+
+fn current_action(app: &AppHandle) -> PrintScreenAction {
+    app.state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.print_screen_action)
+        .unwrap_or_default()
+}
+
+// The overlay and the capture itself run in the main window, like the
+// tray and deep-link captures.
+fn on_pressed(app: &AppHandle) {
+    let action = current_action(app);
+    tracing::info!(?action, "PrintScreen pressed");
+    tray::show_main_window(app);
+    emit_event(app, GraviaEvent::PrintScreenPressed(PrintScreenPressed { action })).ok();
+}
+
+fn register(app: &AppHandle) -> Result<(), ShortcutError> {
+    let shortcuts = app.global_shortcut();
+    if shortcuts.is_registered(shortcut()) {
+        return Ok(());
+    }
+    shortcuts
+        .on_shortcut(shortcut(), |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                on_pressed(app);
+            }
+        })
+        .map_err(|e| {
+            tracing::warn!(error = %e, "failed to register PrintScreen");
+            ShortcutError::InUse { holder: platform::likely_holder() }
+        })
+}
+
+// Hands the key straight back to the OS; no restart needed.
+fn unregister(app: &AppHandle) -> Result<(), ShortcutError> {
+    let shortcuts = app.global_shortcut();
+    if !shortcuts.is_registered(shortcut()) {
+        return Ok(());
+    }
+    shortcuts.unregister(shortcut()).map_err(|e| ShortcutError::Failed(e.to_string()))
+}
+
+fn status(app: &AppHandle) -> PrintScreenStatus {
+    PrintScreenStatus {
+        bound: app.global_shortcut().is_registered(shortcut()),
+        action: current_action(app),
+        conflict: platform::likely_holder(),
+    }
+}
+
+// Applies the saved binding at startup. A failure only gets logged; the
+// settings screen shows it through `get_print_screen_binding`.
+pub fn start(app: &AppHandle) {
+    let enabled = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.bind_print_screen)
+        .unwrap_or(false);
+    if enabled {
+        if let Err(e) = register(app) {
+            tracing::warn!(error = %e, "PrintScreen binding not applied");
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_print_screen_binding(app: AppHandle) -> PrintScreenStatus {
+    status(&app)
+}
+
+#[tauri::command]
+pub fn set_print_screen_binding(
+    app: AppHandle,
+    state: State<'_, SharedSettings>,
+    enabled: bool,
+    action: Option<PrintScreenAction>,
+) -> CommandResult<PrintScreenStatus> {
+    if enabled {
+        register(&app)?;
+    } else {
+        unregister(&app)?;
+    }
+    {
+        let mut current = state.0.lock()?;
+        let mut updated = current.clone();
+        updated.bind_print_screen = enabled;
+        if let Some(action) = action {
+            updated.print_screen_action = action;
+        }
+        settings::save(&app, &updated).map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+        *current = updated;
+    }
+    Ok(status(&app))
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::core::w;
+    use windows::Win32::Foundation::ERROR_SUCCESS;
+    use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+    // "Use the Print screen key to open screen capture" in Settings, on by
+    // default in recent Windows 11 builds.
+    fn snipping_tool_owns_key() -> bool {
+        let mut data = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                w!("Control Panel\\Keyboard"),
+                w!("PrintScreenKeyForSnippingEnabled"),
+                RRF_RT_REG_DWORD,
+                None,
+                Some(&mut data as *mut u32 as *mut _),
+                Some(&mut size),
+            )
+        };
+        status == ERROR_SUCCESS && data == 1
+    }
+
+    pub fn likely_holder() -> Option<String> {
+        snipping_tool_owns_key().then(|| "Snipping Tool".to_string())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    pub fn likely_holder() -> Option<String> {
+        None
+    }
+}
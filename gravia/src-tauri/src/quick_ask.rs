@@ -0,0 +1,128 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, PhysicalPosition, State, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::tray;
+
+pub const QUICK_ASK_LABEL: &str = "quick-ask";
+const WIDTH: f64 = 560.0;
+const HEIGHT: f64 = 140.0;
+// Gap between the cursor and the popup's top-left corner.
+const CURSOR_OFFSET: f64 = 16.0;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct QuickAskPrefill {
+    pub prefill: Option<String>,
+    pub capture_id: Option<String>,
+}
+
+// Prefill for a popup whose page hasn't loaded yet; a freshly created window
+// would miss the `quick-ask-prefill` event, so it pulls this on mount.
+#[derive(Default)]
+pub struct PendingQuickAsk(Mutex<Option<QuickAskPrefill>>);
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct QuickAskSubmission {
+    pub query: String,
+    pub capture_id: Option<String>,
+}
+
+// Opens the quick-ask popup near the cursor, reusing the existing window if
+// it's already up so repeated hotkey presses don't stack popups.
+#[tauri::command]
+pub fn open_quick_ask(
+    app: AppHandle,
+    prefill: Option<String>,
+    capture_id: Option<String>,
+) -> CommandResult<()> {
+    let window = match app.get_webview_window(QUICK_ASK_LABEL) {
+        Some(w) => w,
+        None => WebviewWindowBuilder::new(&app, QUICK_ASK_LABEL, WebviewUrl::App("quick-ask".into()))
+            .title("Quick ask")
+            .inner_size(WIDTH, HEIGHT)
+            .decorations(false)
+            .resizable(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .visible(false)
+            .build()
+            ?,
+    };
+
+    if let Err(e) = position_near_cursor(&app, &window) {
+        tracing::warn!(error = %e, "failed to position quick-ask window");
+    }
+    let payload = QuickAskPrefill { prefill, capture_id };
+    if let Ok(mut pending) = app.state::<PendingQuickAsk>().0.lock() {
+        *pending = Some(payload.clone());
+    }
+    window.show()?;
+    window.set_focus()?;
+    emit_event(&window, GraviaEvent::QuickAskPrefill(payload))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn take_quick_ask_prefill(state: State<'_, PendingQuickAsk>) -> CommandResult<Option<QuickAskPrefill>> {
+    let mut pending = state.0.lock()?;
+    Ok(pending.take())
+}
+
+// The popup is transient: clicking anywhere else dismisses it.
+pub fn handle_focus_changed(window: &tauri::Window, focused: bool) {
+    if !focused && window.label() == QUICK_ASK_LABEL {
+        let _ = window.close();
+    }
+}
+
+fn position_near_cursor(app: &AppHandle, window: &WebviewWindow) -> tauri::Result<()> {
+    let cursor = app.cursor_position()?;
+    let mut x = cursor.x + CURSOR_OFFSET;
+    let mut y = cursor.y + CURSOR_OFFSET;
+
+    // Keep the popup fully on the monitor the cursor is on.
+    if let Some(monitor) = app.monitor_from_point(cursor.x, cursor.y)? {
+        let scale = monitor.scale_factor();
+        let origin = monitor.position();
+        let size = monitor.size();
+        let right = f64::from(origin.x) + f64::from(size.width);
+        let bottom = f64::from(origin.y) + f64::from(size.height);
+        x = x.min(right - WIDTH * scale).max(f64::from(origin.x));
+        y = y.min(bottom - HEIGHT * scale).max(f64::from(origin.y));
+    }
+    window.set_position(PhysicalPosition::new(x, y))
+}
+
+#[tauri::command]
+pub fn close_quick_ask(app: AppHandle) {
+    if let Some(window) = app.get_webview_window(QUICK_ASK_LABEL) {
+        let _ = window.close();
+    }
+}
+
+// Hands the popup's query to the main window, which runs it through the
+// normal chat path. `focus_main` brings the main window forward for replies
+// that need more room than the popup.
+#[tauri::command]
+pub fn submit_quick_ask(
+    app: AppHandle,
+    query: String,
+    capture_id: Option<String>,
+    focus_main: Option<bool>,
+) -> CommandResult<()> {
+    let main = app
+        .get_webview_window("main")
+        .ok_or_else(|| GraviaError::NotFound("main window".into()))?;
+    emit_event(&main, GraviaEvent::QuickAskSubmitted(QuickAskSubmission { query, capture_id }))
+        ?;
+    close_quick_ask(app.clone());
+    if focus_main.unwrap_or(false) {
+        tray::show_main_window(&app);
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::capture::{RedactRect, RedactStyle};
+use crate::cursor_overlay::DesktopRect;
+
+// Side of a pixelation block in frame pixels; well above the height of
+// body text, so none survives.
+const PIXEL_BLOCK: usize = 32;
+
+// Covers `regions` on a `width` x `height` RGBA frame showing `covers`.
+// Regions are in physical desktop pixels; the parts outside the frame are
+// dropped. Returns how many regions touched the frame.
+pub fn apply(
+    rgba: &mut [u8],
+    width: u32,
+    height: u32,
+    covers: DesktopRect,
+    regions: &[RedactRect],
+    style: RedactStyle,
+) -> usize {
+    if covers.width <= 0.0 || covers.height <= 0.0 {
+        return 0;
+    }
+    // Layout units are physical pixels or points depending on the platform;
+    // the frame itself is in physical pixels either way.
+    let origin_x = (covers.x * width as f64 / covers.width).round() as i64;
+    let origin_y = (covers.y * height as f64 / covers.height).round() as i64;
+    let mut applied = 0;
+    for region in regions {
+        let left = (region.x as i64 - origin_x).clamp(0, width as i64) as usize;
+        let top = (region.y as i64 - origin_y).clamp(0, height as i64) as usize;
+        let right = (region.x as i64 + region.width as i64 - origin_x).clamp(0, width as i64) as usize;
+        let bottom = (region.y as i64 + region.height as i64 - origin_y).clamp(0, height as i64) as usize;
+        if left >= right || top >= bottom {
+            continue;
+        }
+        match style {
+            RedactStyle::Fill => fill(rgba, width as usize, [left, top, right, bottom]),
+            RedactStyle::Pixelate => pixelate(rgba, width as usize, [left, top, right, bottom]),
+        }
+        applied += 1;
+    }
+    applied
+}
+
+fn fill(rgba: &mut [u8], width: usize, [left, top, right, bottom]: [usize; 4]) {
+    for y in top..bottom {
+        for px in rgba[(y * width + left) * 4..(y * width + right) * 4].chunks_exact_mut(4) {
+            px.copy_from_slice(&[0, 0, 0, 255]);
+        }
+    }
+}
+
+// Replaces each block with its average color. Blocks are aligned to the
+// region, and the last row and column may be narrower.
+fn pixelate(rgba: &mut [u8], width: usize, [left, top, right, bottom]: [usize; 4]) {
+    for block_top in (top..bottom).step_by(PIXEL_BLOCK) {
+        let block_bottom = (block_top + PIXEL_BLOCK).min(bottom);
+        for block_left in (left..right).step_by(PIXEL_BLOCK) {
+            let block_right = (block_left + PIXEL_BLOCK).min(right);
+            let mut sum = [0u64; 3];
+            for y in block_top..block_bottom {
+                for px in rgba[(y * width + block_left) * 4..(y * width + block_right) * 4].chunks_exact(4) {
+                    for c in 0..3 {
+                        sum[c] += px[c] as u64;
+                    }
+                }
+            }
+            let count = ((block_bottom - block_top) * (block_right - block_left)) as u64;
+            let average = [(sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8, 255];
+            for y in block_top..block_bottom {
+                for px in rgba[(y * width + block_left) * 4..(y * width + block_right) * 4].chunks_exact_mut(4) {
+                    px.copy_from_slice(&average);
+                }
+            }
+        }
+    }
+}
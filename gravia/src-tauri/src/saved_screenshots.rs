@@ -0,0 +1,299 @@
+#[cfg(feature = "capture")]
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "capture")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+use ts_rs::TS;
+
+use crate::capture::CaptureError;
+#[cfg(feature = "capture")]
+use crate::capture::CaptureFormat;
+use crate::command_stats;
+use crate::error::CommandResult;
+use crate::settings::SharedSettings;
+
+// Retention runs at startup and then this often.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+// Only files named after a capture id are saved or swept here, which keeps
+// the sweep off screenshots `save_screenshot` put in the same folder.
+const ID_PREFIX: &str = "cap-";
+
+// The sidecar written next to a saved capture's PNG.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct SavedScreenshot {
+    pub id: String,
+    pub captured_at: DateTime<Utc>,
+    // The classify query it was taken for; `None` for captures asked for
+    // directly.
+    pub query: Option<String>,
+    pub monitor: String,
+    pub screen_id: Option<u32>,
+    pub width: u32,
+    pub height: u32,
+    // Of the PNG, filled in when listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub bytes: u64,
+}
+
+enum Job {
+    #[cfg(feature = "capture")]
+    Save { sidecar: SavedScreenshot, base64: Arc<String>, format: CaptureFormat },
+    // Only fills in a missing query; a capture reused from the cache keeps
+    // the one it was taken for.
+    SetQuery { id: String, query: String },
+}
+
+// Feeds the writer task. Jobs run one at a time in the order queued, so a
+// query always lands after the save it belongs to.
+#[derive(Default)]
+pub struct SavedScreenshots(Mutex<Option<mpsc::UnboundedSender<Job>>>);
+
+pub fn dir(app: &AppHandle) -> Result<PathBuf, CaptureError> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join("screenshots"))
+        .map_err(|e| CaptureError::Failed(e.to_string()))
+}
+
+// Starts the writer and the retention sweep. The sweep runs whether or not
+// saving is on, so turning it off still lets old captures age out.
+pub fn start(app: &AppHandle) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    if let Ok(mut sender) = app.state::<SavedScreenshots>().0.lock() {
+        *sender = Some(tx);
+    }
+    let writer_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(job) = rx.recv().await {
+            let app = writer_app.clone();
+            let written = tauri::async_runtime::spawn_blocking(move || run(&app, job)).await;
+            if let Err(e) = written.map_err(anyhow::Error::from).and_then(|r| r) {
+                tracing::warn!(error = %e, "failed to save capture to disk");
+            }
+        }
+    });
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let sweep_app = app.clone();
+            match tauri::async_runtime::spawn_blocking(move || sweep(&sweep_app)).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(removed)) => tracing::info!(removed, "old saved captures removed"),
+                Ok(Err(e)) => tracing::warn!(error = %e, "saved capture retention sweep failed"),
+                Err(e) => tracing::warn!(error = %e, "saved capture retention sweep failed"),
+            }
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.state::<SharedSettings>().0.lock().is_ok_and(|s| s.save_screenshots)
+}
+
+fn queue(app: &AppHandle, job: Job) {
+    if let Ok(sender) = app.state::<SavedScreenshots>().0.lock() {
+        if let Some(sender) = sender.as_ref() {
+            sender.send(job).ok();
+        }
+    }
+}
+
+// Queues a fresh capture to be written, when the `save_screenshots`
+// setting is on. Returns straight away; the decode and write happen on the
+// writer task.
+#[cfg(feature = "capture")]
+#[allow(clippy::too_many_arguments)]
+pub fn save(
+    app: &AppHandle,
+    id: &str,
+    base64: &Arc<String>,
+    format: CaptureFormat,
+    monitor: &str,
+    screen_id: Option<u32>,
+    width: u32,
+    height: u32,
+) {
+    if !enabled(app) {
+        return;
+    }
+    let sidecar = SavedScreenshot {
+        id: id.to_string(),
+        captured_at: Utc::now(),
+        query: None,
+        monitor: monitor.to_string(),
+        screen_id,
+        width,
+        height,
+        path: None,
+        bytes: 0,
+    };
+    queue(app, Job::Save { sidecar, base64: base64.clone(), format });
+}
+
+// Records the classify query a saved capture was taken for.
+pub fn set_query(app: &AppHandle, id: &str, query: &str) {
+    if enabled(app) {
+        queue(app, Job::SetQuery { id: id.to_string(), query: query.to_string() });
+    }
+}
+
+fn run(app: &AppHandle, job: Job) -> anyhow::Result<()> {
+    let dir = dir(app)?;
+    match job {
+        #[cfg(feature = "capture")]
+        Job::Save { sidecar, base64, format } => {
+            std::fs::create_dir_all(&dir)?;
+            let bytes = base64::engine::general_purpose::STANDARD.decode(base64.as_bytes())?;
+            let png = if format == CaptureFormat::Png {
+                bytes
+            } else {
+                let mut png = Vec::new();
+                image::load_from_memory(&bytes)?
+                    .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+                png
+            };
+            // The sidecar goes last, so a listed capture always has its image.
+            std::fs::write(dir.join(format!("{}.png", sidecar.id)), png)?;
+            std::fs::write(dir.join(format!("{}.json", sidecar.id)), serde_json::to_vec_pretty(&sidecar)?)?;
+        }
+        Job::SetQuery { id, query } => {
+            let path = dir.join(format!("{id}.json"));
+            let Ok(raw) = std::fs::read(&path) else { return Ok(()) };
+            let mut sidecar: SavedScreenshot = serde_json::from_slice(&raw)?;
+            if sidecar.query.is_none() {
+                sidecar.query = Some(query);
+                std::fs::write(&path, serde_json::to_vec_pretty(&sidecar)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A saved capture's files, grouped by id.
+#[derive(Default)]
+struct Entry {
+    files: Vec<PathBuf>,
+    bytes: u64,
+    modified: Option<SystemTime>,
+}
+
+fn entries(dir: &Path) -> std::io::Result<HashMap<String, Entry>> {
+    let mut entries = HashMap::<String, Entry>::new();
+    let listing = match std::fs::read_dir(dir) {
+        Ok(listing) => listing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+        Err(e) => return Err(e),
+    };
+    for file in listing.flatten() {
+        let path = file.path();
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()).filter(|s| s.starts_with(ID_PREFIX)) else {
+            continue;
+        };
+        let Ok(meta) = file.metadata() else { continue };
+        let entry = entries.entry(id.to_string()).or_default();
+        entry.bytes += meta.len();
+        entry.modified = entry.modified.max(meta.modified().ok());
+        entry.files.push(path);
+    }
+    Ok(entries)
+}
+
+// Deletes saved captures older than the retention age, then the oldest
+// until the rest fit the size limit. Returns how many went.
+fn sweep(app: &AppHandle) -> anyhow::Result<usize> {
+    let (max_age_days, max_mb) = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| (s.saved_screenshot_retention_days, s.saved_screenshot_retention_mb))
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    let mut entries: Vec<Entry> = entries(&dir(app)?)?.into_values().collect();
+    // Newest first.
+    entries.sort_by(|a, b| b.modified.cmp(&a.modified));
+    let max_age = Duration::from_secs(max_age_days as u64 * 24 * 60 * 60);
+    let max_bytes = max_mb.saturating_mul(1024 * 1024);
+    let now = SystemTime::now();
+    let (mut kept_bytes, mut removed) = (0u64, 0);
+    for entry in entries {
+        let age = entry.modified.and_then(|m| now.duration_since(m).ok()).unwrap_or_default();
+        let too_old = max_age_days > 0 && age > max_age;
+        let too_big = max_mb > 0 && kept_bytes + entry.bytes > max_bytes;
+        if !too_old && !too_big {
+            kept_bytes += entry.bytes;
+            continue;
+        }
+        for file in &entry.files {
+            if let Err(e) = std::fs::remove_file(file) {
+                tracing::warn!(path = %file.display(), error = %e, "failed to remove saved capture");
+            }
+        }
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+fn valid_id(id: &str) -> bool {
+    id.starts_with(ID_PREFIX) && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// Captures saved to disk with the `save_screenshots` setting, newest first.
+#[tauri::command]
+pub async fn list_saved_screenshots(app: AppHandle) -> CommandResult<Vec<SavedScreenshot>> {
+    command_stats::track(&app, "list_saved_screenshots", async {
+        let dir = dir(&app)?;
+        let saved = tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<Vec<SavedScreenshot>> {
+            let mut saved = Vec::new();
+            for id in entries(&dir)?.into_keys() {
+                let Ok(raw) = std::fs::read(dir.join(format!("{id}.json"))) else { continue };
+                let Ok(mut sidecar) = serde_json::from_slice::<SavedScreenshot>(&raw) else { continue };
+                let png = dir.join(format!("{id}.png"));
+                sidecar.bytes = std::fs::metadata(&png).map(|m| m.len()).unwrap_or(0);
+                sidecar.path = Some(png);
+                saved.push(sidecar);
+            }
+            saved.sort_by(|a, b| b.captured_at.cmp(&a.captured_at));
+            Ok(saved)
+        })
+        .await??;
+        Ok(saved)
+    })
+    .await
+}
+
+// Deletes a saved capture's image and sidecar. The capture stays in the
+// in-memory history if it's still there.
+#[tauri::command]
+pub async fn delete_saved_screenshot(app: AppHandle, id: String) -> CommandResult<()> {
+    command_stats::track(&app, "delete_saved_screenshot", async {
+        if !valid_id(&id) {
+            return Err(CaptureError::CaptureNotFound(id).into());
+        }
+        let dir = dir(&app)?;
+        let mut removed = false;
+        for extension in ["png", "json"] {
+            match std::fs::remove_file(dir.join(format!("{id}.{extension}"))) {
+                Ok(()) => removed = true,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(CaptureError::Failed(e.to_string()).into()),
+            }
+        }
+        if !removed {
+            return Err(CaptureError::CaptureNotFound(id).into());
+        }
+        tracing::info!(capture_id = %id, "saved capture deleted");
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,81 @@
+use serde::Serialize;
+use ts_rs::TS;
+
+// Pixels sampled at most; averages and shares settle long before this.
+#[cfg(feature = "capture")]
+const MAX_SAMPLES: usize = 65_536;
+#[cfg(feature = "capture")]
+const DOMINANT_COLORS: usize = 5;
+// Share of the image one color needs for it to count as blank, e.g. a
+// monitor that's off or a solid lock screen.
+#[cfg(feature = "capture")]
+const BLANK_SHARE: f32 = 0.97;
+// Mean luminance under which an image counts as blank however varied.
+#[cfg(feature = "capture")]
+const BLANK_LUMINANCE: f32 = 0.02;
+
+// A color that covers a good part of an image.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct DominantColor {
+    // `#rrggbb`.
+    pub hex: String,
+    // Of the image, 0-1.
+    pub share: f32,
+}
+
+// Brightness and colors of a capture, from the `analyze` option.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ScreenAnalysis {
+    // 0 (black) to 1 (white).
+    pub mean_luminance: f32,
+    // Most common first.
+    pub dominant_colors: Vec<DominantColor>,
+    // Nearly one solid color or nearly black, so there's nothing to see.
+    pub is_mostly_blank: bool,
+}
+
+// Analyzes an RGBA image from an even spread of its pixels. Colors are
+// bucketed at 4 bits per channel and each reported as its bucket's average,
+// so gradients and antialiasing fold into the color they belong to.
+#[cfg(feature = "capture")]
+pub fn analyze(rgba: &[u8], width: u32, height: u32) -> ScreenAnalysis {
+    let pixels = (width as usize * height as usize).min(rgba.len() / 4);
+    let step = pixels.div_ceil(MAX_SAMPLES).max(1);
+    // Per bucket: sample count and channel sums.
+    let mut buckets = vec![(0u32, [0u64; 3]); 4096];
+    let (mut luminance, mut samples) = (0f64, 0u32);
+    for px in rgba[..pixels * 4].chunks_exact(4).step_by(step) {
+        let (r, g, b) = (px[0], px[1], px[2]);
+        // Rec. 709 luma, as for grayscale captures.
+        luminance += (r as f64 * 0.2126 + g as f64 * 0.7152 + b as f64 * 0.0722) / 255.0;
+        samples += 1;
+        let bucket = &mut buckets[(r as usize >> 4) << 8 | (g as usize >> 4) << 4 | b as usize >> 4];
+        bucket.0 += 1;
+        for (sum, c) in bucket.1.iter_mut().zip([r, g, b]) {
+            *sum += c as u64;
+        }
+    }
+    if samples == 0 {
+        return ScreenAnalysis { mean_luminance: 0.0, dominant_colors: Vec::new(), is_mostly_blank: true };
+    }
+
+    buckets.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+    let dominant_colors: Vec<DominantColor> = buckets
+        .iter()
+        .take(DOMINANT_COLORS)
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, sums)| {
+            let [r, g, b] = sums.map(|sum| (sum / *count as u64) as u8);
+            DominantColor { hex: format!("#{r:02x}{g:02x}{b:02x}"), share: *count as f32 / samples as f32 }
+        })
+        .collect();
+    let mean_luminance = (luminance / samples as f64) as f32;
+    let top_share = dominant_colors.first().map_or(0.0, |c| c.share);
+    ScreenAnalysis {
+        mean_luminance,
+        is_mostly_blank: top_share >= BLANK_SHARE || mean_luminance < BLANK_LUMINANCE,
+        dominant_colors,
+    }
+}
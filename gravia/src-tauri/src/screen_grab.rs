@@ -0,0 +1,1689 @@
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+
+use crate::annotate;
+use crate::base64_stream::Base64Writer;
+use crate::capture::{
+    self, BurstCapture, BurstFrame, ByteBudget, CaptureBackend, CaptureError, CaptureFormat, CaptureOptions,
+    CapturePerf, CaptureTarget, CaptureTiming, CaptureTimings, CapturedScreen, DisplayScale, Downscale, PixelFormat,
+    PngCompression, PngFilter, RedactRect, RedactStyle, ScreenArea, ScreenCapture, ScreenInfo, WindowHandling,
+};
+use crate::capture_history::{CaptureHistory, NewCapture};
+use crate::cursor_overlay::{self, Cursor, DesktopRect};
+use crate::displays;
+use crate::events::{emit_event, GraviaEvent};
+use crate::pixel_format;
+use crate::redaction;
+use crate::saved_screenshots;
+use crate::screen_analysis::{self, ScreenAnalysis};
+use crate::screen_hash::{self, ScreenHashes};
+use crate::settings::SharedSettings;
+use crate::window_focus::{self, WindowFocus};
+use crate::window_hider::{self, HideError, HideWait};
+use crate::window_list;
+
+// Upper bound on waiting for a hidden window to leave the screen; slow
+// compositors take well under this.
+const MAX_HIDE_WAIT: Duration = Duration::from_millis(300);
+// Longest settle time accepted after the window reports hidden.
+const MAX_HIDE_DELAY_MS: u64 = 2000;
+// Long edge of the preview from the `thumbnail` option, and its JPEG
+// quality.
+const THUMBNAIL_EDGE: u32 = 256;
+const THUMBNAIL_QUALITY: u8 = 70;
+// Fitting a `max_bytes` budget: JPEG quality drops this much a try down to
+// the minimum, then each try shrinks the image to at most this share of the
+// last size, never below the minimum long edge. Encodings can't always be
+// made to fit, e.g. noise, so the tries are capped too.
+const BUDGET_QUALITY_STEP: u8 = 15;
+const BUDGET_MIN_QUALITY: u8 = 40;
+const BUDGET_MAX_STEP: f64 = 0.9;
+const BUDGET_MIN_EDGE: u32 = 320;
+const MAX_BUDGET_ATTEMPTS: u32 = 12;
+// The screenshots crate hands over RGBA on every platform; on Windows it
+// swaps the GDI bitmap's BGRA itself.
+const STANDARD_SOURCE_FORMAT: PixelFormat = PixelFormat::Rgba;
+
+pub async fn grab_hiding_window(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+) -> Result<CapturedScreen, CaptureError> {
+    let started = Instant::now();
+    let app = window.app_handle().clone();
+    let grab_options = options.clone();
+    let timeout = capture_timeout(&app);
+    let (result, window_handling, waited) = with_window_out_of_shot(window, options, cancel, || {
+        // Grabbing and encoding take long enough to stall other commands
+        // if run on the async runtime itself.
+        let worker = tauri::async_runtime::spawn_blocking(move || {
+            let perf = app.state::<CapturePerf>();
+            let cursor = cursor_for_capture(&app, &grab_options);
+            let blocked = blocked_windows(&grab_options);
+            let (parallel, backend) = (parallel_processing(&app), preferred_backend(&app));
+            grab_and_encode(&perf, target, &grab_options, cursor.as_ref(), &blocked, parallel, backend)
+        });
+        within_timeout(timeout, worker)
+    })
+    .await?;
+    let Encoded {
+        base64,
+        content_hash,
+        monitor,
+        backend,
+        width,
+        height,
+        downscale,
+        thumbnail,
+        hash,
+        redacted,
+        analysis,
+        scale,
+        budget,
+        timings,
+    } = result?
+        .map_err(capture_error)
+        .map_err(|e| match e {
+            CaptureError::ScreenNotFound(id) => displays::screen_missing(window.app_handle(), id),
+            e => e,
+        })?;
+    // Shared with the history rather than copied.
+    let base64 = Arc::new(base64);
+    let thumbnail = thumbnail.map(Arc::new);
+    let unchanged = window.state::<ScreenHashes>().observe(target, hash, options.unchanged_distance());
+    let id = window.state::<CaptureHistory>().record(NewCapture {
+        base64: base64.clone(),
+        content_hash: content_hash.clone(),
+        monitor: monitor.clone(),
+        backend,
+        target,
+        options: options.clone(),
+        width,
+        height,
+        downscale,
+        thumbnail: thumbnail.clone(),
+        window_handling,
+        redacted,
+        analysis: analysis.clone(),
+        scale,
+        budget,
+    });
+    saved_screenshots::save(
+        window.app_handle(),
+        &id,
+        &base64,
+        options.format,
+        &monitor,
+        target_screen(target),
+        width,
+        height,
+    );
+    capture::release_pool_when_idle(window.app_handle());
+    tracing::info!(
+        capture_id = %id,
+        monitor = %monitor,
+        ?backend,
+        ?window_handling,
+        unchanged,
+        redacted,
+        format = ?options.format,
+        bytes = base64.len(),
+        "screen captured"
+    );
+    let timings = report_timings(window, &id, &monitor, timings, waited, started);
+    Ok(CapturedScreen {
+        id,
+        base64,
+        content_hash,
+        monitor,
+        backend,
+        format: options.format,
+        downscale,
+        thumbnail,
+        window_handling,
+        adjustment: None,
+        from_cache: false,
+        unchanged,
+        redacted,
+        analysis,
+        scale,
+        budget,
+        timings: Some(timings),
+    })
+}
+
+// Captures every display behind a single hide of the window. Each display
+// is recorded in the history on its own.
+pub async fn grab_all_hiding_window(
+    window: &tauri::Window,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+) -> Result<Vec<ScreenCapture>, CaptureError> {
+    let started = Instant::now();
+    let app = window.app_handle().clone();
+    let grab_options = options.clone();
+    let timeout = capture_timeout(&app);
+    let (result, window_handling, waited) = with_window_out_of_shot(window, options, cancel, || {
+        let worker = tauri::async_runtime::spawn_blocking(move || {
+            let perf = app.state::<CapturePerf>();
+            let cursor = cursor_for_capture(&app, &grab_options);
+            let blocked = blocked_windows(&grab_options);
+            grab_and_encode_all(&perf, &grab_options, cursor.as_ref(), &blocked, parallel_processing(&app))
+        });
+        within_timeout(timeout, worker)
+    })
+    .await?;
+    let grabs = result?.map_err(capture_error)?;
+    let history = window.state::<CaptureHistory>();
+    let hashes = window.state::<ScreenHashes>();
+    let captures = grabs
+        .into_iter()
+        .map(|grab| {
+            let mut capture = ScreenCapture {
+                screen_id: grab.screen_id,
+                width: grab.width,
+                height: grab.height,
+                capture_id: None,
+                base64: None,
+                format: options.format,
+                bytes: None,
+                content_hash: None,
+                backend: None,
+                downscale: None,
+                thumbnail_base64: None,
+                window_handling,
+                adjustment: None,
+                unchanged: false,
+                redacted: 0,
+                analysis: None,
+                scale: None,
+                budget: None,
+                timings: None,
+                error: None,
+                error_code: None,
+            };
+            match grab.result {
+                Ok(Encoded {
+                    base64,
+                    content_hash,
+                    monitor,
+                    backend,
+                    width,
+                    height,
+                    downscale,
+                    thumbnail,
+                    hash,
+                    redacted,
+                    analysis,
+                    scale,
+                    budget,
+                    timings,
+                }) => {
+                    let base64 = Arc::new(base64);
+                    let thumbnail = thumbnail.map(Arc::new);
+                    let target = CaptureTarget::Screen(grab.screen_id);
+                    capture.unchanged = hashes.observe(target, hash, options.unchanged_distance());
+                    let id = history.record(NewCapture {
+                        base64: base64.clone(),
+                        content_hash: content_hash.clone(),
+                        monitor: monitor.clone(),
+                        backend,
+                        target,
+                        options: options.clone(),
+                        width,
+                        height,
+                        downscale,
+                        thumbnail: thumbnail.clone(),
+                        window_handling,
+                        redacted,
+                        analysis: analysis.clone(),
+                        scale,
+                        budget,
+                    });
+                    saved_screenshots::save(
+                        window.app_handle(),
+                        &id,
+                        &base64,
+                        options.format,
+                        &monitor,
+                        Some(grab.screen_id),
+                        width,
+                        height,
+                    );
+                    tracing::info!(capture_id = %id, screen_id = grab.screen_id, bytes = base64.len(), "screen captured");
+                    capture.timings = Some(report_timings(window, &id, &monitor, timings, waited, started));
+                    capture.capture_id = Some(id);
+                    capture.bytes = Some(capture::decoded_len(&base64));
+                    capture.content_hash = Some(content_hash);
+                    capture.backend = Some(backend);
+                    capture.downscale = downscale;
+                    capture.thumbnail_base64 = thumbnail;
+                    capture.redacted = redacted;
+                    capture.analysis = analysis;
+                    capture.scale = scale;
+                    capture.budget = budget;
+                    capture.base64 = Some(base64);
+                }
+                Err(e) => {
+                    tracing::warn!(screen_id = grab.screen_id, error = %e, "screen capture failed");
+                    let e = capture_error(e);
+                    capture.error_code = Some(e.code().to_string());
+                    capture.error = Some(e.to_string());
+                }
+            }
+            capture
+        })
+        .collect();
+    capture::release_pool_when_idle(window.app_handle());
+    Ok(captures)
+}
+
+// One frame of a burst, as grabbed.
+struct BurstGrab {
+    index: u8,
+    captured_at: chrono::DateTime<chrono::Utc>,
+    offset: Duration,
+    result: anyhow::Result<Encoded>,
+}
+
+// Captures `frames` frames of `target`, `interval` apart, behind a single
+// hide of the window. Each frame is encoded as soon as it's grabbed, so a
+// burst holds encoded images rather than raw ones, then recorded in the
+// history on its own. A frame that can't start within `MAX_BURST_DURATION`
+// of the first is left out, along with the rest.
+pub async fn grab_burst_hiding_window(
+    window: &tauri::Window,
+    target: CaptureTarget,
+    frames: u8,
+    interval: Duration,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+) -> Result<BurstCapture, CaptureError> {
+    let app = window.app_handle().clone();
+    let grab_options = options.clone();
+    // Also cancelled on a timeout, so an abandoned burst stops at its next
+    // frame.
+    let grab_cancel = cancel.child_token();
+    let abandon = grab_cancel.clone();
+    // The burst runs for up to `MAX_BURST_DURATION` before its last grab.
+    let timeout = capture_timeout(&app).map(|t| t + capture::MAX_BURST_DURATION);
+    let (result, window_handling, _) = with_window_out_of_shot(window, options, cancel, || {
+        let worker = tauri::async_runtime::spawn_blocking(move || {
+            let perf = app.state::<CapturePerf>();
+            let (parallel, backend) = (parallel_processing(&app), preferred_backend(&app));
+            let started = Instant::now();
+            let mut grabs = Vec::with_capacity(frames as usize);
+            for index in 0..frames {
+                if grab_cancel.is_cancelled() {
+                    break;
+                }
+                let due = interval * index as u32;
+                if let Some(wait) = due.checked_sub(started.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+                let offset = started.elapsed();
+                if offset > capture::MAX_BURST_DURATION {
+                    break;
+                }
+                // Looked up each frame; the pointer and windows may move.
+                let cursor = cursor_for_capture(&app, &grab_options);
+                let blocked = blocked_windows(&grab_options);
+                let captured_at = chrono::Utc::now();
+                let result =
+                    grab_and_encode(&perf, target, &grab_options, cursor.as_ref(), &blocked, parallel, backend);
+                grabs.push(BurstGrab { index, captured_at, offset, result });
+            }
+            grabs
+        });
+        within_timeout(timeout, worker)
+    })
+    .await?;
+    let grabs = result.inspect_err(|_| abandon.cancel())?;
+    let truncated = grabs.len() < frames as usize;
+    let history = window.state::<CaptureHistory>();
+    let hashes = window.state::<ScreenHashes>();
+    let frames = grabs
+        .into_iter()
+        .map(|grab| {
+            let mut frame = BurstFrame {
+                index: grab.index,
+                captured_at: grab.captured_at,
+                offset_ms: ms(grab.offset),
+                capture_id: None,
+                base64: None,
+                format: options.format,
+                bytes: None,
+                content_hash: None,
+                downscale: None,
+                adjustment: None,
+                unchanged: false,
+                redacted: 0,
+                error: None,
+                error_code: None,
+            };
+            match grab.result {
+                Ok(Encoded {
+                    base64,
+                    content_hash,
+                    monitor,
+                    backend,
+                    width,
+                    height,
+                    downscale,
+                    hash,
+                    redacted,
+                    scale,
+                    budget,
+                    ..
+                }) => {
+                    let base64 = Arc::new(base64);
+                    frame.unchanged = hashes.observe(target, hash, options.unchanged_distance());
+                    let id = history.record(NewCapture {
+                        base64: base64.clone(),
+                        content_hash: content_hash.clone(),
+                        monitor: monitor.clone(),
+                        backend,
+                        target,
+                        options: options.clone(),
+                        width,
+                        height,
+                        downscale,
+                        thumbnail: None,
+                        window_handling,
+                        redacted,
+                        analysis: None,
+                        scale,
+                        budget,
+                    });
+                    saved_screenshots::save(
+                        window.app_handle(),
+                        &id,
+                        &base64,
+                        options.format,
+                        &monitor,
+                        target_screen(target),
+                        width,
+                        height,
+                    );
+                    tracing::info!(capture_id = %id, index = grab.index, bytes = base64.len(), "burst frame captured");
+                    frame.capture_id = Some(id);
+                    frame.bytes = Some(capture::decoded_len(&base64));
+                    frame.content_hash = Some(content_hash);
+                    frame.downscale = downscale;
+                    frame.redacted = redacted;
+                    frame.base64 = Some(base64);
+                }
+                Err(e) => {
+                    tracing::warn!(index = grab.index, error = %e, "burst frame failed");
+                    let e = capture_error(e);
+                    frame.error_code = Some(e.code().to_string());
+                    frame.error = Some(e.to_string());
+                }
+            }
+            frame
+        })
+        .collect();
+    capture::release_pool_when_idle(window.app_handle());
+    Ok(BurstCapture { frames, window_handling, truncated })
+}
+
+// The grab pipeline's typed error where it raised one.
+fn capture_error(e: anyhow::Error) -> CaptureError {
+    e.downcast::<CaptureError>().unwrap_or_else(|e| CaptureError::Failed(e.to_string()))
+}
+
+// Runs `grab` with our window kept out of the capture, unless the options
+// or settings say to leave it alone. Where the OS can exclude the window
+// from captures it stays on screen; otherwise it's hidden for the grab.
+async fn with_window_out_of_shot<F, Fut, T>(
+    window: &tauri::Window,
+    options: &CaptureOptions,
+    cancel: &CancellationToken,
+    grab: F,
+) -> Result<(T, WindowHandling, HideWait), CaptureError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let (hide, settle) = hide_settings(window.app_handle(), options);
+    let (result, hide_wait, handling) = if !hide {
+        if cancel.is_cancelled() {
+            return Err(CaptureError::Cancelled);
+        }
+        (grab().await, HideWait::default(), WindowHandling::Untouched)
+    } else {
+        match window_hider::with_window_excluded(window, cancel, grab).await {
+            Ok(excluded) => {
+                let (result, waited) = excluded.ok_or(CaptureError::Cancelled)?;
+                (result, waited, WindowHandling::Excluded)
+            }
+            Err(grab) => {
+                let focus = window.state::<WindowFocus>();
+                let _focus_guard = window_focus::suppress_for_capture(&focus);
+                let (result, waited) = window_hider::with_window_hidden(window, MAX_HIDE_WAIT, settle, cancel, grab)
+                    .await
+                    .map_err(|e| match e {
+                        HideError::Hide(e) => CaptureError::WindowHideFailed(e),
+                        HideError::Show(e) => CaptureError::WindowRestoreFailed(e),
+                    })?
+                    .ok_or(CaptureError::Cancelled)?;
+                (result, waited, WindowHandling::Hidden)
+            }
+        }
+    };
+    window.state::<CapturePerf>().last_hide_wait_ms.store(ms(hide_wait.total()), Ordering::Relaxed);
+    // The grab itself can't be interrupted; drop what it produced.
+    if cancel.is_cancelled() {
+        return Err(CaptureError::Cancelled);
+    }
+    Ok((result, handling, hide_wait))
+}
+
+// Completes a capture's timings with the parts measured around the grab and
+// sends them out as `capture-timing`.
+fn report_timings(
+    window: &tauri::Window,
+    capture_id: &str,
+    monitor: &str,
+    timings: CaptureTimings,
+    waited: HideWait,
+    started: Instant,
+) -> CaptureTimings {
+    let timings = CaptureTimings {
+        hide_ms: ms(waited.hide),
+        settle_ms: ms(waited.settle),
+        total_ms: ms(started.elapsed()),
+        ..timings
+    };
+    let timing = CaptureTiming { capture_id: capture_id.to_string(), monitor: monitor.to_string(), timings };
+    emit_event(window, GraviaEvent::CaptureTiming(timing)).ok();
+    timings
+}
+
+fn ms(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+// Whether to hide the window, and how long to let it settle once it reports
+// hidden. Options win over the settings.
+fn hide_settings(app: &AppHandle, options: &CaptureOptions) -> (bool, Duration) {
+    let (hide, delay_ms) = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| (s.hide_window_for_capture, s.capture_hide_delay_ms))
+        .unwrap_or((true, window_hider::COMPOSITOR_FRAME.as_millis() as u64));
+    let hide = options.hide_window.unwrap_or(hide);
+    let delay_ms = options.hide_delay_ms.unwrap_or(delay_ms).min(MAX_HIDE_DELAY_MS);
+    (hide, Duration::from_millis(delay_ms))
+}
+
+// The `capture_timeout_ms` setting; `None` when it's off.
+fn capture_timeout(app: &AppHandle) -> Option<Duration> {
+    let timeout_ms = app.state::<SharedSettings>().0.lock().map(|s| s.capture_timeout_ms).unwrap_or(0);
+    (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms))
+}
+
+// Waits on a capture worker for up to `timeout`. A misbehaving display
+// driver can hold a grab for many seconds, and the grab itself can't be
+// interrupted, so a worker that runs over is left to finish on its own.
+// Whatever it produces is dropped with its join handle: the history, the
+// screen hashes and saved captures are only written once it's awaited here.
+async fn within_timeout<T>(
+    timeout: Option<Duration>,
+    worker: impl Future<Output = Result<T, tauri::Error>>,
+) -> Result<T, CaptureError> {
+    let joined = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, worker).await.map_err(|_| {
+            tracing::warn!(timeout_ms = ms(timeout), "capture timed out, abandoning its worker");
+            CaptureError::Timeout { timeout_ms: ms(timeout) }
+        })?,
+        None => worker.await,
+    };
+    joined.map_err(|e| CaptureError::Failed(e.to_string()))
+}
+
+fn preferred_backend(app: &AppHandle) -> CaptureBackend {
+    app.state::<SharedSettings>().0.lock().map(|s| s.capture_backend).unwrap_or_default()
+}
+
+pub fn list_screens() -> anyhow::Result<Vec<ScreenInfo>> {
+    let mut screens: Vec<ScreenInfo> = screenshots::Screen::all()?
+        .into_iter()
+        .map(|s| {
+            let info = s.display_info;
+            ScreenInfo {
+                id: info.id,
+                x: info.x,
+                y: info.y,
+                width: info.width,
+                height: info.height,
+                scale_factor: info.scale_factor,
+                is_primary: info.is_primary,
+            }
+        })
+        .collect();
+    screens.sort_by_key(|s| (s.x, s.y, s.id));
+    Ok(screens)
+}
+
+// The display under the mouse cursor, else the primary one. `None` when
+// neither can be told, which captures the first screen.
+pub fn active_screen_id(app: &AppHandle) -> Option<u32> {
+    let screens = list_screens().ok()?;
+    let cursor = app.cursor_position();
+    if let Err(e) = &cursor {
+        // Wayland doesn't hand out the global cursor position.
+        tracing::debug!(error = %e, "cursor position unavailable, using the primary screen");
+    }
+    let under_cursor = cursor.ok().and_then(|p| {
+        let (x, y) = capture::cursor_layout_position(&screens, (p.x, p.y));
+        screen_at(&screens, x, y)
+    });
+    under_cursor.or_else(|| screens.iter().find(|s| s.is_primary)).map(|s| s.id)
+}
+
+// The mouse pointer as it looks right now, when `options` asks for it in
+// the capture.
+fn cursor_for_capture(app: &AppHandle, options: &CaptureOptions) -> Option<Cursor> {
+    if !options.include_cursor {
+        return None;
+    }
+    let position = match app.cursor_position() {
+        Ok(position) => position,
+        Err(e) => {
+            tracing::debug!(error = %e, "cursor position unavailable, leaving it out of the capture");
+            return None;
+        }
+    };
+    // The display under the pointer sets its size, whichever is captured.
+    let screens = list_screens().ok()?;
+    let (x, y) = capture::cursor_layout_position(&screens, (position.x, position.y));
+    cursor_overlay::locate(x, y, screen_at(&screens, x, y)?)
+}
+
+// Where windows of the options' blocked apps are on screen right now, in
+// physical desktop pixels. Listed right before the grab so they match what
+// it shows.
+fn blocked_windows(options: &CaptureOptions) -> Vec<RedactRect> {
+    if options.blocked_apps.is_empty() {
+        return Vec::new();
+    }
+    let windows = match window_list::list_windows() {
+        Ok(windows) => windows,
+        Err(e) => {
+            tracing::warn!(error = %e, "windows could not be listed, capture blocklist not applied");
+            return Vec::new();
+        }
+    };
+    windows
+        .iter()
+        .filter(|w| window_list::belongs_to_any(w, &options.blocked_apps))
+        .map(|w| RedactRect { x: w.bounds.x, y: w.bounds.y, width: w.bounds.width, height: w.bounds.height })
+        .collect()
+}
+
+// The display holding a point in layout units. Screen bounds are half-open,
+// so a point on the edge between two displays belongs to the right or lower
+// one. Origins can be negative for displays left of or above the primary.
+fn screen_at(screens: &[ScreenInfo], x: f64, y: f64) -> Option<&ScreenInfo> {
+    screens.iter().find(|s| {
+        let (left, top) = (s.x as f64, s.y as f64);
+        x >= left && x < left + s.width as f64 && y >= top && y < top + s.height as f64
+    })
+}
+
+// Row-parallel pixel work only pays off with a few cores to spare.
+fn parallel_processing(app: &AppHandle) -> bool {
+    let enabled = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.parallel_capture_processing)
+        .unwrap_or(true);
+    enabled && std::thread::available_parallelism().is_ok_and(|n| n.get() > 2)
+}
+
+// An RGBA frame, top row first, from whichever grabber ran.
+struct GrabbedFrame {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    monitor: String,
+    backend: CaptureBackend,
+    // Capture memory held once the frame is ready, before encoding.
+    held_bytes: usize,
+    // The part of the desktop shown; `None` for a single window.
+    covers: Option<DesktopRect>,
+    // Channel order the grabber delivered, before conversion to RGBA.
+    source_format: PixelFormat,
+    // Of the display shown; `None` for a window or several displays.
+    scale_factor: Option<f32>,
+    // Spent turning the grabber's pixels into RGBA, part of the grab.
+    convert: Duration,
+}
+
+impl GrabbedFrame {
+    fn draw_cursor(&mut self, cursor: Option<&Cursor>) {
+        if let (Some(cursor), Some(covers)) = (cursor, self.covers) {
+            cursor_overlay::draw(&mut self.rgba, self.width, self.height, covers, cursor);
+        }
+    }
+
+    // Returns how many of `regions` fell on the frame. A window capture
+    // can't be placed on the desktop, so it's left alone.
+    fn redact(&mut self, regions: &[RedactRect], style: RedactStyle) -> usize {
+        match self.covers {
+            Some(covers) => redaction::apply(&mut self.rgba, self.width, self.height, covers, regions, style),
+            None => 0,
+        }
+    }
+}
+
+// Grabs a screen and returns it base64-encoded in the requested format,
+// with a display label and the backend that produced it. The encoder writes
+// straight into the base64 string, so the image bytes never exist as a
+// separate buffer.
+fn grab_and_encode(
+    perf: &CapturePerf,
+    target: CaptureTarget,
+    options: &CaptureOptions,
+    cursor: Option<&Cursor>,
+    blocked: &[RedactRect],
+    parallel: bool,
+    backend: CaptureBackend,
+) -> anyhow::Result<Encoded> {
+    ensure_screen_recording_allowed()?;
+    let started = Instant::now();
+    // Desktop duplication only covers the primary display, so anything else
+    // goes through the standard grabber.
+    let frame = match target {
+        CaptureTarget::Default => grab_primary(perf, backend, parallel)?,
+        CaptureTarget::Screen(id) => recorded(perf, grab_standard(Some(id), parallel))?,
+        CaptureTarget::VirtualDesktop => recorded(perf, grab_virtual_desktop(perf, parallel))?,
+        CaptureTarget::Region(area) => recorded(perf, grab_area(area, parallel))?,
+        CaptureTarget::Window(id) => {
+            let (width, height, mut rgba) = window_list::capture(id)?;
+            let converting = Instant::now();
+            pixel_format::to_rgba_in_place(&mut rgba, PixelFormat::Bgrx, parallel);
+            let convert = converting.elapsed();
+            let monitor = format!("window ({width}x{height})");
+            GrabbedFrame {
+                width,
+                height,
+                held_bytes: rgba.len(),
+                rgba,
+                monitor,
+                backend: CaptureBackend::Standard,
+                covers: None,
+                source_format: PixelFormat::Bgrx,
+                scale_factor: None,
+                convert,
+            }
+        }
+    };
+    perf.last_grab_ms.store(ms(started.elapsed()), Ordering::Relaxed);
+    encode_frame(perf, frame, target_screen(target), started.elapsed(), options, cursor, blocked)
+}
+
+// The display a target sits on, when it's a single one.
+fn target_screen(target: CaptureTarget) -> Option<u32> {
+    match target {
+        CaptureTarget::Screen(id) => Some(id),
+        CaptureTarget::Region(area) => Some(area.screen_id),
+        _ => None,
+    }
+}
+
+// macOS hands back just the wallpaper rather than failing when screen
+// recording hasn't been allowed, so that's checked before grabbing.
+#[cfg(target_os = "macos")]
+fn ensure_screen_recording_allowed() -> Result<(), CaptureError> {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGPreflightScreenCaptureAccess() -> bool;
+    }
+    // SAFETY: no arguments; only reads the app's privacy permission.
+    if unsafe { CGPreflightScreenCaptureAccess() } {
+        Ok(())
+    } else {
+        Err(CaptureError::PermissionDenied)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn ensure_screen_recording_allowed() -> Result<(), CaptureError> {
+    Ok(())
+}
+
+// A frame encoded and ready to hand out.
+struct Encoded {
+    base64: String,
+    // SHA-256 of the encoded image bytes, in hex.
+    content_hash: String,
+    monitor: String,
+    backend: CaptureBackend,
+    // Of the encoded image, after any downscale.
+    width: u32,
+    height: u32,
+    downscale: Option<Downscale>,
+    // Base64 JPEG preview, when asked for.
+    thumbnail: Option<String>,
+    // Perceptual hash of the image, for spotting an unchanged screen.
+    hash: u64,
+    // Redaction rectangles that fell on the image.
+    redacted: usize,
+    analysis: Option<ScreenAnalysis>,
+    scale: Option<DisplayScale>,
+    // Set when `max_bytes` made it encode again.
+    budget: Option<ByteBudget>,
+    // The stages timed during grab and encode; the rest is filled in later.
+    timings: CaptureTimings,
+}
+
+// One display's result from a capture of every display.
+struct ScreenGrab {
+    screen_id: u32,
+    width: u32,
+    height: u32,
+    result: anyhow::Result<Encoded>,
+}
+
+// Grabs and encodes every display in turn. Only failing to list the
+// displays fails the whole call; a display that can't be captured gets an
+// error entry.
+fn grab_and_encode_all(
+    perf: &CapturePerf,
+    options: &CaptureOptions,
+    cursor: Option<&Cursor>,
+    blocked: &[RedactRect],
+    parallel: bool,
+) -> anyhow::Result<Vec<ScreenGrab>> {
+    ensure_screen_recording_allowed()?;
+    let screens = screenshots::Screen::all()?;
+    if screens.is_empty() {
+        return Err(CaptureError::NoDisplay.into());
+    }
+    Ok(screens
+        .into_iter()
+        .map(|screen| {
+            let info = screen.display_info;
+            let started = Instant::now();
+            let result = grab_screen(screen, parallel).and_then(|frame| {
+                perf.last_grab_ms.store(ms(started.elapsed()), Ordering::Relaxed);
+                encode_frame(perf, frame, Some(info.id), started.elapsed(), options, cursor, blocked)
+            });
+            ScreenGrab { screen_id: info.id, width: info.width, height: info.height, result }
+        })
+        .collect())
+}
+
+// Processes and encodes a grabbed frame. `grabbed` is how long the grab took,
+// conversion included. `screen_id` is the display it's from, if known.
+fn encode_frame(
+    perf: &CapturePerf,
+    mut frame: GrabbedFrame,
+    screen_id: Option<u32>,
+    grabbed: Duration,
+    options: &CaptureOptions,
+    cursor: Option<&Cursor>,
+    blocked: &[RedactRect],
+) -> anyhow::Result<Encoded> {
+    if let Ok(mut last) = perf.last_backend.lock() {
+        *last = frame.backend;
+    }
+    if let Ok(mut last) = perf.last_source_format.lock() {
+        *last = frame.source_format;
+    }
+    let started = Instant::now();
+    // Drawn at full size so it scales down with the rest of the frame.
+    frame.draw_cursor(cursor);
+    // At full size, where the rectangles are in the frame's own pixels, and
+    // before anything is made from the pixels.
+    let redacted = frame.redact(&options.redact_regions, options.redact_style)
+        // Blocklisted windows are always blacked out, whatever the style.
+        + frame.redact(blocked, RedactStyle::Fill);
+    let scale = frame.scale_factor.map(|f| DisplayScale::new(f, frame.width, frame.height));
+    let (mut frame, downscale) = downscale_frame(perf, frame, options, scale)?;
+    // Taken from the frame as sent, before its channels are packed below.
+    let thumbnail = if options.thumbnail { Some(encode_thumbnail(&frame, options.grayscale)?) } else { None };
+    let hash = screen_hash::dhash(&frame.rgba, frame.width, frame.height);
+    let analysis = options.analyze.then(|| screen_analysis::analyze(&frame.rgba, frame.width, frame.height));
+    // After the hash, which a new time on every capture would throw off.
+    if options.stamp {
+        let source = screen_id.map_or_else(|| frame.monitor.clone(), |id| format!("screen {id}"));
+        let taken = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+        let text = format!("{taken}  {source}  Gravia {}", env!("CARGO_PKG_VERSION"));
+        let corner = options.stamp_corner.unwrap_or_default();
+        annotate::stamp(&mut frame.rgba, frame.width, frame.height, &text, corner);
+    }
+
+    // Sized from the last capture; encodings of the same screen rarely
+    // differ much.
+    let mut writer = Base64Writer::with_capacity(perf.last_png_len.load(Ordering::Relaxed));
+    let (width, height) = (frame.width, frame.height);
+    let settings = EncodeSettings::new(options);
+    let processed = started.elapsed();
+    // Encoding scrambles the pixels, and a budget may need them again.
+    let pristine = options.max_bytes.map(|_| {
+        let mut copy = perf.pool.take(frame.rgba.len());
+        copy.extend_from_slice(&frame.rgba);
+        copy
+    });
+    encode_rgba(&mut writer, &mut frame.rgba, width, height, settings)?;
+    let mut encoded = started.elapsed() - processed;
+    let peak = frame.held_bytes + pristine.as_ref().map_or(0, Vec::len) + writer.capacity();
+    perf.pool.give(frame.rgba);
+    perf.last_png_len.store(writer.input_len(), Ordering::Relaxed);
+    perf.last_peak_bytes.store(peak, Ordering::Relaxed);
+    perf.last_encode_ms.store(ms(started.elapsed()), Ordering::Relaxed);
+    perf.captures.fetch_add(1, Ordering::Relaxed);
+
+    let finishing = Instant::now();
+    let busy = writer.busy();
+    let (mut base64, mut content_hash) = writer.finish_with_hash();
+    let base64_time = busy + finishing.elapsed();
+
+    let (mut width, mut height, mut downscale, mut budget) = (width, height, downscale, None);
+    if let Some(pixels) = pristine {
+        let fitting = Instant::now();
+        let max_bytes = options.max_bytes.unwrap_or(usize::MAX);
+        if base64.len() > max_bytes {
+            let fitted = fit_budget(&pixels, width, height, options, max_bytes, base64.len())?;
+            // Sizes are kept relative to the grab, so positions still map
+            // back to the screen.
+            let (original_width, original_height) =
+                downscale.map_or((width, height), |d| (d.original_width, d.original_height));
+            if (fitted.width, fitted.height) != (width, height) {
+                downscale = Some(Downscale {
+                    original_width,
+                    original_height,
+                    width: fitted.width,
+                    height: fitted.height,
+                });
+            }
+            (width, height, budget) = (fitted.width, fitted.height, Some(fitted.budget));
+            if let Some((smaller, hash)) = fitted.base64 {
+                (base64, content_hash) = (smaller, hash);
+            }
+        }
+        perf.pool.give(pixels);
+        encoded += fitting.elapsed();
+    }
+    let timings = CaptureTimings {
+        grab_ms: ms(grabbed.saturating_sub(frame.convert)),
+        convert_ms: ms(frame.convert),
+        process_ms: ms(processed),
+        encode_ms: ms(encoded.saturating_sub(busy)),
+        base64_ms: ms(base64_time),
+        ..CaptureTimings::default()
+    };
+    Ok(Encoded {
+        base64,
+        content_hash,
+        monitor: frame.monitor,
+        backend: frame.backend,
+        width,
+        height,
+        downscale,
+        thumbnail,
+        hash,
+        redacted,
+        analysis,
+        scale,
+        budget,
+        timings,
+    })
+}
+
+// The outcome of fitting an encoding into a byte budget.
+pub struct BudgetFit {
+    // With its content hash; `None` when no try came out smaller than the
+    // first encoding.
+    pub base64: Option<(String, String)>,
+    pub width: u32,
+    pub height: u32,
+    pub budget: ByteBudget,
+}
+
+// Encodes `rgba` again until its base64 fits `max_bytes`, given that the
+// first encoding came to `first_len`. JPEG drops quality first; then every
+// format shrinks by about the square root of how far over it still is, as
+// the size goes with the pixel count. Stops at the first fit, the minimum
+// edge or the last try, and hands back the smallest encoding made. Also what
+// `payload_guard` shrinks oversized IPC payloads with.
+pub fn fit_budget(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    options: &CaptureOptions,
+    max_bytes: usize,
+    first_len: usize,
+) -> anyhow::Result<BudgetFit> {
+    let jpeg = options.format == CaptureFormat::Jpeg;
+    let settings = EncodeSettings::new(options);
+    let min_scale = (BUDGET_MIN_EDGE as f64 / width.max(height) as f64).min(1.0);
+    let (mut quality, mut scale, mut len) = (options.jpeg_quality(), 1.0_f64, first_len);
+    let mut fit = BudgetFit {
+        base64: None,
+        width,
+        height,
+        budget: ByteBudget {
+            max_bytes,
+            original_bytes: first_len,
+            bytes: first_len,
+            scale: 1.0,
+            quality: jpeg.then_some(quality),
+            attempts: 0,
+            fits: false,
+        },
+    };
+    for attempt in 1..=MAX_BUDGET_ATTEMPTS {
+        if jpeg && quality > BUDGET_MIN_QUALITY {
+            quality = quality.saturating_sub(BUDGET_QUALITY_STEP).max(BUDGET_MIN_QUALITY);
+        } else if scale > min_scale {
+            let step = (max_bytes as f64 / len as f64).sqrt().min(BUDGET_MAX_STEP);
+            scale = (scale * step).max(min_scale);
+        } else {
+            break;
+        }
+        let w = ((width as f64 * scale).round() as u32).max(1);
+        let h = ((height as f64 * scale).round() as u32).max(1);
+        let mut pixels =
+            if (w, h) == (width, height) { rgba.to_vec() } else { resize_rgba(rgba, width, height, w, h)? };
+        let mut writer = Base64Writer::with_capacity(max_bytes / 4 * 3);
+        encode_rgba(&mut writer, &mut pixels, w, h, EncodeSettings { jpeg_quality: quality, ..settings })?;
+        let (base64, hash) = writer.finish_with_hash();
+        len = base64.len();
+        fit.budget.attempts = attempt;
+        if len < fit.budget.bytes {
+            fit.budget.bytes = len;
+            fit.budget.scale = scale as f32;
+            fit.budget.quality = jpeg.then_some(quality);
+            (fit.width, fit.height, fit.base64) = (w, h, Some((base64, hash)));
+        }
+        if len <= max_bytes {
+            fit.budget.fits = true;
+            break;
+        }
+    }
+    tracing::info!(
+        max_bytes,
+        original_bytes = first_len,
+        bytes = fit.budget.bytes,
+        attempts = fit.budget.attempts,
+        fits = fit.budget.fits,
+        "capture encoded to fit its byte budget"
+    );
+    Ok(fit)
+}
+
+// The capture options `encode_rgba` goes by.
+#[derive(Debug, Clone, Copy)]
+struct EncodeSettings {
+    format: CaptureFormat,
+    grayscale: bool,
+    jpeg_quality: u8,
+    png_compression: PngCompression,
+    png_filter: PngFilter,
+}
+
+impl EncodeSettings {
+    fn new(options: &CaptureOptions) -> Self {
+        Self {
+            format: options.format,
+            grayscale: options.grayscale,
+            jpeg_quality: options.jpeg_quality(),
+            png_compression: options.png_compression.unwrap_or_default(),
+            png_filter: options.png_filter.unwrap_or_default(),
+        }
+    }
+}
+
+// Encodes RGBA pixels into `writer`. Channels are packed down in place
+// rather than into a copy of the frame, so `rgba` is left scrambled.
+fn encode_rgba(
+    writer: &mut impl std::io::Write,
+    rgba: &mut [u8],
+    width: u32,
+    height: u32,
+    settings: EncodeSettings,
+) -> anyhow::Result<()> {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::codecs::png::{self, PngEncoder};
+    use image::codecs::webp::WebPEncoder;
+    use image::{ExtendedColorType, ImageEncoder};
+
+    let EncodeSettings { format, grayscale, jpeg_quality, .. } = settings;
+    let pixels = rgba.len() / 4;
+    let (channels, color) = if grayscale {
+        // Rec. 709 luma, the weights `image` uses for its own conversion.
+        for i in 0..pixels {
+            let px = &rgba[i * 4..i * 4 + 3];
+            let luma = (px[0] as u32 * 2126 + px[1] as u32 * 7152 + px[2] as u32 * 722) / 10000;
+            rgba[i] = luma as u8;
+        }
+        (1, ExtendedColorType::L8)
+    } else if format == CaptureFormat::Jpeg {
+        // JPEG has no alpha.
+        for i in 0..pixels {
+            rgba.copy_within(i * 4..i * 4 + 3, i * 3);
+        }
+        (3, ExtendedColorType::Rgb8)
+    } else {
+        (4, ExtendedColorType::Rgba8)
+    };
+    let data = &rgba[..pixels * channels];
+    let written = match format {
+        CaptureFormat::Png => {
+            let compression = match settings.png_compression {
+                PngCompression::Fast => png::CompressionType::Fast,
+                PngCompression::Default => png::CompressionType::Default,
+                PngCompression::Best => png::CompressionType::Best,
+            };
+            let filter = match settings.png_filter {
+                PngFilter::None => png::FilterType::NoFilter,
+                PngFilter::Sub => png::FilterType::Sub,
+                PngFilter::Up => png::FilterType::Up,
+                PngFilter::Avg => png::FilterType::Avg,
+                PngFilter::Paeth => png::FilterType::Paeth,
+                PngFilter::Adaptive => png::FilterType::Adaptive,
+            };
+            PngEncoder::new_with_quality(writer, compression, filter).write_image(data, width, height, color)
+        }
+        CaptureFormat::Jpeg => {
+            JpegEncoder::new_with_quality(writer, jpeg_quality).write_image(data, width, height, color)
+        }
+        // The `image` crate only writes lossless WebP.
+        CaptureFormat::Webp => WebPEncoder::new_lossless(writer).write_image(data, width, height, color),
+    };
+    written.map_err(|e| CaptureError::EncodeFailed(e.to_string()))?;
+    Ok(())
+}
+
+// A small JPEG preview of `frame`, resized the same way as a downscale.
+fn encode_thumbnail(frame: &GrabbedFrame, grayscale: bool) -> anyhow::Result<String> {
+    let (width, height) = fit_within(frame.width, frame.height, THUMBNAIL_EDGE, THUMBNAIL_EDGE)
+        .unwrap_or((frame.width, frame.height));
+    let mut rgba = resize_rgba(&frame.rgba, frame.width, frame.height, width, height)?;
+    let mut writer = Base64Writer::with_capacity(0);
+    let settings = EncodeSettings {
+        format: CaptureFormat::Jpeg,
+        grayscale,
+        jpeg_quality: THUMBNAIL_QUALITY,
+        png_compression: PngCompression::default(),
+        png_filter: PngFilter::default(),
+    };
+    encode_rgba(&mut writer, &mut rgba, width, height, settings)?;
+    Ok(writer.finish())
+}
+
+// Scales a frame down to fit `max_width` x `max_height`, keeping its aspect
+// ratio. A frame that already fits is passed through untouched.
+fn downscale_frame(
+    perf: &CapturePerf,
+    mut frame: GrabbedFrame,
+    options: &CaptureOptions,
+    scale: Option<DisplayScale>,
+) -> anyhow::Result<(GrabbedFrame, Option<Downscale>)> {
+    let (original_width, original_height) = (frame.width, frame.height);
+    let mut max_width = options.max_width.unwrap_or(u32::MAX);
+    let mut max_height = options.max_height.unwrap_or(u32::MAX);
+    if let Some(scale) = scale.filter(|_| options.logical_resolution) {
+        max_width = max_width.min(scale.logical_width);
+        max_height = max_height.min(scale.logical_height);
+    }
+    let Some((width, height)) = fit_within(original_width, original_height, max_width, max_height) else {
+        return Ok((frame, None));
+    };
+
+    let scaled = resize_rgba(&frame.rgba, original_width, original_height, width, height)?;
+    tracing::debug!(original_width, original_height, width, height, "capture downscaled");
+    let held_bytes = frame.held_bytes + scaled.len();
+    perf.pool.give(std::mem::replace(&mut frame.rgba, scaled));
+    let frame = GrabbedFrame { width, height, held_bytes, ..frame };
+    Ok((frame, Some(Downscale { original_width, original_height, width, height })))
+}
+
+// The largest size within `max_width` x `max_height` with the aspect ratio
+// of `width` x `height`, or `None` if that already fits.
+fn fit_within(width: u32, height: u32, max_width: u32, max_height: u32) -> Option<(u32, u32)> {
+    if width <= max_width && height <= max_height {
+        return None;
+    }
+    let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+    Some((
+        ((width as f64 * scale).round() as u32).clamp(1, max_width),
+        ((height as f64 * scale).round() as u32).clamp(1, max_height),
+    ))
+}
+
+fn resize_rgba(rgba: &[u8], width: u32, height: u32, to_width: u32, to_height: u32) -> anyhow::Result<Vec<u8>> {
+    use image::imageops::{self, FilterType};
+    use image::{ImageBuffer, Rgba};
+
+    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, rgba)
+        .ok_or_else(|| CaptureError::ConversionFailed(format!("frame is smaller than {width}x{height}")))?;
+    Ok(imageops::resize(&image, to_width, to_height, FilterType::Triangle).into_raw())
+}
+
+// A way of grabbing the primary display.
+trait Grabber {
+    fn backend(&self) -> CaptureBackend;
+    fn grab(&self, perf: &CapturePerf, parallel: bool) -> anyhow::Result<GrabbedFrame>;
+}
+
+struct StandardGrabber;
+struct DxgiGrabber;
+
+impl Grabber for StandardGrabber {
+    fn backend(&self) -> CaptureBackend {
+        CaptureBackend::Standard
+    }
+
+    fn grab(&self, _perf: &CapturePerf, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+        grab_standard(None, parallel)
+    }
+}
+
+impl Grabber for DxgiGrabber {
+    fn backend(&self) -> CaptureBackend {
+        CaptureBackend::Dxgi
+    }
+
+    fn grab(&self, perf: &CapturePerf, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+        grab_dxgi(perf, parallel)
+    }
+}
+
+// Grabs the primary display with the preferred backend, falling back to
+// the other when it fails, e.g. while protected content is on screen.
+// Fails with the preferred backend's error when neither delivers.
+fn grab_primary(perf: &CapturePerf, preferred: CaptureBackend, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    let grabbers: [&dyn Grabber; 2] = match preferred {
+        CaptureBackend::Standard => [&StandardGrabber, &DxgiGrabber],
+        CaptureBackend::Auto | CaptureBackend::Dxgi => [&DxgiGrabber, &StandardGrabber],
+    };
+    let mut first_error = None;
+    for grabber in grabbers {
+        // Off Windows there's nothing to fall back to.
+        if grabber.backend() == CaptureBackend::Dxgi && !cfg!(target_os = "windows") {
+            continue;
+        }
+        match grabber.grab(perf, parallel) {
+            Ok(frame) => {
+                perf.record_backend(grabber.backend(), Ok(()));
+                return Ok(frame);
+            }
+            Err(e) => {
+                tracing::debug!(backend = ?grabber.backend(), error = %e, "capture backend failed");
+                perf.record_backend(grabber.backend(), Err(e.to_string()));
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| anyhow::anyhow!("no capture backend is available")))
+}
+
+// Records a standard grab of something other than the primary display.
+fn recorded(perf: &CapturePerf, result: anyhow::Result<GrabbedFrame>) -> anyhow::Result<GrabbedFrame> {
+    perf.record_backend(CaptureBackend::Standard, result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+    result
+}
+
+// The primary display's scale factor, which desktop duplication doesn't
+// report.
+#[cfg(target_os = "windows")]
+fn primary_scale_factor() -> Option<f32> {
+    let screens = screenshots::Screen::all().ok()?;
+    screens.into_iter().find(|s| s.display_info.is_primary).map(|s| s.display_info.scale_factor)
+}
+
+// Desktop duplication of the primary display.
+#[cfg(target_os = "windows")]
+fn grab_dxgi(perf: &CapturePerf, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    let frame = crate::dxgi_capture::grab(&perf.pool, parallel)?;
+    Ok(GrabbedFrame {
+        width: frame.width,
+        height: frame.height,
+        held_bytes: frame.rgba.len(),
+        rgba: frame.rgba,
+        monitor: frame.monitor,
+        backend: CaptureBackend::Dxgi,
+        // The primary display, whose top-left is the desktop origin.
+        covers: Some(DesktopRect { x: 0.0, y: 0.0, width: frame.width as f64, height: frame.height as f64 }),
+        source_format: frame.source_format,
+        scale_factor: primary_scale_factor(),
+        convert: frame.convert,
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn grab_dxgi(_perf: &CapturePerf, _parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    anyhow::bail!("desktop duplication is only available on Windows")
+}
+
+// The screenshots crate's grabber; GDI on Windows. Takes the primary screen,
+// or the first one listed, unless `screen_id` names one. Enumeration order
+// changes as displays come and go, so the first one isn't a stable choice.
+fn grab_standard(screen_id: Option<u32>, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    use screenshots::Screen;
+
+    let mut screens = Screen::all()?;
+    let screen = match screen_id {
+        Some(id) => screens
+            .into_iter()
+            .find(|s| s.display_info.id == id)
+            .ok_or(CaptureError::ScreenNotFound(id))?,
+        None => {
+            let primary = screens.iter().position(|s| s.display_info.is_primary).unwrap_or(0);
+            if primary >= screens.len() {
+                return Err(CaptureError::NoDisplay.into());
+            }
+            screens.swap_remove(primary)
+        }
+    };
+    grab_screen(screen, parallel)
+}
+
+// Grabs every display and lays them out on one canvas by their desktop
+// positions, which may be negative. Displays report their bounds in layout
+// units that are physical pixels on some platforms and scaled points on
+// others, so the canvas uses the densest display's pixels per unit and any
+// display grabbed at a different density is resized to match.
+fn grab_virtual_desktop(perf: &CapturePerf, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    use image::imageops::FilterType;
+    use image::{ImageBuffer, Rgba};
+
+    let mut grabbed = Vec::new();
+    for screen in screenshots::Screen::all()? {
+        let info = screen.display_info;
+        match grab_screen(screen, parallel) {
+            Ok(frame) => grabbed.push((info, frame)),
+            // Left black rather than failing the whole desktop.
+            Err(e) => tracing::warn!(screen_id = info.id, error = %e, "screen left out of desktop capture"),
+        }
+    }
+    if grabbed.is_empty() {
+        return Err(CaptureError::NoDisplay.into());
+    }
+    let convert = grabbed.iter().map(|(_, frame)| frame.convert).sum();
+
+    let scale = grabbed
+        .iter()
+        .map(|(info, frame)| frame.width as f64 / info.width.max(1) as f64)
+        .fold(1.0_f64, f64::max);
+    let left = grabbed.iter().map(|(i, _)| i.x as i64).min().unwrap_or(0);
+    let top = grabbed.iter().map(|(i, _)| i.y as i64).min().unwrap_or(0);
+    let right = grabbed.iter().map(|(i, _)| i.x as i64 + i.width as i64).max().unwrap_or(0);
+    let bottom = grabbed.iter().map(|(i, _)| i.y as i64 + i.height as i64).max().unwrap_or(0);
+    let to_px = |units: i64| (units as f64 * scale).round() as usize;
+    let (width, height) = (to_px(right - left), to_px(bottom - top));
+
+    let mut canvas = perf.pool.take(width * height * 4);
+    canvas.resize(width * height * 4, 0);
+    // Opaque black.
+    canvas.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    let mut largest_frame = 0;
+    for (info, frame) in grabbed {
+        largest_frame = largest_frame.max(frame.held_bytes);
+        let (w, h) = (to_px(info.width as i64), to_px(info.height as i64));
+        let rgba = if (frame.width as usize, frame.height as usize) == (w, h) {
+            frame.rgba
+        } else {
+            let image = ImageBuffer::<Rgba<u8>, _>::from_raw(frame.width, frame.height, frame.rgba)
+                .ok_or_else(|| CaptureError::ConversionFailed("frame size mismatch".into()))?;
+            image::imageops::resize(&image, w as u32, h as u32, FilterType::Triangle).into_raw()
+        };
+        let (x, y) = (to_px(info.x as i64 - left), to_px(info.y as i64 - top));
+        let copy = w.min(width.saturating_sub(x)) * 4;
+        for (row, src) in rgba.chunks_exact(w * 4).enumerate().take(height.saturating_sub(y)) {
+            let start = ((y + row) * width + x) * 4;
+            canvas[start..start + copy].copy_from_slice(&src[..copy]);
+        }
+        perf.pool.give(rgba);
+    }
+
+    Ok(GrabbedFrame {
+        width: width as u32,
+        height: height as u32,
+        monitor: format!("virtual desktop ({width}x{height})"),
+        backend: CaptureBackend::Standard,
+        held_bytes: canvas.len() + largest_frame,
+        rgba: canvas,
+        covers: Some(DesktopRect {
+            x: left as f64,
+            y: top as f64,
+            width: (right - left) as f64,
+            height: (bottom - top) as f64,
+        }),
+        source_format: STANDARD_SOURCE_FORMAT,
+        // Displays of different densities can't share one.
+        scale_factor: None,
+        convert,
+    })
+}
+
+fn grab_screen(screen: screenshots::Screen, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    let info = screen.display_info;
+    let monitor = if info.is_primary {
+        format!("primary display ({}x{})", info.width, info.height)
+    } else {
+        format!("display {} ({}x{})", info.id, info.width, info.height)
+    };
+    let covers = DesktopRect {
+        x: info.x as f64,
+        y: info.y as f64,
+        width: info.width as f64,
+        height: info.height as f64,
+    };
+    let shot = screen.capture()?;
+    Ok(frame_from_shot(shot, monitor, covers, info.scale_factor, parallel))
+}
+
+// Grabs `area` of its display, in that display's layout units.
+fn grab_area(area: ScreenArea, parallel: bool) -> anyhow::Result<GrabbedFrame> {
+    let screen = screenshots::Screen::all()?
+        .into_iter()
+        .find(|s| s.display_info.id == area.screen_id)
+        .ok_or(CaptureError::ScreenNotFound(area.screen_id))?;
+    let monitor = format!("region of display {} ({}x{})", area.screen_id, area.width, area.height);
+    let info = screen.display_info;
+    let covers = DesktopRect {
+        x: (info.x + area.x) as f64,
+        y: (info.y + area.y) as f64,
+        width: area.width as f64,
+        height: area.height as f64,
+    };
+    let shot = screen.capture_area(area.x, area.y, area.width, area.height)?;
+    Ok(frame_from_shot(shot, monitor, covers, info.scale_factor, parallel))
+}
+
+fn frame_from_shot(
+    shot: screenshots::image::RgbaImage,
+    monitor: String,
+    covers: DesktopRect,
+    scale_factor: f32,
+    parallel: bool,
+) -> GrabbedFrame {
+    let (width, height) = shot.dimensions();
+    let mut rgba = shot.into_raw();
+    let started = Instant::now();
+    pixel_format::to_rgba_in_place(&mut rgba, STANDARD_SOURCE_FORMAT, parallel);
+    let convert = started.elapsed();
+    GrabbedFrame {
+        width,
+        height,
+        held_bytes: rgba.len(),
+        rgba,
+        monitor,
+        backend: CaptureBackend::Standard,
+        covers: Some(covers),
+        source_format: STANDARD_SOURCE_FORMAT,
+        scale_factor: Some(scale_factor),
+        convert,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Random RGBA, which no encoder can do much with.
+    fn noise(width: u32, height: u32) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..width * height * 4)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn first_len(rgba: &[u8], width: u32, height: u32, options: &CaptureOptions) -> usize {
+        let mut writer = Base64Writer::with_capacity(0);
+        encode_rgba(&mut writer, &mut rgba.to_vec(), width, height, EncodeSettings::new(options)).unwrap();
+        writer.finish().len()
+    }
+
+    fn frame(width: u32, height: u32, scale_factor: f32) -> GrabbedFrame {
+        GrabbedFrame {
+            width,
+            height,
+            rgba: vec![128; (width * height * 4) as usize],
+            monitor: format!("{width}x{height}"),
+            backend: CaptureBackend::Standard,
+            held_bytes: 0,
+            covers: None,
+            source_format: PixelFormat::Rgba,
+            scale_factor: Some(scale_factor),
+            convert: Duration::ZERO,
+        }
+    }
+
+    // A 200% laptop panel next to a 100% external display: each capture
+    // goes by its own display's factor.
+    #[test]
+    fn mixed_dpi_displays_scale_by_their_own_factor() {
+        let perf = CapturePerf::default();
+        let full = CaptureOptions::default();
+        let logical = CaptureOptions { logical_resolution: true, ..Default::default() };
+        let capped = CaptureOptions { max_width: Some(400), ..logical.clone() };
+        // Factor, physical size, logical size, and the size with
+        // `logical_resolution` alone and with a 400px `max_width` as well.
+        let displays = [
+            (2.0, (640, 400), (320, 200), (320, 200), (320, 200)),
+            (1.0, (480, 270), (480, 270), (480, 270), (400, 225)),
+        ];
+        for (factor, physical, logical_size, at_logical, at_capped) in displays {
+            let grabbed = frame(physical.0, physical.1, factor);
+            let scale = DisplayScale::new(grabbed.scale_factor.unwrap(), grabbed.width, grabbed.height);
+            assert_eq!(
+                (scale.scale_factor, (scale.physical_width, scale.physical_height)),
+                (factor, physical),
+                "{factor}x"
+            );
+            assert_eq!((scale.logical_width, scale.logical_height), logical_size, "{factor}x");
+
+            for (options, expected) in [(&full, physical), (&logical, at_logical), (&capped, at_capped)] {
+                let grabbed = frame(physical.0, physical.1, factor);
+                let (shrunk, downscale) = downscale_frame(&perf, grabbed, options, Some(scale)).unwrap();
+                assert_eq!((shrunk.width, shrunk.height), expected, "{factor}x");
+                assert_eq!(shrunk.rgba.len(), (expected.0 * expected.1 * 4) as usize);
+                // Downscales are told against the physical size, so positions
+                // still map back to the display.
+                let original = downscale.map(|d| (d.original_width, d.original_height));
+                assert_eq!(original, (expected != physical).then_some(physical), "{factor}x");
+            }
+        }
+    }
+
+    #[test]
+    fn grayscale_encodes_a_single_channel() {
+        use base64::Engine;
+
+        let (width, height) = (33, 17);
+        // Pure red, green and blue stripes, which come out as their luma.
+        let stripes = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+        let rgba: Vec<u8> = (0..width * height).flat_map(|i| stripes[(i % 3) as usize]).collect();
+        for format in [CaptureFormat::Png, CaptureFormat::Jpeg] {
+            let options = CaptureOptions { format, grayscale: true, quality: Some(100), ..Default::default() };
+            let mut writer = Base64Writer::with_capacity(0);
+            encode_rgba(&mut writer, &mut rgba.clone(), width, height, EncodeSettings::new(&options)).unwrap();
+            let bytes = base64::engine::general_purpose::STANDARD.decode(writer.finish()).unwrap();
+
+            let decoded = image::load_from_memory(&bytes).unwrap();
+            assert_eq!(decoded.color(), image::ColorType::L8, "{format:?}");
+            assert_eq!((decoded.width(), decoded.height()), (width, height), "{format:?}");
+            if format == CaptureFormat::Png {
+                assert_eq!(&decoded.into_luma8().into_raw()[..3], &[54, 182, 18]);
+            }
+        }
+    }
+
+    #[test]
+    fn identical_captures_hash_the_same() {
+        use base64::Engine;
+
+        let (width, height) = (48, 32);
+        let rgba = noise(width, height);
+        let mut changed = rgba.clone();
+        changed[0] ^= 0xff;
+        for format in [CaptureFormat::Png, CaptureFormat::Jpeg, CaptureFormat::Webp] {
+            let options = CaptureOptions { format, ..Default::default() };
+            let hash = |pixels: &[u8]| {
+                let mut writer = Base64Writer::with_capacity(0);
+                encode_rgba(&mut writer, &mut pixels.to_vec(), width, height, EncodeSettings::new(&options)).unwrap();
+                let (base64, hash) = writer.finish_with_hash();
+                let bytes = base64::engine::general_purpose::STANDARD.decode(base64).unwrap();
+                assert_eq!(hash, crate::base64_stream::content_hash(&bytes), "{format:?}");
+                hash
+            };
+            assert_eq!(hash(&rgba), hash(&rgba), "{format:?}");
+            assert_ne!(hash(&rgba), hash(&changed), "{format:?}");
+        }
+    }
+
+    // Something like a desktop: flat panels, a gradient and rows of
+    // text-like marks, which is what PNG captures mostly hold.
+    fn desktop(width: u32, height: u32) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let px = if y < 32 {
+                    [32, 32, 40, 255]
+                } else if x < 240 {
+                    [(y * 255 / height) as u8, 90, 160, 255]
+                } else if (y / 8) % 3 != 2 && ((x * 7 + y * 3) / 5) % 4 == 0 {
+                    [20, 20, 20, 255]
+                } else {
+                    [250, 250, 250, 255]
+                };
+                rgba.extend_from_slice(&px);
+            }
+        }
+        rgba
+    }
+
+    // Benchmark-style: prints what each level costs and checks that more
+    // effort never makes the file bigger and that nothing is lost.
+    #[test]
+    fn png_compression_levels_trade_time_for_size() {
+        use base64::Engine;
+
+        let (width, height) = (1280, 720);
+        let rgba = desktop(width, height);
+        let mut sizes = Vec::new();
+        for compression in [PngCompression::Fast, PngCompression::Default, PngCompression::Best] {
+            let options = CaptureOptions { png_compression: Some(compression), ..Default::default() };
+            let started = Instant::now();
+            let mut writer = Base64Writer::with_capacity(0);
+            encode_rgba(&mut writer, &mut rgba.clone(), width, height, EncodeSettings::new(&options)).unwrap();
+            let png = base64::engine::general_purpose::STANDARD.decode(writer.finish()).unwrap();
+            println!("{compression:?}: {} bytes in {:?}", png.len(), started.elapsed());
+            assert_eq!(image::load_from_memory(&png).unwrap().into_rgba8().into_raw(), rgba, "{compression:?}");
+            sizes.push(png.len());
+        }
+        assert!(sizes[0] >= sizes[1] && sizes[1] >= sizes[2], "fast, default, best: {sizes:?}");
+    }
+
+    #[test]
+    fn a_budget_is_met_by_shrinking_the_image() {
+        let (width, height) = (960, 720);
+        let rgba = noise(width, height);
+        let options = CaptureOptions::default();
+        let first = first_len(&rgba, width, height, &options);
+        let max_bytes = first / 4;
+
+        let fit = fit_budget(&rgba, width, height, &options, max_bytes, first).unwrap();
+        let (base64, _) = fit.base64.unwrap();
+        assert!(fit.budget.fits && base64.len() <= max_bytes, "{:?}", fit.budget);
+        assert_eq!((fit.budget.bytes, fit.budget.original_bytes), (base64.len(), first));
+        assert!(fit.budget.attempts < MAX_BUDGET_ATTEMPTS, "{:?}", fit.budget);
+        assert_eq!(fit.budget.quality, None);
+        // Smaller, about as much as it had to be, and the same shape.
+        assert!(fit.width < width && fit.width >= width / 4, "{}x{}", fit.width, fit.height);
+        assert_eq!(fit.height, (height as f32 * fit.budget.scale).round() as u32);
+    }
+
+    #[test]
+    fn a_jpeg_budget_lowers_the_quality_before_the_size() {
+        let (width, height) = (640, 480);
+        let rgba = noise(width, height);
+        let options = CaptureOptions { format: CaptureFormat::Jpeg, ..Default::default() };
+        let first = first_len(&rgba, width, height, &options);
+
+        let fit = fit_budget(&rgba, width, height, &options, first * 3 / 4, first).unwrap();
+        assert!(fit.budget.fits, "{:?}", fit.budget);
+        assert_eq!((fit.width, fit.height, fit.budget.scale), (width, height, 1.0));
+        assert!(fit.budget.quality.unwrap() < options.jpeg_quality());
+    }
+
+    #[test]
+    fn noise_that_never_fits_stops_at_the_minimum_edge() {
+        let (width, height) = (640, 480);
+        let rgba = noise(width, height);
+        for format in [CaptureFormat::Png, CaptureFormat::Jpeg] {
+            let options = CaptureOptions { format, ..Default::default() };
+            let first = first_len(&rgba, width, height, &options);
+
+            let fit = fit_budget(&rgba, width, height, &options, 100, first).unwrap();
+            assert!(!fit.budget.fits, "{format:?}");
+            assert!(fit.budget.attempts < MAX_BUDGET_ATTEMPTS, "{format:?}: {:?}", fit.budget);
+            // Still hands back the smallest try.
+            assert_eq!((fit.width, fit.height), (BUDGET_MIN_EDGE, 240), "{format:?}");
+            assert_eq!(fit.base64.unwrap().0.len(), fit.budget.bytes);
+            assert!(fit.budget.bytes < first);
+        }
+    }
+
+    // A Retina primary, a 1x display to its left sharing the edge at x = 0,
+    // and one above it sharing its top edge, all in layout units.
+    fn displays() -> Vec<ScreenInfo> {
+        let screen = |id, x, y, width, height, scale_factor| ScreenInfo {
+            id,
+            x,
+            y,
+            width,
+            height,
+            scale_factor,
+            is_primary: id == 1,
+        };
+        vec![
+            screen(1, 0, 0, 1440, 900, 2.0),
+            screen(2, -1280, 0, 1280, 1024, 1.0),
+            screen(3, 0, -1080, 1920, 1080, 1.0),
+        ]
+    }
+
+    #[test]
+    fn the_cursor_finds_displays_with_negative_origins_and_shared_edges() {
+        let screens = displays();
+        // Given in layout units, and sent in as the cursor comes: physical
+        // pixels at the primary's scale.
+        let at = |x: f64, y: f64| {
+            let per_px = capture::layout_units_per_pixel(&screens[0]);
+            let (x, y) = capture::cursor_layout_position(&screens, (x / per_px, y / per_px));
+            screen_at(&screens, x, y).map(|s| s.id)
+        };
+        assert_eq!(at(10.0, 10.0), Some(1));
+        assert_eq!(at(1439.0, 899.0), Some(1));
+        assert_eq!(at(-0.5, 10.0), Some(2));
+        assert_eq!(at(-1280.0, 1023.0), Some(2));
+        assert_eq!(at(10.0, -1080.0), Some(3));
+        // Shared edges go to the right or lower display.
+        assert_eq!(at(0.0, 10.0), Some(1));
+        assert_eq!(at(10.0, 0.0), Some(1));
+        assert_eq!(at(-1.0, -1.0), None);
+        assert_eq!(at(-1281.0, 0.0), None);
+        assert_eq!(at(-10.0, 1024.0), None);
+        assert_eq!(at(1440.0, 10.0), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::Mutex;
+
+use crate::capture::CaptureTarget;
+
+// Hamming distance between two hashes at or below which the screen counts
+// as unchanged. Cursor blinks and clock ticks stay under it.
+pub const DEFAULT_UNCHANGED_DISTANCE: u32 = 3;
+// Targets whose last hash is remembered; regions come in endless variety.
+const MAX_TARGETS: usize = 16;
+// The hash compares neighbours on a 9x8 grid of average brightness.
+const GRID_WIDTH: usize = 9;
+const GRID_HEIGHT: usize = 8;
+// Pixels sampled along each side of a grid cell; more adds nothing but time.
+const CELL_SAMPLES: usize = 16;
+
+// The last capture's hash per target, for telling whether the screen moved.
+#[derive(Default)]
+pub struct ScreenHashes(Mutex<Vec<(CaptureTarget, u64)>>);
+
+impl ScreenHashes {
+    // Records `hash` as the latest for `target` and returns whether it's
+    // within `max_distance` of the one before.
+    pub fn observe(&self, target: CaptureTarget, hash: u64, max_distance: u32) -> bool {
+        let Ok(mut hashes) = self.0.lock() else { return false };
+        let previous = match hashes.iter().position(|(t, _)| *t == target) {
+            Some(i) => Some(hashes.remove(i).1),
+            None => None,
+        };
+        hashes.push((target, hash));
+        if hashes.len() > MAX_TARGETS {
+            hashes.remove(0);
+        }
+        previous.is_some_and(|previous| distance(previous, hash) <= max_distance)
+    }
+
+    // Drops `target`'s hash, e.g. for a display that was unplugged, so its
+    // next capture isn't compared with a different screen's.
+    pub fn forget(&self, target: CaptureTarget) {
+        if let Ok(mut hashes) = self.0.lock() {
+            hashes.retain(|(t, _)| *t != target);
+        }
+    }
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+// Difference hash of an RGBA image: one bit per horizontally adjacent pair
+// of grid cells, set when the left one is brighter. Stable under resizing
+// and recompression, so only real changes move it.
+pub fn dhash(rgba: &[u8], width: u32, height: u32) -> u64 {
+    let (width, height) = (width as usize, height as usize);
+    if width == 0 || height == 0 || rgba.len() < width * height * 4 {
+        return 0;
+    }
+    let mut grid = [[0u32; GRID_WIDTH]; GRID_HEIGHT];
+    for (gy, row) in grid.iter_mut().enumerate() {
+        let (top, bottom) = cell_span(gy, GRID_HEIGHT, height);
+        for (gx, cell) in row.iter_mut().enumerate() {
+            let (left, right) = cell_span(gx, GRID_WIDTH, width);
+            let (step_x, step_y) = (((right - left) / CELL_SAMPLES).max(1), ((bottom - top) / CELL_SAMPLES).max(1));
+            let (mut sum, mut count) = (0u32, 0u32);
+            for y in (top..bottom).step_by(step_y) {
+                for x in (left..right).step_by(step_x) {
+                    let px = &rgba[(y * width + x) * 4..];
+                    // Rec. 709 luma, as for grayscale captures.
+                    sum += (px[0] as u32 * 2126 + px[1] as u32 * 7152 + px[2] as u32 * 722) / 10000;
+                    count += 1;
+                }
+            }
+            *cell = sum / count.max(1);
+        }
+    }
+
+    let mut hash = 0u64;
+    for row in &grid {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] > pair[1]);
+        }
+    }
+    hash
+}
+
+// The pixel range of cell `index` out of `cells` along a side of `len`
+// pixels. Never empty, so images smaller than the grid still hash.
+fn cell_span(index: usize, cells: usize, len: usize) -> (usize, usize) {
+    let start = (index * len / cells).min(len - 1);
+    (start, ((index + 1) * len / cells).clamp(start + 1, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Brightness rising left to right, or falling when `reversed`.
+    fn gradient(width: u32, height: u32, reversed: bool) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for _ in 0..height {
+            for x in 0..width {
+                let x = if reversed { width - 1 - x } else { x };
+                let v = (x * 255 / (width - 1)) as u8;
+                rgba.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        rgba
+    }
+
+    #[test]
+    fn identical_images_hash_the_same() {
+        let image = gradient(64, 48, false);
+        assert_eq!(distance(dhash(&image, 64, 48), dhash(&image.clone(), 64, 48)), 0);
+    }
+
+    #[test]
+    fn resizing_keeps_the_hash() {
+        let small = dhash(&gradient(64, 48, true), 64, 48);
+        assert_eq!(distance(small, dhash(&gradient(640, 480, true), 640, 480)), 0);
+    }
+
+    #[test]
+    fn a_mirrored_gradient_flips_every_bit() {
+        let rising = dhash(&gradient(64, 48, false), 64, 48);
+        let falling = dhash(&gradient(64, 48, true), 64, 48);
+        assert_eq!(distance(rising, falling), 64);
+    }
+
+    #[test]
+    fn images_smaller_than_the_grid_still_hash() {
+        let image = gradient(3, 2, true);
+        assert_ne!(dhash(&image, 3, 2), 0);
+        assert_eq!(dhash(&[], 0, 0), 0);
+        // A buffer too short for its size is treated as nothing.
+        assert_eq!(dhash(&image[..8], 3, 2), 0);
+    }
+
+    #[test]
+    fn unchanged_up_to_the_distance_and_not_past_it() {
+        let hashes = ScreenHashes::default();
+        let target = CaptureTarget::Screen(1);
+        // Nothing to compare the first capture with.
+        assert!(!hashes.observe(target, 0, DEFAULT_UNCHANGED_DISTANCE));
+        assert!(hashes.observe(target, 0b111, DEFAULT_UNCHANGED_DISTANCE));
+        assert!(!hashes.observe(target, 0b1111_0111, DEFAULT_UNCHANGED_DISTANCE));
+        // Compared with the latest hash, not the first.
+        assert!(hashes.observe(target, 0b1111_0111, 0));
+    }
+
+    #[test]
+    fn targets_are_compared_separately_and_can_be_forgotten() {
+        let hashes = ScreenHashes::default();
+        let (first, second) = (CaptureTarget::Screen(1), CaptureTarget::Screen(2));
+        hashes.observe(first, 0, 0);
+        assert!(!hashes.observe(second, 0, 0));
+        hashes.forget(first);
+        assert!(!hashes.observe(first, 0, 0));
+        assert!(hashes.observe(second, 0, 0));
+    }
+}
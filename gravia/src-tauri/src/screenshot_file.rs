@@ -0,0 +1,185 @@
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::capture::{
+    self, CaptureError, CaptureFormat, CaptureOperations, CaptureOptions, CaptureTarget, RateLimitPolicy,
+};
+use crate::capture_history::CaptureHistory;
+use crate::command_stats;
+use crate::error::{CommandResult, GraviaError};
+
+// Suffixes tried after a timestamped name that's already taken.
+const MAX_NAME_ATTEMPTS: u32 = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveError {
+    #[error("folder {} does not exist", .0.display())]
+    DirectoryNotFound(PathBuf),
+    #[error("not allowed to write {}", .0.display())]
+    PermissionDenied(PathBuf),
+    #[error("{} already exists", .0.display())]
+    FileExists(PathBuf),
+    #[error("failed to write {}: {reason}", .path.display())]
+    WriteFailed { path: PathBuf, reason: String },
+}
+
+impl SaveError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SaveError::DirectoryNotFound(_) => "directory_not_found",
+            SaveError::PermissionDenied(_) => "permission_denied",
+            SaveError::FileExists(_) => "file_exists",
+            SaveError::WriteFailed { .. } => "write_failed",
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            SaveError::DirectoryNotFound(path)
+            | SaveError::PermissionDenied(path)
+            | SaveError::FileExists(path)
+            | SaveError::WriteFailed { path, .. } => path,
+        }
+    }
+
+    fn from_io(path: &Path, err: std::io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::NotFound => SaveError::DirectoryNotFound(path.parent().unwrap_or(path).to_path_buf()),
+            ErrorKind::PermissionDenied => SaveError::PermissionDenied(path.to_path_buf()),
+            ErrorKind::AlreadyExists => SaveError::FileExists(path.to_path_buf()),
+            _ => SaveError::WriteFailed { path: path.to_path_buf(), reason: err.to_string() },
+        }
+    }
+}
+
+enum Destination {
+    // Exactly this file.
+    Exact { path: PathBuf, overwrite: bool },
+    // A new timestamped file in this folder, created if needed.
+    Timestamped(PathBuf),
+}
+
+// Captures the primary display and writes it to `path`, which must be
+// absolute and whose folder must exist. An existing file is only replaced
+// with `overwrite`. Without a path, `prompt` asks with a save dialog first;
+// otherwise the capture goes to a new timestamped file under the app data
+// folder. Returns where it was written, or `None` if the dialog was
+// dismissed.
+#[tauri::command]
+pub async fn save_screenshot(
+    window: tauri::Window,
+    operations: State<'_, CaptureOperations>,
+    path: Option<String>,
+    prompt: Option<bool>,
+    overwrite: Option<bool>,
+    options: Option<CaptureOptions>,
+    operation_id: Option<String>,
+) -> CommandResult<Option<PathBuf>> {
+    command_stats::track(window.app_handle(), "save_screenshot", async {
+        let options = CaptureOptions::resolve(options)?;
+        let destination = match (path, prompt.unwrap_or(false)) {
+            (Some(path), _) => {
+                let path = PathBuf::from(path);
+                if !path.is_absolute() {
+                    return Err(GraviaError::InvalidArgument("path must be absolute".into()));
+                }
+                Destination::Exact { path, overwrite: overwrite.unwrap_or(false) }
+            }
+            // The dialog has already confirmed replacing an existing file.
+            (None, true) => match pick_path(&window, options.format).await {
+                Some(path) => Destination::Exact { path, overwrite: true },
+                None => return Ok(None),
+            },
+            (None, false) => Destination::Timestamped(default_dir(window.app_handle())?),
+        };
+
+        let operation = operations.begin(operation_id);
+        let shot = capture::capture_unguarded(
+            &window,
+            CaptureTarget::Default,
+            &options,
+            RateLimitPolicy::Reuse,
+            &operation.token,
+        )
+        .await?;
+        let bytes = window
+            .state::<CaptureHistory>()
+            .image_bytes(&shot.id)
+            .ok_or_else(|| CaptureError::CaptureNotFound(shot.id.clone()))?;
+        let extension = options.format.extension();
+        let path = tauri::async_runtime::spawn_blocking(move || write(destination, &bytes, extension))
+            .await
+            .map_err(|e| CaptureError::Failed(e.to_string()))??;
+        tracing::info!(capture_id = %shot.id, path = %path.display(), "screenshot saved");
+        Ok(Some(path))
+    })
+    .await
+}
+
+fn default_dir(app: &AppHandle) -> Result<PathBuf, CaptureError> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join("screenshots"))
+        .map_err(|e| CaptureError::Failed(e.to_string()))
+}
+
+async fn pick_path(window: &tauri::Window, format: CaptureFormat) -> Option<PathBuf> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let name = format!("{}.{}", timestamped_stem(), format.extension());
+    window
+        .dialog()
+        .file()
+        .set_parent(window)
+        .set_file_name(name)
+        .add_filter("Image", &[format.extension()])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    rx.await.ok().flatten().and_then(|path| path.into_path().ok())
+}
+
+fn timestamped_stem() -> String {
+    format!("Gravia-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+fn write(destination: Destination, bytes: &[u8], extension: &str) -> Result<PathBuf, SaveError> {
+    match destination {
+        Destination::Exact { path, overwrite } => {
+            write_file(&path, bytes, overwrite)?;
+            Ok(path)
+        }
+        Destination::Timestamped(dir) => {
+            std::fs::create_dir_all(&dir).map_err(|e| SaveError::from_io(&dir, e))?;
+            let stem = timestamped_stem();
+            // Several saves within a second get `-2`, `-3`, ... appended.
+            for attempt in 1..=MAX_NAME_ATTEMPTS {
+                let name = match attempt {
+                    1 => format!("{stem}.{extension}"),
+                    n => format!("{stem}-{n}.{extension}"),
+                };
+                let path = dir.join(name);
+                match write_file(&path, bytes, false) {
+                    Ok(()) => return Ok(path),
+                    Err(SaveError::FileExists(_)) => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            Err(SaveError::FileExists(dir.join(format!("{stem}.{extension}"))))
+        }
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8], overwrite: bool) -> Result<(), SaveError> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = options.open(path).map_err(|e| SaveError::from_io(path, e))?;
+    file.write_all(bytes).map_err(|e| SaveError::from_io(path, e))
+}
@@ -0,0 +1,111 @@
+use crate::error::CommandResult;
+
+const SERVICE: &str = "com.naitik.gravia";
+
+struct SecretSpec {
+    name: &'static str,
+    // Exported to the sidecar under this name when set.
+    env_var: Option<&'static str>,
+}
+
+// The only names the webview may write. Values never go back to the
+// webview; Rust-side consumers read them with `read`.
+const ALLOWED: &[SecretSpec] = &[
+    SecretSpec { name: "gemini_api_key", env_var: Some("GEMINI_API_KEY") },
+    SecretSpec { name: "backend_auth_token", env_var: Some("GRAVIA_BACKEND_TOKEN") },
+    SecretSpec { name: "remote_classifier_token", env_var: None },
+    SecretSpec { name: "proxy_password", env_var: None },
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("`{0}` is not a known secret name")]
+    NotAllowed(String),
+    #[error("the system keyring is locked")]
+    KeyringLocked,
+    #[error("the system keyring is unavailable: {0}")]
+    KeyringUnavailable(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl SecretError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SecretError::NotAllowed(_) => "not_allowed",
+            SecretError::KeyringLocked => "keyring_locked",
+            SecretError::KeyringUnavailable(_) => "keyring_unavailable",
+            SecretError::Failed(_) => "failed",
+        }
+    }
+}
+
+impl From<keyring::Error> for SecretError {
+    fn from(e: keyring::Error) -> Self {
+        match e {
+            keyring::Error::NoStorageAccess(_) => SecretError::KeyringLocked,
+            keyring::Error::PlatformFailure(e) => SecretError::KeyringUnavailable(e.to_string()),
+            other => SecretError::Failed(other.to_string()),
+        }
+    }
+}
+
+fn entry(name: &str) -> Result<keyring::Entry, SecretError> {
+    if !ALLOWED.iter().any(|s| s.name == name) {
+        return Err(SecretError::NotAllowed(name.to_string()));
+    }
+    Ok(keyring::Entry::new(SERVICE, name)?)
+}
+
+pub fn read(name: &str) -> Result<Option<String>, SecretError> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Environment for the sidecar, built from whichever secrets are set.
+pub fn sidecar_env() -> Vec<(&'static str, String)> {
+    ALLOWED
+        .iter()
+        .filter_map(|spec| {
+            let var = spec.env_var?;
+            match read(spec.name) {
+                Ok(value) => value.map(|v| (var, v)),
+                Err(e) => {
+                    tracing::warn!(secret = spec.name, error = %e, "failed to read secret for sidecar");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// The keyring can block, e.g. on an unlock prompt, so these run off the
+// async runtime.
+#[tauri::command]
+pub async fn set_secret(name: String, value: String) -> CommandResult<()> {
+    tauri::async_runtime::spawn_blocking(move || -> CommandResult<()> {
+        entry(&name)?.set_password(&value).map_err(SecretError::from)?;
+        tracing::info!(secret = %name, "secret stored");
+        Ok(())
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn get_secret_exists(name: String) -> CommandResult<bool> {
+    Ok(tauri::async_runtime::spawn_blocking(move || read(&name)).await??.is_some())
+}
+
+#[tauri::command]
+pub async fn delete_secret(name: String) -> CommandResult<()> {
+    tauri::async_runtime::spawn_blocking(move || -> CommandResult<()> {
+        match entry(&name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(SecretError::from(e).into()),
+        }
+    })
+    .await?
+}
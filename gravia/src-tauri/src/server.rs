@@ -0,0 +1,211 @@
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::watch;
+use ts_rs::TS;
+
+use crate::backend_socket::BackendSocket;
+use crate::command_stats;
+use crate::error::{CommandResult, ServerError};
+use crate::events::{emit_event, GraviaEvent, ServerReady};
+use crate::http_client;
+use crate::lifecycle::Sidecar;
+use crate::metrics::{self, Counter};
+use crate::secrets;
+use crate::settings::SharedSettings;
+
+// How long callers of `ensure_running` wait for a cold start.
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerStatus {
+    // Lazy start is on and nothing has needed the server yet.
+    NotStartedLazy,
+    Starting,
+    Running,
+    Stopped,
+    Failed,
+}
+
+// Owns the backend server's startup. Every start goes through `request_start`,
+// so concurrent callers share one spawn and wait on the same status.
+pub struct ServerSupervisor {
+    status: watch::Sender<ServerStatus>,
+}
+
+impl Default for ServerSupervisor {
+    fn default() -> Self {
+        Self { status: watch::Sender::new(ServerStatus::Stopped) }
+    }
+}
+
+impl ServerSupervisor {
+    pub fn status(&self) -> ServerStatus {
+        *self.status.borrow()
+    }
+
+    fn set(&self, status: ServerStatus) {
+        self.status.send_replace(status);
+    }
+
+    // Resolves once something has asked for the server; right away unless
+    // it's still waiting on a lazy start.
+    pub async fn wait_until_requested(&self) {
+        let mut rx = self.status.subscribe();
+        rx.wait_for(|s| *s != ServerStatus::NotStartedLazy).await.ok();
+    }
+}
+
+// Called from setup. Spawns the server unless `lazy_server_start` is on.
+pub fn start(app: &AppHandle) -> Result<(), ServerError> {
+    let lazy = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| s.lazy_server_start)
+        .unwrap_or(false);
+    if lazy {
+        tracing::info!(target: "sidecar", "lazy start on, server not started");
+        app.state::<ServerSupervisor>().set(ServerStatus::NotStartedLazy);
+        return Ok(());
+    }
+    request_start(app)
+}
+
+// Starts the server if it isn't running or already starting. Doesn't wait
+// for it to come up.
+pub fn request_start(app: &AppHandle) -> Result<(), ServerError> {
+    let supervisor = app.state::<ServerSupervisor>();
+    let claimed = supervisor.status.send_if_modified(|status| {
+        let idle = matches!(status, ServerStatus::NotStartedLazy | ServerStatus::Stopped | ServerStatus::Failed);
+        if idle {
+            *status = ServerStatus::Starting;
+        }
+        idle
+    });
+    if !claimed {
+        return Ok(());
+    }
+    match spawn(app) {
+        Ok(()) => {
+            metrics::increment(app, Counter::ServerStarts);
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!(target: "sidecar", error = %e, "failed to start server");
+            metrics::increment(app, Counter::ServerStartFailures);
+            supervisor.set(ServerStatus::Failed);
+            Err(e)
+        }
+    }
+}
+
+// Starts the server if needed and waits until it reports ready.
+pub async fn ensure_running(app: &AppHandle, timeout: Duration) -> Result<(), ServerError> {
+    let supervisor = app.state::<ServerSupervisor>();
+    let mut rx = supervisor.status.subscribe();
+    request_start(app)?;
+    let settled = tokio::time::timeout(timeout, rx.wait_for(|s| *s != ServerStatus::Starting)).await;
+    match settled {
+        Ok(Ok(status)) if *status == ServerStatus::Running => Ok(()),
+        Ok(_) => Err(ServerError::ExitedEarly),
+        Err(_) => Err(ServerError::StartTimeout { secs: timeout.as_secs() }),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn already_running() -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", "IMAGENAME eq server.exe"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains("server.exe"))
+        .unwrap_or(false)
+}
+
+// Only Windows has a reliable check; elsewhere a stale server just makes the
+// new one fail to bind.
+#[cfg(not(target_os = "windows"))]
+fn already_running() -> bool {
+    false
+}
+
+fn mark_ready(app: &AppHandle, already_running: bool) {
+    app.state::<ServerSupervisor>().set(ServerStatus::Running);
+    emit_event(app, GraviaEvent::ServerReady(ServerReady { already_running })).ok();
+    app.state::<BackendSocket>().notify_server_ready();
+}
+
+fn spawn(app: &AppHandle) -> Result<(), ServerError> {
+    if already_running() {
+        tracing::info!(target: "sidecar", "server.exe already running, skipping sidecar startup");
+        mark_ready(app, true);
+        return Ok(());
+    }
+
+    let proxy_env = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .map(|s| http_client::sidecar_env(&s.proxy))
+        .unwrap_or_default();
+    let sidecar = app
+        .shell()
+        .sidecar("server")
+        .map_err(|e| ServerError::SpawnFailed(e.to_string()))?
+        .envs(secrets::sidecar_env())
+        .envs(proxy_env);
+    let (mut rx, child) = sidecar
+        .spawn()
+        .map_err(|e| ServerError::SpawnFailed(e.to_string()))?;
+    app.state::<Sidecar>().set_child(child);
+    tracing::info!(target: "sidecar", "server.exe spawned");
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut server_started = false;
+
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line_bytes) => {
+                    let line = String::from_utf8_lossy(&line_bytes);
+                    tracing::info!(target: "sidecar", stream = "stdout", "{}", line.trim_end());
+
+                    if line.contains("Server started successfully") && !server_started {
+                        server_started = true;
+                        mark_ready(&app, false);
+                        tracing::info!(target: "sidecar", "server is ready");
+                    }
+                }
+                CommandEvent::Stderr(err_bytes) => {
+                    tracing::warn!(target: "sidecar", stream = "stderr", "{}", String::from_utf8_lossy(&err_bytes).trim_end());
+                }
+                CommandEvent::Terminated(code) => {
+                    tracing::info!(target: "sidecar", code = ?code.code, signal = ?code.signal, "server.exe exited");
+                    app.state::<Sidecar>().mark_terminated();
+                    app.state::<ServerSupervisor>().set(ServerStatus::Stopped);
+                    app.state::<BackendSocket>().notify_server_stopped();
+                }
+                _ => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn ensure_server_running(app: AppHandle) -> CommandResult<ServerStatus> {
+    command_stats::track(&app, "ensure_server_running", async {
+        ensure_running(&app, START_TIMEOUT).await?;
+        Ok(app.state::<ServerSupervisor>().status())
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_server_status(state: State<'_, ServerSupervisor>) -> ServerStatus {
+    state.status()
+}
@@ -0,0 +1,404 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use ts_rs::TS;
+
+use crate::classifier::{ChatMessage, SessionManager};
+use crate::command_stats;
+use crate::error::{CommandResult, SessionError};
+use crate::persist;
+use crate::settings::SharedSettings;
+use crate::SharedSession;
+
+const SESSION_FILE: &str = "session.json";
+// A burst of changes is saved once it has been quiet this long.
+const SETTLE: Duration = Duration::from_secs(2);
+// How long quitting waits on the final save.
+const EXIT_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Keeps the classifier's chat history on disk, so a restart or a crash
+// doesn't cost it its context. Writing on every message would hammer the
+// disk during bulk work, so changes are saved in batches: once they settle,
+// and at the latest `session_autosave_secs` after the first unsaved one.
+#[derive(Default)]
+pub struct SessionStore {
+    changed: Notify,
+    // Held across the write, so an older snapshot can't land after a newer one.
+    saved: Mutex<Saved>,
+}
+
+// The history revision last written, and when.
+#[derive(Debug, Clone, Copy, Default)]
+struct Saved {
+    revision: u64,
+    at: Option<DateTime<Utc>>,
+}
+
+impl SessionStore {
+    // Wakes the autosave after a change to the session history.
+    pub fn mark_dirty(&self) {
+        self.changed.notify_one();
+    }
+
+    // Hands the history to `write` if it changed since the last save, and
+    // returns whether it did. A failed write leaves it to the next save.
+    fn save<W>(&self, session: &Mutex<SessionManager>, write: W) -> CommandResult<bool>
+    where
+        W: FnOnce(&[ChatMessage]) -> anyhow::Result<()>,
+    {
+        let mut saved = self.saved.lock()?;
+        let (revision, history) = {
+            let session = session.lock()?;
+            (session.history_revision(), session.history())
+        };
+        if revision == saved.revision {
+            return Ok(false);
+        }
+        write(&history).map_err(|e| SessionError::SaveFailed(e.to_string()))?;
+        *saved = Saved { revision, at: Some(Utc::now()) };
+        Ok(true)
+    }
+
+    // Calls `save` once changes have settled, or `interval()` after the
+    // first unsaved one while they keep coming.
+    async fn autosave(&self, interval: impl Fn() -> Duration, mut save: impl FnMut()) {
+        loop {
+            self.changed.notified().await;
+            let deadline = Instant::now() + interval();
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => break,
+                    _ = tokio::time::sleep(SETTLE) => break,
+                    _ = self.changed.notified() => {}
+                }
+            }
+            save();
+        }
+    }
+}
+
+// The classifier keeps a single session, so these describe that one session
+// rather than a list of them.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct SessionStats {
+    pub messages: usize,
+    // Changes not on disk yet.
+    pub unsaved_changes: bool,
+    // When the session was last saved; `None` until that has happened this run.
+    pub session_flushed_at: Option<DateTime<Utc>>,
+    #[ts(type = "number")]
+    pub autosave_secs: u64,
+}
+
+fn session_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(SESSION_FILE))
+}
+
+fn autosave_secs(app: &AppHandle) -> u64 {
+    app.state::<SharedSettings>().0.lock().map(|s| s.session_autosave_secs).unwrap_or(30).max(1)
+}
+
+pub fn mark_dirty(app: &AppHandle) {
+    app.state::<SessionStore>().mark_dirty();
+}
+
+// Restores the saved history and starts the autosave, plus the end-of-session
+// save on Windows.
+pub fn start(app: &AppHandle) {
+    let history: Vec<ChatMessage> = session_path(app)
+        .ok()
+        .and_then(|path| persist::read_json(app, &path))
+        .unwrap_or_default();
+    let store = app.state::<SessionStore>();
+    let session = app.state::<Arc<SharedSession>>();
+    if let (Ok(mut saved), Ok(mut session)) = (store.saved.lock(), session.0.lock()) {
+        for message in history {
+            session.add_message(message);
+        }
+        // Just read, so there's nothing to write back.
+        saved.revision = session.history_revision();
+    }
+
+    #[cfg(target_os = "windows")]
+    end_session::watch(app);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let interval = || Duration::from_secs(autosave_secs(&app));
+        let save = || {
+            if let Err(e) = flush(&app) {
+                tracing::warn!(error = %e, "failed to autosave the session");
+            }
+        };
+        app.state::<SessionStore>().autosave(interval, save).await;
+    });
+}
+
+// Writes the session history if it changed since the last save.
+pub fn flush(app: &AppHandle) -> CommandResult<bool> {
+    let session = app.state::<Arc<SharedSession>>();
+    app.state::<SessionStore>()
+        .save(&session.0, |history| persist::write_json_atomic(&session_path(app)?, &history, true))
+}
+
+// The final save on quit or exit. Waits at most `EXIT_FLUSH_TIMEOUT`, so a
+// stalled disk can't hold the exit up; the write carries on if it overruns.
+pub fn flush_on_exit(app: &AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        let _ = tx.send(flush(&handle));
+    });
+    match rx.recv_timeout(EXIT_FLUSH_TIMEOUT) {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => tracing::error!(error = %e, "failed to save the session on exit"),
+        Err(_) => tracing::warn!(timeout = ?EXIT_FLUSH_TIMEOUT, "session save on exit timed out"),
+    }
+}
+
+// Logging off or shutting down Windows can end the process without a
+// `RunEvent::Exit`, so a hidden window saves the session when the session ends.
+#[cfg(target_os = "windows")]
+mod end_session {
+    use std::sync::OnceLock;
+    use tauri::AppHandle;
+    use windows::core::w;
+    use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, TranslateMessage, MSG,
+        WINDOW_EX_STYLE, WINDOW_STYLE, WM_ENDSESSION, WM_QUERYENDSESSION, WNDCLASSW,
+    };
+
+    // The window procedure has no user-data pointer set up, so it reaches the
+    // app through this.
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        match msg {
+            // Never holds the shutdown up; saving waits until it's certain.
+            WM_QUERYENDSESSION => LRESULT(1),
+            WM_ENDSESSION => {
+                // A zero `wparam` means the shutdown was cancelled.
+                if wparam.0 != 0 {
+                    if let Some(app) = APP.get() {
+                        super::flush_on_exit(app);
+                    }
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+
+    pub fn watch(app: &AppHandle) {
+        if APP.set(app.clone()).is_err() {
+            return;
+        }
+        std::thread::spawn(|| unsafe {
+            let instance: HINSTANCE = match GetModuleHandleW(None) {
+                Ok(module) => module.into(),
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to watch for the end of the Windows session");
+                    return;
+                }
+            };
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance,
+                lpszClassName: w!("GraviaEndSession"),
+                ..Default::default()
+            };
+            if RegisterClassW(&class) == 0 {
+                tracing::warn!("failed to register the end-of-session window class");
+                return;
+            }
+            // Hidden but top-level: message-only windows don't get the
+            // end-of-session messages.
+            if let Err(e) = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                w!("GraviaEndSession"),
+                w!(""),
+                WINDOW_STYLE::default(),
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                instance,
+                None,
+            ) {
+                tracing::warn!(error = %e, "failed to create the end-of-session window");
+                return;
+            }
+
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        });
+    }
+}
+
+fn stats(app: &AppHandle) -> CommandResult<SessionStats> {
+    let saved = *app.state::<SessionStore>().saved.lock()?;
+    let (revision, messages) = {
+        let session = app.state::<Arc<SharedSession>>();
+        let session = session.0.lock()?;
+        (session.history_revision(), session.history_len())
+    };
+    Ok(SessionStats {
+        messages,
+        unsaved_changes: revision != saved.revision,
+        session_flushed_at: saved.at,
+        autosave_secs: autosave_secs(app),
+    })
+}
+
+#[tauri::command]
+pub fn get_session_stats(app: AppHandle) -> CommandResult<SessionStats> {
+    stats(&app)
+}
+
+// Saves the session history straight away, e.g. before something that
+// might take the app down.
+#[tauri::command]
+pub async fn flush_sessions_now(app: AppHandle) -> CommandResult<SessionStats> {
+    command_stats::track(&app, "flush_sessions_now", async {
+        flush(&app)?;
+        stats(&app)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(10);
+
+    // A session and store saving to an in-memory "disk".
+    struct Harness {
+        session: Mutex<SessionManager>,
+        store: SessionStore,
+        disk: Mutex<Vec<ChatMessage>>,
+        writes: Mutex<usize>,
+    }
+
+    impl Harness {
+        fn new() -> Self {
+            Self {
+                session: Mutex::new(SessionManager::new(200)),
+                store: SessionStore::default(),
+                disk: Mutex::new(Vec::new()),
+                writes: Mutex::new(0),
+            }
+        }
+
+        fn add(&self, n: usize) {
+            let message = ChatMessage {
+                role: "user".to_string(),
+                content: format!("message {n}"),
+                timestamp: Utc::now(),
+                triggered_screenshot: None,
+            };
+            self.session.lock().unwrap().add_message(message);
+            self.store.mark_dirty();
+        }
+
+        fn save(&self) -> CommandResult<bool> {
+            self.store.save(&self.session, |history| {
+                *self.disk.lock().unwrap() = history.to_vec();
+                *self.writes.lock().unwrap() += 1;
+                Ok(())
+            })
+        }
+
+        fn on_disk(&self) -> usize {
+            self.disk.lock().unwrap().len()
+        }
+
+        fn writes(&self) -> usize {
+            *self.writes.lock().unwrap()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_crash_between_autosaves_loses_at_most_the_window() {
+        let h = Harness::new();
+        let autosave = h.store.autosave(|| WINDOW, || {
+            let _ = h.save();
+        });
+        // Closer together than SETTLE, so only the interval ever saves.
+        let traffic = async {
+            let mut added_at = Vec::new();
+            for n in 0..100 {
+                h.add(n);
+                added_at.push(Instant::now());
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                // Crashing now keeps what's on disk; the rest must be recent.
+                let now = Instant::now();
+                let lost = &added_at[h.on_disk()..];
+                assert!(lost.iter().all(|at| now - *at <= WINDOW), "lost a message older than {WINDOW:?}");
+            }
+        };
+        tokio::select! {
+            _ = autosave => unreachable!(),
+            _ = traffic => {}
+        }
+        // 50s of messages, batched instead of written one by one.
+        assert!((4..=6).contains(&h.writes()), "{} writes", h.writes());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_burst_is_saved_once_it_settles() {
+        let h = Harness::new();
+        let autosave = h.store.autosave(|| WINDOW, || {
+            let _ = h.save();
+        });
+        let started = Instant::now();
+        let burst = async {
+            for n in 0..20 {
+                h.add(n);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            tokio::time::sleep(SETTLE).await;
+        };
+        tokio::select! {
+            _ = autosave => unreachable!(),
+            _ = burst => {}
+        }
+        assert_eq!((h.on_disk(), h.writes()), (20, 1));
+        assert!(started.elapsed() < WINDOW);
+    }
+
+    #[test]
+    fn an_unchanged_history_is_not_rewritten() {
+        let h = Harness::new();
+        assert!(!h.save().unwrap());
+        h.add(0);
+        assert!(h.save().unwrap());
+        assert!(!h.save().unwrap());
+        assert_eq!(h.writes(), 1);
+        assert!(h.store.saved.lock().unwrap().at.is_some());
+    }
+
+    #[test]
+    fn a_failed_write_is_retried_by_the_next_save() {
+        let h = Harness::new();
+        h.add(0);
+        let failed = h.store.save(&h.session, |_| anyhow::bail!("disk full"));
+        assert!(matches!(failed, Err(crate::error::GraviaError::Session(SessionError::SaveFailed(_)))));
+        assert!(h.store.saved.lock().unwrap().at.is_none());
+        assert!(h.save().unwrap());
+        assert_eq!(h.on_disk(), 1);
+    }
+}
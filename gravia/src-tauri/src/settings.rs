@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+use crate::capture::{CaptureBackend, RedactRect, StampCorner};
+use crate::error::{CommandResult, SettingsError};
+use crate::http_client;
+use crate::logging;
+use crate::persist;
+use crate::print_screen::PrintScreenAction;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseBehavior {
+    Hide,
+    Quit,
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    Stable,
+    Beta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    System,
+    Manual,
+    None,
+}
+
+// The proxy password lives in the keyring as `proxy_password`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    pub url: Option<String>,
+    pub username: Option<String>,
+}
+
+impl Default for ProxySettings {
+    fn default() -> Self {
+        Self { mode: ProxyMode::System, url: None, username: None }
+    }
+}
+
+// Settings owned by the Rust side of the app. Everything else lives in the
+// backend's settings store; only things the native layer needs to decide on
+// its own belong here.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct Settings {
+    pub notify_on_auto_capture: bool,
+    pub allow_clipboard_read: bool,
+    pub clipboard_permission_prompted: bool,
+    #[ts(type = "number")]
+    pub idle_threshold_secs: u64,
+    pub on_close: CloseBehavior,
+    // Captures come back at one per this long, up to `capture_burst` in a
+    // row. 0 turns the rate limit off.
+    #[ts(type = "number")]
+    pub min_capture_interval_ms: u64,
+    pub capture_burst: u32,
+    pub log_level: String,
+    // UI language for strings the native side produces, e.g. `hi`.
+    pub locale: String,
+    pub update_channel: UpdateChannel,
+    pub proxy: ProxySettings,
+    pub allow_microphone: bool,
+    #[ts(type = "number")]
+    pub max_recording_secs: u64,
+    // Input RMS (0..1) below which the mic counts as silent, and how long
+    // that has to last before `mic-silence` fires.
+    pub silence_threshold: f32,
+    #[ts(type = "number")]
+    pub silence_duration_ms: u64,
+    // Only applies while the frontend is in compact mode.
+    pub auto_hide_on_blur: bool,
+    #[ts(type = "number")]
+    pub auto_hide_delay_ms: u64,
+    // Background polling backs off on battery, and further below
+    // `low_battery_percent` or with the OS power saver on.
+    pub throttle_on_battery: bool,
+    pub low_battery_percent: u8,
+    // Probed to tell "online" from "connected to a network"; should be a host
+    // the backend actually needs.
+    pub connectivity_probe_url: String,
+    // Changed through `set_print_screen_binding`, which also (un)registers
+    // the key.
+    pub bind_print_screen: bool,
+    pub print_screen_action: PrintScreenAction,
+    // Spread pixel conversion over all cores; ignored on two cores or fewer.
+    pub parallel_capture_processing: bool,
+    // Leave the backend server stopped until something needs it.
+    pub lazy_server_start: bool,
+    // Captures whose base64 exceeds this are shrunk, or rejected with
+    // `payload_too_large` when `auto_shrink` is off.
+    #[ts(type = "number")]
+    pub max_ipc_payload_bytes: usize,
+    pub auto_shrink: bool,
+    // Local-only usage counters; nothing leaves the machine.
+    pub collect_usage_metrics: bool,
+    // Identical classify requests this close together share one result.
+    #[ts(type = "number")]
+    pub classify_coalesce_window_ms: u64,
+    // Emit app-side log entries as `app-log` events for the debug panel.
+    pub debug_log_stream: bool,
+    // `auto`, `dxgi` (Windows only) or `standard`.
+    pub capture_backend: CaptureBackend,
+    // Keep Gravia's window out of captures: excluded while staying on
+    // screen where the OS allows, otherwise hidden, waiting this long after
+    // it reports hidden for fade-out animations to finish. Capture options
+    // can override both.
+    pub hide_window_for_capture: bool,
+    #[ts(type = "number")]
+    pub capture_hide_delay_ms: u64,
+    // A capture of the same target within this long is handed back instead
+    // of taking a new one, unless the capture options force it. 0 disables.
+    #[ts(type = "number")]
+    pub capture_cache_ttl_ms: u64,
+    // A capture still grabbing or encoding after this long fails with
+    // `timeout`, e.g. when a remote desktop's display driver stalls. 0
+    // waits indefinitely.
+    #[ts(type = "number")]
+    pub capture_timeout_ms: u64,
+    // Default `max_bytes` for auto-captures, kept under the backend's 5 MB
+    // request limit. 0 disables.
+    #[ts(type = "number")]
+    pub auto_capture_max_bytes: usize,
+    // Where the `stamp` capture option puts its label.
+    pub stamp_corner: StampCorner,
+    // Off, auto-captures are never stamped, whatever classify is asked.
+    pub stamp_auto_captures: bool,
+    // Writes every capture to `screenshots` in the app data folder as a PNG
+    // with a JSON sidecar, swept by the two retention limits below.
+    pub save_screenshots: bool,
+    // Saved captures older than this are deleted. 0 keeps them regardless of age.
+    pub saved_screenshot_retention_days: u32,
+    // The oldest saved captures go once together they pass this. 0 disables.
+    #[ts(type = "number")]
+    pub saved_screenshot_retention_mb: u64,
+    // Rectangles, in physical desktop pixels, redacted from every capture
+    // on top of any a request passes, e.g. a password manager docked at
+    // the screen edge.
+    pub redact_regions: Vec<RedactRect>,
+    // Apps, by process name, whose windows are blacked out of every
+    // capture wherever they are on screen.
+    pub capture_blocklist: Vec<String>,
+    // The classifier's chat history is saved once changes settle, and at
+    // the latest this long after the first unsaved one, which is as much
+    // as a crash can lose.
+    #[ts(type = "number")]
+    pub session_autosave_secs: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            notify_on_auto_capture: true,
+            allow_clipboard_read: false,
+            clipboard_permission_prompted: false,
+            idle_threshold_secs: 300,
+            on_close: CloseBehavior::Hide,
+            min_capture_interval_ms: 500,
+            capture_burst: 4,
+            log_level: "info".to_string(),
+            locale: "en".to_string(),
+            update_channel: UpdateChannel::Stable,
+            proxy: ProxySettings::default(),
+            allow_microphone: false,
+            max_recording_secs: 60,
+            silence_threshold: 0.01,
+            silence_duration_ms: 2000,
+            auto_hide_on_blur: false,
+            auto_hide_delay_ms: 400,
+            throttle_on_battery: true,
+            low_battery_percent: 20,
+            connectivity_probe_url: "https://generativelanguage.googleapis.com/".to_string(),
+            bind_print_screen: false,
+            print_screen_action: PrintScreenAction::RegionSelect,
+            parallel_capture_processing: true,
+            lazy_server_start: false,
+            max_ipc_payload_bytes: 8 * 1024 * 1024,
+            auto_shrink: true,
+            collect_usage_metrics: false,
+            classify_coalesce_window_ms: 1000,
+            debug_log_stream: false,
+            capture_backend: CaptureBackend::Auto,
+            hide_window_for_capture: true,
+            capture_hide_delay_ms: 17,
+            capture_cache_ttl_ms: 5000,
+            capture_timeout_ms: 3000,
+            auto_capture_max_bytes: 4 * 1024 * 1024,
+            stamp_corner: StampCorner::BottomRight,
+            stamp_auto_captures: true,
+            save_screenshots: false,
+            saved_screenshot_retention_days: 7,
+            saved_screenshot_retention_mb: 500,
+            redact_regions: Vec::new(),
+            capture_blocklist: Vec::new(),
+            session_autosave_secs: 30,
+        }
+    }
+}
+
+pub struct SharedSettings(pub Mutex<Settings>);
+
+pub fn settings_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    let dir = app.path().app_config_dir()?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+// Why `load` fell back to defaults. Settings are loaded before logging is
+// set up, since they pick the log level, so these are handed back to be
+// logged once it is.
+pub enum LoadWarning {
+    NoPath(String),
+    Unreadable(PathBuf),
+}
+
+impl LoadWarning {
+    pub fn log(&self) {
+        match self {
+            LoadWarning::NoPath(error) => {
+                tracing::warn!(%error, "failed to resolve settings path, using defaults")
+            }
+            LoadWarning::Unreadable(path) => {
+                tracing::warn!(path = %path.display(), "invalid settings file and no usable backup, using defaults")
+            }
+        }
+    }
+}
+
+pub fn load(app: &AppHandle) -> (Settings, Option<LoadWarning>) {
+    let path = match settings_path(app) {
+        Ok(p) => p,
+        Err(e) => return (Settings::default(), Some(LoadWarning::NoPath(e.to_string()))),
+    };
+    if !path.exists() {
+        return (Settings::default(), None);
+    }
+    match persist::read_json(app, &path) {
+        Some(settings) => (settings, None),
+        None => (Settings::default(), Some(LoadWarning::Unreadable(path))),
+    }
+}
+
+pub fn save(app: &AppHandle, settings: &Settings) -> anyhow::Result<()> {
+    persist::write_json_atomic(&settings_path(app)?, settings, true)
+}
+
+#[tauri::command]
+pub fn get_settings(state: State<'_, SharedSettings>) -> CommandResult<Settings> {
+    let settings = state.0.lock()?;
+    Ok(settings.clone())
+}
+
+// Saving writes to disk, so it runs off the async runtime.
+#[tauri::command]
+pub async fn update_settings(app: AppHandle, settings: Settings) -> CommandResult<Settings> {
+    tauri::async_runtime::spawn_blocking(move || replace(&app, settings)).await?
+}
+
+fn replace(app: &AppHandle, settings: Settings) -> CommandResult<Settings> {
+    let state = app.state::<SharedSettings>();
+    let mut current = state.0.lock()?;
+    // A level that doesn't parse would otherwise be saved and only fail at
+    // the next start.
+    let level_changed = current.log_level != settings.log_level;
+    if level_changed {
+        logging::check_level(&settings.log_level)?;
+    }
+    save(app, &settings).map_err(|e| SettingsError::SaveFailed(e.to_string()))?;
+    if current.proxy != settings.proxy {
+        http_client::rebuild(app, &settings.proxy);
+    }
+    if level_changed {
+        if let Err(e) = logging::apply_level(&settings.log_level) {
+            tracing::warn!(error = %e, "saved log level not applied");
+        }
+    }
+    logging::set_streaming(settings.debug_log_stream);
+    *current = settings;
+    Ok(current.clone())
+}
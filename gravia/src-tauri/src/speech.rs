@@ -0,0 +1,295 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+use tts::Tts;
+
+use crate::error::CommandResult;
+use crate::events::{emit_event, GraviaEvent};
+
+// Keeps each utterance short enough that `stop_speaking` lands quickly.
+const MAX_CHUNK_CHARS: usize = 300;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TtsError {
+    #[error("text-to-speech is unavailable: {0}")]
+    Unavailable(String),
+    #[error("voice `{0}` was not found")]
+    VoiceNotFound(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl TtsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TtsError::Unavailable(_) => "unavailable",
+            TtsError::VoiceNotFound(_) => "voice_not_found",
+            TtsError::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct VoiceInfo {
+    pub id: String,
+    pub name: String,
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SpeakingState {
+    Started { #[ts(type = "number")] id: u64 },
+    // `interrupted` when stopped or replaced before the end.
+    Finished { #[ts(type = "number")] id: u64, interrupted: bool },
+    Error { #[ts(type = "number")] id: u64, message: String },
+}
+
+enum Outcome {
+    Completed,
+    Stopped,
+    Replaced(Request),
+}
+
+enum Request {
+    Speak { id: u64, chunks: Vec<String>, voice: Option<String>, rate: Option<f32> },
+    Stop,
+    ListVoices(Sender<Result<Vec<VoiceInfo>, TtsError>>),
+}
+
+// The OS speech engines want to stay on one thread, so a worker owns the
+// `Tts` and everything else talks to it through this channel.
+pub struct Speaker {
+    requests: Sender<Request>,
+    next_id: AtomicU64,
+}
+
+pub fn start(app: &AppHandle) {
+    let (requests, rx) = mpsc::channel();
+    let worker_app = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("tts".into())
+        .spawn(move || run(worker_app, rx));
+    if let Err(e) = spawned {
+        tracing::error!(error = %e, "failed to start text-to-speech worker");
+    }
+    app.manage(Speaker { requests, next_id: AtomicU64::new(1) });
+}
+
+// Splits at sentence ends (including the Devanagari danda), then at spaces
+// for anything still too long.
+fn chunk_text(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let at_end = matches!(c, '.' | '!' | '?' | '।' | '\n')
+            && !matches!(chars.peek(), Some(next) if !next.is_whitespace());
+        if at_end {
+            sentences.push(std::mem::take(&mut current));
+        }
+    }
+    sentences.push(current);
+
+    let mut chunks = Vec::new();
+    for sentence in sentences.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let mut chunk = String::new();
+        for word in sentence.split_whitespace() {
+            if !chunk.is_empty() && chunk.chars().count() + word.chars().count() >= MAX_CHUNK_CHARS {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+            if !chunk.is_empty() {
+                chunk.push(' ');
+            }
+            chunk.push_str(word);
+        }
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+fn voices_of(tts: &Tts) -> Result<Vec<VoiceInfo>, TtsError> {
+    let voices = tts.voices().map_err(|e| TtsError::Failed(e.to_string()))?;
+    Ok(voices
+        .into_iter()
+        .map(|v| VoiceInfo { id: v.id(), name: v.name(), language: v.language().to_string() })
+        .collect())
+}
+
+fn configure(tts: &mut Tts, voice: Option<&str>, rate: Option<f32>) -> Result<(), TtsError> {
+    if let Some(id) = voice {
+        let voices = tts.voices().map_err(|e| TtsError::Failed(e.to_string()))?;
+        let voice = voices
+            .iter()
+            .find(|v| v.id() == id)
+            .ok_or_else(|| TtsError::VoiceNotFound(id.to_string()))?;
+        tts.set_voice(voice).map_err(|e| TtsError::Failed(e.to_string()))?;
+    }
+    // `rate` is a multiple of the engine's normal rate, since raw ranges
+    // differ per platform.
+    let rate = (tts.normal_rate() * rate.unwrap_or(1.0)).clamp(tts.min_rate(), tts.max_rate());
+    tts.set_rate(rate).map_err(|e| TtsError::Failed(e.to_string()))?;
+    Ok(())
+}
+
+fn run(app: AppHandle, rx: Receiver<Request>) {
+    let mut tts = match Tts::default() {
+        Ok(tts) => tts,
+        Err(e) => {
+            tracing::warn!(error = %e, "text-to-speech is unavailable");
+            let error = e.to_string();
+            for request in rx {
+                match request {
+                    Request::Speak { id, .. } => {
+                        emit_event(&app, GraviaEvent::SpeakingState(SpeakingState::Error { id, message: error.clone() })).ok();
+                    }
+                    Request::ListVoices(reply) => {
+                        let _ = reply.send(Err(TtsError::Unavailable(error.clone())));
+                    }
+                    Request::Stop => {}
+                }
+            }
+            return;
+        }
+    };
+
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    let has_callbacks = tts.supported_features().utterance_callbacks
+        && tts
+            .on_utterance_end(Some(Box::new(move |_| {
+                let _ = done_tx.send(());
+            })))
+            .is_ok();
+
+    let mut next = rx.recv().ok();
+    while let Some(request) = next.take() {
+        match request {
+            Request::Stop => {}
+            Request::ListVoices(reply) => {
+                let _ = reply.send(voices_of(&tts));
+            }
+            Request::Speak { id, chunks, voice, rate } => {
+                if let Err(e) = configure(&mut tts, voice.as_deref(), rate) {
+                    emit_event(&app, GraviaEvent::SpeakingState(SpeakingState::Error { id, message: e.to_string() })).ok();
+                } else {
+                    emit_event(&app, GraviaEvent::SpeakingState(SpeakingState::Started { id })).ok();
+                    let done = has_callbacks.then_some(&done_rx);
+                    let state = match speak_chunks(&mut tts, &chunks, done, &rx) {
+                        Ok(Outcome::Completed) => SpeakingState::Finished { id, interrupted: false },
+                        Ok(Outcome::Stopped) => SpeakingState::Finished { id, interrupted: true },
+                        Ok(Outcome::Replaced(request)) => {
+                            // Handled on the next turn of the loop.
+                            next = Some(request);
+                            SpeakingState::Finished { id, interrupted: true }
+                        }
+                        Err(e) => SpeakingState::Error { id, message: e.to_string() },
+                    };
+                    emit_event(&app, GraviaEvent::SpeakingState(state)).ok();
+                }
+            }
+        }
+        if next.is_none() {
+            next = rx.recv().ok();
+        }
+    }
+}
+
+// Speaks chunk by chunk, watching for new requests in between. Engines
+// without end-of-utterance callbacks (`done` is None) are polled instead.
+fn speak_chunks(
+    tts: &mut Tts,
+    chunks: &[String],
+    done: Option<&Receiver<()>>,
+    rx: &Receiver<Request>,
+) -> Result<Outcome, TtsError> {
+    if let Some(done) = done {
+        while done.try_recv().is_ok() {}
+    }
+    for chunk in chunks {
+        tts.speak(chunk.as_str(), false).map_err(|e| TtsError::Failed(e.to_string()))?;
+        loop {
+            let finished = match done {
+                Some(done) => match done.recv_timeout(POLL_INTERVAL) {
+                    Ok(()) => true,
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => return Ok(Outcome::Stopped),
+                },
+                None => {
+                    std::thread::sleep(POLL_INTERVAL);
+                    matches!(tts.is_speaking(), Ok(false))
+                }
+            };
+            if finished {
+                break;
+            }
+            match rx.try_recv() {
+                Ok(Request::ListVoices(reply)) => {
+                    let _ = reply.send(voices_of(tts));
+                }
+                Ok(request) => {
+                    let _ = tts.stop();
+                    return Ok(match request {
+                        Request::Speak { .. } => Outcome::Replaced(request),
+                        _ => Outcome::Stopped,
+                    });
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    let _ = tts.stop();
+                    return Ok(Outcome::Stopped);
+                }
+            }
+        }
+    }
+    Ok(Outcome::Completed)
+}
+
+// Speaks `text`, cutting off anything already being spoken. Returns the id
+// used in `speaking-state` events for this request.
+#[tauri::command]
+pub async fn speak_text(
+    state: State<'_, Speaker>,
+    text: String,
+    voice: Option<String>,
+    rate: Option<f32>,
+) -> CommandResult<u64> {
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    // Splitting a long text into sentences is worth keeping off the runtime.
+    let chunks = tauri::async_runtime::spawn_blocking(move || chunk_text(&text)).await?;
+    state
+        .requests
+        .send(Request::Speak { id, chunks, voice, rate })
+        .map_err(|_| TtsError::Unavailable("speech worker has stopped".into()))?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn stop_speaking(state: State<'_, Speaker>) -> CommandResult<()> {
+    state
+        .requests
+        .send(Request::Stop)
+        .map_err(|_| TtsError::Unavailable("speech worker has stopped".into()))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_voices(state: State<'_, Speaker>) -> CommandResult<Vec<VoiceInfo>> {
+    let (reply, rx) = mpsc::channel();
+    state
+        .requests
+        .send(Request::ListVoices(reply))
+        .map_err(|_| TtsError::Unavailable("speech worker has stopped".into()))?;
+    // The worker may be mid-sentence, so wait for it off the async runtime.
+    let voices = tauri::async_runtime::spawn_blocking(move || rx.recv_timeout(REPLY_TIMEOUT))
+        .await?
+        .map_err(|_| TtsError::Failed("speech worker did not answer".into()))??;
+    Ok(voices)
+}
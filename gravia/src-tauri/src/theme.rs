@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::events::{emit_event, GraviaEvent};
+use crate::tray;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct SystemTheme {
+    // Theme apps are asked to use.
+    pub mode: ThemeMode,
+    // Theme of the taskbar / system surfaces, which can differ from `mode`.
+    pub taskbar_mode: ThemeMode,
+    // `#rrggbb`, when the platform exposes one.
+    pub accent_color: Option<String>,
+    pub high_contrast: bool,
+}
+
+// Last theme we reported, so repeated change notifications don't re-emit.
+pub struct ThemeState(pub Mutex<Option<SystemTheme>>);
+
+impl ThemeState {
+    pub fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+}
+
+#[tauri::command]
+pub fn get_system_theme(app: AppHandle) -> SystemTheme {
+    current_theme(&app)
+}
+
+fn current_theme(app: &AppHandle) -> SystemTheme {
+    platform::read_theme().unwrap_or_else(|| {
+        // Fall back to what the webview's window reports.
+        let mode = app
+            .get_webview_window("main")
+            .and_then(|w| w.theme().ok())
+            .map(|t| match t {
+                tauri::Theme::Light => ThemeMode::Light,
+                _ => ThemeMode::Dark,
+            })
+            .unwrap_or(ThemeMode::Light);
+        SystemTheme {
+            mode,
+            taskbar_mode: mode,
+            accent_color: None,
+            high_contrast: false,
+        }
+    })
+}
+
+// Re-reads the theme and, if it changed, emits `system-theme-changed` and
+// swaps the tray icon variant.
+pub fn refresh(app: &AppHandle) {
+    let theme = current_theme(app);
+    let state = app.state::<ThemeState>();
+    let Ok(mut last) = state.0.lock() else { return };
+    if last.as_ref() == Some(&theme) {
+        return;
+    }
+    if last.as_ref().map(|t| t.taskbar_mode) != Some(theme.taskbar_mode) {
+        tray::set_theme(app, theme.taskbar_mode == ThemeMode::Light);
+    }
+    emit_event(app, GraviaEvent::SystemThemeChanged(theme.clone())).ok();
+    *last = Some(theme);
+}
+
+pub fn start(app: &AppHandle) -> SystemTheme {
+    let theme = current_theme(app);
+    if let Ok(mut last) = app.state::<ThemeState>().0.lock() {
+        *last = Some(theme.clone());
+    }
+    platform::watch(app.clone());
+    theme
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{SystemTheme, ThemeMode};
+    use tauri::AppHandle;
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::{BOOL, ERROR_SUCCESS, HANDLE};
+    use windows::Win32::Graphics::Dwm::DwmGetColorizationColor;
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegGetValueW, RegNotifyChangeKeyValue, RegOpenKeyExW, HKEY,
+        HKEY_CURRENT_USER, KEY_NOTIFY, REG_NOTIFY_CHANGE_LAST_SET, RRF_RT_REG_DWORD,
+    };
+    use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETHIGHCONTRAST, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    const PERSONALIZE_KEY: PCWSTR =
+        w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize");
+    const DWM_KEY: PCWSTR = w!("Software\\Microsoft\\Windows\\DWM");
+
+    fn read_dword(key: PCWSTR, value: PCWSTR) -> Option<u32> {
+        let mut data = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                key,
+                value,
+                RRF_RT_REG_DWORD,
+                None,
+                Some(&mut data as *mut u32 as *mut _),
+                Some(&mut size),
+            )
+        };
+        (status == ERROR_SUCCESS).then_some(data)
+    }
+
+    fn mode_from(light: Option<u32>) -> ThemeMode {
+        match light {
+            Some(0) => ThemeMode::Dark,
+            _ => ThemeMode::Light,
+        }
+    }
+
+    pub fn read_theme() -> Option<SystemTheme> {
+        let mode = mode_from(read_dword(PERSONALIZE_KEY, w!("AppsUseLightTheme")));
+        let taskbar_mode = mode_from(read_dword(PERSONALIZE_KEY, w!("SystemUsesLightTheme")));
+
+        let mut color = 0u32;
+        let mut opaque = BOOL::default();
+        let accent_color = unsafe { DwmGetColorizationColor(&mut color, &mut opaque) }
+            .ok()
+            .map(|_| format!("#{:06x}", color & 0x00ff_ffff));
+
+        let mut hc = HIGHCONTRASTW {
+            cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+            ..Default::default()
+        };
+        let high_contrast = unsafe {
+            SystemParametersInfoW(
+                SPI_GETHIGHCONTRAST,
+                hc.cbSize,
+                Some(&mut hc as *mut HIGHCONTRASTW as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+        }
+        .is_ok()
+            && hc.dwFlags.contains(HCF_HIGHCONTRASTON);
+
+        Some(SystemTheme { mode, taskbar_mode, accent_color, high_contrast })
+    }
+
+    // Blocks on registry change notifications for each key that carries
+    // theme or accent state; no polling.
+    pub fn watch(app: AppHandle) {
+        for key in [PERSONALIZE_KEY, DWM_KEY] {
+            let app = app.clone();
+            let key_path = unsafe { key.to_string() }.unwrap_or_default();
+            std::thread::spawn(move || {
+                let key_w: Vec<u16> = key_path.encode_utf16().chain(Some(0)).collect();
+                let mut hkey = HKEY::default();
+                let status = unsafe {
+                    RegOpenKeyExW(HKEY_CURRENT_USER, PCWSTR(key_w.as_ptr()), 0, KEY_NOTIFY, &mut hkey)
+                };
+                if status != ERROR_SUCCESS {
+                    tracing::warn!(key = %key_path, ?status, "failed to open registry key for theme watching");
+                    return;
+                }
+                loop {
+                    let status = unsafe {
+                        RegNotifyChangeKeyValue(
+                            hkey,
+                            false,
+                            REG_NOTIFY_CHANGE_LAST_SET,
+                            HANDLE::default(),
+                            false,
+                        )
+                    };
+                    if status != ERROR_SUCCESS {
+                        tracing::warn!(key = %key_path, ?status, "theme watch stopped");
+                        break;
+                    }
+                    super::refresh(&app);
+                }
+                let _ = unsafe { RegCloseKey(hkey) };
+            });
+        }
+    }
+}
+
+// Elsewhere the window's ThemeChanged event drives `refresh`.
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use super::SystemTheme;
+    use tauri::AppHandle;
+
+    pub fn read_theme() -> Option<SystemTheme> {
+        None
+    }
+
+    pub fn watch(_app: AppHandle) {}
+}
@@ -0,0 +1,98 @@
+use tauri::image::Image;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager};
+
+use crate::{capture, lifecycle, window_focus};
+
+const TRAY_ID: &str = "main";
+const TRAY_ICON_PNG: &[u8] = include_bytes!("../icons/32x32.png");
+
+// Menu items whose state changes after the tray is built.
+struct TrayItems {
+    pause_captures: CheckMenuItem<tauri::Wry>,
+}
+
+pub fn build(app: &AppHandle, light_taskbar: bool) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, "show", "Show Gravia", true, None::<&str>)?;
+    let pause_captures =
+        CheckMenuItem::with_id(app, "pause_captures", "Pause captures", true, false, None::<&str>)?;
+    let restart = MenuItem::with_id(app, "restart", "Restart", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show, &pause_captures, &restart, &quit])?;
+    app.manage(TrayItems { pause_captures });
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(tray_icon(light_taskbar)?)
+        .tooltip("Gravia")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show" => show_main_window(app),
+            "pause_captures" => {
+                let paused = app.state::<capture::CapturePause>().is_active();
+                capture::set_paused(app, !paused, None);
+            }
+            "restart" => lifecycle::shutdown(app, true),
+            "quit" => lifecycle::shutdown(app, false),
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+    Ok(())
+}
+
+// Swap to the icon variant that stays visible on the current taskbar.
+pub fn set_theme(app: &AppHandle, light_taskbar: bool) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else { return };
+    match tray_icon(light_taskbar) {
+        Ok(icon) => {
+            if let Err(e) = tray.set_icon(Some(icon)) {
+                tracing::warn!(error = %e, "failed to update tray icon");
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "failed to build tray icon"),
+    }
+}
+
+pub fn set_capture_paused(app: &AppHandle, paused: bool) {
+    if let Some(items) = app.try_state::<TrayItems>() {
+        let _ = items.pause_captures.set_checked(paused);
+    }
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let tooltip = if paused { "Gravia (captures paused)" } else { "Gravia" };
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    window_focus::cancel_pending_hide(app);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+// Monochrome silhouette of the app icon: dark on light taskbars, light on
+// dark ones. Alpha is kept so the outline stays anti-aliased.
+fn tray_icon(light_taskbar: bool) -> tauri::Result<Image<'static>> {
+    let decoded = Image::from_bytes(TRAY_ICON_PNG)?;
+    let shade = if light_taskbar { 0x1f } else { 0xf5 };
+    let mut rgba = decoded.rgba().to_vec();
+    for px in rgba.chunks_exact_mut(4) {
+        px[0] = shade;
+        px[1] = shade;
+        px[2] = shade;
+    }
+    Ok(Image::new_owned(rgba, decoded.width(), decoded.height()))
+}
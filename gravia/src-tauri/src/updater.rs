@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use ts_rs::TS;
+
+use crate::error::{CommandResult, GraviaError};
+use crate::events::{emit_event, GraviaEvent};
+use crate::http_client;
+use crate::lifecycle::{self, Sidecar};
+use crate::settings::{SharedSettings, UpdateChannel};
+
+const STABLE_MANIFEST: &str = "https://github.com/Naitik4516/Gravia/releases/latest/download/latest.json";
+const BETA_MANIFEST: &str = "https://github.com/Naitik4516/Gravia/releases/download/beta/latest.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    #[error("no update is available")]
+    NoUpdateAvailable,
+    #[error("the update's signature could not be verified")]
+    SignatureInvalid,
+    #[error("an update is already being installed")]
+    AlreadyInstalling,
+    #[error("the backend server is being updated, try again shortly")]
+    SidecarUpdateInProgress,
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl UpdateError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            UpdateError::NoUpdateAvailable => "no_update_available",
+            UpdateError::SignatureInvalid => "signature_invalid",
+            UpdateError::AlreadyInstalling => "already_installing",
+            UpdateError::SidecarUpdateInProgress => "sidecar_update_in_progress",
+            UpdateError::Failed(_) => "failed",
+        }
+    }
+}
+
+impl From<tauri_plugin_updater::Error> for UpdateError {
+    fn from(e: tauri_plugin_updater::Error) -> Self {
+        match e {
+            tauri_plugin_updater::Error::Minisign(_) | tauri_plugin_updater::Error::SignatureUtf8(_) => {
+                UpdateError::SignatureInvalid
+            }
+            other => UpdateError::Failed(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub available_version: Option<String>,
+    pub release_notes: Option<String>,
+    pub channel: UpdateChannel,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct DownloadProgress {
+    #[ts(type = "number")]
+    pub downloaded: u64,
+    #[ts(type = "number | null")]
+    pub total: Option<u64>,
+}
+
+// The update found by the last check, so installing doesn't re-query.
+#[derive(Default)]
+pub struct UpdaterState {
+    pending: Mutex<Option<Update>>,
+    installing: AtomicBool,
+}
+
+fn manifest_url(channel: UpdateChannel) -> &'static str {
+    match channel {
+        UpdateChannel::Stable => STABLE_MANIFEST,
+        UpdateChannel::Beta => BETA_MANIFEST,
+    }
+}
+
+async fn check(app: &AppHandle, channel: UpdateChannel) -> Result<Option<Update>, UpdateError> {
+    let url = Url::parse(manifest_url(channel)).map_err(|e| UpdateError::Failed(e.to_string()))?;
+    let proxy = app
+        .state::<SharedSettings>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|s| http_client::proxy_url(&s.proxy));
+    let mut builder = app.updater_builder().endpoints(vec![url])?;
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    let updater = builder.build()?;
+    Ok(updater.check().await?)
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle, state: State<'_, UpdaterState>) -> CommandResult<UpdateInfo> {
+    let channel = app.state::<SharedSettings>().0.lock()?.update_channel;
+    let update = check(&app, channel).await?;
+    tracing::info!(?channel, available = ?update.as_ref().map(|u| &u.version), "update check finished");
+
+    let info = UpdateInfo {
+        current_version: app.package_info().version.to_string(),
+        available_version: update.as_ref().map(|u| u.version.clone()),
+        release_notes: update.as_ref().and_then(|u| u.body.clone()),
+        channel,
+    };
+    *state.pending.lock()? = update;
+    Ok(info)
+}
+
+// Downloads and verifies the update, then quits through the normal cleanup
+// path (so the sidecar is shut down) before handing over to the installer.
+// Emits `update-download-progress` while downloading.
+#[tauri::command]
+pub async fn install_update(app: AppHandle, state: State<'_, UpdaterState>) -> CommandResult<()> {
+    if app.state::<Sidecar>().is_updating() {
+        return Err(UpdateError::SidecarUpdateInProgress.into());
+    }
+    if state.installing.swap(true, Ordering::SeqCst) {
+        return Err(UpdateError::AlreadyInstalling.into());
+    }
+    let result = download_and_install(&app, &state).await;
+    if result.is_err() {
+        state.installing.store(false, Ordering::SeqCst);
+    }
+    result
+}
+
+async fn download_and_install(app: &AppHandle, state: &UpdaterState) -> CommandResult<()> {
+    let pending = state.pending.lock()?.clone();
+    let update = match pending {
+        Some(update) => update,
+        None => {
+            let channel = app.state::<SharedSettings>().0.lock()?.update_channel;
+            check(app, channel).await?.ok_or(UpdateError::NoUpdateAvailable)?
+        }
+    };
+
+    let mut downloaded = 0u64;
+    let progress_app = app.clone();
+    let bytes = update
+        .download(
+            move |chunk, total| {
+                downloaded += chunk as u64;
+                let progress = DownloadProgress { downloaded, total };
+                emit_event(&progress_app, GraviaEvent::UpdateDownloadProgress(progress)).ok();
+            },
+            || {},
+        )
+        .await
+        .map_err(UpdateError::from)?;
+    tracing::info!(version = %update.version, bytes = bytes.len(), "update downloaded and verified");
+
+    // The download already checked the signature; only the install is left.
+    if app.state::<Sidecar>().is_updating() {
+        return Err(UpdateError::SidecarUpdateInProgress.into());
+    }
+    let started = lifecycle::shutdown_then(app, true, move |_| {
+        if let Err(e) = update.install(bytes) {
+            tracing::error!(error = %e, "failed to install update");
+        }
+    });
+    if !started {
+        return Err(GraviaError::internal("quit already in progress"));
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::events::{emit_event, GraviaEvent};
+use crate::settings::SharedSettings;
+
+const MAIN_LABEL: &str = "main";
+const DEBOUNCE: Duration = Duration::from_millis(120);
+// The window's own focus events trail a capture's hide/show a little.
+const CAPTURE_SETTLE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct FocusChanged {
+    pub focused: bool,
+}
+
+// Focus tracking for the main window. `generation` is bumped by every focus
+// event and re-summon, so a debounced emit or pending hide can tell whether
+// it is still the latest thing that happened.
+#[derive(Default)]
+pub struct WindowFocus {
+    compact: AtomicBool,
+    generation: AtomicU64,
+    captures_in_flight: AtomicUsize,
+    capture_ended: Mutex<Option<Instant>>,
+    last_emitted: Mutex<Option<bool>>,
+}
+
+impl WindowFocus {
+    fn capture_settling(&self) -> bool {
+        if self.captures_in_flight.load(Ordering::SeqCst) > 0 {
+            return true;
+        }
+        self.capture_ended
+            .lock()
+            .ok()
+            .and_then(|t| *t)
+            .is_some_and(|t| t.elapsed() < CAPTURE_SETTLE)
+    }
+}
+
+// Held across a capture's hide/show so it doesn't read as the user leaving.
+pub struct CaptureFocusGuard<'a>(&'a WindowFocus);
+
+impl Drop for CaptureFocusGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut ended) = self.0.capture_ended.lock() {
+            *ended = Some(Instant::now());
+        }
+        self.0.captures_in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.0.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+pub fn suppress_for_capture(focus: &WindowFocus) -> CaptureFocusGuard<'_> {
+    focus.captures_in_flight.fetch_add(1, Ordering::SeqCst);
+    CaptureFocusGuard(focus)
+}
+
+// Called when the window is brought back by the tray, hotkey or a deep link.
+pub fn cancel_pending_hide(app: &AppHandle) {
+    app.state::<WindowFocus>().generation.fetch_add(1, Ordering::SeqCst);
+}
+
+fn pointer_over(window: &tauri::Window) -> bool {
+    let (Ok(cursor), Ok(pos), Ok(size)) = (window.cursor_position(), window.outer_position(), window.outer_size())
+    else {
+        return false;
+    };
+    let (left, top) = (f64::from(pos.x), f64::from(pos.y));
+    cursor.x >= left
+        && cursor.y >= top
+        && cursor.x < left + f64::from(size.width)
+        && cursor.y < top + f64::from(size.height)
+}
+
+// Emits `window-focus-changed` once focus has settled, and in compact mode
+// with `auto_hide_on_blur` on, hides the window a while after it loses focus.
+pub fn handle_focus_changed(window: &tauri::Window, focused: bool) {
+    if window.label() != MAIN_LABEL {
+        return;
+    }
+    let app = window.app_handle().clone();
+    let focus = app.state::<WindowFocus>();
+    let generation = focus.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    if focus.capture_settling() {
+        return;
+    }
+
+    let window = window.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(DEBOUNCE).await;
+        let focus = app.state::<WindowFocus>();
+        if focus.generation.load(Ordering::SeqCst) != generation || focus.capture_settling() {
+            return;
+        }
+        if let Ok(mut last) = focus.last_emitted.lock() {
+            if *last != Some(focused) {
+                *last = Some(focused);
+                emit_event(&app, GraviaEvent::WindowFocusChanged(FocusChanged { focused })).ok();
+            }
+        }
+
+        if focused || !focus.compact.load(Ordering::SeqCst) {
+            return;
+        }
+        let (enabled, delay) = app
+            .state::<SharedSettings>()
+            .0
+            .lock()
+            .map(|s| (s.auto_hide_on_blur, Duration::from_millis(s.auto_hide_delay_ms)))
+            .unwrap_or((false, Duration::ZERO));
+        if !enabled {
+            return;
+        }
+        tokio::time::sleep(delay.saturating_sub(DEBOUNCE)).await;
+        if focus.generation.load(Ordering::SeqCst) != generation || pointer_over(&window) {
+            return;
+        }
+        if let Err(e) = window.hide() {
+            tracing::warn!(error = %e, "failed to auto-hide window");
+        }
+    });
+}
+
+// The frontend reports its layout so auto-hide only applies to the overlay.
+#[tauri::command]
+pub fn set_compact_mode(app: AppHandle, enabled: bool) {
+    let focus = app.state::<WindowFocus>();
+    focus.compact.store(enabled, Ordering::SeqCst);
+    focus.generation.fetch_add(1, Ordering::SeqCst);
+}
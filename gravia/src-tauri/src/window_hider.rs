@@ -0,0 +1,473 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+// How often to check whether a hide has taken effect. Polled because no
+// platform tells us: tauri has no visibility event, and `hide` returns
+// before the compositor has taken the window off screen.
+const HIDE_POLL: Duration = Duration::from_millis(4);
+// One frame at 60Hz: the window can still be on screen for the frame the
+// compositor was composing when the hide landed. The default settle time.
+pub const COMPOSITOR_FRAME: Duration = Duration::from_millis(17);
+
+// How long a capture waited on the window before grabbing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HideWait {
+    // Until the window was off screen, or its capture exclusion applied.
+    pub hide: Duration,
+    // The settle time after that.
+    pub settle: Duration,
+}
+
+impl HideWait {
+    pub fn total(&self) -> Duration {
+        self.hide + self.settle
+    }
+}
+
+// The window couldn't be taken out of a capture or brought back after it.
+#[derive(Debug, thiserror::Error)]
+pub enum HideError {
+    #[error("failed to hide the window before capturing: {0}")]
+    Hide(String),
+    #[error("failed to show the window again after capturing: {0}")]
+    Show(String),
+}
+
+// Shows and refocuses a window we hid once dropped, so it comes back on
+// every way out of a capture: errors, cancellation, the capture future
+// being dropped, a panic. `restore` does the same but hands back a failed
+// show instead of only logging it.
+pub struct RestoreGuard<'a> {
+    window: &'a dyn WindowHider,
+    armed: bool,
+}
+
+impl<'a> RestoreGuard<'a> {
+    pub fn new(window: &'a dyn WindowHider) -> Self {
+        Self { window, armed: true }
+    }
+
+    pub fn restore(mut self) -> Result<(), HideError> {
+        self.armed = false;
+        show_and_focus(self.window)
+    }
+}
+
+impl Drop for RestoreGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            if let Err(e) = show_and_focus(self.window) {
+                tracing::error!(error = %e, "window not restored after an interrupted capture");
+            }
+        }
+    }
+}
+
+// Focus is best effort; a window back on screen can be clicked.
+fn show_and_focus(window: &dyn WindowHider) -> Result<(), HideError> {
+    window.show().map_err(HideError::Show)?;
+    if let Err(e) = window.focus() {
+        tracing::warn!(error = %e, "failed to refocus window");
+    }
+    Ok(())
+}
+
+// Lifts a window's capture exclusion once dropped, like `RestoreGuard`.
+struct ExclusionGuard<'a>(&'a dyn WindowHider);
+
+impl Drop for ExclusionGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.set_excluded_from_capture(false) {
+            tracing::warn!(error = %e, "failed to lift capture exclusion");
+        }
+    }
+}
+
+// The window operations a capture needs, so the hide/capture/restore
+// sequence doesn't depend on a live webview.
+pub trait WindowHider: Send + Sync {
+    fn hide(&self) -> Result<(), String>;
+    fn show(&self) -> Result<(), String>;
+    fn focus(&self) -> Result<(), String>;
+    fn is_visible(&self) -> bool;
+    fn current_monitor(&self) -> Option<String>;
+    // Leaves the window on screen but out of screen captures. Fails where
+    // the OS can't do that.
+    fn set_excluded_from_capture(&self, excluded: bool) -> Result<(), String>;
+}
+
+impl WindowHider for tauri::Window {
+    fn hide(&self) -> Result<(), String> {
+        tauri::Window::hide(self).map_err(|e| e.to_string())
+    }
+
+    fn show(&self) -> Result<(), String> {
+        tauri::Window::show(self).map_err(|e| e.to_string())
+    }
+
+    fn focus(&self) -> Result<(), String> {
+        self.set_focus().map_err(|e| e.to_string())
+    }
+
+    fn is_visible(&self) -> bool {
+        tauri::Window::is_visible(self).unwrap_or(true)
+    }
+
+    fn current_monitor(&self) -> Option<String> {
+        tauri::Window::current_monitor(self).ok().flatten().and_then(|m| m.name().cloned())
+    }
+
+    // WDA_EXCLUDEFROMCAPTURE needs Windows 10 2004; older versions reject
+    // it. Both GDI grabs and desktop duplication honor it.
+    #[cfg(target_os = "windows")]
+    fn set_excluded_from_capture(&self, excluded: bool) -> Result<(), String> {
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::UI::WindowsAndMessaging::{
+            SetWindowDisplayAffinity, WDA_EXCLUDEFROMCAPTURE, WDA_NONE,
+        };
+
+        let hwnd = HWND(self.hwnd().map_err(|e| e.to_string())?.0 as *mut _);
+        let affinity = if excluded { WDA_EXCLUDEFROMCAPTURE } else { WDA_NONE };
+        unsafe { SetWindowDisplayAffinity(hwnd, affinity) }.map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn set_excluded_from_capture(&self, _excluded: bool) -> Result<(), String> {
+        Err("not supported on this platform".to_string())
+    }
+}
+
+// Hides `window`, waits until it reports hidden (at most `max_wait`) plus
+// `settle` for the compositor and any fade-out, runs `capture`, then
+// restores it. Returns the capture's result and how long the wait took. A
+// window that was already hidden is left hidden. Returns `Ok(None)` without
+// capturing if `cancel` fires while waiting. The window is restored
+// whatever happens, by `RestoreGuard`; a failed hide or show fails with
+// `HideError`, dropping any capture taken.
+pub async fn with_window_hidden<F, Fut, T>(
+    window: &dyn WindowHider,
+    max_wait: Duration,
+    settle: Duration,
+    cancel: &CancellationToken,
+    capture: F,
+) -> Result<Option<(T, HideWait)>, HideError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let mut cancelled = cancel.is_cancelled();
+    // Only a window we hid gets shown again.
+    let mut guard = None;
+    if window.is_visible() && !cancelled {
+        if let Err(e) = window.hide() {
+            tracing::warn!(error = %e, monitor = ?window.current_monitor(), "failed to hide window before screenshot");
+            // It may have gone partway, e.g. hidden but not reported so.
+            show_and_focus(window).ok();
+            return Err(HideError::Hide(e));
+        }
+        guard = Some(RestoreGuard::new(window));
+    }
+    let mut waited = HideWait::default();
+    if guard.is_some() {
+        tokio::select! {
+            (acknowledged, wait) = wait_until_hidden(window, max_wait, settle) => {
+                if !acknowledged {
+                    tracing::warn!(?max_wait, "window still visible, capturing anyway");
+                }
+                waited = wait;
+            }
+            _ = cancel.cancelled() => cancelled = true,
+        }
+    }
+    let result = if cancelled { None } else { Some((capture().await, waited)) };
+    if let Some(guard) = guard {
+        guard.restore().inspect_err(|e| tracing::error!(error = %e, "window not restored after screenshot"))?;
+    }
+    Ok(result)
+}
+
+// Runs `capture` with `window` excluded from screen captures but left on
+// screen, so it neither flickers nor loses focus, then lifts the exclusion,
+// also when the capture is cut short. Hands `capture` back unrun where
+// exclusion isn't supported. Returns `Ok(None)` without capturing if
+// `cancel` fires first.
+pub async fn with_window_excluded<F, Fut, T>(
+    window: &dyn WindowHider,
+    cancel: &CancellationToken,
+    capture: F,
+) -> Result<Option<(T, HideWait)>, F>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    if cancel.is_cancelled() {
+        return Ok(None);
+    }
+    if let Err(e) = window.set_excluded_from_capture(true) {
+        tracing::debug!(error = %e, "capture exclusion unavailable, hiding instead");
+        return Err(capture);
+    }
+    let _excluded = ExclusionGuard(window);
+    // The exclusion shows up from the next composed frame.
+    let started = Instant::now();
+    let waited = tokio::select! {
+        _ = tokio::time::sleep(COMPOSITOR_FRAME) => Some(HideWait { hide: started.elapsed(), settle: Duration::ZERO }),
+        _ = cancel.cancelled() => None,
+    };
+    Ok(match waited {
+        Some(waited) => Some((capture().await, waited)),
+        None => None,
+    })
+}
+
+// Returns whether the window reported hidden within `max_wait`, and how
+// long each part of the wait took. `settle` is waited out either way.
+async fn wait_until_hidden(window: &dyn WindowHider, max_wait: Duration, settle: Duration) -> (bool, HideWait) {
+    let started = Instant::now();
+    let deadline = started + max_wait;
+    let mut hidden = true;
+    while window.is_visible() {
+        if Instant::now() >= deadline {
+            hidden = false;
+            break;
+        }
+        tokio::time::sleep(HIDE_POLL).await;
+    }
+    let hide = started.elapsed();
+    tokio::time::sleep(settle).await;
+    (hidden, HideWait { hide, settle: started.elapsed() - hide })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Stands in for the webview window. A hide takes `hide_lag` visibility
+    // checks to show; `None` never does.
+    #[derive(Default)]
+    struct FakeWindow {
+        state: Mutex<FakeState>,
+        hide_lag: Option<u32>,
+        fail_hide: bool,
+        fail_show: bool,
+        excludable: bool,
+    }
+
+    #[derive(Default)]
+    struct FakeState {
+        hidden: bool,
+        checks_until_hidden: Option<u32>,
+        hides: u32,
+        shows: u32,
+        excluded: bool,
+    }
+
+    impl FakeWindow {
+        fn visible() -> Self {
+            Self { hide_lag: Some(2), ..Self::default() }
+        }
+
+        fn hidden() -> Self {
+            let window = Self::visible();
+            window.state.lock().unwrap().hidden = true;
+            window
+        }
+
+        fn counts(&self) -> (u32, u32) {
+            let state = self.state.lock().unwrap();
+            (state.hides, state.shows)
+        }
+
+        fn on_screen(&self) -> bool {
+            !self.state.lock().unwrap().hidden
+        }
+    }
+
+    impl WindowHider for FakeWindow {
+        fn hide(&self) -> Result<(), String> {
+            let mut state = self.state.lock().unwrap();
+            state.hides += 1;
+            if self.fail_hide {
+                return Err("hide refused".into());
+            }
+            state.checks_until_hidden = self.hide_lag;
+            Ok(())
+        }
+
+        fn show(&self) -> Result<(), String> {
+            let mut state = self.state.lock().unwrap();
+            state.shows += 1;
+            if self.fail_show {
+                return Err("show refused".into());
+            }
+            state.hidden = false;
+            state.checks_until_hidden = None;
+            Ok(())
+        }
+
+        fn focus(&self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn is_visible(&self) -> bool {
+            let mut state = self.state.lock().unwrap();
+            match state.checks_until_hidden {
+                Some(0) => {
+                    state.hidden = true;
+                    state.checks_until_hidden = None;
+                }
+                Some(n) => state.checks_until_hidden = Some(n - 1),
+                None => {}
+            }
+            !state.hidden
+        }
+
+        fn current_monitor(&self) -> Option<String> {
+            None
+        }
+
+        fn set_excluded_from_capture(&self, excluded: bool) -> Result<(), String> {
+            if !self.excludable {
+                return Err("not supported".into());
+            }
+            self.state.lock().unwrap().excluded = excluded;
+            Ok(())
+        }
+    }
+
+    const MAX_WAIT: Duration = Duration::from_millis(300);
+
+    #[tokio::test(start_paused = true)]
+    async fn the_window_is_off_screen_for_the_capture_and_back_after() {
+        let (window, cancel) = (FakeWindow::visible(), CancellationToken::new());
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async { window.on_screen() })
+            .await
+            .unwrap();
+        assert!(matches!(result, Some((false, _))));
+        assert!(window.on_screen());
+        assert_eq!(window.counts(), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_capture_still_restores_the_window() {
+        let (window, cancel) = (FakeWindow::visible(), CancellationToken::new());
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {
+            Err::<(), _>("grab failed")
+        })
+        .await
+        .unwrap();
+        assert!(matches!(result, Some((Err("grab failed"), _))));
+        assert!(window.on_screen());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelling_while_waiting_restores_without_capturing() {
+        let window = FakeWindow { hide_lag: None, ..FakeWindow::default() };
+        let cancel = CancellationToken::new();
+        let captured = std::sync::atomic::AtomicBool::new(false);
+        let hidden = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {
+            captured.store(true, std::sync::atomic::Ordering::SeqCst);
+        });
+        let canceller = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancel.cancel();
+        };
+        let (result, ()) = tokio::join!(hidden, canceller);
+        assert!(result.unwrap().is_none());
+        assert!(!captured.into_inner());
+        assert!(window.on_screen());
+        assert_eq!(window.counts(), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropping_the_capture_midway_restores_the_window() {
+        let (window, cancel) = (FakeWindow::visible(), CancellationToken::new());
+        let stuck = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, std::future::pending::<()>);
+        assert!(tokio::time::timeout(Duration::from_secs(1), stuck).await.is_err());
+        assert!(window.on_screen());
+        assert_eq!(window.counts(), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_window_that_was_already_hidden_stays_hidden() {
+        let (window, cancel) = (FakeWindow::hidden(), CancellationToken::new());
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {}).await.unwrap();
+        assert!(result.is_some());
+        assert!(!window.on_screen());
+        assert_eq!(window.counts(), (0, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_hide_skips_the_capture_and_tries_to_show() {
+        let window = FakeWindow { fail_hide: true, ..FakeWindow::visible() };
+        let cancel = CancellationToken::new();
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {}).await;
+        assert!(matches!(result, Err(HideError::Hide(_))));
+        assert_eq!(window.counts(), (1, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_show_is_reported() {
+        let window = FakeWindow { fail_show: true, ..FakeWindow::visible() };
+        let cancel = CancellationToken::new();
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {}).await;
+        assert!(matches!(result, Err(HideError::Show(_))));
+        // Reported once, not retried again by the guard.
+        assert_eq!(window.counts(), (1, 1));
+    }
+
+    #[test]
+    fn a_dropped_guard_shows_the_window_and_a_restored_one_does_not_again() {
+        let window = FakeWindow::hidden();
+        drop(RestoreGuard::new(&window));
+        assert_eq!(window.counts(), (0, 1));
+        RestoreGuard::new(&window).restore().unwrap();
+        assert_eq!(window.counts(), (0, 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_wait_ends_at_the_first_poll_that_sees_the_window_hidden() {
+        let (window, cancel) = (FakeWindow::visible(), CancellationToken::new());
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {}).await.unwrap();
+        let ((), waited) = result.unwrap();
+        // Still visible for the two checks the hide lags by.
+        assert_eq!(waited.hide, 2 * HIDE_POLL);
+        assert_eq!(waited.settle, COMPOSITOR_FRAME);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_window_that_never_hides_is_captured_after_max_wait() {
+        let window = FakeWindow { hide_lag: None, ..FakeWindow::default() };
+        let cancel = CancellationToken::new();
+        let result = with_window_hidden(&window, MAX_WAIT, COMPOSITOR_FRAME, &cancel, || async {}).await.unwrap();
+        let ((), waited) = result.unwrap();
+        assert!(waited.hide >= MAX_WAIT && waited.hide < MAX_WAIT + HIDE_POLL);
+        assert_eq!(waited.settle, COMPOSITOR_FRAME);
+        assert!(window.on_screen());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exclusion_is_lifted_after_the_capture() {
+        let window = FakeWindow { excludable: true, ..FakeWindow::visible() };
+        let cancel = CancellationToken::new();
+        let excluded_during = with_window_excluded(&window, &cancel, || async {
+            window.state.lock().unwrap().excluded
+        });
+        let Ok(Some((excluded_during, _))) = excluded_during.await else { panic!("capture didn't run") };
+        assert!(excluded_during);
+        assert!(!window.state.lock().unwrap().excluded);
+        // Never hidden: it stayed on screen the whole time.
+        assert_eq!(window.counts(), (0, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn without_exclusion_the_capture_is_handed_back() {
+        let (window, cancel) = (FakeWindow::visible(), CancellationToken::new());
+        let handed_back = with_window_excluded(&window, &cancel, || async { 1 }).await;
+        let Err(capture) = handed_back else { panic!("expected the capture back") };
+        assert_eq!(capture().await, 1);
+    }
+}
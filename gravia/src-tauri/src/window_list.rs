@@ -0,0 +1,276 @@
+use crate::capture::CaptureError;
+use crate::foreground::{ForegroundError, WindowRect};
+
+// Candidates listed in an ambiguous-match error.
+const MAX_CANDIDATES: usize = 10;
+
+// A visible top-level window belonging to another app.
+#[derive(Debug, Clone)]
+pub struct WindowEntry {
+    // Native handle; only meaningful to `capture`.
+    pub id: u64,
+    pub title: String,
+    pub process_name: String,
+    pub pid: u32,
+    pub bounds: WindowRect,
+}
+
+pub fn list_windows() -> Result<Vec<WindowEntry>, ForegroundError> {
+    platform::list_windows()
+}
+
+// Renders a window even if others cover it. Returns top-down BGRX, as GDI
+// draws it; see `PixelFormat::Bgrx`.
+#[cfg(feature = "capture")]
+pub fn capture(id: u64) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+    platform::capture(id)
+}
+
+// Whether the window belongs to one of `apps`, given by process name with
+// or without `.exe`, ignoring case.
+pub fn belongs_to_any(window: &WindowEntry, apps: &[String]) -> bool {
+    let process = window.process_name.to_lowercase();
+    let process = process.trim_end_matches(".exe");
+    apps.iter().any(|app| app.to_lowercase().trim_end_matches(".exe") == process)
+}
+
+// Picks the window whose title or process name contains `matcher`, ignoring
+// case. Among several, one whose title or process name equals it outright
+// wins; otherwise the match is ambiguous and the candidates are listed.
+pub fn find<'a>(windows: &'a [WindowEntry], matcher: &str) -> Result<&'a WindowEntry, CaptureError> {
+    let needle = matcher.trim().to_lowercase();
+    let matches: Vec<&WindowEntry> = windows
+        .iter()
+        .filter(|w| w.title.to_lowercase().contains(&needle) || w.process_name.to_lowercase().contains(&needle))
+        .collect();
+    match matches.as_slice() {
+        [] => Err(CaptureError::WindowNotFound(matcher.to_string())),
+        [only] => Ok(only),
+        several => {
+            let exact = |w: &&&WindowEntry| {
+                w.title.to_lowercase() == needle || w.process_name.to_lowercase().trim_end_matches(".exe") == needle
+            };
+            let mut exact_matches = several.iter().filter(exact);
+            match (exact_matches.next(), exact_matches.next()) {
+                (Some(only), None) => Ok(only),
+                _ => Err(CaptureError::AmbiguousWindow {
+                    matcher: matcher.to_string(),
+                    candidates: several.iter().take(MAX_CANDIDATES).map(|w| w.title.clone()).collect(),
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::WindowEntry;
+    use crate::foreground::ForegroundError;
+    use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+    use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextLengthW, IsIconic, IsWindowVisible};
+    #[cfg(feature = "capture")]
+    use windows::Win32::{
+        Foundation::RECT,
+        Graphics::Gdi::{
+            CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits, ReleaseDC,
+            SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+        },
+        Storage::Xps::{PrintWindow, PRINT_WINDOW_FLAGS},
+        UI::WindowsAndMessaging::GetWindowRect,
+    };
+
+    // Asks the window to render through DWM, which also covers windows
+    // drawn with DirectX. Not in the generated constants.
+    #[cfg(feature = "capture")]
+    const PW_RENDERFULLCONTENT: PRINT_WINDOW_FLAGS = PRINT_WINDOW_FLAGS(2);
+
+    pub fn list_windows() -> Result<Vec<WindowEntry>, ForegroundError> {
+        let mut handles: Vec<HWND> = Vec::new();
+        unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let handles = &mut *(lparam.0 as *mut Vec<HWND>);
+            handles.push(hwnd);
+            true.into()
+        }
+        unsafe { EnumWindows(Some(collect), LPARAM(&mut handles as *mut Vec<HWND> as isize)) }
+            .map_err(|e| ForegroundError::Os(e.to_string()))?;
+
+        let own_pid = std::process::id();
+        let mut windows = Vec::new();
+        for hwnd in handles {
+            if !is_listed(hwnd) {
+                continue;
+            }
+            let Ok(app) = crate::foreground::app_for_window(hwnd) else { continue };
+            if app.pid == own_pid {
+                continue;
+            }
+            let Ok(bounds) = crate::foreground::window_bounds(hwnd) else { continue };
+            windows.push(WindowEntry {
+                id: hwnd.0 as usize as u64,
+                title: app.title,
+                process_name: app.process_name,
+                pid: app.pid,
+                bounds,
+            });
+        }
+        Ok(windows)
+    }
+
+    // Visible, titled, uncloaked and not minimized; a minimized window has
+    // nothing to render.
+    fn is_listed(hwnd: HWND) -> bool {
+        unsafe {
+            if !IsWindowVisible(hwnd).as_bool() || IsIconic(hwnd).as_bool() || GetWindowTextLengthW(hwnd) == 0 {
+                return false;
+            }
+            let mut cloaked = 0u32;
+            let read = DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_CLOAKED,
+                &mut cloaked as *mut u32 as *mut _,
+                std::mem::size_of::<u32>() as u32,
+            );
+            read.is_err() || cloaked == 0
+        }
+    }
+
+    #[cfg(feature = "capture")]
+    pub fn capture(id: u64) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        let hwnd = HWND(id as usize as *mut _);
+        let mut rect = RECT::default();
+        unsafe { GetWindowRect(hwnd, &mut rect)? };
+        let width = (rect.right - rect.left).max(0);
+        let height = (rect.bottom - rect.top).max(0);
+        if width == 0 || height == 0 {
+            anyhow::bail!("window has no area");
+        }
+
+        unsafe {
+            let screen_dc = GetDC(HWND::default());
+            let mem_dc = CreateCompatibleDC(screen_dc);
+            let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+            let previous = SelectObject(mem_dc, bitmap);
+            let printed = PrintWindow(hwnd, mem_dc, PW_RENDERFULLCONTENT).as_bool();
+
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width,
+                    // Negative height asks for rows top-down.
+                    biHeight: -height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut pixels = vec![0u8; width as usize * height as usize * 4];
+            let rows = GetDIBits(
+                mem_dc,
+                bitmap,
+                0,
+                height as u32,
+                Some(pixels.as_mut_ptr() as *mut _),
+                &mut info,
+                DIB_RGB_COLORS,
+            );
+
+            SelectObject(mem_dc, previous);
+            let _ = DeleteObject(bitmap);
+            let _ = DeleteDC(mem_dc);
+            ReleaseDC(HWND::default(), screen_dc);
+
+            if !printed || rows == 0 {
+                anyhow::bail!("window could not be rendered");
+            }
+            Ok((width as u32, height as u32, pixels))
+        }
+    }
+}
+
+// Window listing and capture are Windows-only, like foreground tracking.
+// Listing fails with `foreground.unsupported` elsewhere.
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use super::WindowEntry;
+    use crate::foreground::ForegroundError;
+
+    pub fn list_windows() -> Result<Vec<WindowEntry>, ForegroundError> {
+        Err(ForegroundError::Unsupported)
+    }
+
+    #[cfg(feature = "capture")]
+    pub fn capture(_id: u64) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        anyhow::bail!("window capture is not supported on this platform")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u64, title: &str, process_name: &str) -> WindowEntry {
+        WindowEntry {
+            id,
+            title: title.to_string(),
+            process_name: process_name.to_string(),
+            pid: id as u32,
+            bounds: WindowRect { x: 0, y: 0, width: 800, height: 600 },
+        }
+    }
+
+    enum Expected {
+        Window(&'static str),
+        NotFound,
+        Ambiguous(&'static [&'static str]),
+    }
+
+    #[test]
+    fn find_matches_titles_and_process_names() {
+        use Expected::*;
+        let windows = [
+            window(1, "Inbox - Mail", "OUTLOOK.EXE"),
+            window(2, "notes.txt - Notepad", "notepad.exe"),
+            window(3, "README.md - Notepad", "notepad.exe"),
+            window(4, "Visual Studio", "Code.exe"),
+            window(5, "code review - Slack", "slack.exe"),
+        ];
+        let cases = [
+            // Part of a title, in any case and with stray whitespace.
+            ("inbox", Window("Inbox - Mail")),
+            ("  MAIL ", Window("Inbox - Mail")),
+            ("readme", Window("README.md - Notepad")),
+            // Part of a process name, with or without `.exe`.
+            ("outlook", Window("Inbox - Mail")),
+            ("Outlook.exe", Window("Inbox - Mail")),
+            // Several match, but only one process is called that outright.
+            ("code", Window("Visual Studio")),
+            // Several match and none stands out.
+            ("notepad", Ambiguous(&["notes.txt - Notepad", "README.md - Notepad"])),
+            ("firefox", NotFound),
+        ];
+        for (matcher, expected) in cases {
+            match (find(&windows, matcher), expected) {
+                (Ok(found), Window(title)) => assert_eq!(found.title, title, "{matcher:?}"),
+                (Err(CaptureError::WindowNotFound(m)), NotFound) => assert_eq!(m, matcher),
+                (Err(CaptureError::AmbiguousWindow { matcher: m, candidates }), Ambiguous(titles)) => {
+                    assert_eq!((m.as_str(), candidates), (matcher, titles.iter().map(|t| t.to_string()).collect()));
+                }
+                (found, _) => panic!("{matcher:?}: unexpected {found:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn an_ambiguous_match_lists_at_most_the_candidate_cap() {
+        let windows: Vec<_> =
+            (0..MAX_CANDIDATES as u64 + 5).map(|i| window(i, &format!("Terminal {i}"), "wt.exe")).collect();
+        let Err(CaptureError::AmbiguousWindow { candidates, .. }) = find(&windows, "terminal") else {
+            panic!("expected an ambiguous match");
+        };
+        assert_eq!(candidates.len(), MAX_CANDIDATES);
+        assert_eq!(candidates[0], "Terminal 0");
+    }
+}
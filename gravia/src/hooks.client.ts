@@ -1,16 +1,11 @@
-import { defaultWindowIcon } from "@tauri-apps/api/app";
-import { Menu } from "@tauri-apps/api/menu";
-import { TrayIcon } from "@tauri-apps/api/tray";
 import type { ServerInit } from '@sveltejs/kit';
 import { register as registerShortcut, isRegistered as isShortcutRegistered, unregister as unregisterShortcut } from '@tauri-apps/plugin-global-shortcut';
-import { exit, relaunch } from '@tauri-apps/plugin-process';
-import { getCurrentWindow, Window } from '@tauri-apps/api/window';
+import { Window } from '@tauri-apps/api/window';
 import { toggleWindowMode } from '$lib/state.svelte';
 import { chatClient } from '$lib/chat/chatService';
 import { globalState } from '$lib/state.svelte';
 import { settingsCategoryUrl } from '$lib/constants/api';
 import { once } from '@tauri-apps/api/event';
-import { emit } from '@tauri-apps/api/event';
 
 const registerShortcuts = async () => {
     try {
@@ -76,53 +71,7 @@ const registerShortcuts = async () => {
 export const init: ServerInit = async () => {
 
     await once('server-ready', async (_event) => {
-        const appWindow = getCurrentWindow();
-
-        const menu = await Menu.new({
-            items: [
-                {
-                    id: "quit",
-                    text: "Quit",
-                    action: async () => {
-                        await emit('app-close');
-                        await exit(0);
-
-                    }
-                },
-                {
-                    id: "restart",
-                    text: "Restart",
-                    action: async () => {
-                        await emit('app-close');
-                        await relaunch();
-                    }
-                }
-            ],
-        });
-
-        const iconCandidate = await defaultWindowIcon();
-        const options = {
-            icon: iconCandidate || '',
-            menu,
-            menuOnLeftClick: false,
-            action: (event) => {
-                switch (event.type) {
-                    case 'Click':
-                        console.log(`mouse ${event.button} button pressed`);
-                        if (event.button !== 'Left') break;
-                        appWindow.unminimize();
-                        appWindow.show();
-                        appWindow.setFocus();
-                        break;
-                    case 'DoubleClick':
-                        console.log(`mouse ${event.button} button pressed`);
-                        break;
-                }
-            }
-        };
-
-        const tray = await TrayIcon.new(options);
-
+        // The tray icon and its Quit/Restart menu are owned by the Rust side.
         const splashscreen = await Window.getByLabel("splashscreen");
         const mainWindow = await Window.getByLabel("main");
         splashscreen?.close();
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Color } from "./Color";
+import type { Point } from "./Point";
+
+export type Annotation = { "kind": "rect", x: number, y: number, width: number, height: number, color: Color | null, stroke_width: number | null, } | { "kind": "filled_rect", x: number, y: number, width: number, height: number, color: Color | null, } | { "kind": "arrow", from: Point, to: Point, color: Color | null, stroke_width: number | null, } | { "kind": "label", x: number, y: number, text: string, color: Color | null, background: Color | null, size: number | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileAttachment } from "./FileAttachment";
+
+export type Attachment = { "type": "file" } & FileAttachment;
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AttachmentPreview = { "kind": "text", text: string, truncated: boolean, } | { "kind": "image", data_url: string, width: number, height: number, } | { "kind": "none" };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AutoCaptureNotice = { session_id: string | null, message_id: string | null, captured_at: string, monitor: string, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureBackend } from "./CaptureBackend";
+
+export type BackendDiagnostics = { backend: CaptureBackend, available: boolean, last_success_at: string | null, last_error: string | null, last_error_at: string | null, failures: number, };
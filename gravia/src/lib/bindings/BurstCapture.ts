@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BurstFrame } from "./BurstFrame";
+import type { WindowHandling } from "./WindowHandling";
+
+export type BurstCapture = { frames: Array<BurstFrame>, window_handling: WindowHandling, truncated: boolean, };
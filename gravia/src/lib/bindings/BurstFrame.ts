@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureFormat } from "./CaptureFormat";
+import type { Downscale } from "./Downscale";
+import type { PayloadAdjustment } from "./PayloadAdjustment";
+
+export type BurstFrame = { index: number, captured_at: string, offset_ms: number, capture_id: string | null, base64: string | null, format: CaptureFormat, bytes: number | null, content_hash: string | null, downscale: Downscale | null, adjustment: PayloadAdjustment | null, unchanged: boolean, redacted: number, error: string | null, error_code: string | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ByteBudget = { max_bytes: number, original_bytes: number, bytes: number, scale: number, quality: number | null, attempts: number, fits: boolean, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackendDiagnostics } from "./BackendDiagnostics";
+import type { CaptureBackend } from "./CaptureBackend";
+
+export type CaptureDiagnostics = { preferred: CaptureBackend, backends: Array<BackendDiagnostics>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ByteBudget } from "./ByteBudget";
+import type { CaptureFormat } from "./CaptureFormat";
+import type { DisplayScale } from "./DisplayScale";
+import type { Downscale } from "./Downscale";
+
+export type CaptureInfo = { capture_id: string, captured_at: string, monitor: string, format: CaptureFormat, width: number, height: number, bytes: number, content_hash: string, downscale: Downscale | null, scale: DisplayScale | null, budget: ByteBudget | null, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureFormat } from "./CaptureFormat";
+import type { PngCompression } from "./PngCompression";
+import type { PngFilter } from "./PngFilter";
+import type { RedactRect } from "./RedactRect";
+import type { RedactStyle } from "./RedactStyle";
+import type { StampCorner } from "./StampCorner";
+
+export type CaptureOptions = { format: CaptureFormat, quality: number | null, max_width: number | null, max_height: number | null, grayscale: boolean, include_cursor: boolean, thumbnail: boolean, analyze: boolean, logical_resolution: boolean, png_compression: PngCompression | null, png_filter: PngFilter | null, max_bytes: number | null, unchanged_distance: number | null, include_unchanged: boolean, redact_regions: Array<RedactRect>, redact_style: RedactStyle, stamp: boolean, stamp_corner: StampCorner | null, hide_window: boolean | null, hide_delay_ms: number | null, retry_attempts: number | null, retry_backoff_ms: number | null, force: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureBackend } from "./CaptureBackend";
+import type { PixelFormat } from "./PixelFormat";
+import type { PoolStats } from "./PoolStats";
+
+export type CapturePerfStats = { captures: number, last_hide_wait_ms: number, last_grab_ms: number, last_encode_ms: number, last_peak_bytes: number, last_backend: CaptureBackend, last_source_format: PixelFormat, pool: PoolStats, };
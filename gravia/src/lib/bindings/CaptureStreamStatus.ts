@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Throttle } from "./Throttle";
+
+export type CaptureStreamStatus = { active: boolean, interval_ms: number | null, effective_interval_ms: number | null, idle_paused: boolean, throttle: Throttle, };
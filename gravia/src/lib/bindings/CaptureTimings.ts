@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CaptureTimings = { hide_ms: number, settle_ms: number, grab_ms: number, convert_ms: number, process_ms: number, encode_ms: number, base64_ms: number, total_ms: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ContextInfo } from "./ContextInfo";
+import type { Decision } from "./Decision";
+import type { Reason } from "./Reason";
+
+export type ClassificationResult = { needs_screenshot: boolean, decision: Decision, query_id: string | null, confidence: number, screenshot_score: number, no_screenshot_score: number, reasons: Array<Reason>, reasoning: Array<string>, summary_code: string, summary: string, context_info: ContextInfo, suppressed_reason: string | null, text_oriented: boolean, override: string | null, screenshot_base64: string | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KeywordEdits } from "./KeywordEdits";
+
+export type ClassifierConfig = { screenshot_keywords: KeywordEdits, no_screenshot_keywords: KeywordEdits, followup_patterns: KeywordEdits, ui_patterns: KeywordEdits, error_patterns: KeywordEdits, task_indicators: KeywordEdits, force_capture_phrases: KeywordEdits, forbid_capture_phrases: KeywordEdits, ask_below_confidence: number, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ClassifierConfig } from "./ClassifierConfig";
+import type { Keywords } from "./Keywords";
+
+export type ClassifierConfigState = { config: ClassifierConfig, keywords: Keywords, };
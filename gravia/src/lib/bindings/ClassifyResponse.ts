@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ByteBudget } from "./ByteBudget";
+import type { CaptureBackend } from "./CaptureBackend";
+import type { CaptureFormat } from "./CaptureFormat";
+import type { CaptureTimings } from "./CaptureTimings";
+import type { ClassificationResult } from "./ClassificationResult";
+import type { DisplayScale } from "./DisplayScale";
+import type { Downscale } from "./Downscale";
+import type { PayloadAdjustment } from "./PayloadAdjustment";
+import type { ScreenAnalysis } from "./ScreenAnalysis";
+import type { ScreenCapture } from "./ScreenCapture";
+import type { ScreenText } from "./ScreenText";
+import type { WindowHandling } from "./WindowHandling";
+
+export type ClassifyResponse = { classification: ClassificationResult, screenshot_base64: string | null, screenshot_format: CaptureFormat | null, screenshot_bytes: number | null, content_hash: string | null, screenshot_downscale: Downscale | null, thumbnail_base64: string | null, capture_backend: CaptureBackend | null, window_handling: WindowHandling | null, capture_id: string | null, payload_adjustment: PayloadAdjustment | null, screenshots: Array<ScreenCapture> | null, screenshot_from_cache: boolean, screenshot_unchanged: boolean, screenshot_redacted: number, screenshot_analysis: ScreenAnalysis | null, screenshot_scale: DisplayScale | null, screenshot_budget: ByteBudget | null, capture_timings: CaptureTimings | null, capture_attempts: number, screenshot_blank: boolean, screen_text: ScreenText | null, coalesced: boolean, };
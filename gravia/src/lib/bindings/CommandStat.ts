@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CommandStat = { command: string, calls: number, errors: Record<string, number>, p50_us: number, p95_us: number, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureFormat } from "./CaptureFormat";
+
+export type ConfirmedCapture = { capture_id: string, screenshot_base64: string, screenshot_format: CaptureFormat, content_hash: string, monitor: string, };
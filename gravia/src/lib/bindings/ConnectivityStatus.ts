@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Connectivity } from "./Connectivity";
+
+export type ConnectivityStatus = { state: Connectivity, changed_at: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ContextInfo = { has_context: boolean, context_type: string | null, recent_screenshot: boolean, assistant_gave_instructions: boolean, user_in_middle_of_task: boolean, context_strength: number, };
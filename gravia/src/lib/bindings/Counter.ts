@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Counter = "classifications" | "screenshots_wanted" | "captures_taken" | "captures_reused" | "capture_failures" | "payloads_shrunk" | "server_starts" | "server_start_failures";
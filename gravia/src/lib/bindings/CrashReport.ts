@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CrashReport = { timestamp: string, app_version: string, thread: string, message: string, location: string | null, backtrace: string, log_tail: Array<string>, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Counter } from "./Counter";
+import type { Histogram } from "./Histogram";
+import type { HistogramData } from "./HistogramData";
+
+export type DailyMetrics = { date: string, counters: { [key in Counter]?: number }, histograms: { [key in Histogram]?: HistogramData }, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type DisplayScale = { scale_factor: number, physical_width: number, physical_height: number, logical_width: number, logical_height: number, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AttachmentPreview } from "./AttachmentPreview";
+
+export type FileAttachment = { id: string, name: string, path: string, mime_type: string, size: number, by_reference: boolean, preview: AttachmentPreview, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ForegroundApp = { title: string, process_name: string, executable_path: string, pid: number, is_gravia: boolean, };
@@ -0,0 +1,35 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ActionExecuted } from "./ActionExecuted";
+import type { Attachment } from "./Attachment";
+import type { AutoCaptureNotice } from "./AutoCaptureNotice";
+import type { CapturePauseState } from "./CapturePauseState";
+import type { CaptureThrottled } from "./CaptureThrottled";
+import type { CaptureTiming } from "./CaptureTiming";
+import type { ClipboardPermissionRequested } from "./ClipboardPermissionRequested";
+import type { CloseRequested } from "./CloseRequested";
+import type { ConfigInvalid } from "./ConfigInvalid";
+import type { ConfigReloaded } from "./ConfigReloaded";
+import type { ConnectionState } from "./ConnectionState";
+import type { ConnectivityStatus } from "./ConnectivityStatus";
+import type { DeepLinkIntent } from "./DeepLinkIntent";
+import type { DownloadProgress } from "./DownloadProgress";
+import type { FocusChanged } from "./FocusChanged";
+import type { ForegroundApp } from "./ForegroundApp";
+import type { IdleEvent } from "./IdleEvent";
+import type { JsonValue } from "./serde_json/JsonValue";
+import type { LogEntry } from "./LogEntry";
+import type { MicLevel } from "./MicLevel";
+import type { MicSilence } from "./MicSilence";
+import type { PersistenceRecovered } from "./PersistenceRecovered";
+import type { PowerState } from "./PowerState";
+import type { PrintScreenPressed } from "./PrintScreenPressed";
+import type { QuickAskPrefill } from "./QuickAskPrefill";
+import type { QuickAskSubmission } from "./QuickAskSubmission";
+import type { RecordingLimitReached } from "./RecordingLimitReached";
+import type { ScreenInfo } from "./ScreenInfo";
+import type { ScreenshotFrame } from "./ScreenshotFrame";
+import type { ServerReady } from "./ServerReady";
+import type { SpeakingState } from "./SpeakingState";
+import type { SystemTheme } from "./SystemTheme";
+
+export type GraviaEvent = ServerReady | ConnectionState | JsonValue | ConnectivityStatus | PowerState | ConfigReloaded | ConfigInvalid | PersistenceRecovered | SystemTheme | IdleEvent | IdleEvent | ForegroundApp | FocusChanged | CloseRequested | DeepLinkIntent | PrintScreenPressed | CapturePauseState | Array<ScreenInfo> | ScreenshotFrame | CaptureTiming | CaptureThrottled | AutoCaptureNotice | Array<Attachment> | ClipboardPermissionRequested | QuickAskPrefill | QuickAskSubmission | ActionExecuted | MicLevel | MicSilence | RecordingLimitReached | SpeakingState | DownloadProgress | LogEntry;
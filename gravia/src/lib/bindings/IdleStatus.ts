@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Throttle } from "./Throttle";
+
+export type IdleStatus = { supported: boolean, idle: boolean, idle_seconds: number, poll_interval_ms: number, throttle: Throttle, };
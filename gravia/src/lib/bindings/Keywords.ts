@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Keywords = { screenshot: Array<string>, no_screenshot: Array<string>, followup: Array<string>, ui: Array<string>, error: Array<string>, task: Array<string>, force_capture: Array<string>, forbid_capture: Array<string>, };
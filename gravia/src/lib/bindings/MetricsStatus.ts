@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Throttle } from "./Throttle";
+
+export type MetricsStatus = { enabled: boolean, idle_paused: boolean, flush_interval_ms: number, throttle: Throttle, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type OpenAction = { "kind": "open_url", url: string, } | { "kind": "open_path", path: string, } | { "kind": "open_settings_page", page: string, } | { "kind": "launch_app", name: string, };
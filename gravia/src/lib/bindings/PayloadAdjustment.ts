@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PayloadAdjustment = { original_bytes: number, bytes: number, scale: number, width: number, height: number, format: string, content_hash: string, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PowerSource } from "./PowerSource";
+
+export type PowerState = { source: PowerSource, battery_percent: number | null, power_saver: boolean, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProxyFailure } from "./ProxyFailure";
+
+export type ProxyTestOutcome = { "result": "reachable", status: number, } | { "result": "failed", reason: ProxyFailure, message: string, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ByteBudget } from "./ByteBudget";
+import type { CaptureFormat } from "./CaptureFormat";
+import type { CaptureTimings } from "./CaptureTimings";
+import type { DisplayScale } from "./DisplayScale";
+import type { Downscale } from "./Downscale";
+import type { PayloadAdjustment } from "./PayloadAdjustment";
+import type { ScreenAnalysis } from "./ScreenAnalysis";
+import type { WindowHandling } from "./WindowHandling";
+
+export type RegionCapture = { capture_id: string, base64: string, format: CaptureFormat, bytes: number, content_hash: string, screen_id: number, x: number, y: number, width: number, height: number, scale_factor: number, clamped: boolean, downscale: Downscale | null, thumbnail_base64: string | null, window_handling: WindowHandling, adjustment: PayloadAdjustment | null, from_cache: boolean, unchanged: boolean, redacted: number, analysis: ScreenAnalysis | null, scale: DisplayScale | null, budget: ByteBudget | null, timings: CaptureTimings | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SavedScreenshot = { id: string, captured_at: string, query: string | null, monitor: string, screen_id: number | null, width: number, height: number, path: string | null, bytes: number, };
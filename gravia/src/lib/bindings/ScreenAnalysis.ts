@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DominantColor } from "./DominantColor";
+
+export type ScreenAnalysis = { mean_luminance: number, dominant_colors: Array<DominantColor>, is_mostly_blank: boolean, };
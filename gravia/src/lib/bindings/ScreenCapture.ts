@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ByteBudget } from "./ByteBudget";
+import type { CaptureBackend } from "./CaptureBackend";
+import type { CaptureFormat } from "./CaptureFormat";
+import type { CaptureTimings } from "./CaptureTimings";
+import type { DisplayScale } from "./DisplayScale";
+import type { Downscale } from "./Downscale";
+import type { PayloadAdjustment } from "./PayloadAdjustment";
+import type { ScreenAnalysis } from "./ScreenAnalysis";
+import type { WindowHandling } from "./WindowHandling";
+
+export type ScreenCapture = { screen_id: number, width: number, height: number, capture_id: string | null, base64: string | null, format: CaptureFormat, bytes: number | null, content_hash: string | null, backend: CaptureBackend | null, downscale: Downscale | null, thumbnail_base64: string | null, window_handling: WindowHandling, adjustment: PayloadAdjustment | null, unchanged: boolean, redacted: number, analysis: ScreenAnalysis | null, scale: DisplayScale | null, budget: ByteBudget | null, timings: CaptureTimings | null, error: string | null, error_code: string | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ScreenInfo = { id: number, x: number, y: number, width: number, height: number, scale_factor: number, is_primary: boolean, };
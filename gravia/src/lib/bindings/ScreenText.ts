@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Downscale } from "./Downscale";
+import type { OcrWord } from "./OcrWord";
+
+export type ScreenText = { capture_id: string, text: string, words: Array<OcrWord>, downscale: Downscale | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureFormat } from "./CaptureFormat";
+
+export type ScreenshotFrame = { sequence: number, capture_id: string, base64: string | null, format: CaptureFormat, unchanged: boolean, dropped: number, };
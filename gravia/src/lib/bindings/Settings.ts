@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CaptureBackend } from "./CaptureBackend";
+import type { CloseBehavior } from "./CloseBehavior";
+import type { PrintScreenAction } from "./PrintScreenAction";
+import type { ProxySettings } from "./ProxySettings";
+import type { RedactRect } from "./RedactRect";
+import type { StampCorner } from "./StampCorner";
+import type { UpdateChannel } from "./UpdateChannel";
+
+export type Settings = { notify_on_auto_capture: boolean, allow_clipboard_read: boolean, clipboard_permission_prompted: boolean, idle_threshold_secs: number, on_close: CloseBehavior, min_capture_interval_ms: number, capture_burst: number, log_level: string, locale: string, update_channel: UpdateChannel, proxy: ProxySettings, allow_microphone: boolean, max_recording_secs: number, silence_threshold: number, silence_duration_ms: number, auto_hide_on_blur: boolean, auto_hide_delay_ms: number, throttle_on_battery: boolean, low_battery_percent: number, connectivity_probe_url: string, bind_print_screen: boolean, print_screen_action: PrintScreenAction, parallel_capture_processing: boolean, lazy_server_start: boolean, max_ipc_payload_bytes: number, auto_shrink: boolean, collect_usage_metrics: boolean, classify_coalesce_window_ms: number, debug_log_stream: boolean, capture_backend: CaptureBackend, hide_window_for_capture: boolean, capture_hide_delay_ms: number, capture_cache_ttl_ms: number, capture_timeout_ms: number, auto_capture_max_bytes: number, stamp_corner: StampCorner, stamp_auto_captures: boolean, save_screenshots: boolean, saved_screenshot_retention_days: number, saved_screenshot_retention_mb: number, redact_regions: Array<RedactRect>, capture_blocklist: Array<string>, session_autosave_secs: number, };
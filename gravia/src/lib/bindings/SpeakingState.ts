@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SpeakingState = { "state": "started", id: number, } | { "state": "finished", id: number, interrupted: boolean, } | { "state": "error", id: number, message: string, };
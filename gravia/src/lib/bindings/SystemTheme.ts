@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThemeMode } from "./ThemeMode";
+
+export type SystemTheme = { mode: ThemeMode, taskbar_mode: ThemeMode, accent_color: string | null, high_contrast: boolean, };
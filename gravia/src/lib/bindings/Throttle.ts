@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThrottleReason } from "./ThrottleReason";
+
+export type Throttle = { interval_factor: number, pause_optional: boolean, reason: ThrottleReason | null, };
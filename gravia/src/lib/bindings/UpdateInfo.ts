@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UpdateChannel } from "./UpdateChannel";
+
+export type UpdateInfo = { current_version: string, available_version: string | null, release_notes: string | null, channel: UpdateChannel, };
@@ -462,7 +462,10 @@ export interface ClassifyResult {
     confidence: number;
     screenshot_score: number;
     no_screenshot_score: number;
+    reasons: { code: string; args: Record<string, string> }[];
     reasoning: string[];
+    summary_code: 'screenshot' | 'no_screenshot' | 'suppressed';
+    summary: string;
     context_info: {
       has_context: boolean;
       context_type?: string | null;